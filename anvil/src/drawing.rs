@@ -7,7 +7,7 @@ use slog::Logger;
 use smithay::utils::Buffer;
 use smithay::{
     backend::renderer::{Frame, ImportAll, Renderer, Texture},
-    desktop::space::{RenderElement, SpaceOutputTuple, SurfaceTree},
+    desktop::space::{ElementKind, RenderElement, SpaceOutputTuple, SurfaceTree},
     reexports::wayland_server::protocol::wl_surface,
     utils::{Logical, Physical, Point, Rectangle, Scale, Size, Transform},
     wayland::{
@@ -41,11 +41,12 @@ pub fn draw_cursor(
             .unwrap()
             .hotspot
     });
-    SurfaceTree {
+    SurfaceTree::new(
         surface,
         position,
-        z_index: 100, /* Cursor should always be on-top */
-    }
+        100, /* Cursor should always be on-top */
+        ElementKind::Cursor,
+    )
 }
 
 pub fn draw_dnd_icon(
@@ -59,11 +60,12 @@ pub fn draw_dnd_icon(
             "Trying to display as a dnd icon a surface that does not have the DndIcon role."
         );
     }
-    SurfaceTree {
+    SurfaceTree::new(
         surface,
-        position: location.into(),
-        z_index: 100, /* Cursor should always be on-top */
-    }
+        location,
+        100, /* Cursor should always be on-top */
+        ElementKind::Cursor,
+    )
 }
 
 pub struct PointerElement<T: Texture> {
@@ -137,6 +139,10 @@ where
         )?;
         Ok(())
     }
+
+    fn kind(&self) -> ElementKind {
+        ElementKind::Cursor
+    }
 }
 
 #[cfg(feature = "debug")]
@@ -49,7 +49,7 @@ use smithay::{
 #[cfg(feature = "xwayland")]
 use crate::xwayland::X11State;
 #[cfg(feature = "xwayland")]
-use smithay::xwayland::{XWayland, XWaylandEvent};
+use smithay::xwayland::{X11Focus, XWayland, XWaylandEvent};
 
 pub struct CalloopData<BackendData: 'static> {
     pub state: AnvilState<BackendData>,
@@ -104,6 +104,8 @@ pub struct AnvilState<BackendData: 'static> {
     pub xwayland: XWayland,
     #[cfg(feature = "xwayland")]
     pub x11_state: Option<X11State>,
+    #[cfg(feature = "xwayland")]
+    pub x11_focus: X11Focus,
 }
 
 delegate_compositor!(@<BackendData: Backend + 'static> AnvilState<BackendData>);
@@ -257,7 +259,9 @@ impl<BackendData: Backend + 'static> AnvilState<BackendData> {
         let data_device_state = DataDeviceState::new::<Self, _>(&dh, log.clone());
         let layer_shell_state = WlrLayerShellState::new::<Self, _>(&dh, log.clone());
         let output_manager_state = OutputManagerState::new();
-        let primary_selection_state = PrimarySelectionState::new::<Self, _>(&dh, log.clone());
+        let mut primary_selection_state = PrimarySelectionState::new::<Self, _>(&dh, log.clone());
+        // write compositor selections without blocking on clients and coalesce client selections
+        primary_selection_state.set_loop_handle(handle.clone());
         let seat_state = SeatState::new();
         let shm_state = ShmState::new::<Self, _>(&dh, vec![], log.clone());
         let viewporter_state = ViewporterState::new::<Self, _>(&dh, log.clone());
@@ -273,11 +277,17 @@ impl<BackendData: Backend + 'static> AnvilState<BackendData> {
         let cursor_status2 = cursor_status.clone();
         seat.add_pointer(move |new_status| *cursor_status2.lock().unwrap() = new_status);
 
+        #[cfg(feature = "xwayland")]
+        let x11_focus = X11Focus::default();
+        #[cfg(feature = "xwayland")]
+        let x11_focus2 = x11_focus.clone();
         seat.add_keyboard(XkbConfig::default(), 200, 25, move |seat, surface| {
             let focus = surface.and_then(|s| dh.get_client(s.id()).ok());
             let focus2 = surface.and_then(|s| dh.get_client(s.id()).ok());
             set_data_device_focus(&dh, seat, focus);
             set_primary_focus(&dh, seat, focus2);
+            #[cfg(feature = "xwayland")]
+            x11_focus2.set_focus(surface);
         })
         .expect("Failed to initialize the keyboard");
 
@@ -335,6 +345,8 @@ impl<BackendData: Backend + 'static> AnvilState<BackendData> {
             xwayland,
             #[cfg(feature = "xwayland")]
             x11_state: None,
+            #[cfg(feature = "xwayland")]
+            x11_focus,
         }
     }
 }
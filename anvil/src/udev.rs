@@ -32,7 +32,7 @@ use smithay::{
         renderer::{
             gles2::Gles2Renderbuffer,
             multigpu::{egl::EglGlesBackend, GpuManager, MultiRenderer, MultiTexture},
-            Bind, Frame, ImportMem, Renderer,
+            AlphaMode, Bind, Frame, ImportMem, Renderer,
         },
         session::{auto::AutoSession, Session, Signal as SessionSignal},
         udev::{all_gpus, primary_gpu, UdevBackend, UdevEvent},
@@ -225,6 +225,7 @@ pub fn run_udev(log: Logger) {
             &fps_image.to_rgba8(),
             (fps_image.width() as i32, fps_image.height() as i32).into(),
             false,
+            AlphaMode::Straight,
         )
         .expect("Unable to upload FPS texture");
 
@@ -737,6 +738,7 @@ impl AnvilState<UdevData> {
                             &frame.pixels_rgba,
                             (frame.width as i32, frame.height as i32).into(),
                             false,
+                            AlphaMode::Premultiplied,
                         )
                         .expect("Failed to import cursor bitmap");
                     pointer_images.push((frame, texture.clone()));
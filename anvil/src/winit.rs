@@ -2,7 +2,7 @@ use std::{sync::atomic::Ordering, time::Duration};
 
 use slog::Logger;
 #[cfg(feature = "debug")]
-use smithay::backend::renderer::{gles2::Gles2Texture, ImportMem};
+use smithay::backend::renderer::{gles2::Gles2Texture, AlphaMode, ImportMem};
 #[cfg(feature = "egl")]
 use smithay::{
     backend::{
@@ -127,6 +127,7 @@ pub fn run_winit(log: Logger) {
                 &fps_image.to_rgba8(),
                 (fps_image.width() as i32, fps_image.height() as i32).into(),
                 false,
+                AlphaMode::Straight,
             )
             .expect("Unable to upload FPS texture");
 
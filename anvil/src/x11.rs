@@ -9,7 +9,7 @@ use crate::{
 };
 use slog::Logger;
 #[cfg(feature = "debug")]
-use smithay::backend::renderer::{gles2::Gles2Texture, ImportMem};
+use smithay::backend::renderer::{gles2::Gles2Texture, AlphaMode, ImportMem};
 #[cfg(feature = "egl")]
 use smithay::{
     backend::{
@@ -163,6 +163,7 @@ pub fn run_x11(log: Logger) {
             &fps_image.to_rgba8(),
             (fps_image.width() as i32, fps_image.height() as i32).into(),
             false,
+            AlphaMode::Straight,
         )
         .expect("Unable to upload FPS texture");
 
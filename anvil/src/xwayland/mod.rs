@@ -6,6 +6,7 @@ use smithay::{
     reexports::wayland_server::{protocol::wl_surface::WlSurface, Client, DisplayHandle, Resource},
     utils::{x11rb::X11Source, Logical, Point},
     wayland::compositor::give_role,
+    xwayland::X11Focus,
 };
 use x11rb::{
     connection::Connection as _,
@@ -29,7 +30,8 @@ impl<BackendData: 'static> AnvilState<BackendData> {
     }
 
     pub fn xwayland_ready(&mut self, connection: UnixStream, client: Client) {
-        let (wm, source) = X11State::start_wm(connection, client, self.log.clone()).unwrap();
+        let (wm, source) =
+            X11State::start_wm(connection, client, self.x11_focus.clone(), self.log.clone()).unwrap();
         self.x11_state = Some(wm);
         let log = self.log.clone();
         self.handle
@@ -46,6 +48,7 @@ impl<BackendData: 'static> AnvilState<BackendData> {
 
     pub fn xwayland_exited(&mut self) {
         let _ = self.x11_state.take();
+        self.x11_focus.disconnect();
         error!(self.log, "Xwayland crashed");
     }
 }
@@ -64,6 +67,7 @@ pub struct X11State {
     conn: Arc<RustConnection>,
     atoms: Atoms,
     client: Client,
+    focus: X11Focus,
     log: slog::Logger,
     unpaired_surfaces: HashMap<u32, (X11Window, Point<i32, Logical>)>,
 }
@@ -72,6 +76,7 @@ impl X11State {
     fn start_wm(
         connection: UnixStream,
         client: Client,
+        focus: X11Focus,
         log: slog::Logger,
     ) -> Result<(Self, X11Source), Box<dyn std::error::Error>> {
         // Create an X11 connection. XWayland only uses screen 0.
@@ -112,10 +117,12 @@ impl X11State {
         conn.flush()?;
 
         let conn = Arc::new(conn);
+        focus.connect(Arc::clone(&conn), log.clone());
         let wm = Self {
             conn: Arc::clone(&conn),
             atoms,
             client,
+            focus,
             unpaired_surfaces: Default::default(),
             log: log.clone(),
         };
@@ -225,6 +232,7 @@ impl X11State {
             return;
         }
 
+        self.focus.insert_window(surface.clone(), window);
         let x11surface = X11Surface { surface };
         space.map_window(&Window::new(Kind::X11(x11surface)), location, None, true);
     }
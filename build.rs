@@ -40,6 +40,8 @@ fn gl_generate() {
                 "EGL_KHR_gl_image",
                 "EGL_EXT_buffer_age",
                 "EGL_EXT_swap_buffers_with_damage",
+                "EGL_KHR_fence_sync",
+                "EGL_ANDROID_native_fence_sync",
             ],
         )
         .write_bindings(gl_generator::GlobalGenerator, &mut file)
@@ -58,6 +60,8 @@ fn gl_generate() {
                 "GL_OES_EGL_image_external",
                 "GL_EXT_texture_format_BGRA8888",
                 "GL_EXT_unpack_subimage",
+                "GL_KHR_robustness",
+                "GL_EXT_disjoint_timer_query",
             ],
         )
         .write_bindings(gl_generator::StructGenerator, &mut file)
@@ -4,17 +4,31 @@
 //! Once the last `Dmabuf` reference is dropped, its file descriptor is closed and
 //! underlying resources are freed.
 //!
+//! Clones of a `Dmabuf` are considered the same buffer. If you need a distinct handle
+//! to the same memory, use [`Dmabuf::new_handle`], which duplicates the file descriptors.
+//!
+//! To be explicit about which notion of identity a map keyed by dmabufs uses, wrap them into
+//! [`ByIdentity`] or [`ByContent`].
+//!
 //! If you want to hold on to a potentially alive dmabuf without blocking the free up
 //! of the underlying resources, you may `downgrade` a `Dmabuf` reference to a `WeakDmabuf`.
 //!
 //! This can be especially useful in resources where other parts of the stack should decide upon
 //! the lifetime of the buffer. E.g. when you are only caching associated resources for a dmabuf.
 
-use super::{Buffer, Format, Fourcc, Modifier};
+#[cfg(feature = "test_utils")]
+use super::Allocator;
+use super::{format, Buffer, Format, Fourcc, Modifier};
 use crate::utils::{Buffer as BufferCoords, Size};
+use nix::{
+    fcntl::{fcntl, FcntlArg},
+    sys::mman,
+};
 use std::hash::{Hash, Hasher};
-use std::os::unix::io::{IntoRawFd, RawFd};
-use std::sync::{Arc, Weak};
+use std::io;
+use std::os::unix::io::{FromRawFd, IntoRawFd, OwnedFd, RawFd};
+use std::ptr;
+use std::sync::{Arc, Mutex, Weak};
 
 /// Maximum amount of planes this implementation supports
 pub const MAX_PLANES: usize = 4;
@@ -31,6 +45,8 @@ pub(crate) struct DmabufInternal {
     ///
     /// This is a bitflag, to be compared with the `Flags` enum re-exported by this module.
     pub flags: DmabufFlags,
+    /// Optional label for diagnostics
+    pub label: Mutex<Option<Arc<str>>>,
 }
 
 #[derive(Debug)]
@@ -60,6 +76,22 @@ impl Drop for Plane {
     }
 }
 
+/// Metadata of a single plane of a [`Dmabuf`], without its file descriptor
+///
+/// Unlike the buffer itself this can be freely copied and kept around after the buffer
+/// was dropped, e.g. for logging the layout of buffers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PlaneMeta {
+    /// The plane index
+    pub plane_idx: u32,
+    /// Offset from the start of the file descriptor
+    pub offset: u32,
+    /// Stride for this plane
+    pub stride: u32,
+    /// Modifier for this plane
+    pub modifier: Modifier,
+}
+
 bitflags::bitflags! {
     /// Possible flags for a DMA buffer
     pub struct DmabufFlags: u32 {
@@ -105,6 +137,82 @@ impl Hash for WeakDmabuf {
     }
 }
 
+/// Wrapper comparing and hashing a [`Dmabuf`] by the identity of its handle
+///
+/// This is the same behavior the `Dmabuf` itself implements: clones are equal, while handles
+/// created by [`Dmabuf::new_handle`] or imported separately are not, even if they refer to the same memory.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ByIdentity(pub Dmabuf);
+
+/// Wrapper comparing and hashing a [`Dmabuf`] by the underlying buffer it refers to
+///
+/// Two dmabufs are considered equal, if they have the same size, format and flags and all their planes
+/// refer to the same file (not necessarily the same file descriptor) with the same plane index, offset,
+/// stride and modifier. This makes handles created by [`Dmabuf::new_handle`] or the same buffer
+/// imported multiple times by a client compare equal.
+///
+/// The files of the planes are queried every time the wrapper is compared or hashed.
+#[derive(Debug, Clone)]
+pub struct ByContent(pub Dmabuf);
+
+type PlaneKey = (Option<(u64, u64)>, u32, u32, u32, u64);
+
+impl ByContent {
+    fn planes(&self) -> impl Iterator<Item = PlaneKey> + '_ {
+        self.0 .0.planes.iter().map(|plane| {
+            // fall back to comparing nothing but the layout, if the fd cannot be queried
+            let file = plane
+                .fd
+                .and_then(|fd| nix::sys::stat::fstat(fd).ok())
+                .map(|stat| (stat.st_dev as u64, stat.st_ino as u64));
+            (
+                file,
+                plane.plane_idx,
+                plane.offset,
+                plane.stride,
+                u64::from(plane.modifier),
+            )
+        })
+    }
+}
+
+impl PartialEq for ByContent {
+    fn eq(&self, other: &Self) -> bool {
+        let (this, other_buffer) = (&self.0 .0, &other.0 .0);
+        Arc::ptr_eq(this, other_buffer)
+            || (this.size == other_buffer.size
+                && this.format == other_buffer.format
+                && this.flags == other_buffer.flags
+                && this.planes.len() == other_buffer.planes.len()
+                && self.planes().eq(other.planes()))
+    }
+}
+impl Eq for ByContent {}
+
+impl Hash for ByContent {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0 .0.size.w.hash(state);
+        self.0 .0.size.h.hash(state);
+        (self.0 .0.format as u32).hash(state);
+        self.0 .0.flags.bits().hash(state);
+        for plane in self.planes() {
+            plane.hash(state);
+        }
+    }
+}
+
+impl From<Dmabuf> for ByIdentity {
+    fn from(buffer: Dmabuf) -> Self {
+        ByIdentity(buffer)
+    }
+}
+
+impl From<Dmabuf> for ByContent {
+    fn from(buffer: Dmabuf) -> Self {
+        ByContent(buffer)
+    }
+}
+
 impl Buffer for Dmabuf {
     fn size(&self) -> Size<i32, BufferCoords> {
         self.0.size
@@ -144,6 +252,33 @@ impl DmabufBuilder {
         true
     }
 
+    /// Replace the flags of the constructed Dmabuf
+    pub fn set_flags(&mut self, flags: DmabufFlags) {
+        self.internal.flags = flags;
+    }
+
+    /// Add flags to the constructed Dmabuf, keeping already set flags
+    pub fn add_flags(&mut self, flags: DmabufFlags) {
+        self.internal.flags.insert(flags);
+    }
+
+    /// Remove flags from the constructed Dmabuf
+    pub fn remove_flags(&mut self, flags: DmabufFlags) {
+        self.internal.flags.remove(flags);
+    }
+
+    /// Returns the flags currently set for the constructed Dmabuf
+    pub fn flags(&self) -> DmabufFlags {
+        self.internal.flags
+    }
+
+    /// Set a label for the constructed Dmabuf
+    ///
+    /// See [`Dmabuf::set_label`].
+    pub fn label(&mut self, label: &str) {
+        *self.internal.label.get_mut().unwrap() = Some(Arc::from(label));
+    }
+
     /// Build a `Dmabuf` out of the provided parameters and planes
     ///
     /// Returns `None` if the builder has no planes attached.
@@ -170,6 +305,7 @@ impl Dmabuf {
                 size: src.size(),
                 format: src.format().code,
                 flags,
+                label: Mutex::new(None),
             },
         }
     }
@@ -186,6 +322,7 @@ impl Dmabuf {
                 size: size.into(),
                 format,
                 flags,
+                label: Mutex::new(None),
             },
         }
     }
@@ -210,6 +347,34 @@ impl Dmabuf {
         self.0.planes.iter().map(|p| p.stride)
     }
 
+    /// Returns the metadata of all planes of this buffer
+    pub fn plane_metadata(&self) -> Vec<PlaneMeta> {
+        self.0
+            .planes
+            .iter()
+            .map(|p| PlaneMeta {
+                plane_idx: p.plane_idx,
+                offset: p.offset,
+                stride: p.stride,
+                modifier: p.modifier,
+            })
+            .collect()
+    }
+
+    /// Returns the precision of the color channels of this buffer, derived from its format
+    ///
+    /// See [`format::get_bit_depth`] for details.
+    pub fn bit_depth(&self) -> Option<format::BitDepth> {
+        format::get_bit_depth(self.0.format)
+    }
+
+    /// Returns the horizontal and vertical subsampling of the given plane of this buffer
+    ///
+    /// See [`format::get_plane_subsampling`] for details.
+    pub fn plane_subsampling(&self, plane_idx: usize) -> Option<(u32, u32)> {
+        format::get_plane_subsampling(self.0.format, plane_idx)
+    }
+
     /// Returns if this buffer format has any vendor-specific modifiers set or is implicit/linear
     pub fn has_modifier(&self) -> bool {
         self.0.planes[0].modifier != Modifier::Invalid && self.0.planes[0].modifier != Modifier::Linear
@@ -220,10 +385,560 @@ impl Dmabuf {
         self.0.flags.contains(DmabufFlags::Y_INVERT)
     }
 
+    /// Hash of the metadata of this buffer
+    ///
+    /// Covers the size, format, flags and the index, offset, stride and modifier of every plane,
+    /// but neither the file descriptors nor the files they refer to. This is *not* a hash of the
+    /// pixel data: buffers with equal layout but different contents share the same hash.
+    /// It is cheap to compute and therefore useful as a pre-filter, before comparing buffers
+    /// by [identity](ByIdentity) or [content](ByContent).
+    ///
+    /// The hash is stable for the lifetime of the process, but may change between versions of smithay.
+    pub fn metadata_hash(&self) -> u64 {
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        self.0.size.w.hash(&mut hasher);
+        self.0.size.h.hash(&mut hasher);
+        (self.0.format as u32).hash(&mut hasher);
+        self.0.flags.bits().hash(&mut hasher);
+        for plane in self.0.planes.iter() {
+            plane.plane_idx.hash(&mut hasher);
+            plane.offset.hash(&mut hasher);
+            plane.stride.hash(&mut hasher);
+            u64::from(plane.modifier).hash(&mut hasher);
+        }
+        hasher.finish()
+    }
+
+    /// Set a label for this buffer
+    ///
+    /// The label has no meaning to smithay, but is included in the `Debug` output of the buffer.
+    /// It can be used to tag buffers with their purpose (e.g. `"cursor"` or `"screencopy"`)
+    /// to make them identifiable when debugging.
+    ///
+    /// The label is shared by all clones of this `Dmabuf`.
+    pub fn set_label(&self, label: &str) {
+        *self.0.label.lock().unwrap() = Some(Arc::from(label));
+    }
+
+    /// Returns the label of this buffer, if any was set
+    pub fn label(&self) -> Option<Arc<str>> {
+        self.0.label.lock().unwrap().clone()
+    }
+
     /// Create a weak reference to this dmabuf
     pub fn weak(&self) -> WeakDmabuf {
         WeakDmabuf(Arc::downgrade(&self.0))
     }
+
+    /// Create a new, independent handle to the same underlying buffer
+    ///
+    /// Opposed to [`Clone::clone`], which only creates another reference to the same handle,
+    /// this duplicates the file descriptors of all planes. The returned `Dmabuf` refers to the same
+    /// memory, but does not compare equal to `self` and has its own lifetime. This allows multiple
+    /// subsystems to associate their own state (e.g. synchronization fences) with the same buffer
+    /// by using the handle as a key, without interfering with each other.
+    pub fn new_handle(&self) -> io::Result<Dmabuf> {
+        let mut builder = Dmabuf::builder(self.0.size, self.0.format, self.0.flags);
+        if let Some(label) = self.label() {
+            builder.label(&label);
+        }
+        for (plane, fd) in self.0.planes.iter().zip(self.dup_fds()?) {
+            builder.add_plane(
+                fd.into_raw_fd(),
+                plane.plane_idx,
+                plane.offset,
+                plane.stride,
+                plane.modifier,
+            );
+        }
+        // the source buffer has at least one plane
+        Ok(builder.build().unwrap())
+    }
+
+    /// Create a new handle to the same buffer describing it with the given modifier
+    ///
+    /// Buffers shared with an implicit modifier ([`Modifier::Invalid`]) may only learn their actual
+    /// layout later, e.g. by querying it from EGL after importing them. This creates a new handle like
+    /// [`Dmabuf::new_handle`] with the modifier of all planes replaced, keeping the label and flags.
+    ///
+    /// Only buffers with an implicit or [linear](Modifier::Linear) modifier can be resolved, to prevent
+    /// misdescribing buffers with an explicitly known layout.
+    pub fn with_resolved_modifier(&self, modifier: Modifier) -> Result<Dmabuf, DmabufError> {
+        if let Some(plane) = self
+            .0
+            .planes
+            .iter()
+            .find(|plane| !matches!(plane.modifier, Modifier::Invalid | Modifier::Linear))
+        {
+            return Err(DmabufError::ModifierKnown(plane.modifier));
+        }
+
+        let mut builder = Dmabuf::builder(self.0.size, self.0.format, self.0.flags);
+        if let Some(label) = self.label() {
+            builder.label(&label);
+        }
+        let fds = self.dup_fds().map_err(DmabufError::Duplicate)?;
+        for (plane, fd) in self.0.planes.iter().zip(fds) {
+            builder.add_plane(
+                fd.into_raw_fd(),
+                plane.plane_idx,
+                plane.offset,
+                plane.stride,
+                modifier,
+            );
+        }
+        // the source buffer has at least one plane
+        Ok(builder.build().unwrap())
+    }
+
+    /// Duplicate the file descriptors of all planes
+    ///
+    /// The returned file descriptors are owned by the caller and have the close-on-exec flag set.
+    /// They are ordered like [`Dmabuf::handles`], [`Dmabuf::offsets`] and [`Dmabuf::strides`],
+    /// so the buffer can be reconstructed with a [`DmabufBuilder`] e.g. in another process.
+    pub fn dup_fds(&self) -> io::Result<Vec<OwnedFd>> {
+        self.handles()
+            .map(|fd| {
+                // Set to 3 so the fd cannot become stdin, stdout or stderr
+                fcntl(fd, FcntlArg::F_DUPFD_CLOEXEC(3))
+                    .map(|fd| unsafe { OwnedFd::from_raw_fd(fd) })
+                    .map_err(io::Error::from)
+            })
+            .collect()
+    }
+
+    /// Copy the contents of this buffer into memory
+    ///
+    /// This maps the buffer and copies its pixels row by row into a newly allocated vector,
+    /// which is useful as a fallback, if a buffer cannot be imported directly (e.g. into the renderer
+    /// of another gpu). Returns the pixel data together with its stride in bytes. The rows are tightly
+    /// packed, so the stride may differ from the one of the buffer.
+    ///
+    /// Only single-plane buffers with a [linear](Modifier::Linear) layout can be copied and no format
+    /// conversion is done, so `format` needs to match the format of the buffer. Tiled buffers
+    /// need to be linearized by a renderer first, e.g. by importing them and using
+    /// [`ExportMem::copy_texture`](crate::backend::renderer::ExportMem::copy_texture).
+    pub fn copy_to_memory(&self, format: Fourcc) -> Result<(Vec<u8>, i32), DmabufError> {
+        if format != self.0.format {
+            return Err(DmabufError::FormatMismatch {
+                buffer: self.0.format,
+                requested: format,
+            });
+        }
+        if self.num_planes() != 1 {
+            return Err(DmabufError::MultiPlanar(self.num_planes()));
+        }
+        let plane = &self.0.planes[0];
+        if plane.modifier != Modifier::Linear {
+            return Err(DmabufError::NotLinear(plane.modifier));
+        }
+        let bpp = format::get_bpp(format).ok_or(DmabufError::UnsupportedFormat(format))?;
+
+        let width = self.0.size.w.max(0) as usize;
+        let height = self.0.size.h.max(0) as usize;
+        let row_size = width * bpp / 8;
+        let src_stride = plane.stride as usize;
+        if src_stride < row_size {
+            return Err(DmabufError::Validation(DmabufValidationError::StrideTooSmall {
+                stride: plane.stride,
+                min_stride: row_size as u32,
+            }));
+        }
+        let offset = plane.offset as usize;
+        let map_size = offset + src_stride * height;
+        if row_size == 0 || height == 0 {
+            return Ok((Vec::new(), row_size as i32));
+        }
+
+        let fd = plane.fd.unwrap();
+        let ptr = unsafe {
+            mman::mmap(
+                ptr::null_mut(),
+                map_size,
+                mman::ProtFlags::PROT_READ,
+                mman::MapFlags::MAP_SHARED,
+                fd,
+                0,
+            )
+        }
+        .map_err(|err| DmabufError::Map(err.into()))?;
+
+        // let the exporter know, we are accessing the buffer with the cpu
+        let _ = unsafe { ioctl::dma_buf_sync(fd, &ioctl::DMA_BUF_SYNC_START) };
+        // SAFETY: the mapping is at least `map_size` bytes long and only read while it is alive
+        let data = unsafe { std::slice::from_raw_parts(ptr as *const u8, map_size) };
+        let mut pixels = Vec::with_capacity(row_size * height);
+        for row in 0..height {
+            let start = offset + row * src_stride;
+            pixels.extend_from_slice(&data[start..start + row_size]);
+        }
+        let _ = unsafe { ioctl::dma_buf_sync(fd, &ioctl::DMA_BUF_SYNC_END) };
+
+        let _ = unsafe { mman::munmap(ptr, map_size) };
+        Ok((pixels, row_size as i32))
+    }
+
+    /// Combine planes of several buffers into a single multi-planar buffer
+    ///
+    /// Every entry of `planes` names a buffer and the index of one of its planes, which becomes the plane
+    /// of the combined buffer at the same position. This is useful e.g. for video decoders exporting the luma
+    /// and chroma planes of a `NV12` frame as separate `R8` and `GR88` buffers.
+    ///
+    /// The file descriptors are duplicated, so the combined buffer does not keep the source buffers alive.
+    /// Offsets and strides are taken from the source planes, the flags from the first buffer.
+    ///
+    /// Fails if the amount of planes does not match `fourcc`, a plane index does not exist,
+    /// the planes use different modifiers or the first plane is smaller than `size`.
+    /// Further planes may be subsampled and are not checked against `size`.
+    pub fn combine_planes(
+        planes: Vec<(Dmabuf, u32)>,
+        fourcc: Fourcc,
+        size: impl Into<Size<i32, BufferCoords>>,
+    ) -> Result<Dmabuf, DmabufError> {
+        let size = size.into();
+        let expected = format::get_plane_count(fourcc);
+        if planes.is_empty() || planes.len() > MAX_PLANES || expected.map_or(false, |n| n != planes.len()) {
+            return Err(DmabufValidationError::PlaneCount {
+                format: fourcc,
+                expected: expected.unwrap_or(1),
+                actual: planes.len(),
+            }
+            .into());
+        }
+
+        let mut builder = Dmabuf::builder(size, fourcc, planes[0].0 .0.flags);
+        let modifier = planes[0]
+            .0
+            .find_plane(planes[0].1)
+            .ok_or(DmabufError::MissingPlane {
+                index: 0,
+                plane: planes[0].1,
+            })?
+            .modifier;
+        for (index, (buffer, plane_idx)) in planes.iter().enumerate() {
+            let plane = buffer.find_plane(*plane_idx).ok_or(DmabufError::MissingPlane {
+                index,
+                plane: *plane_idx,
+            })?;
+            if plane.modifier != modifier {
+                return Err(DmabufError::ModifierMismatch {
+                    expected: modifier,
+                    actual: plane.modifier,
+                });
+            }
+            if index == 0 && (buffer.0.size.w < size.w || buffer.0.size.h < size.h) {
+                return Err(DmabufError::PlaneTooSmall {
+                    size: buffer.0.size,
+                    required: size,
+                });
+            }
+            // Set to 3 so the fd cannot become stdin, stdout or stderr
+            let fd = fcntl(plane.fd.unwrap(), FcntlArg::F_DUPFD_CLOEXEC(3))
+                .map_err(|err| DmabufError::Duplicate(err.into()))?;
+            builder.add_plane(fd, index as u32, plane.offset, plane.stride, plane.modifier);
+        }
+
+        // there is at least one plane
+        Ok(builder.build().unwrap())
+    }
+
+    fn find_plane(&self, plane_idx: u32) -> Option<&Plane> {
+        self.0.planes.iter().find(|plane| plane.plane_idx == plane_idx)
+    }
+
+    /// Check if the planes of this buffer are plausible for its format
+    ///
+    /// This checks, that the amount of planes matches the format and that the stride of the first plane
+    /// is large enough to hold a row of pixels. Buffers with vendor-specific modifiers may carry additional
+    /// auxiliary planes, so only a lower bound of planes is checked for those.
+    ///
+    /// Unknown formats are not validated and always pass.
+    pub fn validate_for_format(&self) -> Result<(), DmabufValidationError> {
+        if let Some(expected) = format::get_plane_count(self.0.format) {
+            let actual = self.num_planes();
+            if actual < expected || (actual > expected && !self.has_modifier()) {
+                return Err(DmabufValidationError::PlaneCount {
+                    format: self.0.format,
+                    expected,
+                    actual,
+                });
+            }
+        }
+
+        // multi-planar formats do not have a sensible bpp value for the first plane
+        if let Some(bpp) = format::get_bpp(self.0.format) {
+            let min_stride = (self.0.size.w.max(0) as u32) * (bpp as u32 / 8);
+            let stride = self.0.planes[0].stride;
+            if stride < min_stride {
+                return Err(DmabufValidationError::StrideTooSmall { stride, min_stride });
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Errors returned by [`Dmabuf::validate_for_format`]
+#[derive(Debug, thiserror::Error)]
+pub enum DmabufValidationError {
+    /// The amount of planes does not match the format
+    #[error("Format {format} expects {expected} planes, but the buffer has {actual}")]
+    PlaneCount {
+        /// Format of the buffer
+        format: Fourcc,
+        /// Amount of planes the format consists of
+        expected: usize,
+        /// Amount of planes of the buffer
+        actual: usize,
+    },
+    /// The stride of the first plane is too small for the width of the buffer
+    #[error("Stride of {stride} bytes is smaller than a row of pixels ({min_stride} bytes)")]
+    StrideTooSmall {
+        /// Stride of the first plane
+        stride: u32,
+        /// Minimum stride required by width and format
+        min_stride: u32,
+    },
+}
+
+/// Errors returned by [`Dmabuf::copy_to_memory`], [`Dmabuf::combine_planes`]
+/// and [`Dmabuf::with_resolved_modifier`]
+#[derive(Debug, thiserror::Error)]
+pub enum DmabufError {
+    /// The requested format differs from the format of the buffer
+    #[error("Buffer of format {buffer} cannot be copied as {requested}")]
+    FormatMismatch {
+        /// Format of the buffer
+        buffer: Fourcc,
+        /// Format requested for the copy
+        requested: Fourcc,
+    },
+    /// The format of the buffer has no known amount of bits per pixel
+    #[error("Copying buffers of format {0} is not supported")]
+    UnsupportedFormat(Fourcc),
+    /// The buffer consists of multiple planes
+    #[error("Only single-plane buffers can be copied, but the buffer has {0} planes")]
+    MultiPlanar(usize),
+    /// The buffer does not have a linear layout
+    #[error("Only linear buffers can be copied, but the buffer has modifier {0:?}")]
+    NotLinear(Modifier),
+    /// The planes of the buffer are invalid
+    #[error(transparent)]
+    Validation(#[from] DmabufValidationError),
+    /// Mapping the buffer failed
+    #[error("Failed to map the buffer")]
+    Map(#[source] io::Error),
+    /// A buffer to combine does not have the requested plane
+    #[error("Buffer {index} has no plane {plane}")]
+    MissingPlane {
+        /// Position of the buffer in the list of planes to combine
+        index: usize,
+        /// Requested plane index
+        plane: u32,
+    },
+    /// The planes to combine use different modifiers
+    #[error("Planes with modifier {actual:?} cannot be combined with planes using {expected:?}")]
+    ModifierMismatch {
+        /// Modifier of the first plane
+        expected: Modifier,
+        /// Modifier of the mismatching plane
+        actual: Modifier,
+    },
+    /// The first plane to combine is smaller than the combined buffer
+    #[error("Plane of size {size:?} is too small for a buffer of size {required:?}")]
+    PlaneTooSmall {
+        /// Size of the buffer providing the first plane
+        size: Size<i32, BufferCoords>,
+        /// Size of the combined buffer
+        required: Size<i32, BufferCoords>,
+    },
+    /// Duplicating a file descriptor failed
+    #[error("Failed to duplicate a file descriptor")]
+    Duplicate(#[source] io::Error),
+    /// The buffer already has an explicit modifier, that cannot be replaced
+    #[error("Buffer with the explicit modifier {0:?} cannot be resolved")]
+    ModifierKnown(Modifier),
+}
+
+/// Deterministic pixel patterns written by [`Dmabuf::test_pattern`]
+///
+/// Colors are given as straight RGBA values with 8 bits per channel.
+#[cfg(feature = "test_utils")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TestPattern {
+    /// Alternating squares of two colors, starting with the first one in the top-left corner
+    Checkerboard {
+        /// Edge length of a square in pixels
+        square: u32,
+        /// Colors of the squares
+        colors: [[u8; 4]; 2],
+    },
+    /// Horizontal gradient from the left to the right edge of the buffer
+    Gradient {
+        /// Color of the leftmost column
+        from: [u8; 4],
+        /// Color of the rightmost column
+        to: [u8; 4],
+    },
+}
+
+#[cfg(feature = "test_utils")]
+impl TestPattern {
+    /// Color of the pixel at the given position in a buffer of the given width
+    ///
+    /// This is the reference to compare sampled or read back pixels against.
+    pub fn pixel(&self, x: u32, y: u32, width: u32) -> [u8; 4] {
+        match *self {
+            TestPattern::Checkerboard { square, colors } => {
+                let square = square.max(1);
+                colors[((x / square + y / square) % 2) as usize]
+            }
+            TestPattern::Gradient { from, to } => {
+                let t = if width > 1 {
+                    x as f32 / (width - 1) as f32
+                } else {
+                    0.0
+                };
+                let mut color = [0; 4];
+                for ((color, from), to) in color.iter_mut().zip(from).zip(to) {
+                    *color = (from as f32 + (to as f32 - from as f32) * t).round() as u8;
+                }
+                color
+            }
+        }
+    }
+}
+
+/// Errors returned by [`Dmabuf::test_pattern`]
+#[cfg(feature = "test_utils")]
+#[derive(Debug, thiserror::Error)]
+pub enum TestPatternError {
+    /// The allocator failed to create a buffer
+    #[error("Failed to allocate the buffer")]
+    Allocation(#[source] Box<dyn std::error::Error + Send + Sync>),
+    /// The allocated buffer could not be exported as a dmabuf
+    #[error("Failed to export the buffer")]
+    Export(#[source] Box<dyn std::error::Error + Send + Sync>),
+    /// Writing the pattern failed
+    #[error(transparent)]
+    Fill(#[from] DmabufError),
+}
+
+#[cfg(feature = "test_utils")]
+impl Dmabuf {
+    /// Allocate a linear buffer filled with a deterministic pattern
+    ///
+    /// This is meant for tests of renderers and other consumers of dmabufs, e.g. to check sampled or
+    /// read back pixels against [`TestPattern::pixel`]. The buffer is allocated with a
+    /// [linear](Modifier::Linear) layout using the given allocator and filled using the cpu,
+    /// see [`Dmabuf::fill_test_pattern`] for the supported formats.
+    ///
+    /// Only available with the `test_utils` feature.
+    pub fn test_pattern<A, B>(
+        allocator: &mut A,
+        size: impl Into<Size<i32, BufferCoords>>,
+        fourcc: Fourcc,
+        pattern: TestPattern,
+    ) -> Result<Dmabuf, TestPatternError>
+    where
+        A: Allocator<B>,
+        A::Error: Send + Sync + 'static,
+        B: Buffer + AsDmabuf,
+        B::Error: std::error::Error + Send + Sync + 'static,
+    {
+        let size = size.into();
+        let buffer = allocator
+            .create_buffer(size.w as u32, size.h as u32, fourcc, &[Modifier::Linear])
+            .map_err(|err| TestPatternError::Allocation(Box::new(err)))?;
+        let dmabuf = buffer
+            .export()
+            .map_err(|err| TestPatternError::Export(Box::new(err)))?;
+        dmabuf.fill_test_pattern(&pattern)?;
+        Ok(dmabuf)
+    }
+
+    /// Overwrite the contents of this buffer with a deterministic pattern
+    ///
+    /// Only single-plane buffers with a [linear](Modifier::Linear) layout and one of the formats
+    /// [`Fourcc::Argb8888`], [`Fourcc::Xrgb8888`], [`Fourcc::Abgr8888`] or [`Fourcc::Xbgr8888`]
+    /// can be filled. The alpha channel of the formats without alpha is left undefined.
+    ///
+    /// Only available with the `test_utils` feature.
+    pub fn fill_test_pattern(&self, pattern: &TestPattern) -> Result<(), DmabufError> {
+        // byte positions of red, green, blue and alpha in memory
+        let order = match self.0.format {
+            Fourcc::Argb8888 | Fourcc::Xrgb8888 => [2, 1, 0, 3],
+            Fourcc::Abgr8888 | Fourcc::Xbgr8888 => [0, 1, 2, 3],
+            format => return Err(DmabufError::UnsupportedFormat(format)),
+        };
+        if self.num_planes() != 1 {
+            return Err(DmabufError::MultiPlanar(self.num_planes()));
+        }
+        let plane = &self.0.planes[0];
+        if plane.modifier != Modifier::Linear {
+            return Err(DmabufError::NotLinear(plane.modifier));
+        }
+
+        let width = self.0.size.w.max(0) as usize;
+        let height = self.0.size.h.max(0) as usize;
+        let row_size = width * 4;
+        let stride = plane.stride as usize;
+        if stride < row_size {
+            return Err(DmabufError::Validation(DmabufValidationError::StrideTooSmall {
+                stride: plane.stride,
+                min_stride: row_size as u32,
+            }));
+        }
+        if row_size == 0 || height == 0 {
+            return Ok(());
+        }
+        let offset = plane.offset as usize;
+        let map_size = offset + stride * height;
+
+        let fd = plane.fd.unwrap();
+        let ptr = unsafe {
+            mman::mmap(
+                ptr::null_mut(),
+                map_size,
+                mman::ProtFlags::PROT_READ | mman::ProtFlags::PROT_WRITE,
+                mman::MapFlags::MAP_SHARED,
+                fd,
+                0,
+            )
+        }
+        .map_err(|err| DmabufError::Map(err.into()))?;
+
+        let _ = unsafe { ioctl::dma_buf_sync(fd, &ioctl::DMA_BUF_SYNC_WRITE_START) };
+        // SAFETY: the mapping is at least `map_size` bytes long and only written while it is alive
+        let data = unsafe { std::slice::from_raw_parts_mut(ptr as *mut u8, map_size) };
+        for y in 0..height {
+            let row = &mut data[offset + y * stride..offset + y * stride + row_size];
+            for (x, pixel) in row.chunks_exact_mut(4).enumerate() {
+                let color = pattern.pixel(x as u32, y as u32, width as u32);
+                for (channel, position) in order.iter().enumerate() {
+                    pixel[*position] = color[channel];
+                }
+            }
+        }
+        let _ = unsafe { ioctl::dma_buf_sync(fd, &ioctl::DMA_BUF_SYNC_WRITE_END) };
+
+        let _ = unsafe { mman::munmap(ptr, map_size) };
+        Ok(())
+    }
+}
+
+// see linux/dma-buf.h
+mod ioctl {
+    const DMA_BUF_SYNC_READ: u64 = 1 << 0;
+    pub const DMA_BUF_SYNC_START: u64 = DMA_BUF_SYNC_READ;
+    pub const DMA_BUF_SYNC_END: u64 = (1 << 2) | DMA_BUF_SYNC_READ;
+    #[cfg(feature = "test_utils")]
+    const DMA_BUF_SYNC_WRITE: u64 = 2 << 0;
+    #[cfg(feature = "test_utils")]
+    pub const DMA_BUF_SYNC_WRITE_START: u64 = DMA_BUF_SYNC_WRITE;
+    #[cfg(feature = "test_utils")]
+    pub const DMA_BUF_SYNC_WRITE_END: u64 = (1 << 2) | DMA_BUF_SYNC_WRITE;
+    nix::ioctl_write_ptr!(dma_buf_sync, b'b', 0, u64);
 }
 
 impl WeakDmabuf {
@@ -1,6 +1,7 @@
 //! Format info tables for DRM formats.
 //!
-//! This module provides three functions, [`get_opaque`], [`has_alpha`] and [`get_bpp`].
+//! This module provides functions to query properties of formats, like [`get_opaque`], [`has_alpha`],
+//! [`get_bpp`], [`get_depth`], [`get_plane_count`] and [`get_bit_depth`].
 //!
 //! [`get_opaque`] returns the opaque alternative of a DRM format with an alpha channel.
 //!
@@ -36,6 +37,26 @@
 //! assert_eq!(get_depth(Fourcc::Argb8888), Some(32));
 //! assert_eq!(get_depth(Fourcc::Xrgb8888), Some(24));
 //! ```
+//!
+//! [`get_plane_count`] returns the number of planes a buffer of the format consists of.
+//!
+//! ```
+//! # use smithay::backend::allocator::Fourcc;
+//! # use smithay::backend::allocator::format::get_plane_count;
+//! assert_eq!(get_plane_count(Fourcc::Argb8888), Some(1));
+//! assert_eq!(get_plane_count(Fourcc::Nv12), Some(2));
+//! assert_eq!(get_plane_count(Fourcc::Yuv420), Some(3));
+//! ```
+//!
+//! [`get_bit_depth`] returns the precision of the color channels of a format.
+//!
+//! ```
+//! # use smithay::backend::allocator::Fourcc;
+//! # use smithay::backend::allocator::format::{get_bit_depth, BitDepth};
+//! assert_eq!(get_bit_depth(Fourcc::Argb8888), Some(BitDepth::Bits8));
+//! assert_eq!(get_bit_depth(Fourcc::Xrgb2101010), Some(BitDepth::Bits10));
+//! assert_eq!(get_bit_depth(Fourcc::P010), Some(BitDepth::Bits10));
+//! ```
 
 /// Macro to generate table lookup functions for formats.
 ///
@@ -115,6 +136,192 @@ macro_rules! format_tables {
     };
 }
 
+/// Returns the number of planes of the specified format.
+///
+/// This does not account for auxiliary planes, that might be required by vendor-specific modifiers.
+///
+/// Unknown formats will always return [`None`].
+pub const fn get_plane_count(fourcc: crate::backend::allocator::Fourcc) -> Option<usize> {
+    use crate::backend::allocator::Fourcc;
+
+    match fourcc {
+        // packed YUV
+        Fourcc::Yuyv | Fourcc::Yvyu | Fourcc::Uyvy | Fourcc::Vyuy | Fourcc::Ayuv => Some(1),
+        // two plane YUV
+        Fourcc::Nv12
+        | Fourcc::Nv21
+        | Fourcc::Nv16
+        | Fourcc::Nv61
+        | Fourcc::Nv24
+        | Fourcc::Nv42
+        | Fourcc::P010
+        | Fourcc::P012
+        | Fourcc::P016
+        | Fourcc::P210 => Some(2),
+        // three plane YUV
+        Fourcc::Yuv410
+        | Fourcc::Yvu410
+        | Fourcc::Yuv411
+        | Fourcc::Yvu411
+        | Fourcc::Yuv420
+        | Fourcc::Yvu420
+        | Fourcc::Yuv422
+        | Fourcc::Yvu422
+        | Fourcc::Yuv444
+        | Fourcc::Yvu444 => Some(3),
+        // everything in the format tables is a packed single plane format
+        x if get_bpp(x).is_some() => Some(1),
+        _ => None,
+    }
+}
+
+/// Returns the subsampling of the specified plane of a format.
+///
+/// The horizontal and vertical factors, the width and height of the buffer are divided by
+/// (rounding up) to get the dimensions of the plane. The first plane of every format, containing
+/// the luma or RGB data, is not subsampled, so it is always `(1, 1)`. The chroma planes of
+/// 4:2:0 formats like [`Nv12`](crate::backend::allocator::Fourcc::Nv12) are subsampled with `(2, 2)`.
+/// Packed formats consist of a single plane and are not subsampled, even if their chroma is.
+///
+/// Unknown formats and planes not part of the format will always return [`None`].
+pub const fn get_plane_subsampling(
+    fourcc: crate::backend::allocator::Fourcc,
+    plane: usize,
+) -> Option<(u32, u32)> {
+    use crate::backend::allocator::Fourcc;
+
+    match get_plane_count(fourcc) {
+        Some(count) if plane < count => {}
+        _ => return None,
+    }
+    if plane == 0 {
+        return Some((1, 1));
+    }
+
+    match fourcc {
+        // 4:4:4
+        Fourcc::Nv24 | Fourcc::Nv42 | Fourcc::Yuv444 | Fourcc::Yvu444 => Some((1, 1)),
+        // 4:2:2
+        Fourcc::Nv16 | Fourcc::Nv61 | Fourcc::P210 | Fourcc::Yuv422 | Fourcc::Yvu422 => Some((2, 1)),
+        // 4:2:0
+        Fourcc::Nv12
+        | Fourcc::Nv21
+        | Fourcc::P010
+        | Fourcc::P012
+        | Fourcc::P016
+        | Fourcc::Yuv420
+        | Fourcc::Yvu420 => Some((2, 2)),
+        // 4:1:1
+        Fourcc::Yuv411 | Fourcc::Yvu411 => Some((4, 1)),
+        // 4:1:0
+        Fourcc::Yuv410 | Fourcc::Yvu410 => Some((4, 4)),
+        _ => None,
+    }
+}
+
+/// Precision of the color channels of a format
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BitDepth {
+    /// 8 bits per channel
+    Bits8,
+    /// 10 bits per channel
+    Bits10,
+    /// 12 bits per channel
+    Bits12,
+    /// 16 bits per channel
+    Bits16,
+    /// 16 bit floating point per channel
+    Float16,
+}
+
+impl BitDepth {
+    /// Returns the number of bits per channel
+    pub const fn bits(&self) -> u32 {
+        match self {
+            BitDepth::Bits8 => 8,
+            BitDepth::Bits10 => 10,
+            BitDepth::Bits12 => 12,
+            BitDepth::Bits16 | BitDepth::Float16 => 16,
+        }
+    }
+
+    /// Returns true, if the channels are stored as floating point values
+    pub const fn is_float(&self) -> bool {
+        matches!(self, BitDepth::Float16)
+    }
+}
+
+/// Returns the precision of the color channels of the specified format.
+///
+/// This does not include the precision of the alpha channel, which might be lower,
+/// e.g. for [`Argb2101010`](crate::backend::allocator::Fourcc::Argb2101010).
+///
+/// Unknown formats and formats with less than 8 bits per channel, or with different bits per
+/// color channel, like [`Rgb565`](crate::backend::allocator::Fourcc::Rgb565), will always return [`None`].
+pub const fn get_bit_depth(fourcc: crate::backend::allocator::Fourcc) -> Option<BitDepth> {
+    use crate::backend::allocator::Fourcc;
+
+    match fourcc {
+        // 8-bit RGB
+        Fourcc::R8
+        | Fourcc::Rg88
+        | Fourcc::Gr88
+        | Fourcc::Rgb888
+        | Fourcc::Bgr888
+        | Fourcc::Argb8888
+        | Fourcc::Xrgb8888
+        | Fourcc::Abgr8888
+        | Fourcc::Xbgr8888
+        | Fourcc::Rgba8888
+        | Fourcc::Rgbx8888
+        | Fourcc::Bgra8888
+        | Fourcc::Bgrx8888 => Some(BitDepth::Bits8),
+        // 8-bit YUV
+        Fourcc::Yuyv
+        | Fourcc::Yvyu
+        | Fourcc::Uyvy
+        | Fourcc::Vyuy
+        | Fourcc::Ayuv
+        | Fourcc::Nv12
+        | Fourcc::Nv21
+        | Fourcc::Nv16
+        | Fourcc::Nv61
+        | Fourcc::Nv24
+        | Fourcc::Nv42
+        | Fourcc::Yuv410
+        | Fourcc::Yvu410
+        | Fourcc::Yuv411
+        | Fourcc::Yvu411
+        | Fourcc::Yuv420
+        | Fourcc::Yvu420
+        | Fourcc::Yuv422
+        | Fourcc::Yvu422
+        | Fourcc::Yuv444
+        | Fourcc::Yvu444 => Some(BitDepth::Bits8),
+        // 10-bit RGB
+        Fourcc::Argb2101010
+        | Fourcc::Xrgb2101010
+        | Fourcc::Abgr2101010
+        | Fourcc::Xbgr2101010
+        | Fourcc::Rgba1010102
+        | Fourcc::Rgbx1010102
+        | Fourcc::Bgra1010102
+        | Fourcc::Bgrx1010102
+        | Fourcc::Axbxgxrx106106106106 => Some(BitDepth::Bits10),
+        // 10-bit YUV
+        Fourcc::P010 | Fourcc::P210 => Some(BitDepth::Bits10),
+        // 12-bit YUV
+        Fourcc::P012 => Some(BitDepth::Bits12),
+        // 16-bit
+        Fourcc::R16 | Fourcc::Rg1616 | Fourcc::Gr1616 | Fourcc::P016 => Some(BitDepth::Bits16),
+        // half float
+        Fourcc::Argb16161616f | Fourcc::Xrgb16161616f | Fourcc::Abgr16161616f | Fourcc::Xbgr16161616f => {
+            Some(BitDepth::Float16)
+        }
+        _ => None,
+    }
+}
+
 format_tables! {
     // 8-bit bpp Red
     R8 { alpha: false, bpp: 8, depth: 8 },
@@ -348,7 +555,11 @@ format_tables! {
 
 #[cfg(test)]
 mod tests {
-    use super::{_impl_formats, get_bpp, get_depth, get_opaque, has_alpha};
+    use super::{
+        _impl_formats, get_bit_depth, get_bpp, get_depth, get_opaque, get_plane_count, get_plane_subsampling,
+        has_alpha,
+    };
+    use crate::backend::allocator::Fourcc;
 
     /// Tests that opaque alternatives are not the same as the variant with alpha.
     #[test]
@@ -443,4 +654,67 @@ mod tests {
             );
         }
     }
+
+    // Every format in the tables is a packed format, consisting of exactly one plane
+    #[test]
+    fn table_formats_are_single_plane() {
+        for &format in _impl_formats() {
+            assert_eq!(
+                get_plane_count(format),
+                Some(1),
+                "{} is not reported as a single plane format",
+                format
+            );
+        }
+    }
+
+    // The opaque alternative of a format keeps the precision of the color channels
+    #[test]
+    fn opaque_has_same_bit_depth() {
+        for &format in _impl_formats() {
+            if let Some(opaque) = get_opaque(format) {
+                assert_eq!(
+                    get_bit_depth(format),
+                    get_bit_depth(opaque),
+                    "{} and its opaque alternative {} have a different bit depth",
+                    format,
+                    opaque
+                );
+            }
+        }
+    }
+
+    // Every plane of a known format has a subsampling, the first one is never subsampled
+    #[test]
+    fn planes_have_subsampling() {
+        let planar = [
+            Fourcc::Nv12,
+            Fourcc::Nv16,
+            Fourcc::Nv24,
+            Fourcc::P010,
+            Fourcc::Yuv410,
+            Fourcc::Yuv411,
+            Fourcc::Yuv420,
+            Fourcc::Yvu422,
+            Fourcc::Yuyv,
+        ];
+        for &format in _impl_formats().iter().chain(planar.iter()) {
+            let count = get_plane_count(format).unwrap();
+            assert_eq!(get_plane_subsampling(format, 0), Some((1, 1)));
+            for plane in 1..count {
+                assert!(
+                    get_plane_subsampling(format, plane).is_some(),
+                    "plane {} of {} has no subsampling",
+                    plane,
+                    format
+                );
+            }
+            assert_eq!(get_plane_subsampling(format, count), None);
+        }
+
+        assert_eq!(get_plane_subsampling(Fourcc::Nv12, 1), Some((2, 2)));
+        assert_eq!(get_plane_subsampling(Fourcc::Yuv420, 2), Some((2, 2)));
+        assert_eq!(get_plane_subsampling(Fourcc::Nv16, 1), Some((2, 1)));
+        assert_eq!(get_plane_subsampling(Fourcc::Yuv444, 2), Some((1, 1)));
+    }
 }
@@ -3,6 +3,8 @@
 //! The re-exported [`GbmDevice`](gbm::Device) implements the [`Allocator`](super::Allocator) trait
 //! and [`GbmBuffer`](gbm::BufferObject) satisfies the [`Buffer`](super::Buffer) trait while also allowing
 //! conversions to and from [dmabufs](super::dmabuf).
+//!
+//! Buffers, that need to be cleared before use, can be allocated with [`create_buffer_zeroed`].
 
 use super::{
     dmabuf::{AsDmabuf, Dmabuf, DmabufFlags, MAX_PLANES},
@@ -39,6 +41,40 @@ impl<A: AsRawFd + 'static, T> Allocator<GbmBuffer<T>> for GbmDevice<A> {
     }
 }
 
+/// Allocate a new buffer, that is guaranteed to be cleared to zero
+///
+/// Allocators may hand out memory, that was previously used by other processes.
+/// For sensitive content this function clears the buffer after allocation, before it is returned.
+///
+/// *Note*: This maps the buffer into memory and writes every byte of it. For tiled buffers
+/// the driver has to copy the contents through a linear staging buffer. This is expensive,
+/// so only use this for buffers, whose contents are not overwritten completely before being displayed.
+pub fn create_buffer_zeroed<A: AsRawFd + 'static, T: 'static>(
+    device: &mut GbmDevice<A>,
+    width: u32,
+    height: u32,
+    fourcc: Fourcc,
+    modifiers: &[Modifier],
+) -> std::io::Result<GbmBuffer<T>> {
+    let mut buffer: GbmBuffer<T> = device.create_buffer(width, height, fourcc, modifiers)?;
+    let planes = buffer
+        .plane_count()
+        .map_err(|err| std::io::Error::new(std::io::ErrorKind::Other, err))?;
+    if planes > 1 {
+        // gbm only allows mapping the first plane
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "Zeroing multi-planar buffers is unsupported",
+        ));
+    }
+    buffer
+        .map_mut(device, 0, 0, width, height, |mapping| {
+            mapping.buffer_mut().fill(0)
+        })
+        .map_err(|err| std::io::Error::new(std::io::ErrorKind::Other, err))??;
+    Ok(buffer)
+}
+
 impl<T> Buffer for GbmBuffer<T> {
     fn size(&self) -> Size<i32, BufferCoords> {
         (
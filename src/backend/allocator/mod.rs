@@ -14,6 +14,7 @@
 //!
 //! Helpers:
 //! - [`Swapchain`] to help with buffer management for framebuffers
+//! - [`AsyncAllocator`] to move allocations off the calling thread
 
 pub mod dmabuf;
 #[cfg(feature = "backend_drm")]
@@ -25,6 +26,7 @@ pub mod gbm;
 pub mod vulkan;
 
 mod swapchain;
+mod threaded;
 use std::{
     cell::RefCell,
     rc::Rc,
@@ -33,6 +35,7 @@ use std::{
 
 use crate::utils::{Buffer as BufferCoords, Size};
 pub use swapchain::{Slot, Swapchain};
+pub use threaded::{Allocation, AllocationId, AsyncAllocator};
 
 pub use drm_fourcc::{
     DrmFormat as Format, DrmFourcc as Fourcc, DrmModifier as Modifier, DrmVendor as Vendor,
@@ -51,6 +54,17 @@ pub trait Buffer {
     }
     /// Size of the two-dimensional buffer
     fn size(&self) -> Size<i32, BufferCoords>;
+    /// Aspect ratio (width divided by height) of the two-dimensional buffer
+    ///
+    /// Returns `None` for buffers with a height of zero.
+    fn aspect_ratio(&self) -> Option<f64> {
+        let size = self.size();
+        if size.h == 0 {
+            None
+        } else {
+            Some(size.w as f64 / size.h as f64)
+        }
+    }
     /// Pixel format of the buffer
     fn format(&self) -> Format;
 }
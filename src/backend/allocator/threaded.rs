@@ -0,0 +1,147 @@
+//! Allocation of buffers on a dedicated thread.
+//!
+//! Some allocators (most notably gbm) may block for a noticeable amount of time
+//! when allocating large buffers. The [`AsyncAllocator`] moves these allocations
+//! off the calling thread and delivers the results through a [`calloop`] channel,
+//! that can be inserted into the event loop of the compositor.
+
+use std::{
+    fmt,
+    sync::mpsc,
+    thread::{self, JoinHandle},
+};
+
+use calloop::channel::{self, Channel};
+
+use super::{Allocator, Buffer, Fourcc, Modifier};
+
+/// Identifier of an allocation requested from an [`AsyncAllocator`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct AllocationId(u64);
+
+/// Result of an allocation carried out by an [`AsyncAllocator`]
+#[derive(Debug)]
+pub struct Allocation<B, E> {
+    /// Id of the request, as returned by [`AsyncAllocator::create_buffer`]
+    pub id: AllocationId,
+    /// Result of the allocation as returned by the wrapped [`Allocator`]
+    pub result: Result<B, E>,
+}
+
+struct Request {
+    id: AllocationId,
+    width: u32,
+    height: u32,
+    fourcc: Fourcc,
+    modifiers: Vec<Modifier>,
+}
+
+/// Adapter running the allocations of any [`Allocator`] on a dedicated thread.
+///
+/// Allocations are requested with [`AsyncAllocator::create_buffer`], which returns immediately.
+/// The resulting buffers (or errors) are send through the [`Channel`] returned by
+/// [`AsyncAllocator::new`], which needs to be inserted into your event loop.
+///
+/// ## Ordering
+///
+/// All allocations are carried out by a single worker thread in the order they were requested.
+/// Results are therefor always delivered in the same order as the calls to `create_buffer`,
+/// even if multiple allocations are in flight at the same time. The returned [`AllocationId`]s
+/// are monotonically increasing and can be used to match results to requests.
+///
+/// Dropping the `AsyncAllocator` blocks until all pending allocations are finished.
+/// Results of allocations finished after the [`Channel`] was dropped are discarded.
+pub struct AsyncAllocator<B, E> {
+    requests: Option<mpsc::Sender<Request>>,
+    thread: Option<JoinHandle<()>>,
+    next_id: u64,
+    _types: std::marker::PhantomData<fn() -> (B, E)>,
+}
+
+impl<B, E> fmt::Debug for AsyncAllocator<B, E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AsyncAllocator")
+            .field("thread", &self.thread)
+            .field("next_id", &self.next_id)
+            .finish()
+    }
+}
+
+impl<B, E> AsyncAllocator<B, E>
+where
+    B: Buffer + Send + 'static,
+    E: std::error::Error + Send + 'static,
+{
+    /// Create a new `AsyncAllocator` moving the given allocator onto a new worker thread.
+    ///
+    /// Returns the adapter and a [`Channel`] delivering the results of all allocations.
+    pub fn new<A>(mut allocator: A) -> std::io::Result<(AsyncAllocator<B, E>, Channel<Allocation<B, E>>)>
+    where
+        A: Allocator<B, Error = E> + Send + 'static,
+    {
+        let (requests, receiver) = mpsc::channel::<Request>();
+        let (sender, channel) = channel::channel();
+
+        let thread = thread::Builder::new()
+            .name("smithay-allocator".into())
+            .spawn(move || {
+                for request in receiver {
+                    let result = allocator.create_buffer(
+                        request.width,
+                        request.height,
+                        request.fourcc,
+                        &request.modifiers,
+                    );
+                    // The receiving end might already be gone, in which case nobody is interested anymore.
+                    let _ = sender.send(Allocation {
+                        id: request.id,
+                        result,
+                    });
+                }
+            })?;
+
+        Ok((
+            AsyncAllocator {
+                requests: Some(requests),
+                thread: Some(thread),
+                next_id: 0,
+                _types: std::marker::PhantomData,
+            },
+            channel,
+        ))
+    }
+
+    /// Request a buffer with the given dimensions and pixel format.
+    ///
+    /// The resulting buffer will be delivered through the channel
+    /// tagged with the returned [`AllocationId`].
+    pub fn create_buffer(
+        &mut self,
+        width: u32,
+        height: u32,
+        fourcc: Fourcc,
+        modifiers: &[Modifier],
+    ) -> AllocationId {
+        let id = AllocationId(self.next_id);
+        self.next_id += 1;
+        // the worker thread only quits once `requests` is dropped, so this cannot fail
+        let _ = self.requests.as_ref().unwrap().send(Request {
+            id,
+            width,
+            height,
+            fourcc,
+            modifiers: modifiers.to_vec(),
+        });
+        id
+    }
+}
+
+impl<B, E> Drop for AsyncAllocator<B, E> {
+    fn drop(&mut self) {
+        // close the request queue, so the worker finishes all pending allocations and quits
+        self.requests.take();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
@@ -4,7 +4,7 @@ use std::io;
 use std::os::unix::io::{AsRawFd, RawFd};
 use std::path::PathBuf;
 use std::sync::{atomic::AtomicBool, Arc};
-use std::time::{Instant, SystemTime};
+use std::time::{Duration, Instant, SystemTime};
 
 use calloop::{EventSource, Interest, Poll, PostAction, Readiness, Token, TokenFactory};
 use drm::control::{connector, crtc, Device as ControlDevice, Event, Mode, ResourceHandles};
@@ -337,6 +337,81 @@ pub enum Time {
     Realtime(SystemTime),
 }
 
+impl Time {
+    /// Returns the timestamp as the duration since the epoch of its clock
+    pub fn as_duration(&self) -> Duration {
+        match self {
+            // see `process_events` on how monotonic timestamps are created
+            Time::Monotonic(instant) => instant.duration_since(unsafe { std::mem::zeroed::<Instant>() }),
+            Time::Realtime(time) => time.duration_since(SystemTime::UNIX_EPOCH).unwrap_or_default(),
+        }
+    }
+}
+
+bitflags::bitflags! {
+    /// Flags describing how a frame was presented, matching the kinds of `wp_presentation_feedback`
+    pub struct PresentationFlags: u32 {
+        /// The presentation was synchronized to the vertical retrace of the display
+        const VSYNC = 0x1;
+        /// The timestamp was provided by the display hardware
+        const HW_CLOCK = 0x2;
+        /// The display hardware signalled the completion of the presentation
+        const HW_COMPLETION = 0x4;
+        /// The client buffer was scanned out directly without any copy
+        const ZERO_COPY = 0x8;
+    }
+}
+
+/// Presentation timing of a frame, as needed to implement `wp_presentation`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PresentationFeedback {
+    /// Seconds of the presentation timestamp
+    pub tv_sec: u64,
+    /// Nanoseconds of the presentation timestamp
+    pub tv_nsec: u32,
+    /// Duration of a refresh cycle of the display in nanoseconds, or zero if unknown
+    pub refresh_ns: u32,
+    /// Sequence number of the presented frame
+    pub seq: u64,
+    /// Flags describing the presentation
+    pub flags: PresentationFlags,
+}
+
+impl EventMetadata {
+    /// Create the presentation feedback of a page-flip event
+    ///
+    /// - `mode` is the mode of the crtc the event was received for, used to calculate the refresh rate
+    /// - `zero_copy` should be set, if the presented frame directly scanned out a client buffer,
+    ///   which only the caller knows.
+    pub fn presentation_feedback(&self, mode: Option<Mode>, zero_copy: bool) -> PresentationFeedback {
+        let duration = self.time.as_duration();
+        let refresh_ns = mode
+            .map(|mode| {
+                let info: drm_ffi::drm_mode_modeinfo = mode.into();
+                if info.clock == 0 {
+                    0
+                } else {
+                    // clock is in kHz
+                    (info.htotal as u64 * info.vtotal as u64 * 1_000_000 / info.clock as u64) as u32
+                }
+            })
+            .unwrap_or(0);
+        // page-flip events are always vsync'ed and timestamped by the kernel at completion
+        let mut flags =
+            PresentationFlags::VSYNC | PresentationFlags::HW_CLOCK | PresentationFlags::HW_COMPLETION;
+        if zero_copy {
+            flags |= PresentationFlags::ZERO_COPY;
+        }
+        PresentationFeedback {
+            tv_sec: duration.as_secs(),
+            tv_nsec: duration.subsec_nanos(),
+            refresh_ns,
+            seq: self.sequence as u64,
+            flags,
+        }
+    }
+}
+
 impl<A> EventSource for DrmDevice<A>
 where
     A: AsRawFd + 'static,
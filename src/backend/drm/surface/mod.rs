@@ -1,9 +1,10 @@
 #[cfg(feature = "backend_session")]
 use std::cell::RefCell;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::convert::TryFrom;
 use std::os::unix::io::{AsRawFd, RawFd};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use drm::control::{connector, crtc, framebuffer, plane, property, Device as ControlDevice, Mode};
 use drm::{Device as BasicDevice, DriverCapability};
@@ -140,6 +141,45 @@ impl<A: AsRawFd + 'static> DrmSurface<A> {
         }
     }
 
+    /// Returns the time between two vblanks of the [current mode](DrmSurface::current_mode)
+    ///
+    /// Returns `None`, if no valid mode is set.
+    pub fn refresh_interval(&self) -> Option<Duration> {
+        let mode = self.current_mode();
+        let (_, _, htotal) = mode.hsync();
+        let (_, _, vtotal) = mode.vsync();
+        // the clock is given in kHz
+        let clock = mode.clock() as u64;
+        let pixels = htotal as u64 * vtotal as u64;
+        if clock == 0 || pixels == 0 {
+            return None;
+        }
+        Some(Duration::from_nanos(pixels * 1_000_000 / clock))
+    }
+
+    /// Returns the vblank closest to `target`, at which a new frame can still be presented
+    ///
+    /// The vblanks are extrapolated from `last_vblank`, e.g. the [time](super::DrmEventMetadata::time)
+    /// of the last page-flip event, and the [refresh interval](DrmSurface::refresh_interval)
+    /// of the current mode. Vblanks that already passed are never returned, so the result
+    /// may be later than `target`.
+    ///
+    /// A page flip always completes at the next vblank, so to present a frame at the returned time,
+    /// call [`DrmSurface::page_flip`] during the refresh interval preceding it,
+    /// e.g. by delaying rendering with a timer. This allows animations to target specific refresh
+    /// cycles instead of presenting as soon as possible.
+    ///
+    /// Returns `None`, if no valid mode is set.
+    pub fn vblank_for(&self, last_vblank: Instant, target: Instant) -> Option<Instant> {
+        let interval = self.refresh_interval()?.as_nanos();
+        let elapsed = |time: Instant| time.saturating_duration_since(last_vblank).as_nanos();
+
+        // round to the closest vblank, but use at least the next one from now on
+        let frames = (elapsed(target) + interval / 2) / interval;
+        let frames = frames.max(elapsed(Instant::now()) / interval + 1);
+        Some(last_vblank + Duration::from_nanos((frames * interval) as u64))
+    }
+
     /// Tries to set a new [`Mode`](drm::control::Mode)
     /// to be used after the next commit.
     ///
@@ -390,6 +430,21 @@ impl<A: AsRawFd + 'static> DrmSurface<A> {
         planes(self, &self.crtc, self.has_universal_planes)
     }
 
+    /// Returns the supported pixel formats of every plane available for this surface
+    ///
+    /// This includes the cursor and overlay planes, see [`DrmSurface::supported_formats`].
+    /// The formats of the overlay planes can for example be used to add scanout tranches
+    /// to a [`DmabufFeedbackBuilder`](crate::wayland::dmabuf::DmabufFeedbackBuilder),
+    /// so clients allocate buffers suitable for direct scanout.
+    pub fn plane_formats(&self) -> Result<HashMap<plane::Handle, HashSet<Format>>, Error> {
+        let planes = self.planes()?;
+        std::iter::once(planes.primary)
+            .chain(planes.cursor)
+            .chain(planes.overlay)
+            .map(|plane| Ok((plane, self.supported_formats(plane)?)))
+            .collect()
+    }
+
     /// Tests if a framebuffer can be used with this surface.
     ///
     /// # Arguments
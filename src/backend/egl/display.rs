@@ -502,6 +502,27 @@ impl EGLDisplay {
 
     /// Imports a [`Dmabuf`] as an [`EGLImage`]
     pub fn create_image_from_dmabuf(&self, dmabuf: &Dmabuf) -> Result<EGLImage, Error> {
+        self.create_image_from_dmabuf_with_colorspace(dmabuf, false)
+    }
+
+    /// Imports a [`Dmabuf`] as an [`EGLImage`] with sRGB encoding
+    ///
+    /// GL interprets the contents of the resulting image as sRGB encoded, values are decoded to linear
+    /// when being read and encoded when being written, which includes blending.
+    ///
+    /// Requires `EGL_EXT_image_gl_colorspace`.
+    pub fn create_srgb_image_from_dmabuf(&self, dmabuf: &Dmabuf) -> Result<EGLImage, Error> {
+        if !self.extensions.iter().any(|s| s == "EGL_EXT_image_gl_colorspace") {
+            return Err(Error::EglExtensionNotSupported(&["EGL_EXT_image_gl_colorspace"]));
+        }
+        self.create_image_from_dmabuf_with_colorspace(dmabuf, true)
+    }
+
+    fn create_image_from_dmabuf_with_colorspace(
+        &self,
+        dmabuf: &Dmabuf,
+        srgb: bool,
+    ) -> Result<EGLImage, Error> {
         if !self.extensions.iter().any(|s| s == "EGL_KHR_image_base")
             && !self
                 .extensions
@@ -535,6 +556,12 @@ impl EGLDisplay {
             ffi::egl::LINUX_DRM_FOURCC_EXT as i32,
             dmabuf.format().code as u32 as i32,
         ]);
+        if srgb {
+            out.extend(&[
+                ffi::egl::GL_COLORSPACE as i32,
+                ffi::egl::GL_COLORSPACE_SRGB as i32,
+            ]);
+        }
 
         let names = [
             [
@@ -1,10 +1,11 @@
 //! Implementation of the rendering traits using OpenGL ES 2
 
-use cgmath::{prelude::*, Matrix3, Vector2};
+use cgmath::{prelude::*, Matrix3, Vector2, Vector3};
 use core::slice;
 use std::{
     borrow::Cow,
-    collections::HashSet,
+    cell::{Cell, RefCell},
+    collections::{HashSet, VecDeque},
     convert::TryFrom,
     ffi::CStr,
     fmt,
@@ -15,28 +16,32 @@ use std::{
         atomic::{AtomicPtr, Ordering},
         mpsc::{channel, Receiver, Sender},
     },
+    time::Duration,
 };
 
 #[cfg(feature = "wayland_frontend")]
-use std::{cell::RefCell, collections::HashMap};
+use std::collections::HashMap;
 
 mod shaders;
+mod sync;
 mod version;
 
+pub use sync::SyncPoint;
+
 use super::{
-    Bind, ExportDma, ExportMem, Frame, ImportDma, ImportMem, Offscreen, Renderer, Texture, TextureFilter,
-    TextureMapping, Unbind,
+    AlphaMode, Bind, ExportDma, ExportMem, Frame, ImportDma, ImportMem, Offscreen, Renderer, Texture,
+    TextureFilter, TextureMapping, Unbind,
 };
 use crate::backend::allocator::{
     dmabuf::{Dmabuf, WeakDmabuf},
-    Format,
+    Format, Fourcc, Modifier,
 };
 use crate::backend::egl::{
     ffi::egl::{self as ffi_egl, types::EGLImage},
-    EGLContext, EGLSurface, MakeCurrentError,
+    EGLContext, EGLDisplay, EGLError, EGLSurface, MakeCurrentError,
 };
 use crate::backend::SwapBuffersError;
-use crate::utils::{Buffer as BufferCoord, Physical, Rectangle, Size, Transform};
+use crate::utils::{Buffer as BufferCoord, Physical, Point, Rectangle, Size, Transform};
 
 #[cfg(all(feature = "wayland_frontend", feature = "use_system_lib"))]
 use super::ImportEgl;
@@ -63,6 +68,11 @@ struct Gles2TexProgram {
     uniform_tex_matrix: ffi::types::GLint,
     uniform_matrix: ffi::types::GLint,
     uniform_alpha: ffi::types::GLint,
+    uniform_tint: ffi::types::GLint,
+    uniform_decode_srgb: ffi::types::GLint,
+    uniform_apply_color_matrix: ffi::types::GLint,
+    uniform_color_matrix: ffi::types::GLint,
+    uniform_color_offset: ffi::types::GLint,
     attrib_vert: ffi::types::GLint,
     attrib_vert_position: ffi::types::GLint,
 }
@@ -76,6 +86,45 @@ struct Gles2SolidProgram {
     attrib_position: ffi::types::GLint,
 }
 
+#[derive(Debug, Clone)]
+struct Gles2BorderProgram {
+    program: ffi::types::GLuint,
+    uniform_matrix: ffi::types::GLint,
+    uniform_color: ffi::types::GLint,
+    uniform_rect: ffi::types::GLint,
+    uniform_thickness: ffi::types::GLint,
+    uniform_radius: ffi::types::GLint,
+    attrib_vert: ffi::types::GLint,
+    attrib_position: ffi::types::GLint,
+}
+
+#[derive(Debug, Clone)]
+struct Gles2ShadowProgram {
+    program: ffi::types::GLuint,
+    uniform_matrix: ffi::types::GLint,
+    uniform_color: ffi::types::GLint,
+    uniform_rect: ffi::types::GLint,
+    uniform_sigma: ffi::types::GLint,
+    uniform_radius: ffi::types::GLint,
+    attrib_vert: ffi::types::GLint,
+    attrib_position: ffi::types::GLint,
+}
+
+// Timer queries measuring the gpu time of frames
+#[derive(Debug, Default)]
+struct GpuTimer {
+    // queries of finished frames, whose results are not available yet, oldest first
+    pending: VecDeque<ffi::types::GLuint>,
+    // queries, whose results were read and can be reused
+    free: Vec<ffi::types::GLuint>,
+    // query of the frame currently rendered
+    active: Option<ffi::types::GLuint>,
+    last_frame_time: Option<Duration>,
+}
+
+// Results are usually available one or two frames late, drop older queries if they are not
+const MAX_PENDING_TIMER_QUERIES: usize = 4;
+
 /// A handle to a GLES2 texture
 #[derive(Debug, Clone)]
 pub struct Gles2Texture(Rc<Gles2TextureInternal>);
@@ -103,6 +152,7 @@ impl Gles2Texture {
             is_external: false,
             y_inverted: false,
             size,
+            srgb: false,
             egl_images: None,
             destruction_callback_sender: renderer.destruction_callback_sender.clone(),
         }))
@@ -111,9 +161,26 @@ impl Gles2Texture {
     /// OpenGL texture id of this texture
     ///
     /// This id will become invalid, when the Gles2Texture is dropped and does not transfer ownership.
+    ///
+    /// The id is only valid inside the GL context of the renderer, that created the texture
+    /// (or a context shared with it), see [`Gles2Renderer::with_context`]. Modifying the texture
+    /// through the id, e.g. changing its parameters, may result in rendering issues.
     pub fn tex_id(&self) -> ffi::types::GLuint {
         self.0.texture
     }
+
+    /// OpenGL texture target of this texture
+    ///
+    /// This is either `GL_TEXTURE_2D` or `GL_TEXTURE_EXTERNAL_OES` for textures imported
+    /// from buffers, that can only be sampled as external images.
+    /// The same restrictions as for [`Gles2Texture::tex_id`] apply.
+    pub fn tex_target(&self) -> ffi::types::GLenum {
+        if self.0.is_external {
+            ffi::TEXTURE_EXTERNAL_OES
+        } else {
+            ffi::TEXTURE_2D
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -123,6 +190,8 @@ struct Gles2TextureInternal {
     is_external: bool,
     y_inverted: bool,
     size: Size<i32, BufferCoord>,
+    // contents are decoded from sRGB by GL on sampling
+    srgb: bool,
     egl_images: Option<Vec<EGLImage>>,
     destruction_callback_sender: Sender<CleanupResource>,
 }
@@ -197,6 +266,66 @@ impl Drop for Gles2Mapping {
     }
 }
 
+/// Pending asynchronous copy of a region of a [`Gles2Frame`]
+///
+/// Created by [`Gles2Frame::begin_copy_region`]. The pixels are read into a pixel buffer object
+/// without waiting for the gpu, the ticket can be polled with [`CopyTicket::try_take`] until
+/// the copy has completed.
+#[derive(Debug)]
+pub struct CopyTicket {
+    pbo: ffi::types::GLuint,
+    size: Size<i32, BufferCoord>,
+    sync: SyncPoint,
+    destruction_callback_sender: Sender<CleanupResource>,
+}
+
+impl CopyTicket {
+    /// Size of the copied region
+    pub fn size(&self) -> Size<i32, BufferCoord> {
+        self.size
+    }
+
+    /// Returns true, if the copy has completed and [`CopyTicket::try_take`] will return the pixels
+    pub fn is_ready(&self) -> bool {
+        self.sync.is_signaled()
+    }
+
+    /// Returns the copied pixels, if the copy has completed
+    ///
+    /// Does not block, `Ok(None)` is returned while the gpu is still busy.
+    /// The pixels are in `RGBA8888` format and, like for [`Gles2Mapping`], the rows are
+    /// stored bottom to top. The renderer has to be the one that created the ticket.
+    pub fn try_take(&self, renderer: &mut Gles2Renderer) -> Result<Option<Vec<u8>>, Gles2Error> {
+        if !self.sync.is_signaled() {
+            return Ok(None);
+        }
+        renderer.make_current()?;
+        let len = (self.size.w * self.size.h * 4) as usize;
+        unsafe {
+            renderer.gl.BindBuffer(ffi::PIXEL_PACK_BUFFER, self.pbo);
+            let ptr = renderer
+                .gl
+                .MapBufferRange(ffi::PIXEL_PACK_BUFFER, 0, len as isize, ffi::MAP_READ_BIT);
+            if ptr.is_null() {
+                renderer.gl.BindBuffer(ffi::PIXEL_PACK_BUFFER, 0);
+                return Err(Gles2Error::MappingError);
+            }
+            let data = slice::from_raw_parts(ptr as *const u8, len).to_vec();
+            renderer.gl.UnmapBuffer(ffi::PIXEL_PACK_BUFFER);
+            renderer.gl.BindBuffer(ffi::PIXEL_PACK_BUFFER, 0);
+            Ok(Some(data))
+        }
+    }
+}
+
+impl Drop for CopyTicket {
+    fn drop(&mut self) {
+        let _ = self
+            .destruction_callback_sender
+            .send(CleanupResource::Mapping(self.pbo, ptr::null_mut()));
+    }
+}
+
 #[derive(Debug, Clone)]
 struct Gles2Buffer {
     dmabuf: WeakDmabuf,
@@ -265,6 +394,25 @@ impl Drop for Gles2Target {
     }
 }
 
+// Pooled render target of `Gles2Frame::with_offscreen`
+struct OffscreenTarget {
+    texture: Gles2Texture,
+    fbo: ffi::types::GLuint,
+    format: ffi::types::GLenum,
+    // if the target was used during the current frame
+    used: Cell<bool>,
+}
+
+impl Drop for OffscreenTarget {
+    fn drop(&mut self) {
+        let _ = self
+            .texture
+            .0
+            .destruction_callback_sender
+            .send(CleanupResource::FramebufferObject(self.fbo));
+    }
+}
+
 /// A renderer utilizing OpenGL ES 2
 pub struct Gles2Renderer {
     buffers: Vec<Gles2Buffer>,
@@ -272,6 +420,8 @@ pub struct Gles2Renderer {
     pub(crate) extensions: Vec<String>,
     tex_programs: [Gles2TexProgram; shaders::FRAGMENT_COUNT],
     solid_program: Gles2SolidProgram,
+    border_program: Gles2BorderProgram,
+    shadow_program: Gles2ShadowProgram,
     dmabuf_cache: std::collections::HashMap<WeakDmabuf, Gles2Texture>,
     egl: EGLContext,
     #[cfg(all(feature = "wayland_frontend", feature = "use_system_lib"))]
@@ -286,11 +436,36 @@ pub struct Gles2Renderer {
     min_filter: TextureFilter,
     max_filter: TextureFilter,
     supports_instancing: bool,
+    capabilities: RendererCapabilities,
+    offscreen_targets: Rc<RefCell<Vec<OffscreenTarget>>>,
+    flags: RendererFlags,
+    debug_flags: DebugFlags,
+    debug_frame: usize,
+    context_lost: bool,
+    gpu_timer: Option<GpuTimer>,
+    clear_color: [f32; 4],
     logger_ptr: Option<*mut ::slog::Logger>,
     logger: ::slog::Logger,
     _not_send: *mut (),
 }
 
+/// Limits and optional features of the GL implementation used by a [`Gles2Renderer`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RendererCapabilities {
+    /// Maximum width and height of a texture (`GL_MAX_TEXTURE_SIZE`)
+    pub max_texture_size: i32,
+    /// Maximum amount of texture units accessible by a fragment shader (`GL_MAX_TEXTURE_IMAGE_UNITS`)
+    pub max_texture_units: i32,
+    /// Support for instanced rendering, used to draw multiple damage rectangles in one call
+    pub instancing: bool,
+    /// Support for external textures (`GL_OES_EGL_image_external`)
+    pub external_textures: bool,
+    /// Support for debug output (`GL_KHR_debug`)
+    pub debug_output: bool,
+    /// Support for debug markers (`GL_EXT_debug_marker`)
+    pub debug_marker: bool,
+}
+
 struct RendererId(usize);
 impl Drop for RendererId {
     fn drop(&mut self) {
@@ -298,6 +473,93 @@ impl Drop for RendererId {
     }
 }
 
+/// Blend modes supported by the [`Gles2Frame`]
+///
+/// All modes expect premultiplied alpha, as used by wayland buffers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BlendMode {
+    /// Draw on top of the existing content (default)
+    Over,
+    /// Add the drawn colors to the existing content
+    Add,
+    /// Multiply the drawn colors with the existing content
+    Multiply,
+    /// Inverse multiply the drawn colors with the existing content
+    Screen,
+}
+
+impl Default for BlendMode {
+    fn default() -> Self {
+        BlendMode::Over
+    }
+}
+
+impl BlendMode {
+    fn factors(&self) -> (ffi::types::GLenum, ffi::types::GLenum) {
+        match self {
+            BlendMode::Over => (ffi::ONE, ffi::ONE_MINUS_SRC_ALPHA),
+            BlendMode::Add => (ffi::ONE, ffi::ONE),
+            BlendMode::Multiply => (ffi::DST_COLOR, ffi::ONE_MINUS_SRC_ALPHA),
+            BlendMode::Screen => (ffi::ONE, ffi::ONE_MINUS_SRC_COLOR),
+        }
+    }
+}
+
+bitflags::bitflags! {
+    /// Flags changing the behavior of a [`Gles2Renderer`]
+    ///
+    /// See [`Gles2Renderer::with_flags`].
+    pub struct RendererFlags: u32 {
+        /// Blend in linear color space instead of sRGB encoded values
+        ///
+        /// Translucent content and gradients are blended correctly, which changes the look
+        /// compared to most other compositors and costs additional conversions.
+        /// Render targets need to be sRGB encoded, so GL can decode them for blending and
+        /// encode the result on output. This is taken care of for dmabufs and offscreen buffers,
+        /// [`EGLSurface`]s need to be created with a sRGB colorspace by the caller.
+        /// Contents of imported buffers are decoded from sRGB when sampled.
+        ///
+        /// Requires OpenGL ES 3.0 and `EGL_EXT_image_gl_colorspace`.
+        const LINEAR_BLENDING = 1;
+    }
+}
+
+bitflags::bitflags! {
+    /// Debug flags of the [`Gles2Renderer`]
+    ///
+    /// See [`Gles2Renderer::set_debug_flags`].
+    pub struct DebugFlags: u32 {
+        /// Tint every region drawn during a frame with a semi-transparent color, that changes every frame
+        const TINT_DAMAGE = 1;
+    }
+}
+
+// decode a premultiplied sRGB encoded color, matching the tex shaders
+fn srgb_to_linear(color: [f32; 4]) -> [f32; 4] {
+    let [r, g, b, a] = color;
+    if a == 0.0 {
+        return color;
+    }
+    let decode = |c: f32| {
+        let c = c / a;
+        let c = if c <= 0.04045 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        };
+        c * a
+    };
+    [decode(r), decode(g), decode(b), a]
+}
+
+// colors used for tinting damage, premultiplied
+const DAMAGE_TINTS: [[f32; 4]; 4] = [
+    [0.3, 0.0, 0.0, 0.3],
+    [0.0, 0.3, 0.0, 0.3],
+    [0.0, 0.0, 0.3, 0.3],
+    [0.3, 0.3, 0.0, 0.3],
+];
+
 /// Handle to the currently rendered frame during [`Gles2Renderer::render`](Renderer::render)
 pub struct Gles2Frame {
     current_projection: Matrix3<f32>,
@@ -305,11 +567,35 @@ pub struct Gles2Frame {
     gl: ffi::Gles2,
     tex_programs: [Gles2TexProgram; shaders::FRAGMENT_COUNT],
     solid_program: Gles2SolidProgram,
+    border_program: Gles2BorderProgram,
+    shadow_program: Gles2ShadowProgram,
     vbos: [ffi::types::GLuint; 2],
     size: Size<i32, Physical>,
     min_filter: TextureFilter,
     max_filter: TextureFilter,
+    // overrides both filters, if set
+    texture_filter: Option<TextureFilter>,
+    gl_version: version::GlVersion,
     supports_instancing: bool,
+    blend_mode: BlendMode,
+    // premultiplied color all drawing is multiplied with
+    tint: [f32; 4],
+    // transformation of the straight colors of textures, `None` if it is the identity
+    color_matrix: Option<(Matrix3<f32>, Vector3<f32>)>,
+    // targets are sRGB encoded, shaders output linear colors
+    linear_blending: bool,
+    offscreen_targets: Rc<RefCell<Vec<OffscreenTarget>>>,
+    destruction_callback_sender: Sender<CleanupResource>,
+    egl_display: EGLDisplay,
+    synced: bool,
+    // whether the frame ends the active gpu timer query
+    gpu_timed: bool,
+    // tint of this frame and the regions drawn during it, if damage is tinted
+    debug_damage: Option<([f32; 4], Vec<Rectangle<i32, Physical>>)>,
+    // bounding box of the damage set for this frame, all drawing is clipped to it
+    scissor: Option<Rectangle<i32, Physical>>,
+    // premultiplied color used by `clear_background`
+    clear_color: [f32; 4],
 }
 
 impl fmt::Debug for Gles2Frame {
@@ -318,9 +604,21 @@ impl fmt::Debug for Gles2Frame {
             .field("current_projection", &self.current_projection)
             .field("tex_programs", &self.tex_programs)
             .field("solid_program", &self.solid_program)
+            .field("border_program", &self.border_program)
+            .field("shadow_program", &self.shadow_program)
             .field("size", &self.size)
             .field("min_filter", &self.min_filter)
             .field("max_filter", &self.max_filter)
+            .field("texture_filter", &self.texture_filter)
+            .field("blend_mode", &self.blend_mode)
+            .field("tint", &self.tint)
+            .field("color_matrix", &self.color_matrix)
+            .field("linear_blending", &self.linear_blending)
+            .field("synced", &self.synced)
+            .field("gpu_timed", &self.gpu_timed)
+            .field("debug_damage", &self.debug_damage)
+            .field("scissor", &self.scissor)
+            .field("clear_color", &self.clear_color)
             .finish_non_exhaustive()
     }
 }
@@ -333,10 +631,18 @@ impl fmt::Debug for Gles2Renderer {
             .field("extensions", &self.extensions)
             .field("tex_programs", &self.tex_programs)
             .field("solid_program", &self.solid_program)
+            .field("border_program", &self.border_program)
+            .field("shadow_program", &self.shadow_program)
             // ffi::Gles2 does not implement Debug
             .field("egl", &self.egl)
             .field("min_filter", &self.min_filter)
             .field("max_filter", &self.max_filter)
+            .field("capabilities", &self.capabilities)
+            .field("flags", &self.flags)
+            .field("debug_flags", &self.debug_flags)
+            .field("context_lost", &self.context_lost)
+            .field("gpu_timer", &self.gpu_timer)
+            .field("clear_color", &self.clear_color)
             .field("logger", &self.logger)
             .finish()
     }
@@ -395,6 +701,18 @@ pub enum Gles2Error {
     /// The provided buffer's size did not match the requested one.
     #[error("Error reading buffer, size is too small for the given dimensions")]
     UnexpectedSize,
+    /// The given pixel format is not supported for the requested operation
+    #[error("Unsupported memory format: {0:?}")]
+    UnsupportedMemoryFormat(Fourcc),
+    /// Failed to create a fence
+    #[error("Failed to create a fence")]
+    FenceCreationFailed(#[source] EGLError),
+    /// The GL context was lost, e.g. due to a GPU reset
+    ///
+    /// The renderer can not be used anymore and needs to be recreated,
+    /// all resources imported into it need to be imported again.
+    #[error("The GL context was lost")]
+    ContextLost,
 }
 
 impl From<Gles2Error> for SwapBuffersError {
@@ -407,7 +725,8 @@ impl From<Gles2Error> for SwapBuffersError {
             | x @ Gles2Error::GLExtensionNotSupported(_)
             | x @ Gles2Error::EGLExtensionNotSupported(_)
             | x @ Gles2Error::GLVersionNotSupported(_)
-            | x @ Gles2Error::UnconstraintRenderingOperation => SwapBuffersError::ContextLost(Box::new(x)),
+            | x @ Gles2Error::UnconstraintRenderingOperation
+            | x @ Gles2Error::ContextLost => SwapBuffersError::ContextLost(Box::new(x)),
             Gles2Error::ContextActivationError(err) => err.into(),
             x @ Gles2Error::FramebufferBindingError
             | x @ Gles2Error::BindBufferEGLError(_)
@@ -415,6 +734,8 @@ impl From<Gles2Error> for SwapBuffersError {
             | x @ Gles2Error::BufferAccessError(_)
             | x @ Gles2Error::MappingError
             | x @ Gles2Error::UnexpectedSize
+            | x @ Gles2Error::UnsupportedMemoryFormat(_)
+            | x @ Gles2Error::FenceCreationFailed(_)
             | x @ Gles2Error::EGLBufferAccessError(_) => SwapBuffersError::TemporaryFailure(Box::new(x)),
         }
     }
@@ -427,11 +748,14 @@ impl From<Gles2Error> for SwapBuffersError {
             | x @ Gles2Error::GLExtensionNotSupported(_)
             | x @ Gles2Error::EGLExtensionNotSupported(_)
             | x @ Gles2Error::GLVersionNotSupported(_)
-            | x @ Gles2Error::UnconstraintRenderingOperation => SwapBuffersError::ContextLost(Box::new(x)),
+            | x @ Gles2Error::UnconstraintRenderingOperation
+            | x @ Gles2Error::ContextLost => SwapBuffersError::ContextLost(Box::new(x)),
             Gles2Error::ContextActivationError(err) => err.into(),
             x @ Gles2Error::FramebufferBindingError
             | x @ Gles2Error::MappingError
             | x @ Gles2Error::UnexpectedSize
+            | x @ Gles2Error::UnsupportedMemoryFormat(_)
+            | x @ Gles2Error::FenceCreationFailed(_)
             | x @ Gles2Error::BindBufferEGLError(_) => SwapBuffersError::TemporaryFailure(Box::new(x)),
         }
     }
@@ -520,6 +844,11 @@ unsafe fn texture_program(gl: &ffi::Gles2, frag: &'static str) -> Result<Gles2Te
     let matrix = CStr::from_bytes_with_nul(b"matrix\0").expect("NULL terminated");
     let tex_matrix = CStr::from_bytes_with_nul(b"tex_matrix\0").expect("NULL terminated");
     let alpha = CStr::from_bytes_with_nul(b"alpha\0").expect("NULL terminated");
+    let tint = CStr::from_bytes_with_nul(b"tint\0").expect("NULL terminated");
+    let decode_srgb = CStr::from_bytes_with_nul(b"decode_srgb\0").expect("NULL terminated");
+    let apply_color_matrix = CStr::from_bytes_with_nul(b"apply_color_matrix\0").expect("NULL terminated");
+    let color_matrix = CStr::from_bytes_with_nul(b"color_matrix\0").expect("NULL terminated");
+    let color_offset = CStr::from_bytes_with_nul(b"color_offset\0").expect("NULL terminated");
 
     Ok(Gles2TexProgram {
         program,
@@ -527,6 +856,15 @@ unsafe fn texture_program(gl: &ffi::Gles2, frag: &'static str) -> Result<Gles2Te
         uniform_matrix: gl.GetUniformLocation(program, matrix.as_ptr() as *const ffi::types::GLchar),
         uniform_tex_matrix: gl.GetUniformLocation(program, tex_matrix.as_ptr() as *const ffi::types::GLchar),
         uniform_alpha: gl.GetUniformLocation(program, alpha.as_ptr() as *const ffi::types::GLchar),
+        uniform_tint: gl.GetUniformLocation(program, tint.as_ptr() as *const ffi::types::GLchar),
+        uniform_decode_srgb: gl
+            .GetUniformLocation(program, decode_srgb.as_ptr() as *const ffi::types::GLchar),
+        uniform_apply_color_matrix: gl
+            .GetUniformLocation(program, apply_color_matrix.as_ptr() as *const ffi::types::GLchar),
+        uniform_color_matrix: gl
+            .GetUniformLocation(program, color_matrix.as_ptr() as *const ffi::types::GLchar),
+        uniform_color_offset: gl
+            .GetUniformLocation(program, color_offset.as_ptr() as *const ffi::types::GLchar),
         attrib_vert: gl.GetAttribLocation(program, vert.as_ptr() as *const ffi::types::GLchar),
         attrib_vert_position: gl
             .GetAttribLocation(program, vert_position.as_ptr() as *const ffi::types::GLchar),
@@ -550,6 +888,52 @@ unsafe fn solid_program(gl: &ffi::Gles2) -> Result<Gles2SolidProgram, Gles2Error
     })
 }
 
+unsafe fn border_program(gl: &ffi::Gles2) -> Result<Gles2BorderProgram, Gles2Error> {
+    let program = link_program(gl, shaders::VERTEX_SHADER_BORDER, shaders::FRAGMENT_SHADER_BORDER)?;
+
+    let matrix = CStr::from_bytes_with_nul(b"matrix\0").expect("NULL terminated");
+    let color = CStr::from_bytes_with_nul(b"color\0").expect("NULL terminated");
+    let rect = CStr::from_bytes_with_nul(b"rect\0").expect("NULL terminated");
+    let thickness = CStr::from_bytes_with_nul(b"thickness\0").expect("NULL terminated");
+    let radius = CStr::from_bytes_with_nul(b"radius\0").expect("NULL terminated");
+    let vert = CStr::from_bytes_with_nul(b"vert\0").expect("NULL terminated");
+    let position = CStr::from_bytes_with_nul(b"position\0").expect("NULL terminated");
+
+    Ok(Gles2BorderProgram {
+        program,
+        uniform_matrix: gl.GetUniformLocation(program, matrix.as_ptr() as *const ffi::types::GLchar),
+        uniform_color: gl.GetUniformLocation(program, color.as_ptr() as *const ffi::types::GLchar),
+        uniform_rect: gl.GetUniformLocation(program, rect.as_ptr() as *const ffi::types::GLchar),
+        uniform_thickness: gl.GetUniformLocation(program, thickness.as_ptr() as *const ffi::types::GLchar),
+        uniform_radius: gl.GetUniformLocation(program, radius.as_ptr() as *const ffi::types::GLchar),
+        attrib_vert: gl.GetAttribLocation(program, vert.as_ptr() as *const ffi::types::GLchar),
+        attrib_position: gl.GetAttribLocation(program, position.as_ptr() as *const ffi::types::GLchar),
+    })
+}
+
+unsafe fn shadow_program(gl: &ffi::Gles2) -> Result<Gles2ShadowProgram, Gles2Error> {
+    let program = link_program(gl, shaders::VERTEX_SHADER_BORDER, shaders::FRAGMENT_SHADER_SHADOW)?;
+
+    let matrix = CStr::from_bytes_with_nul(b"matrix\0").expect("NULL terminated");
+    let color = CStr::from_bytes_with_nul(b"color\0").expect("NULL terminated");
+    let rect = CStr::from_bytes_with_nul(b"rect\0").expect("NULL terminated");
+    let sigma = CStr::from_bytes_with_nul(b"sigma\0").expect("NULL terminated");
+    let radius = CStr::from_bytes_with_nul(b"radius\0").expect("NULL terminated");
+    let vert = CStr::from_bytes_with_nul(b"vert\0").expect("NULL terminated");
+    let position = CStr::from_bytes_with_nul(b"position\0").expect("NULL terminated");
+
+    Ok(Gles2ShadowProgram {
+        program,
+        uniform_matrix: gl.GetUniformLocation(program, matrix.as_ptr() as *const ffi::types::GLchar),
+        uniform_color: gl.GetUniformLocation(program, color.as_ptr() as *const ffi::types::GLchar),
+        uniform_rect: gl.GetUniformLocation(program, rect.as_ptr() as *const ffi::types::GLchar),
+        uniform_sigma: gl.GetUniformLocation(program, sigma.as_ptr() as *const ffi::types::GLchar),
+        uniform_radius: gl.GetUniformLocation(program, radius.as_ptr() as *const ffi::types::GLchar),
+        attrib_vert: gl.GetAttribLocation(program, vert.as_ptr() as *const ffi::types::GLchar),
+        attrib_position: gl.GetAttribLocation(program, position.as_ptr() as *const ffi::types::GLchar),
+    })
+}
+
 impl Gles2Renderer {
     /// Creates a new OpenGL ES 2 renderer from a given [`EGLContext`](crate::backend::egl::EGLBuffer).
     ///
@@ -567,6 +951,25 @@ impl Gles2Renderer {
     /// - Shm buffers can be released after a successful import, without the texture handle becoming invalid.
     /// - Texture filtering starts with Linear-downscaling and Linear-upscaling
     pub unsafe fn new<L>(context: EGLContext, logger: L) -> Result<Gles2Renderer, Gles2Error>
+    where
+        L: Into<Option<::slog::Logger>>,
+    {
+        Self::with_flags(context, RendererFlags::empty(), logger)
+    }
+
+    /// Creates a new OpenGL ES 2 renderer from a given [`EGLContext`] with the given [`RendererFlags`].
+    ///
+    /// Returns an error, if the GL implementation does not support the requested flags.
+    ///
+    /// # Safety
+    ///
+    /// This operation will cause undefined behavior if the given EGLContext is active in another thread.
+    /// See [`Gles2Renderer::new`] for further details.
+    pub unsafe fn with_flags<L>(
+        context: EGLContext,
+        flags: RendererFlags,
+        logger: L,
+    ) -> Result<Gles2Renderer, Gles2Error>
     where
         L: Into<Option<::slog::Logger>>,
     {
@@ -574,7 +977,7 @@ impl Gles2Renderer {
 
         context.make_current()?;
 
-        let (gl, gl_version, exts, logger_ptr, supports_instancing) = {
+        let (gl, gl_version, exts, logger_ptr, supports_instancing, capabilities) = {
             let gl = ffi::Gles2::load_with(|s| crate::backend::egl::get_proc_address(s) as *const _);
             let ext_ptr = gl.GetString(ffi::EXTENSIONS) as *const c_char;
             if ext_ptr.is_null() {
@@ -620,12 +1023,42 @@ impl Gles2Renderer {
             if gl_version < version::GLES_3_0 && !exts.iter().any(|ext| ext == "GL_EXT_unpack_subimage") {
                 return Err(Gles2Error::GLExtensionNotSupported(&["GL_EXT_unpack_subimage"]));
             }
+            // required to render into sRGB encoded targets
+            if flags.contains(RendererFlags::LINEAR_BLENDING) {
+                if gl_version < version::GLES_3_0 {
+                    return Err(Gles2Error::GLVersionNotSupported(version::GLES_3_0));
+                }
+                if !context
+                    .display()
+                    .extensions()
+                    .iter()
+                    .any(|ext| ext == "EGL_EXT_image_gl_colorspace")
+                {
+                    return Err(Gles2Error::EGLExtensionNotSupported(&[
+                        "EGL_EXT_image_gl_colorspace",
+                    ]));
+                }
+            }
             // Check if GPU supports instanced rendering.
             let supports_instancing = gl_version >= version::GLES_3_0
                 || (exts.iter().any(|ext| ext == "GL_EXT_instanced_arrays")
                     && exts.iter().any(|ext| ext == "GL_EXT_draw_instanced"));
 
-            let logger = if exts.iter().any(|ext| ext == "GL_KHR_debug") {
+            let mut max_texture_size = 0;
+            gl.GetIntegerv(ffi::MAX_TEXTURE_SIZE, &mut max_texture_size);
+            let mut max_texture_units = 0;
+            gl.GetIntegerv(ffi::MAX_TEXTURE_IMAGE_UNITS, &mut max_texture_units);
+            let capabilities = RendererCapabilities {
+                max_texture_size,
+                max_texture_units,
+                instancing: supports_instancing,
+                external_textures: exts.iter().any(|ext| ext == "GL_OES_EGL_image_external"),
+                debug_output: exts.iter().any(|ext| ext == "GL_KHR_debug"),
+                debug_marker: exts.iter().any(|ext| ext == "GL_EXT_debug_marker"),
+            };
+            debug!(log, "GL Capabilities: {:?}", capabilities);
+
+            let logger = if capabilities.debug_output {
                 let logger = Box::into_raw(Box::new(log.clone()));
                 gl.Enable(ffi::DEBUG_OUTPUT);
                 gl.Enable(ffi::DEBUG_OUTPUT_SYNCHRONOUS);
@@ -635,7 +1068,7 @@ impl Gles2Renderer {
                 None
             };
 
-            (gl, gl_version, exts, logger, supports_instancing)
+            (gl, gl_version, exts, logger, supports_instancing, capabilities)
         };
 
         let tex_programs = [
@@ -644,6 +1077,8 @@ impl Gles2Renderer {
             texture_program(&gl, shaders::FRAGMENT_SHADER_EXTERNAL)?,
         ];
         let solid_program = solid_program(&gl)?;
+        let border_program = border_program(&gl)?;
+        let shadow_program = shadow_program(&gl)?;
 
         // Initialize vertices based on drawing methodology.
         let vertices: &[ffi::types::GLfloat] = if supports_instancing {
@@ -676,6 +1111,8 @@ impl Gles2Renderer {
             gl_version,
             tex_programs,
             solid_program,
+            border_program,
+            shadow_program,
             target: None,
             buffers: Vec::new(),
             dmabuf_cache: std::collections::HashMap::new(),
@@ -685,6 +1122,14 @@ impl Gles2Renderer {
             min_filter: TextureFilter::Linear,
             max_filter: TextureFilter::Linear,
             supports_instancing,
+            capabilities,
+            offscreen_targets: Rc::new(RefCell::new(Vec::new())),
+            flags,
+            debug_flags: DebugFlags::empty(),
+            debug_frame: 0,
+            context_lost: false,
+            gpu_timer: None,
+            clear_color: [0.0, 0.0, 0.0, 1.0],
             logger_ptr,
             logger: log,
             _not_send: std::ptr::null_mut(),
@@ -829,6 +1274,7 @@ impl ImportMemWl for Gles2Renderer {
                             is_external: false,
                             y_inverted: false,
                             size: (width, height).into(),
+                            srgb: false,
                             egl_images: None,
                             destruction_callback_sender: self.destruction_callback_sender.clone(),
                         });
@@ -912,12 +1358,14 @@ impl ImportMem for Gles2Renderer {
         data: &[u8],
         size: Size<i32, BufferCoord>,
         flipped: bool,
+        alpha: AlphaMode,
     ) -> Result<Gles2Texture, Gles2Error> {
         self.make_current()?;
 
         if data.len() < (size.w * size.h * 4) as usize {
             return Err(Gles2Error::UnexpectedSize);
         }
+        let data = alpha.premultiply(&data[..(size.w * size.h * 4) as usize]);
 
         let texture = Gles2Texture(Rc::new({
             let mut tex = 0;
@@ -948,6 +1396,7 @@ impl ImportMem for Gles2Renderer {
                 is_external: false,
                 y_inverted: flipped,
                 size,
+                srgb: false,
                 egl_images: None,
                 destruction_callback_sender: self.destruction_callback_sender.clone(),
             }
@@ -1061,6 +1510,7 @@ impl ImportEgl for Gles2Renderer {
             is_external: egl.format == EGLFormat::External,
             y_inverted: egl.y_inverted,
             size: egl.size,
+            srgb: false,
             egl_images: Some(egl.into_images()),
             destruction_callback_sender: self.destruction_callback_sender.clone(),
         }));
@@ -1080,6 +1530,12 @@ impl ImportDma for Gles2Renderer {
             return Err(Gles2Error::GLExtensionNotSupported(&["GL_OES_EGL_image"]));
         }
 
+        if cfg!(debug_assertions) {
+            if let Err(err) = buffer.validate_for_format() {
+                warn!(self.logger, "Importing malformed dmabuf: {}", err);
+            }
+        }
+
         self.make_current()?;
         self.existing_dmabuf_texture(buffer)?.map(Ok).unwrap_or_else(|| {
             let is_external = !self.egl.dmabuf_render_formats().contains(&buffer.format());
@@ -1096,6 +1552,7 @@ impl ImportDma for Gles2Renderer {
                 is_external,
                 y_inverted: buffer.y_inverted(),
                 size: buffer.size(),
+                srgb: false,
                 egl_images: Some(vec![image]),
                 destruction_callback_sender: self.destruction_callback_sender.clone(),
             }));
@@ -1455,11 +1912,13 @@ impl Bind<Dmabuf> for Gles2Renderer {
             .map(|buf| Ok((buf.clone(), buf.dmabuf.upgrade().unwrap())))
             .unwrap_or_else(|| {
                 trace!(self.logger, "Creating EGLImage for Dmabuf: {:?}", dmabuf);
-                let image = self
-                    .egl
-                    .display()
-                    .create_image_from_dmabuf(&dmabuf)
-                    .map_err(Gles2Error::BindBufferEGLError)?;
+                let display = self.egl.display();
+                let image = if self.linear_blending() {
+                    display.create_srgb_image_from_dmabuf(&dmabuf)
+                } else {
+                    display.create_image_from_dmabuf(&dmabuf)
+                }
+                .map_err(Gles2Error::BindBufferEGLError)?;
 
                 unsafe {
                     let mut rbo = 0;
@@ -1552,10 +2011,15 @@ impl Offscreen<Gles2Texture> for Gles2Renderer {
             let mut tex = 0;
             self.gl.GenTextures(1, &mut tex);
             self.gl.BindTexture(ffi::TEXTURE_2D, tex);
+            let internal_format = if self.linear_blending() {
+                ffi::SRGB8_ALPHA8
+            } else {
+                ffi::RGBA
+            };
             self.gl.TexImage2D(
                 ffi::TEXTURE_2D,
                 0,
-                ffi::RGBA as i32,
+                internal_format as i32,
                 size.w,
                 size.h,
                 0,
@@ -1566,7 +2030,16 @@ impl Offscreen<Gles2Texture> for Gles2Renderer {
             tex
         };
 
-        Ok(unsafe { Gles2Texture::from_raw(self, tex, size) })
+        Ok(Gles2Texture(Rc::new(Gles2TextureInternal {
+            texture: tex,
+            texture_kind: 0,
+            is_external: false,
+            y_inverted: false,
+            size,
+            srgb: self.linear_blending(),
+            egl_images: None,
+            destruction_callback_sender: self.destruction_callback_sender.clone(),
+        })))
     }
 }
 
@@ -1613,8 +2086,13 @@ impl Offscreen<Gles2Renderbuffer> for Gles2Renderer {
             let mut rbo = 0;
             self.gl.GenRenderbuffers(1, &mut rbo);
             self.gl.BindRenderbuffer(ffi::RENDERBUFFER, rbo);
+            let internal_format = if self.linear_blending() {
+                ffi::SRGB8_ALPHA8
+            } else {
+                ffi::RGBA8
+            };
             self.gl
-                .RenderbufferStorage(ffi::RENDERBUFFER, ffi::RGBA8, size.w, size.h);
+                .RenderbufferStorage(ffi::RENDERBUFFER, internal_format, size.w, size.h);
             self.gl.BindRenderbuffer(ffi::RENDERBUFFER, 0);
 
             Ok(Gles2Renderbuffer(Rc::new(Gles2RenderbufferInternal {
@@ -1646,7 +2124,18 @@ impl Drop for Gles2Renderer {
                     self.gl.DeleteProgram(program.program);
                 }
                 self.gl.DeleteProgram(self.solid_program.program);
+                self.gl.DeleteProgram(self.border_program.program);
+                self.gl.DeleteProgram(self.shadow_program.program);
                 self.gl.DeleteBuffers(self.vbos.len() as i32, self.vbos.as_ptr());
+                if let Some(timer) = self.gpu_timer.take() {
+                    let queries = timer
+                        .pending
+                        .into_iter()
+                        .chain(timer.free)
+                        .chain(timer.active)
+                        .collect::<Vec<_>>();
+                    self.gl.DeleteQueriesEXT(queries.len() as i32, queries.as_ptr());
+                }
 
                 if self.extensions.iter().any(|ext| ext == "GL_KHR_debug") {
                     self.gl.Disable(ffi::DEBUG_OUTPUT);
@@ -1665,63 +2154,45 @@ impl Drop for Gles2Renderer {
 }
 
 impl Gles2Renderer {
-    /// Get access to the underlying [`EGLContext`].
-    ///
-    /// *Note*: Modifying the context state, might result in rendering issues.
-    /// The context state is considerd an implementation detail
-    /// and no guarantee is made about what can or cannot be changed.
-    /// To make sure a certain modification does not interfere with
-    /// the renderer's behaviour, check the source.
-    pub fn egl_context(&self) -> &EGLContext {
-        &self.egl
-    }
-
-    /// Run custom code in the GL context owned by this renderer.
+    /// Render a sub-region of a larger area, e.g. one tile of an output spanning multiple framebuffers.
     ///
-    /// The OpenGL state of the renderer is considered an implementation detail
-    /// and no guarantee is made about what can or cannot be changed,
-    /// as such you should reset everything you change back to its previous value
-    /// or check the source code of the version of Smithay you are using to ensure
-    /// your changes don't interfere with the renderer's behavior.
-    /// Doing otherwise can lead to rendering errors while using other functions of this renderer.
-    pub fn with_context<F, R>(&mut self, func: F) -> Result<R, Gles2Error>
+    /// Works like [`Renderer::render`], but the currently bound target only covers `viewport`.
+    /// All coordinates passed to the frame, including damage, are relative to the full area,
+    /// so the same elements can be rendered into every tile without laying them out again.
+    /// Anything outside of `viewport` is cut off. The size of the bound target needs to be
+    /// `viewport.size` after applying `transform`.
+    pub fn render_viewport<F, R>(
+        &mut self,
+        viewport: Rectangle<i32, Physical>,
+        transform: Transform,
+        rendering: F,
+    ) -> Result<R, Gles2Error>
     where
-        F: FnOnce(&mut Self, &ffi::Gles2) -> R,
+        F: FnOnce(&mut Self, &mut Gles2Frame) -> R,
     {
-        self.make_current()?;
-        let gl = self.gl.clone();
-        Ok(func(self, &gl))
-    }
-}
-
-impl Renderer for Gles2Renderer {
-    type Error = Gles2Error;
-    type TextureId = Gles2Texture;
-    type Frame = Gles2Frame;
-
-    fn id(&self) -> usize {
-        self.egl.user_data().get::<RendererId>().unwrap().0
-    }
-
-    fn downscale_filter(&mut self, filter: TextureFilter) -> Result<(), Self::Error> {
-        self.min_filter = filter;
-        Ok(())
-    }
-    fn upscale_filter(&mut self, filter: TextureFilter) -> Result<(), Self::Error> {
-        self.max_filter = filter;
-        Ok(())
+        self.render_at(
+            transform.transform_size(viewport.size),
+            transform,
+            viewport.loc,
+            rendering,
+        )
     }
 
-    fn render<F, R>(
+    fn render_at<F, R>(
         &mut self,
         size: Size<i32, Physical>,
         transform: Transform,
+        offset: Point<i32, Physical>,
         rendering: F,
-    ) -> Result<R, Self::Error>
+    ) -> Result<R, Gles2Error>
     where
-        F: FnOnce(&mut Self, &mut Self::Frame) -> R,
+        F: FnOnce(&mut Self, &mut Gles2Frame) -> R,
     {
         self.make_current()?;
+        if self.context_lost {
+            return Err(Gles2Error::ContextLost);
+        }
+        let timed = self.begin_gpu_timer();
 
         unsafe {
             self.gl.Viewport(0, 0, size.w, size.h);
@@ -1733,45 +2204,63 @@ impl Renderer for Gles2Renderer {
             self.gl.BlendFunc(ffi::ONE, ffi::ONE_MINUS_SRC_ALPHA);
         }
 
-        // replicate https://www.khronos.org/registry/OpenGL-Refpages/gl2.1/xhtml/glOrtho.xml
-        // glOrtho(0, width, 0, height, 1, 1);
-        let mut renderer = Matrix3::<f32>::identity();
-        let t = Matrix3::<f32>::identity();
-        let x = 2.0 / (size.w as f32);
-        let y = 2.0 / (size.h as f32);
-
-        // Rotation & Reflection
-        renderer[0][0] = x * t[0][0];
-        renderer[1][0] = x * t[0][1];
-        renderer[0][1] = y * -t[1][0];
-        renderer[1][1] = y * -t[1][1];
-
-        //Translation
-        renderer[2][0] = -(1.0f32.copysign(renderer[0][0] + renderer[1][0]));
-        renderer[2][1] = -(1.0f32.copysign(renderer[0][1] + renderer[1][1]));
-
-        // We account for OpenGLs coordinate system here
-        let flip180 = Matrix3::new(1.0, 0.0, 0.0, 0.0, -1.0, 0.0, 0.0, 0.0, 1.0);
-
         let mut frame = Gles2Frame {
             gl: self.gl.clone(),
             tex_programs: self.tex_programs.clone(),
             solid_program: self.solid_program.clone(),
-            // output transformation passed in by the user
-            current_projection: flip180 * transform.matrix() * renderer,
+            border_program: self.border_program.clone(),
+            shadow_program: self.shadow_program.clone(),
+            // output transformation passed in by the user,
+            // everything is moved by the offset before being projected
+            current_projection: projection_matrix(size, transform)
+                * Matrix3::from_translation(Vector2::new(-offset.x as f32, -offset.y as f32)),
             transform,
             vbos: self.vbos,
             size,
             min_filter: self.min_filter,
             max_filter: self.max_filter,
+            texture_filter: None,
+            gl_version: self.gl_version,
             supports_instancing: self.supports_instancing,
+            blend_mode: BlendMode::Over,
+            tint: [1.0; 4],
+            color_matrix: None,
+            linear_blending: self.linear_blending(),
+            offscreen_targets: self.offscreen_targets.clone(),
+            destruction_callback_sender: self.destruction_callback_sender.clone(),
+            egl_display: self.egl.display().clone(),
+            synced: false,
+            gpu_timed: timed,
+            debug_damage: if self.debug_flags.contains(DebugFlags::TINT_DAMAGE) {
+                let color = DAMAGE_TINTS[self.debug_frame % DAMAGE_TINTS.len()];
+                self.debug_frame = self.debug_frame.wrapping_add(1);
+                Some((color, Vec::new()))
+            } else {
+                None
+            },
+            scissor: None,
+            clear_color: self.clear_color,
         };
 
         let result = rendering(self, &mut frame);
+        // no-op, if the frame was already finished with a sync point
+        frame.finish_commands();
+
+        // free offscreen targets, that were not needed for this frame
+        self.offscreen_targets
+            .borrow_mut()
+            .retain(|target| target.used.replace(false));
+
+        if timed {
+            self.end_gpu_timer();
+        }
 
         unsafe {
             self.gl.Flush();
-            // We need to wait for the previously submitted GL commands to complete
+            // If the frame was finished with a sync point, waiting for the commands
+            // to complete is the responsibility of the caller.
+            //
+            // Otherwise we need to wait for the previously submitted GL commands to complete
             // or otherwise the buffer could be submitted to the drm surface while
             // still writing to the buffer which results in flickering on the screen.
             // The proper solution would be to create a fence just before calling
@@ -1785,43 +2274,420 @@ impl Renderer for Gles2Renderer {
             // https://gitlab.freedesktop.org/mesa/kmscube/-/blob/9f63f359fab1b5d8e862508e4e51c9dfe339ccb0/drm-atomic.c#L147
             // and here
             // https://gitlab.freedesktop.org/mesa/kmscube/-/blob/9f63f359fab1b5d8e862508e4e51c9dfe339ccb0/drm-atomic.c#L235
-            self.gl.Finish();
+            if !frame.synced {
+                self.gl.Finish();
+            }
             self.gl.Disable(ffi::BLEND);
         }
 
+        if self.check_context_lost() {
+            return Err(Gles2Error::ContextLost);
+        }
+
         Ok(result)
     }
-}
-
-/// Vertices for instanced rendering.
-static INSTANCED_VERTS: [ffi::types::GLfloat; 8] = [
-    1.0, 0.0, // top right
-    0.0, 0.0, // top left
-    1.0, 1.0, // bottom right
-    0.0, 1.0, // bottom left
-];
 
-/// Vertices for rendering individual triangles.
-const MAX_RECTS_PER_DRAW: usize = 10;
-const TRIANGLE_VERTS: [ffi::types::GLfloat; 12 * MAX_RECTS_PER_DRAW] = triangle_verts();
-const fn triangle_verts() -> [ffi::types::GLfloat; 12 * MAX_RECTS_PER_DRAW] {
-    let mut verts = [0.; 12 * MAX_RECTS_PER_DRAW];
-    let mut i = 0;
-    loop {
-        // Top Left.
-        verts[i * 12] = 0.0;
-        verts[i * 12 + 1] = 0.0;
+    /// Set the color used to clear the background of frames
+    ///
+    /// The color is premultiplied, like for [`Frame::clear`]. Frames only clear the regions
+    /// passed to [`Gles2Frame::clear_background`], nothing is cleared implicitly.
+    /// Defaults to opaque black.
+    pub fn set_clear_color(&mut self, color: [f32; 4]) {
+        self.clear_color = color;
+    }
 
-        // Bottom left.
-        verts[i * 12 + 2] = 0.0;
-        verts[i * 12 + 3] = 1.0;
+    /// Returns the color used to clear the background of frames
+    pub fn clear_color(&self) -> [f32; 4] {
+        self.clear_color
+    }
 
-        // Bottom right.
-        verts[i * 12 + 4] = 1.0;
-        verts[i * 12 + 5] = 1.0;
+    /// Returns true, if the GL context of this renderer was lost, e.g. due to a GPU reset
+    ///
+    /// Rendering with a lost context fails with [`Gles2Error::ContextLost`]. The renderer
+    /// can not recover from this state, instead it needs to be recreated and all resources
+    /// need to be imported again.
+    ///
+    /// Context loss is detected using `GL_KHR_robustness` or OpenGL ES 3.2. Most drivers only report
+    /// a lost context, if it was created with a reset notification strategy of `LOSE_CONTEXT_ON_RESET`.
+    pub fn is_context_lost(&mut self) -> bool {
+        if self.context_lost {
+            return true;
+        }
+        if self.make_current().is_err() {
+            // the status can not be queried without a current context
+            return false;
+        }
+        self.check_context_lost()
+    }
 
-        // Top left.
-        verts[i * 12 + 6] = 0.0;
+    // Query the reset status of the current context and remember a lost context
+    fn check_context_lost(&mut self) -> bool {
+        if self.context_lost {
+            return true;
+        }
+        let status = unsafe {
+            if self.extensions.iter().any(|ext| ext == "GL_KHR_robustness") {
+                self.gl.GetGraphicsResetStatusKHR()
+            } else if self.gl_version >= version::GLES_3_2 {
+                self.gl.GetGraphicsResetStatus()
+            } else {
+                ffi::NO_ERROR
+            }
+        };
+        if status != ffi::NO_ERROR {
+            let reason = match status {
+                ffi::GUILTY_CONTEXT_RESET => "caused by this context",
+                ffi::INNOCENT_CONTEXT_RESET => "not caused by this context",
+                _ => "of unknown cause",
+            };
+            error!(self.logger, "GL context lost due to a reset {}", reason);
+            self.context_lost = true;
+        }
+        self.context_lost
+    }
+
+    /// Enable or disable measuring the gpu time of frames
+    ///
+    /// While enabled, the time the gpu spends executing the commands of each frame rendered by
+    /// this renderer is measured using `GL_EXT_disjoint_timer_query` and can be queried using
+    /// [`Gles2Renderer::last_frame_gpu_time`].
+    ///
+    /// Fails with [`Gles2Error::GLExtensionNotSupported`], if the extension is not available.
+    pub fn set_gpu_timing(&mut self, enabled: bool) -> Result<(), Gles2Error> {
+        if enabled == self.gpu_timer.is_some() {
+            return Ok(());
+        }
+        if enabled {
+            if !self
+                .extensions
+                .iter()
+                .any(|ext| ext == "GL_EXT_disjoint_timer_query")
+            {
+                return Err(Gles2Error::GLExtensionNotSupported(&[
+                    "GL_EXT_disjoint_timer_query",
+                ]));
+            }
+            self.gpu_timer = Some(GpuTimer::default());
+        } else if let Some(timer) = self.gpu_timer.take() {
+            self.make_current()?;
+            let queries = timer.pending.into_iter().chain(timer.free).collect::<Vec<_>>();
+            unsafe { self.gl.DeleteQueriesEXT(queries.len() as i32, queries.as_ptr()) };
+        }
+        Ok(())
+    }
+
+    /// Returns true, if the gpu time of frames is measured
+    pub fn gpu_timing(&self) -> bool {
+        self.gpu_timer.is_some()
+    }
+
+    /// Returns the gpu time of the last frame, whose measurement is available
+    ///
+    /// The gpu executes commands asynchronously, so measurements usually become available one or two
+    /// frames later and are not necessarily the ones of the frame rendered last. Measurements invalidated
+    /// by the driver, e.g. due to a change of the gpu clock, are discarded.
+    ///
+    /// Returns `None`, if [gpu timing](Gles2Renderer::set_gpu_timing) is disabled
+    /// or no measurement is available yet.
+    pub fn last_frame_gpu_time(&self) -> Option<Duration> {
+        self.gpu_timer.as_ref().and_then(|timer| timer.last_frame_time)
+    }
+
+    // Collect available measurements and start measuring a new frame, returns false if not measured
+    fn begin_gpu_timer(&mut self) -> bool {
+        let gl = &self.gl;
+        let timer = match self.gpu_timer.as_mut() {
+            // nested frames are accounted to the outer frame
+            Some(timer) if timer.active.is_none() => timer,
+            _ => return false,
+        };
+
+        unsafe {
+            while let Some(&query) = timer.pending.front() {
+                let mut available = 0;
+                gl.GetQueryObjectuivEXT(query, ffi::QUERY_RESULT_AVAILABLE_EXT, &mut available);
+                if available == ffi::FALSE as u32 {
+                    break;
+                }
+                let mut elapsed = 0;
+                gl.GetQueryObjectui64vEXT(query, ffi::QUERY_RESULT_EXT, &mut elapsed);
+                timer.last_frame_time = Some(Duration::from_nanos(elapsed));
+                timer.free.extend(timer.pending.pop_front());
+            }
+
+            // reading the flag resets it
+            let mut disjoint = 0;
+            gl.GetIntegerv(ffi::GPU_DISJOINT_EXT, &mut disjoint);
+            if disjoint != 0 {
+                // the results of all queries in flight are unreliable
+                timer.last_frame_time = None;
+                timer.free.extend(timer.pending.drain(..));
+            }
+            while timer.pending.len() >= MAX_PENDING_TIMER_QUERIES {
+                timer.free.extend(timer.pending.pop_front());
+            }
+
+            let query = timer.free.pop().unwrap_or_else(|| {
+                let mut query = 0;
+                gl.GenQueriesEXT(1, &mut query);
+                query
+            });
+            gl.BeginQueryEXT(ffi::TIME_ELAPSED_EXT, query);
+            timer.active = Some(query);
+        }
+        true
+    }
+
+    // Queue the query of the current frame, which was ended by `Gles2Frame::finish_commands`
+    fn end_gpu_timer(&mut self) {
+        if let Some(timer) = self.gpu_timer.as_mut() {
+            timer.pending.extend(timer.active.take());
+        }
+    }
+
+    /// Set the debug flags of this renderer
+    ///
+    /// They are applied starting with the next frame.
+    pub fn set_debug_flags(&mut self, flags: DebugFlags) {
+        self.debug_flags = flags;
+    }
+
+    /// Returns the debug flags of this renderer
+    pub fn debug_flags(&self) -> DebugFlags {
+        self.debug_flags
+    }
+
+    /// Returns the flags this renderer was created with
+    pub fn flags(&self) -> RendererFlags {
+        self.flags
+    }
+
+    fn linear_blending(&self) -> bool {
+        self.flags.contains(RendererFlags::LINEAR_BLENDING)
+    }
+
+    /// Get access to the underlying [`EGLContext`].
+    ///
+    /// *Note*: Modifying the context state, might result in rendering issues.
+    /// The context state is considerd an implementation detail
+    /// and no guarantee is made about what can or cannot be changed.
+    /// To make sure a certain modification does not interfere with
+    /// the renderer's behaviour, check the source.
+    pub fn egl_context(&self) -> &EGLContext {
+        &self.egl
+    }
+
+    /// Returns the limits and optional features of the underlying GL implementation.
+    ///
+    /// Use this to size resources like texture atlases, instead of running into driver limits.
+    pub fn capabilities(&self) -> &RendererCapabilities {
+        &self.capabilities
+    }
+
+    /// Returns true, if dmabufs of the given format and modifier can be imported.
+    ///
+    /// This only checks the formats queried from EGL when the renderer was created and does not
+    /// try to import anything, so it is cheap enough to filter the formats advertised to clients,
+    /// e.g. in dmabuf feedback. Formats are only queryable with `EGL_EXT_image_dma_buf_import_modifiers`,
+    /// without it just [`Fourcc::Argb8888`] and [`Fourcc::Xrgb8888`] are assumed to work.
+    ///
+    /// [`Modifier::Invalid`] refers to the implicit modifier and is accepted for every supported format.
+    pub fn can_import_format(&self, fourcc: Fourcc, modifier: Modifier) -> bool {
+        if !self.extensions.iter().any(|ext| ext == "GL_OES_EGL_image") {
+            return false;
+        }
+        let formats = self.egl.dmabuf_texture_formats();
+        if modifier == Modifier::Invalid {
+            formats.iter().any(|format| format.code == fourcc)
+        } else {
+            formats.contains(&Format {
+                code: fourcc,
+                modifier,
+            })
+        }
+    }
+
+    /// Run custom code in the GL context owned by this renderer.
+    ///
+    /// The OpenGL state of the renderer is considered an implementation detail
+    /// and no guarantee is made about what can or cannot be changed,
+    /// as such you should reset everything you change back to its previous value
+    /// or check the source code of the version of Smithay you are using to ensure
+    /// your changes don't interfere with the renderer's behavior.
+    /// Doing otherwise can lead to rendering errors while using other functions of this renderer.
+    pub fn with_context<F, R>(&mut self, func: F) -> Result<R, Gles2Error>
+    where
+        F: FnOnce(&mut Self, &ffi::Gles2) -> R,
+    {
+        self.make_current()?;
+        let gl = self.gl.clone();
+        Ok(func(self, &gl))
+    }
+
+    /// Import a chunk of memory with padded rows into a new texture.
+    ///
+    /// Works like [`ImportMem::import_memory`], but accepts data, whose rows are `stride` bytes apart,
+    /// avoiding the need to repack the data before uploading it.
+    /// If `flipped` is true, the first row of the data is interpreted as the bottom of the image.
+    ///
+    /// Supported formats are [`Fourcc::Abgr8888`], [`Fourcc::Xbgr8888`], [`Fourcc::Argb8888`]
+    /// and [`Fourcc::Xrgb8888`]. The `stride` needs to be a multiple of the pixel size (4 bytes)
+    /// and at least `size.w * 4`, the data needs to hold at least `(size.h - 1) * stride + size.w * 4` bytes.
+    pub fn import_memory_with_stride(
+        &mut self,
+        data: &[u8],
+        format: Fourcc,
+        size: Size<i32, BufferCoord>,
+        stride: i32,
+        flipped: bool,
+    ) -> Result<Gles2Texture, Gles2Error> {
+        self.make_current()?;
+
+        // number of bytes per pixel
+        let pixelsize = 4i32;
+
+        let (gl_format, shader_idx) = match format {
+            Fourcc::Abgr8888 => (ffi::RGBA, 0),
+            Fourcc::Xbgr8888 => (ffi::RGBA, 1),
+            Fourcc::Argb8888 => (ffi::BGRA_EXT, 0),
+            Fourcc::Xrgb8888 => (ffi::BGRA_EXT, 1),
+            format => return Err(Gles2Error::UnsupportedMemoryFormat(format)),
+        };
+
+        if size.w <= 0
+            || size.h <= 0
+            || stride % pixelsize != 0
+            || stride < size.w * pixelsize
+            || data.len() < ((size.h - 1) * stride + size.w * pixelsize) as usize
+        {
+            return Err(Gles2Error::UnexpectedSize);
+        }
+
+        let texture = Gles2Texture(Rc::new({
+            let mut tex = 0;
+            unsafe {
+                self.gl.GenTextures(1, &mut tex);
+                self.gl.BindTexture(ffi::TEXTURE_2D, tex);
+                self.gl
+                    .TexParameteri(ffi::TEXTURE_2D, ffi::TEXTURE_WRAP_S, ffi::CLAMP_TO_EDGE as i32);
+                self.gl
+                    .TexParameteri(ffi::TEXTURE_2D, ffi::TEXTURE_WRAP_T, ffi::CLAMP_TO_EDGE as i32);
+                self.gl.PixelStorei(ffi::UNPACK_ROW_LENGTH, stride / pixelsize);
+                self.gl.TexImage2D(
+                    ffi::TEXTURE_2D,
+                    0,
+                    gl_format as i32,
+                    size.w,
+                    size.h,
+                    0,
+                    gl_format,
+                    ffi::UNSIGNED_BYTE as u32,
+                    data.as_ptr() as *const _,
+                );
+                self.gl.PixelStorei(ffi::UNPACK_ROW_LENGTH, 0);
+                self.gl.BindTexture(ffi::TEXTURE_2D, 0);
+            }
+            // new texture, upload in full
+            Gles2TextureInternal {
+                texture: tex,
+                texture_kind: shader_idx,
+                is_external: false,
+                y_inverted: flipped,
+                size,
+                srgb: false,
+                egl_images: None,
+                destruction_callback_sender: self.destruction_callback_sender.clone(),
+            }
+        }));
+
+        Ok(texture)
+    }
+}
+
+impl Renderer for Gles2Renderer {
+    type Error = Gles2Error;
+    type TextureId = Gles2Texture;
+    type Frame = Gles2Frame;
+
+    fn id(&self) -> usize {
+        self.egl.user_data().get::<RendererId>().unwrap().0
+    }
+
+    fn downscale_filter(&mut self, filter: TextureFilter) -> Result<(), Self::Error> {
+        self.min_filter = filter;
+        Ok(())
+    }
+    fn upscale_filter(&mut self, filter: TextureFilter) -> Result<(), Self::Error> {
+        self.max_filter = filter;
+        Ok(())
+    }
+
+    fn render<F, R>(
+        &mut self,
+        size: Size<i32, Physical>,
+        transform: Transform,
+        rendering: F,
+    ) -> Result<R, Self::Error>
+    where
+        F: FnOnce(&mut Self, &mut Self::Frame) -> R,
+    {
+        self.render_at(size, transform, Point::from((0, 0)), rendering)
+    }
+}
+
+fn projection_matrix(size: Size<i32, Physical>, transform: Transform) -> Matrix3<f32> {
+    // replicate https://www.khronos.org/registry/OpenGL-Refpages/gl2.1/xhtml/glOrtho.xml
+    // glOrtho(0, width, 0, height, 1, 1);
+    let mut renderer = Matrix3::<f32>::identity();
+    let t = Matrix3::<f32>::identity();
+    let x = 2.0 / (size.w as f32);
+    let y = 2.0 / (size.h as f32);
+
+    // Rotation & Reflection
+    renderer[0][0] = x * t[0][0];
+    renderer[1][0] = x * t[0][1];
+    renderer[0][1] = y * -t[1][0];
+    renderer[1][1] = y * -t[1][1];
+
+    //Translation
+    renderer[2][0] = -(1.0f32.copysign(renderer[0][0] + renderer[1][0]));
+    renderer[2][1] = -(1.0f32.copysign(renderer[0][1] + renderer[1][1]));
+
+    // We account for OpenGLs coordinate system here
+    let flip180 = Matrix3::new(1.0, 0.0, 0.0, 0.0, -1.0, 0.0, 0.0, 0.0, 1.0);
+
+    flip180 * transform.matrix() * renderer
+}
+
+/// Vertices for instanced rendering.
+static INSTANCED_VERTS: [ffi::types::GLfloat; 8] = [
+    1.0, 0.0, // top right
+    0.0, 0.0, // top left
+    1.0, 1.0, // bottom right
+    0.0, 1.0, // bottom left
+];
+
+/// Vertices for rendering individual triangles.
+const MAX_RECTS_PER_DRAW: usize = 10;
+const TRIANGLE_VERTS: [ffi::types::GLfloat; 12 * MAX_RECTS_PER_DRAW] = triangle_verts();
+const fn triangle_verts() -> [ffi::types::GLfloat; 12 * MAX_RECTS_PER_DRAW] {
+    let mut verts = [0.; 12 * MAX_RECTS_PER_DRAW];
+    let mut i = 0;
+    loop {
+        // Top Left.
+        verts[i * 12] = 0.0;
+        verts[i * 12 + 1] = 0.0;
+
+        // Bottom left.
+        verts[i * 12 + 2] = 0.0;
+        verts[i * 12 + 3] = 1.0;
+
+        // Bottom right.
+        verts[i * 12 + 4] = 1.0;
+        verts[i * 12 + 5] = 1.0;
+
+        // Top left.
+        verts[i * 12 + 6] = 0.0;
         verts[i * 12 + 7] = 0.0;
 
         // Bottom right.
@@ -1845,40 +2711,376 @@ impl Frame for Gles2Frame {
     type TextureId = Gles2Texture;
 
     fn clear(&mut self, color: [f32; 4], at: &[Rectangle<i32, Physical>]) -> Result<(), Self::Error> {
-        if at.is_empty() {
-            return Ok(());
-        }
+        self.track_damage(at.iter().copied());
+        self.draw_solid(color, at, false);
+        Ok(())
+    }
 
+    fn render_texture_from_to(
+        &mut self,
+        texture: &Self::TextureId,
+        src: Rectangle<f64, BufferCoord>,
+        dest: Rectangle<i32, Physical>,
+        damage: &[Rectangle<i32, Physical>],
+        transform: Transform,
+        alpha: f32,
+    ) -> Result<(), Self::Error> {
         let mut mat = Matrix3::<f32>::identity();
-        mat = self.current_projection * mat;
 
-        let damage = at
+        // dest position and scale
+        mat = mat * Matrix3::from_translation(Vector2::new(dest.loc.x as f32, dest.loc.y as f32));
+
+        // src scale, position, tranform and y_inverted
+        let tex_size = texture.size().to_f64();
+        let src_size = src.size;
+
+        let transform_mat = if transform.flipped() {
+            transform.matrix()
+        } else {
+            transform.invert().matrix()
+        };
+
+        let mut tex_mat = Matrix3::<f32>::identity();
+        // first scale to meet the src size
+        tex_mat = tex_mat
+            * Matrix3::from_nonuniform_scale(
+                (src_size.w / tex_size.w) as f32,
+                (src_size.h / tex_size.h) as f32,
+            );
+        // now translate by the src location
+        tex_mat = tex_mat
+            * Matrix3::from_translation(Vector2::new(
+                (src.loc.x / src_size.w) as f32,
+                (src.loc.y / src_size.h) as f32,
+            ));
+        // then apply the transform and if necessary invert the y axis
+        tex_mat = tex_mat * Matrix3::from_translation(Vector2::new(0.5, 0.5));
+        if transform == Transform::Normal {
+            assert_eq!(tex_mat, tex_mat * transform.invert().matrix());
+            assert_eq!(transform.matrix(), Matrix3::<f32>::identity());
+        }
+        tex_mat = tex_mat * transform_mat;
+        if texture.0.y_inverted {
+            tex_mat = tex_mat * Matrix3::new(1.0, 0.0, 0.0, 0.0, -1.0, 0.0, 0.0, 0.0, 1.0);
+        }
+        tex_mat = tex_mat * Matrix3::from_translation(Vector2::new(-0.5, -0.5));
+        // at last scale back to tex space
+        tex_mat = tex_mat
+            * Matrix3::from_nonuniform_scale(
+                (1.0f64 / dest.size.w as f64) as f32,
+                (1.0f64 / dest.size.h as f64) as f32,
+            );
+
+        self.track_damage(damage.iter().map(|rect| rect_at(*rect, dest)));
+        let instances = damage_instances(dest.size, damage);
+        self.render_texture(texture, tex_mat, mat, Some(&instances), alpha)
+    }
+
+    fn transformation(&self) -> Transform {
+        self.transform
+    }
+
+    fn set_texture_filter(&mut self, filter: Option<TextureFilter>) {
+        self.texture_filter = filter;
+    }
+}
+
+// Move damage relative to `dest` into the coordinate space of `dest`, clamped to it
+fn rect_at(damage: Rectangle<i32, Physical>, dest: Rectangle<i32, Physical>) -> Rectangle<i32, Physical> {
+    let rect = Rectangle::from_loc_and_size(damage.loc + dest.loc, damage.size);
+    rect.intersection(dest).unwrap_or_default()
+}
+
+// Clamp the damage to the destination and convert it into instances for `Gles2Frame::render_texture`
+fn damage_instances(
+    dest_size: Size<i32, Physical>,
+    damage: &[Rectangle<i32, Physical>],
+) -> Vec<ffi::types::GLfloat> {
+    damage
+        .iter()
+        .flat_map(|rect| {
+            let rect_constrained_loc = rect
+                .loc
+                .constrain(Rectangle::from_extemities((0, 0), dest_size.to_point()));
+            let rect_clamped_size = rect
+                .size
+                .clamp((0, 0), (dest_size.to_point() - rect_constrained_loc).to_size());
+
+            let rect = Rectangle::from_loc_and_size(rect_constrained_loc, rect_clamped_size);
+            [
+                rect.loc.x as f32,
+                rect.loc.y as f32,
+                rect.size.w as f32,
+                rect.size.h as f32,
+            ]
+        })
+        .collect::<Vec<_>>()
+}
+
+/// Color matrix scaling the saturation of colors
+///
+/// A `saturation` of `0.0` results in grayscale, `1.0` leaves colors unchanged
+/// and values above `1.0` oversaturate. Meant to be used with [`Gles2Frame::with_color_matrix`].
+pub fn saturation_matrix(saturation: f32) -> Matrix3<f32> {
+    // luminance coefficients of BT.709
+    let luma = Vector3::new(0.2126, 0.7152, 0.0722);
+    let gray = Matrix3::from_cols(luma, luma, luma).transpose();
+    gray * (1.0 - saturation) + Matrix3::identity() * saturation
+}
+
+impl Gles2Frame {
+    /// Finish the frame and return a [`SyncPoint`] signaled once the gpu has completed rendering it.
+    ///
+    /// By default [`Gles2Renderer::render`](Renderer::render) blocks until all rendering commands
+    /// are finished. Calling this function instead submits the commands without waiting for them,
+    /// so the caller becomes responsible for waiting on the returned sync point (or passing its
+    /// [exported](SyncPoint::export) fence to the kernel), before using the rendered buffer.
+    ///
+    /// This should be the last operation on the frame, commands issued afterwards are not covered by
+    /// the sync point. Exporting the sync point requires `EGL_ANDROID_native_fence_sync`.
+    pub fn finish_with_sync(&mut self) -> Result<SyncPoint, Gles2Error> {
+        self.finish_commands();
+        let sync = self.create_sync_point()?;
+        self.synced = true;
+        Ok(sync)
+    }
+
+    /// Start copying a region of the current framebuffer without waiting for the gpu.
+    ///
+    /// The pixels are read into a pixel buffer object and the returned [`CopyTicket`] carries a
+    /// [`SyncPoint`] signaled once the copy has completed. Only the commands submitted before
+    /// this call end up in the copy, so calling this as the last operation of a frame captures
+    /// the whole frame.
+    ///
+    /// Unlike [`ExportMem::copy_framebuffer`] followed by [`ExportMem::map_texture`] this never
+    /// stalls the rendering pipeline, which makes it suitable for e.g. screencasting.
+    /// The tradeoff is latency: the pixels usually only become available while the next frame
+    /// is rendered, so polling [`CopyTicket::try_take`] after rendering the next frame
+    /// delivers every frame with one frame of delay.
+    ///
+    /// Requires GL ES 3.0 and `EGL_KHR_fence_sync`.
+    pub fn begin_copy_region(
+        &mut self,
+        region: Rectangle<i32, BufferCoord>,
+    ) -> Result<CopyTicket, Gles2Error> {
+        // pixel buffer objects and glMapBufferRange only exist for GL ES 3.0 and higher
+        if self.gl_version < version::GLES_3_0 {
+            return Err(Gles2Error::GLVersionNotSupported(version::GLES_3_0));
+        }
+
+        let mut pbo = 0;
+        unsafe {
+            self.gl.GenBuffers(1, &mut pbo);
+            self.gl.BindBuffer(ffi::PIXEL_PACK_BUFFER, pbo);
+            self.gl.BufferData(
+                ffi::PIXEL_PACK_BUFFER,
+                (region.size.w * region.size.h * 4) as isize,
+                ptr::null(),
+                ffi::STREAM_READ,
+            );
+            self.gl.ReadPixels(
+                region.loc.x,
+                region.loc.y,
+                region.size.w,
+                region.size.h,
+                ffi::RGBA,
+                ffi::UNSIGNED_BYTE,
+                ptr::null_mut(),
+            );
+            self.gl.BindBuffer(ffi::PIXEL_PACK_BUFFER, 0);
+        }
+        let sync = match self.create_sync_point() {
+            Ok(sync) => sync,
+            Err(err) => {
+                unsafe { self.gl.DeleteBuffers(1, &pbo) };
+                return Err(err);
+            }
+        };
+        Ok(CopyTicket {
+            pbo,
+            size: region.size,
+            sync,
+            destruction_callback_sender: self.destruction_callback_sender.clone(),
+        })
+    }
+
+    fn create_sync_point(&mut self) -> Result<SyncPoint, Gles2Error> {
+        let extensions = self.egl_display.extensions();
+        if !extensions.iter().any(|ext| ext == "EGL_KHR_fence_sync") {
+            return Err(Gles2Error::EGLExtensionNotSupported(&["EGL_KHR_fence_sync"]));
+        }
+        let native = extensions
             .iter()
-            .flat_map(|rect| {
-                [
-                    rect.loc.x as f32,
-                    rect.loc.y as f32,
-                    rect.size.w as f32,
-                    rect.size.h as f32,
-                ]
-            })
-            .collect::<Vec<_>>();
+            .any(|ext| ext == "EGL_ANDROID_native_fence_sync");
+
+        let sync = SyncPoint::create(self.egl_display.get_display_handle(), native)
+            .map_err(Gles2Error::FenceCreationFailed)?;
+        // the native fence fd is only available once the commands were flushed
+        unsafe { self.gl.Flush() };
+        Ok(sync)
+    }
+
+    /// Render a texture repeatedly to fill the rectangle described by dst.
+    ///
+    /// The texture is scaled to `tile_size` and repeated starting at the origin of `dest`,
+    /// tiles at the right and bottom edge are cut off. Like for
+    /// [`render_texture_from_to`](super::Frame::render_texture_from_to), `damage` is relative to `dest`.
+    ///
+    /// *Note*: GLES 2.0 only supports repeating textures with power-of-two dimensions,
+    /// unless `GL_OES_texture_npot` is supported. External textures cannot be repeated
+    /// and are stretched to fill `dest` instead.
+    pub fn render_texture_tiled(
+        &mut self,
+        texture: &Gles2Texture,
+        tile_size: Size<f64, Physical>,
+        dest: Rectangle<i32, Physical>,
+        damage: &[Rectangle<i32, Physical>],
+        alpha: f32,
+    ) -> Result<(), Gles2Error> {
+        if texture.0.is_external {
+            return self.render_texture_from_to(
+                texture,
+                Rectangle::from_loc_and_size((0, 0), texture.size()).to_f64(),
+                dest,
+                damage,
+                Transform::Normal,
+                alpha,
+            );
+        }
+
+        let mat = Matrix3::<f32>::from_translation(Vector2::new(dest.loc.x as f32, dest.loc.y as f32));
+
+        // every tile covers the texture coordinates from 0.0 to 1.0, which are repeated for the following tiles
+        let mut tex_mat = Matrix3::<f32>::identity();
+        if texture.0.y_inverted {
+            tex_mat = tex_mat * Matrix3::new(1.0, 0.0, 0.0, 0.0, -1.0, 0.0, 0.0, 1.0, 1.0);
+        }
+        tex_mat =
+            tex_mat * Matrix3::from_nonuniform_scale((1.0 / tile_size.w) as f32, (1.0 / tile_size.h) as f32);
+
+        self.track_damage(damage.iter().map(|rect| rect_at(*rect, dest)));
+        let instances = damage_instances(dest.size, damage);
+        self.set_texture_wrap(texture, ffi::REPEAT);
+        let res = self.render_texture(texture, tex_mat, mat, Some(&instances), alpha);
+        self.set_texture_wrap(texture, ffi::CLAMP_TO_EDGE);
+        res
+    }
+
+    fn set_texture_wrap(&self, texture: &Gles2Texture, wrap: ffi::types::GLenum) {
+        unsafe {
+            self.gl.BindTexture(ffi::TEXTURE_2D, texture.0.texture);
+            self.gl
+                .TexParameteri(ffi::TEXTURE_2D, ffi::TEXTURE_WRAP_S, wrap as i32);
+            self.gl
+                .TexParameteri(ffi::TEXTURE_2D, ffi::TEXTURE_WRAP_T, wrap as i32);
+            self.gl.BindTexture(ffi::TEXTURE_2D, 0);
+        }
+    }
+
+    /// Render a texture to the current target using given projection matrix and alpha.
+    ///  
+    /// The instances are used to define the regions which should get drawn.
+    /// Each instance has to define 4 [`GLfloat`](ffi::types::GLfloat) which define the
+    /// relative offset and scale for the vertex position and range from `0.0` to `1.0`.
+    /// The first 2 [`GLfloat`](ffi::types::GLfloat) define the relative x and y offset.
+    /// The remaining 2 [`GLfloat`](ffi::types::GLfloat) define the x and y scale.
+    /// This can be used to only update parts of the texture on screen.
+    ///
+    /// The given texture matrix is used to transform the instances into texture coordinates.
+    /// In case the texture is rotated, flipped or y-inverted the matrix has to be set up accordingly.
+    /// Additionally the matrix can be used to crop the texture.
+    pub fn render_texture(
+        &mut self,
+        tex: &Gles2Texture,
+        tex_matrix: Matrix3<f32>,
+        mut matrix: Matrix3<f32>,
+        instances: Option<&[ffi::types::GLfloat]>,
+        alpha: f32,
+    ) -> Result<(), Gles2Error> {
+        let damage = instances.unwrap_or(&[0.0, 0.0, 1.0, 1.0]);
+        if damage.is_empty() {
+            return Ok(());
+        }
+
+        //apply output transformation
+        matrix = self.current_projection * matrix;
+
+        let target = if tex.0.is_external {
+            ffi::TEXTURE_EXTERNAL_OES
+        } else {
+            ffi::TEXTURE_2D
+        };
+
+        // render
+        unsafe {
+            self.gl.ActiveTexture(ffi::TEXTURE0);
+            self.gl.BindTexture(target, tex.0.texture);
+            self.gl.TexParameteri(
+                target,
+                ffi::TEXTURE_MIN_FILTER,
+                match self.texture_filter.unwrap_or(self.min_filter) {
+                    TextureFilter::Nearest => ffi::NEAREST as i32,
+                    TextureFilter::Linear => ffi::LINEAR as i32,
+                },
+            );
+            self.gl.TexParameteri(
+                target,
+                ffi::TEXTURE_MAG_FILTER,
+                match self.texture_filter.unwrap_or(self.max_filter) {
+                    TextureFilter::Nearest => ffi::NEAREST as i32,
+                    TextureFilter::Linear => ffi::LINEAR as i32,
+                },
+            );
+            self.gl.UseProgram(self.tex_programs[tex.0.texture_kind].program);
 
-        unsafe {
-            self.gl.Disable(ffi::BLEND);
-            self.gl.UseProgram(self.solid_program.program);
-            self.gl.Uniform4f(
-                self.solid_program.uniform_color,
-                color[0],
-                color[1],
-                color[2],
-                color[3],
+            self.gl
+                .Uniform1i(self.tex_programs[tex.0.texture_kind].uniform_tex, 0);
+            self.gl.UniformMatrix3fv(
+                self.tex_programs[tex.0.texture_kind].uniform_matrix,
+                1,
+                ffi::FALSE,
+                matrix.as_ptr(),
+            );
+            self.gl.UniformMatrix3fv(
+                self.tex_programs[tex.0.texture_kind].uniform_tex_matrix,
+                1,
+                ffi::FALSE,
+                tex_matrix.as_ptr(),
             );
             self.gl
-                .UniformMatrix3fv(self.solid_program.uniform_matrix, 1, ffi::FALSE, mat.as_ptr());
+                .Uniform1f(self.tex_programs[tex.0.texture_kind].uniform_alpha, alpha);
+            self.gl.Uniform4f(
+                self.tex_programs[tex.0.texture_kind].uniform_tint,
+                self.tint[0],
+                self.tint[1],
+                self.tint[2],
+                self.tint[3],
+            );
+            self.gl.Uniform1i(
+                self.tex_programs[tex.0.texture_kind].uniform_decode_srgb,
+                (self.linear_blending && !tex.0.srgb) as i32,
+            );
+            self.gl.Uniform1i(
+                self.tex_programs[tex.0.texture_kind].uniform_apply_color_matrix,
+                self.color_matrix.is_some() as i32,
+            );
+            if let Some((color_matrix, color_offset)) = self.color_matrix {
+                self.gl.UniformMatrix3fv(
+                    self.tex_programs[tex.0.texture_kind].uniform_color_matrix,
+                    1,
+                    ffi::FALSE,
+                    color_matrix.as_ptr(),
+                );
+                self.gl.Uniform3f(
+                    self.tex_programs[tex.0.texture_kind].uniform_color_offset,
+                    color_offset.x,
+                    color_offset.y,
+                    color_offset.z,
+                );
+            }
 
             self.gl
-                .EnableVertexAttribArray(self.solid_program.attrib_vert as u32);
+                .EnableVertexAttribArray(self.tex_programs[tex.0.texture_kind].attrib_vert as u32);
             self.gl.BindBuffer(ffi::ARRAY_BUFFER, self.vbos[0]);
             self.gl.VertexAttribPointer(
                 self.solid_program.attrib_vert as u32,
@@ -1891,20 +3093,21 @@ impl Frame for Gles2Frame {
 
             // Damage vertices.
             let vertices = if self.supports_instancing {
-                damage
+                Cow::Borrowed(damage)
             } else {
-                // Add the 4 f32s per damage rectangle for each of the 6 vertices.
                 let mut vertices = Vec::with_capacity(damage.len() * 6);
+                // Add the 4 f32s per damage rectangle for each of the 6 vertices.
                 for chunk in damage.chunks(4) {
                     for _ in 0..6 {
                         vertices.extend_from_slice(chunk);
                     }
                 }
-                vertices
+                Cow::Owned(vertices)
             };
 
+            // vert_position
             self.gl
-                .EnableVertexAttribArray(self.solid_program.attrib_position as u32);
+                .EnableVertexAttribArray(self.tex_programs[tex.0.texture_kind].attrib_vert_position as u32);
             self.gl.BindBuffer(ffi::ARRAY_BUFFER, self.vbos[1]);
             self.gl.BufferData(
                 ffi::ARRAY_BUFFER,
@@ -1914,7 +3117,7 @@ impl Frame for Gles2Frame {
             );
 
             self.gl.VertexAttribPointer(
-                self.solid_program.attrib_position as u32,
+                self.tex_programs[tex.0.texture_kind].attrib_vert_position as u32,
                 4,
                 ffi::FLOAT,
                 ffi::FALSE,
@@ -1922,22 +3125,23 @@ impl Frame for Gles2Frame {
                 std::ptr::null(),
             );
 
-            let damage_len = at.len() as i32;
+            let damage_len = (damage.len() / 4) as i32;
             if self.supports_instancing {
                 self.gl
-                    .VertexAttribDivisor(self.solid_program.attrib_vert as u32, 0);
-
-                self.gl
-                    .VertexAttribDivisor(self.solid_program.attrib_position as u32, 1);
+                    .VertexAttribDivisor(self.tex_programs[tex.0.texture_kind].attrib_vert as u32, 0);
+                self.gl.VertexAttribDivisor(
+                    self.tex_programs[tex.0.texture_kind].attrib_vert_position as u32,
+                    1,
+                );
 
                 self.gl.DrawArraysInstanced(ffi::TRIANGLE_STRIP, 0, 4, damage_len);
             } else {
                 // When we have more than 10 rectangles, draw them in batches of 10.
                 for i in 0..(damage_len - 1) / 10 {
-                    self.gl.DrawArrays(ffi::TRIANGLES, 0, 60);
+                    self.gl.DrawArrays(ffi::TRIANGLES, 0, 6);
 
                     // Set damage pointer to the next 10 rectangles.
-                    let offset = (i + 1) as usize * 60 * 4 * std::mem::size_of::<ffi::types::GLfloat>();
+                    let offset = (i + 1) as usize * 6 * 4 * std::mem::size_of::<ffi::types::GLfloat>();
                     self.gl.VertexAttribPointer(
                         self.solid_program.attrib_position as u32,
                         4,
@@ -1954,85 +3158,531 @@ impl Frame for Gles2Frame {
             }
 
             self.gl.BindBuffer(ffi::ARRAY_BUFFER, 0);
+            self.gl.BindTexture(target, 0);
             self.gl
-                .DisableVertexAttribArray(self.solid_program.attrib_vert as u32);
+                .DisableVertexAttribArray(self.tex_programs[tex.0.texture_kind].attrib_vert as u32);
             self.gl
-                .DisableVertexAttribArray(self.solid_program.attrib_position as u32);
-            self.gl.Enable(ffi::BLEND);
-            self.gl.BlendFunc(ffi::ONE, ffi::ONE_MINUS_SRC_ALPHA);
+                .DisableVertexAttribArray(self.tex_programs[tex.0.texture_kind].attrib_vert_position as u32);
         }
 
         Ok(())
     }
 
-    fn render_texture_from_to(
+    /// Clear the given regions with the clear color of the renderer
+    ///
+    /// See [`Gles2Renderer::set_clear_color`]. Regions covered by opaque content drawn afterwards
+    /// don't need to be cleared, passing no regions skips the clear entirely.
+    pub fn clear_background(&mut self, at: &[Rectangle<i32, Physical>]) -> Result<(), Gles2Error> {
+        if at.is_empty() {
+            return Ok(());
+        }
+        self.clear(self.clear_color, at)
+    }
+
+    /// Color used by [`Gles2Frame::clear_background`]
+    pub fn clear_color(&self) -> [f32; 4] {
+        self.clear_color
+    }
+
+    /// Projection matrix for this frame
+    pub fn projection(&self) -> &[f32; 9] {
+        self.current_projection.as_ref()
+    }
+
+    /// Currently used [`BlendMode`] of this frame
+    pub fn blend_mode(&self) -> BlendMode {
+        self.blend_mode
+    }
+
+    /// Run the given closure with a different [`BlendMode`].
+    ///
+    /// All draw operations issued inside the closure use the given blend mode,
+    /// the previous mode is restored afterwards.
+    pub fn with_blend_mode<F, R>(&mut self, mode: BlendMode, func: F) -> R
+    where
+        F: FnOnce(&mut Self) -> R,
+    {
+        let previous = std::mem::replace(&mut self.blend_mode, mode);
+        self.apply_blend_mode();
+        let result = func(self);
+        self.blend_mode = previous;
+        self.apply_blend_mode();
+        result
+    }
+
+    /// Current tint of this frame
+    ///
+    /// The premultiplied color every draw operation is multiplied with, `[1.0; 4]` if untinted.
+    pub fn tint(&self) -> [f32; 4] {
+        self.tint
+    }
+
+    /// Run the given closure with an additional tint.
+    ///
+    /// The colors of all draw operations issued inside the closure are multiplied component-wise
+    /// with the given premultiplied color, e.g. `[0.5, 0.5, 0.5, 1.0]` darkens and `[0.5; 4]` makes
+    /// everything half transparent. Nested tints are multiplied with each other.
+    /// Frames created by [`Gles2Frame::with_offscreen`] start untinted.
+    pub fn with_tint<F, R>(&mut self, tint: [f32; 4], func: F) -> R
+    where
+        F: FnOnce(&mut Self) -> R,
+    {
+        let previous = self.tint;
+        for (current, tint) in self.tint.iter_mut().zip(tint) {
+            *current *= tint;
+        }
+        let result = func(self);
+        self.tint = previous;
+        result
+    }
+
+    /// Current color matrix of this frame and its offset
+    ///
+    /// Returns `None`, if colors are not transformed.
+    pub fn color_matrix(&self) -> Option<(Matrix3<f32>, Vector3<f32>)> {
+        self.color_matrix
+    }
+
+    /// Run the given closure with an additional color matrix.
+    ///
+    /// The straight (not premultiplied) colors of all textures drawn inside the closure are
+    /// transformed by `matrix * color + offset` and clamped, before alpha and tint are applied.
+    /// This can be used for effects like grayscale or desaturation, see [`saturation_matrix`].
+    /// Solid colors and borders are not affected.
+    ///
+    /// Nested color matrices are applied from the inside out, so the matrix of the outermost call
+    /// is applied last. The identity matrix without offset is skipped entirely.
+    /// Frames created by [`Gles2Frame::with_offscreen`] start without a color matrix.
+    pub fn with_color_matrix<F, R>(&mut self, matrix: Matrix3<f32>, offset: [f32; 3], func: F) -> R
+    where
+        F: FnOnce(&mut Self) -> R,
+    {
+        let previous = self.color_matrix;
+        let offset = Vector3::from(offset);
+        let (matrix, offset) = match previous {
+            Some((outer, outer_offset)) => (outer * matrix, outer * offset + outer_offset),
+            None => (matrix, offset),
+        };
+        self.color_matrix = if matrix.is_identity() && offset.is_zero() {
+            None
+        } else {
+            Some((matrix, offset))
+        };
+        let result = func(self);
+        self.color_matrix = previous;
+        result
+    }
+
+    /// Restrict all subsequent drawing of this frame to the given damage
+    ///
+    /// Sets up a scissor covering the bounding box of all rectangles, so the gpu skips all pixels
+    /// outside of it, even for draw operations covering larger areas. This complements the damage
+    /// passed to the individual draw operations and reduces the fill-rate needed for mostly static
+    /// contents. Clearing the frame only affects the damaged area as well.
+    ///
+    /// The damage is given in the coordinate space of the frame, like the damage of the draw operations.
+    /// Calling this again replaces the previous damage, passing an empty slice prevents any drawing.
+    /// Frames created by [`Gles2Frame::with_offscreen`] are not affected.
+    pub fn set_damage(&mut self, damage: &[Rectangle<i32, Physical>]) {
+        let bounds = damage
+            .iter()
+            .copied()
+            .reduce(|bounds, rect| bounds.merge(rect))
+            .unwrap_or_default();
+        self.scissor = Some(bounds);
+        self.apply_scissor();
+    }
+
+    // Clip drawing to the damage of this frame, if any was set
+    fn apply_scissor(&self) {
+        let (x, y, w, h) = match self.scissor {
+            Some(rect) => {
+                // the scissor box is given in window coordinates of the framebuffer
+                let corners = [rect.loc, rect.loc + rect.size.to_point()].map(|point| {
+                    let ndc = self.current_projection * Vector3::new(point.x as f32, point.y as f32, 1.0);
+                    (
+                        (ndc.x + 1.0) / 2.0 * self.size.w as f32,
+                        (ndc.y + 1.0) / 2.0 * self.size.h as f32,
+                    )
+                });
+                let x1 = (corners[0].0.min(corners[1].0).floor() as i32).clamp(0, self.size.w);
+                let y1 = (corners[0].1.min(corners[1].1).floor() as i32).clamp(0, self.size.h);
+                let x2 = (corners[0].0.max(corners[1].0).ceil() as i32).clamp(0, self.size.w);
+                let y2 = (corners[0].1.max(corners[1].1).ceil() as i32).clamp(0, self.size.h);
+                (x1, y1, x2 - x1, y2 - y1)
+            }
+            None => (0, 0, self.size.w, self.size.h),
+        };
+        unsafe {
+            self.gl.Scissor(x, y, w, h);
+        }
+    }
+
+    /// Render into an intermediate texture, e.g. for multi-pass effects.
+    ///
+    /// Binds a texture of the given size and format as the render target and runs the given closure
+    /// with a new frame rendering into it. Afterwards the previous target is restored and
+    /// the texture is returned for sampling in subsequent draw operations of this frame.
+    ///
+    /// Textures are pooled by size and format and reused in subsequent frames, once all references
+    /// to them are dropped. Targets not used during a frame are freed at the end of it.
+    /// The contents of a reused texture are undefined, so you should [`clear`](Frame::clear) it first.
+    ///
+    /// Supported formats are [`Fourcc::Abgr8888`], [`Fourcc::Xbgr8888`], [`Fourcc::Argb8888`]
+    /// and [`Fourcc::Xrgb8888`].
+    pub fn with_offscreen<F, R>(
         &mut self,
-        texture: &Self::TextureId,
-        src: Rectangle<f64, BufferCoord>,
-        dest: Rectangle<i32, Physical>,
-        damage: &[Rectangle<i32, Physical>],
-        transform: Transform,
-        alpha: f32,
-    ) -> Result<(), Self::Error> {
+        size: Size<i32, BufferCoord>,
+        format: Fourcc,
+        func: F,
+    ) -> Result<(Gles2Texture, R), Gles2Error>
+    where
+        F: FnOnce(&mut Gles2Frame) -> R,
+    {
+        let (gl_format, texture_kind) = match format {
+            Fourcc::Abgr8888 => (ffi::RGBA, 0),
+            Fourcc::Xbgr8888 => (ffi::RGBA, 1),
+            Fourcc::Argb8888 => (ffi::BGRA_EXT, 0),
+            Fourcc::Xrgb8888 => (ffi::BGRA_EXT, 1),
+            format => return Err(Gles2Error::UnsupportedMemoryFormat(format)),
+        };
+
+        let mut previous_fbo = 0;
+        unsafe {
+            self.gl.GetIntegerv(ffi::FRAMEBUFFER_BINDING, &mut previous_fbo);
+        }
+
+        let (texture, fbo) = self.offscreen_target(size, gl_format, texture_kind)?;
+        let size = Size::<i32, Physical>::from((size.w, size.h));
+        unsafe {
+            self.gl.BindFramebuffer(ffi::FRAMEBUFFER, fbo);
+            self.gl.Viewport(0, 0, size.w, size.h);
+            self.gl.Scissor(0, 0, size.w, size.h);
+        }
+
+        let mut frame = Gles2Frame {
+            gl: self.gl.clone(),
+            tex_programs: self.tex_programs.clone(),
+            solid_program: self.solid_program.clone(),
+            border_program: self.border_program.clone(),
+            shadow_program: self.shadow_program.clone(),
+            current_projection: projection_matrix(size, Transform::Normal),
+            transform: Transform::Normal,
+            vbos: self.vbos,
+            size,
+            min_filter: self.min_filter,
+            max_filter: self.max_filter,
+            texture_filter: None,
+            gl_version: self.gl_version,
+            supports_instancing: self.supports_instancing,
+            blend_mode: BlendMode::Over,
+            tint: [1.0; 4],
+            color_matrix: None,
+            linear_blending: self.linear_blending,
+            offscreen_targets: self.offscreen_targets.clone(),
+            destruction_callback_sender: self.destruction_callback_sender.clone(),
+            egl_display: self.egl_display.clone(),
+            synced: false,
+            gpu_timed: false,
+            debug_damage: None,
+            scissor: None,
+            clear_color: self.clear_color,
+        };
+        frame.apply_blend_mode();
+        let result = func(&mut frame);
+
+        unsafe {
+            self.gl
+                .BindFramebuffer(ffi::FRAMEBUFFER, previous_fbo as ffi::types::GLuint);
+            self.gl.Viewport(0, 0, self.size.w, self.size.h);
+        }
+        self.apply_scissor();
+        self.apply_blend_mode();
+
+        Ok((texture, result))
+    }
+
+    /// Copy the current contents of the framebuffer into a new texture
+    ///
+    /// The texture has the size of the framebuffer and contains everything drawn during this frame
+    /// so far. The contents are not transformed, so to reproduce the frame draw it using the
+    /// [transformation](Frame::transformation) of this frame as source transform.
+    ///
+    /// This is useful to keep the contents of an output around, e.g. to animate a transition
+    /// without re-rendering the old state.
+    pub fn copy_to_texture(&mut self) -> Gles2Texture {
+        let size = Size::<i32, BufferCoord>::from((self.size.w, self.size.h));
+        let mut alpha_bits = 0;
+        unsafe {
+            self.gl.GetIntegerv(ffi::ALPHA_BITS, &mut alpha_bits);
+        }
+        // the internal format needs to be compatible with the framebuffer
+        let (format, texture_kind) = match (alpha_bits > 0, self.linear_blending) {
+            (true, false) => (ffi::RGBA, 0),
+            (false, false) => (ffi::RGB, 1),
+            (true, true) => (ffi::SRGB8_ALPHA8, 0),
+            (false, true) => (ffi::SRGB8, 1),
+        };
+
+        let mut tex = 0;
+        unsafe {
+            self.gl.GenTextures(1, &mut tex);
+            self.gl.BindTexture(ffi::TEXTURE_2D, tex);
+            self.gl
+                .CopyTexImage2D(ffi::TEXTURE_2D, 0, format, 0, 0, size.w, size.h, 0);
+            self.gl.BindTexture(ffi::TEXTURE_2D, 0);
+        }
+
+        Gles2Texture(Rc::new(Gles2TextureInternal {
+            texture: tex,
+            texture_kind,
+            is_external: false,
+            y_inverted: false,
+            size,
+            srgb: self.linear_blending,
+            egl_images: None,
+            destruction_callback_sender: self.destruction_callback_sender.clone(),
+        }))
+    }
+
+    // Draw solid rectangles, either replacing the existing content or blending on top of it
+    fn draw_solid(&mut self, color: [f32; 4], at: &[Rectangle<i32, Physical>], blend: bool) {
+        if at.is_empty() {
+            return;
+        }
+
         let mut mat = Matrix3::<f32>::identity();
+        mat = self.current_projection * mat;
 
-        // dest position and scale
-        mat = mat * Matrix3::from_translation(Vector2::new(dest.loc.x as f32, dest.loc.y as f32));
+        let color = if self.linear_blending {
+            srgb_to_linear(color)
+        } else {
+            color
+        };
 
-        // src scale, position, tranform and y_inverted
-        let tex_size = texture.size().to_f64();
-        let src_size = src.size;
+        unsafe {
+            if !blend {
+                self.gl.Disable(ffi::BLEND);
+            }
+            self.gl.UseProgram(self.solid_program.program);
+            self.gl.Uniform4f(
+                self.solid_program.uniform_color,
+                color[0] * self.tint[0],
+                color[1] * self.tint[1],
+                color[2] * self.tint[2],
+                color[3] * self.tint[3],
+            );
+            self.gl
+                .UniformMatrix3fv(self.solid_program.uniform_matrix, 1, ffi::FALSE, mat.as_ptr());
+
+            self.draw_rects(
+                self.solid_program.attrib_vert,
+                self.solid_program.attrib_position,
+                at,
+            );
+            if !blend {
+                self.gl.Enable(ffi::BLEND);
+            }
+        }
+        self.apply_blend_mode();
+    }
+
+    /// Fill `geometry` with a solid color
+    ///
+    /// Unlike [`Frame::clear`] the color is blended on top of the existing content
+    /// using the current blend mode, so translucent colors are supported.
+    ///
+    /// The color is expected to be premultiplied, like for [`Frame::clear`].
+    /// Only the parts of `geometry` inside of `damage` are drawn.
+    pub fn draw_solid_color(
+        &mut self,
+        geometry: Rectangle<i32, Physical>,
+        color: [f32; 4],
+        damage: &[Rectangle<i32, Physical>],
+    ) -> Result<(), Gles2Error> {
+        let at = damage
+            .iter()
+            .filter_map(|rect| rect.intersection(geometry))
+            .collect::<Vec<_>>();
+        self.track_damage(at.iter().copied());
+        self.draw_solid(color, &at, true);
+        Ok(())
+    }
+
+    /// Draw a border along the edges of `geometry`
+    ///
+    /// Only the border band with the given `thickness` is drawn, extending inwards from the edges
+    /// of `geometry`. The corners are rounded with the given radii, in the order top-left,
+    /// top-right, bottom-right and bottom-left, which apply to the outer edge of the border.
+    /// Edges are anti-aliased.
+    ///
+    /// The color is expected to be premultiplied, like for [`Frame::clear`].
+    /// Only the parts of the border inside of `damage` are drawn.
+    pub fn draw_border(
+        &mut self,
+        geometry: Rectangle<i32, Physical>,
+        thickness: f32,
+        radius: [f32; 4],
+        color: [f32; 4],
+        damage: &[Rectangle<i32, Physical>],
+    ) -> Result<(), Gles2Error> {
+        // skip the inside of the border, which is not covered anyway
+        let band = radius.iter().fold(thickness, |band, r| band.max(*r)).ceil() as i32;
+        let bands = if band * 2 >= geometry.size.w || band * 2 >= geometry.size.h {
+            vec![geometry]
+        } else {
+            let (x, y) = (geometry.loc.x, geometry.loc.y);
+            let (w, h) = (geometry.size.w, geometry.size.h);
+            vec![
+                Rectangle::from_loc_and_size((x, y), (w, band)),
+                Rectangle::from_loc_and_size((x, y + h - band), (w, band)),
+                Rectangle::from_loc_and_size((x, y + band), (band, h - 2 * band)),
+                Rectangle::from_loc_and_size((x + w - band, y + band), (band, h - 2 * band)),
+            ]
+        };
+        let at = bands
+            .iter()
+            .flat_map(|band| damage.iter().filter_map(|rect| rect.intersection(*band)))
+            .collect::<Vec<_>>();
+        if at.is_empty() {
+            return Ok(());
+        }
+        self.track_damage(at.iter().copied());
+
+        let mat = self.current_projection;
+        let color = if self.linear_blending {
+            srgb_to_linear(color)
+        } else {
+            color
+        };
+
+        unsafe {
+            self.gl.UseProgram(self.border_program.program);
+            self.gl.Uniform4f(
+                self.border_program.uniform_color,
+                color[0] * self.tint[0],
+                color[1] * self.tint[1],
+                color[2] * self.tint[2],
+                color[3] * self.tint[3],
+            );
+            self.gl.Uniform4f(
+                self.border_program.uniform_rect,
+                geometry.loc.x as f32,
+                geometry.loc.y as f32,
+                geometry.size.w as f32,
+                geometry.size.h as f32,
+            );
+            self.gl
+                .Uniform1f(self.border_program.uniform_thickness, thickness);
+            self.gl.Uniform4f(
+                self.border_program.uniform_radius,
+                radius[0],
+                radius[1],
+                radius[2],
+                radius[3],
+            );
+            self.gl
+                .UniformMatrix3fv(self.border_program.uniform_matrix, 1, ffi::FALSE, mat.as_ptr());
+
+            self.draw_rects(
+                self.border_program.attrib_vert,
+                self.border_program.attrib_position,
+                &at,
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Draw a soft shadow of a rounded rectangle
+    ///
+    /// The shadow has the shape of `geometry` with corners rounded by the given radii, in the order
+    /// top-left, top-right, bottom-right and bottom-left, blurred by a gaussian blur fading out
+    /// within `blur_radius` around it. The shadow is computed analytically in a single pass,
+    /// straight edges are exact and corners closely approximated.
+    /// The area covered is `geometry` expanded by `blur_radius` on every side.
+    ///
+    /// The color is expected to be premultiplied, like for [`Frame::clear`].
+    /// Only the parts of the shadow inside of `damage` are drawn.
+    pub fn draw_shadow(
+        &mut self,
+        geometry: Rectangle<i32, Physical>,
+        blur_radius: f32,
+        radius: [f32; 4],
+        color: [f32; 4],
+        damage: &[Rectangle<i32, Physical>],
+    ) -> Result<(), Gles2Error> {
+        let blur = blur_radius.max(0.0).ceil() as i32;
+        let area = Rectangle::from_loc_and_size(
+            (geometry.loc.x - blur, geometry.loc.y - blur),
+            (geometry.size.w + 2 * blur, geometry.size.h + 2 * blur),
+        );
+        let at = damage
+            .iter()
+            .filter_map(|rect| rect.intersection(area))
+            .collect::<Vec<_>>();
+        if at.is_empty() {
+            return Ok(());
+        }
+        self.track_damage(at.iter().copied());
 
-        let transform_mat = if transform.flipped() {
-            transform.matrix()
+        let mat = self.current_projection;
+        let color = if self.linear_blending {
+            srgb_to_linear(color)
         } else {
-            transform.invert().matrix()
+            color
         };
+        // the blur fades out within three standard deviations,
+        // avoid dividing by zero for unblurred shadows
+        let sigma = (blur_radius / 3.0).max(0.01);
 
-        let mut tex_mat = Matrix3::<f32>::identity();
-        // first scale to meet the src size
-        tex_mat = tex_mat
-            * Matrix3::from_nonuniform_scale(
-                (src_size.w / tex_size.w) as f32,
-                (src_size.h / tex_size.h) as f32,
+        unsafe {
+            self.gl.UseProgram(self.shadow_program.program);
+            self.gl.Uniform4f(
+                self.shadow_program.uniform_color,
+                color[0] * self.tint[0],
+                color[1] * self.tint[1],
+                color[2] * self.tint[2],
+                color[3] * self.tint[3],
             );
-        // now translate by the src location
-        tex_mat = tex_mat
-            * Matrix3::from_translation(Vector2::new(
-                (src.loc.x / src_size.w) as f32,
-                (src.loc.y / src_size.h) as f32,
-            ));
-        // then apply the transform and if necessary invert the y axis
-        tex_mat = tex_mat * Matrix3::from_translation(Vector2::new(0.5, 0.5));
-        if transform == Transform::Normal {
-            assert_eq!(tex_mat, tex_mat * transform.invert().matrix());
-            assert_eq!(transform.matrix(), Matrix3::<f32>::identity());
-        }
-        tex_mat = tex_mat * transform_mat;
-        if texture.0.y_inverted {
-            tex_mat = tex_mat * Matrix3::new(1.0, 0.0, 0.0, 0.0, -1.0, 0.0, 0.0, 0.0, 1.0);
-        }
-        tex_mat = tex_mat * Matrix3::from_translation(Vector2::new(-0.5, -0.5));
-        // at last scale back to tex space
-        tex_mat = tex_mat
-            * Matrix3::from_nonuniform_scale(
-                (1.0f64 / dest.size.w as f64) as f32,
-                (1.0f64 / dest.size.h as f64) as f32,
+            self.gl.Uniform4f(
+                self.shadow_program.uniform_rect,
+                geometry.loc.x as f32,
+                geometry.loc.y as f32,
+                geometry.size.w as f32,
+                geometry.size.h as f32,
+            );
+            self.gl.Uniform1f(self.shadow_program.uniform_sigma, sigma);
+            self.gl.Uniform4f(
+                self.shadow_program.uniform_radius,
+                radius[0],
+                radius[1],
+                radius[2],
+                radius[3],
             );
+            self.gl
+                .UniformMatrix3fv(self.shadow_program.uniform_matrix, 1, ffi::FALSE, mat.as_ptr());
 
-        let instances = damage
-            .iter()
-            .flat_map(|rect| {
-                let dest_size = dest.size;
+            self.draw_rects(
+                self.shadow_program.attrib_vert,
+                self.shadow_program.attrib_position,
+                &at,
+            );
+        }
 
-                let rect_constrained_loc = rect
-                    .loc
-                    .constrain(Rectangle::from_extemities((0, 0), dest_size.to_point()));
-                let rect_clamped_size = rect
-                    .size
-                    .clamp((0, 0), (dest_size.to_point() - rect_constrained_loc).to_size());
+        Ok(())
+    }
 
-                let rect = Rectangle::from_loc_and_size(rect_constrained_loc, rect_clamped_size);
+    // Draw the given rectangles with the currently used program
+    unsafe fn draw_rects(
+        &self,
+        attrib_vert: ffi::types::GLint,
+        attrib_position: ffi::types::GLint,
+        at: &[Rectangle<i32, Physical>],
+    ) {
+        let damage = at
+            .iter()
+            .flat_map(|rect| {
                 [
                     rect.loc.x as f32,
                     rect.loc.y as f32,
@@ -2042,179 +3692,175 @@ impl Frame for Gles2Frame {
             })
             .collect::<Vec<_>>();
 
-        self.render_texture(texture, tex_mat, mat, Some(&instances), alpha)
+        self.gl.EnableVertexAttribArray(attrib_vert as u32);
+        self.gl.BindBuffer(ffi::ARRAY_BUFFER, self.vbos[0]);
+        self.gl
+            .VertexAttribPointer(attrib_vert as u32, 2, ffi::FLOAT, ffi::FALSE, 0, std::ptr::null());
+
+        // Damage vertices.
+        let vertices = if self.supports_instancing {
+            damage
+        } else {
+            // Add the 4 f32s per damage rectangle for each of the 6 vertices.
+            let mut vertices = Vec::with_capacity(damage.len() * 6);
+            for chunk in damage.chunks(4) {
+                for _ in 0..6 {
+                    vertices.extend_from_slice(chunk);
+                }
+            }
+            vertices
+        };
+
+        self.gl.EnableVertexAttribArray(attrib_position as u32);
+        self.gl.BindBuffer(ffi::ARRAY_BUFFER, self.vbos[1]);
+        self.gl.BufferData(
+            ffi::ARRAY_BUFFER,
+            (std::mem::size_of::<ffi::types::GLfloat>() * vertices.len()) as isize,
+            vertices.as_ptr() as *const _,
+            ffi::STREAM_DRAW,
+        );
+
+        self.gl.VertexAttribPointer(
+            attrib_position as u32,
+            4,
+            ffi::FLOAT,
+            ffi::FALSE,
+            0,
+            std::ptr::null(),
+        );
+
+        let damage_len = at.len() as i32;
+        if self.supports_instancing {
+            self.gl.VertexAttribDivisor(attrib_vert as u32, 0);
+
+            self.gl.VertexAttribDivisor(attrib_position as u32, 1);
+
+            self.gl.DrawArraysInstanced(ffi::TRIANGLE_STRIP, 0, 4, damage_len);
+        } else {
+            // When we have more than 10 rectangles, draw them in batches of 10.
+            for i in 0..(damage_len - 1) / 10 {
+                self.gl.DrawArrays(ffi::TRIANGLES, 0, 60);
+
+                // Set damage pointer to the next 10 rectangles.
+                let offset = (i + 1) as usize * 60 * 4 * std::mem::size_of::<ffi::types::GLfloat>();
+                self.gl.VertexAttribPointer(
+                    attrib_position as u32,
+                    4,
+                    ffi::FLOAT,
+                    ffi::FALSE,
+                    0,
+                    offset as *const _,
+                );
+            }
+
+            // Draw the up to 10 remaining rectangles.
+            let count = ((damage_len - 1) % 10 + 1) * 6;
+            self.gl.DrawArrays(ffi::TRIANGLES, 0, count);
+        }
+
+        self.gl.BindBuffer(ffi::ARRAY_BUFFER, 0);
+        self.gl.DisableVertexAttribArray(attrib_vert as u32);
+        self.gl.DisableVertexAttribArray(attrib_position as u32);
     }
 
-    fn transformation(&self) -> Transform {
-        self.transform
+    // Remember the given regions, if damage is tinted
+    fn track_damage(&mut self, damage: impl Iterator<Item = Rectangle<i32, Physical>>) {
+        if let Some((_, debug_damage)) = self.debug_damage.as_mut() {
+            debug_damage.extend(damage);
+        }
     }
-}
 
-impl Gles2Frame {
-    /// Render a texture to the current target using given projection matrix and alpha.
-    ///  
-    /// The instances are used to define the regions which should get drawn.
-    /// Each instance has to define 4 [`GLfloat`](ffi::types::GLfloat) which define the
-    /// relative offset and scale for the vertex position and range from `0.0` to `1.0`.
-    /// The first 2 [`GLfloat`](ffi::types::GLfloat) define the relative x and y offset.
-    /// The remaining 2 [`GLfloat`](ffi::types::GLfloat) define the x and y scale.
-    /// This can be used to only update parts of the texture on screen.
-    ///
-    /// The given texture matrix is used to transform the instances into texture coordinates.
-    /// In case the texture is rotated, flipped or y-inverted the matrix has to be set up accordingly.
-    /// Additionally the matrix can be used to crop the texture.
-    pub fn render_texture(
-        &mut self,
-        tex: &Gles2Texture,
-        tex_matrix: Matrix3<f32>,
-        mut matrix: Matrix3<f32>,
-        instances: Option<&[ffi::types::GLfloat]>,
-        alpha: f32,
-    ) -> Result<(), Gles2Error> {
-        let damage = instances.unwrap_or(&[0.0, 0.0, 1.0, 1.0]);
-        if damage.is_empty() {
-            return Ok(());
+    // Issue the last commands of the frame, the damage tint and the end of the gpu timer query.
+    // Runs before the frame is synced or submitted, so they are covered by either.
+    fn finish_commands(&mut self) {
+        if let Some((color, damage)) = self.debug_damage.take() {
+            self.draw_solid(color, &damage, true);
+        }
+        if std::mem::take(&mut self.gpu_timed) {
+            unsafe { self.gl.EndQueryEXT(ffi::TIME_ELAPSED_EXT) };
         }
+    }
 
-        //apply output transformation
-        matrix = self.current_projection * matrix;
+    // Take an unused target from the pool or create a new one
+    fn offscreen_target(
+        &self,
+        size: Size<i32, BufferCoord>,
+        format: ffi::types::GLenum,
+        texture_kind: usize,
+    ) -> Result<(Gles2Texture, ffi::types::GLuint), Gles2Error> {
+        let mut targets = self.offscreen_targets.borrow_mut();
+        if let Some(target) = targets.iter().find(|target| {
+            target.texture.0.size == size
+                && target.format == format
+                && target.texture.0.texture_kind == texture_kind
+                // nobody else is holding on to the texture
+                && Rc::strong_count(&target.texture.0) == 1
+        }) {
+            target.used.set(true);
+            return Ok((target.texture.clone(), target.fbo));
+        }
 
-        let target = if tex.0.is_external {
-            ffi::TEXTURE_EXTERNAL_OES
+        // the pixel format only matters for uploads, sRGB textures need to be specified as RGBA
+        let (internal_format, pixel_format) = if self.linear_blending {
+            (ffi::SRGB8_ALPHA8, ffi::RGBA)
         } else {
-            ffi::TEXTURE_2D
+            (format, format)
         };
 
-        // render
+        let mut tex = 0;
+        let mut fbo = 0;
         unsafe {
-            self.gl.ActiveTexture(ffi::TEXTURE0);
-            self.gl.BindTexture(target, tex.0.texture);
-            self.gl.TexParameteri(
-                target,
-                ffi::TEXTURE_MIN_FILTER,
-                match self.min_filter {
-                    TextureFilter::Nearest => ffi::NEAREST as i32,
-                    TextureFilter::Linear => ffi::LINEAR as i32,
-                },
-            );
-            self.gl.TexParameteri(
-                target,
-                ffi::TEXTURE_MAG_FILTER,
-                match self.max_filter {
-                    TextureFilter::Nearest => ffi::NEAREST as i32,
-                    TextureFilter::Linear => ffi::LINEAR as i32,
-                },
-            );
-            self.gl.UseProgram(self.tex_programs[tex.0.texture_kind].program);
-
-            self.gl
-                .Uniform1i(self.tex_programs[tex.0.texture_kind].uniform_tex, 0);
-            self.gl.UniformMatrix3fv(
-                self.tex_programs[tex.0.texture_kind].uniform_matrix,
-                1,
-                ffi::FALSE,
-                matrix.as_ptr(),
-            );
-            self.gl.UniformMatrix3fv(
-                self.tex_programs[tex.0.texture_kind].uniform_tex_matrix,
-                1,
-                ffi::FALSE,
-                tex_matrix.as_ptr(),
-            );
-            self.gl
-                .Uniform1f(self.tex_programs[tex.0.texture_kind].uniform_alpha, alpha);
-
-            self.gl
-                .EnableVertexAttribArray(self.tex_programs[tex.0.texture_kind].attrib_vert as u32);
-            self.gl.BindBuffer(ffi::ARRAY_BUFFER, self.vbos[0]);
-            self.gl.VertexAttribPointer(
-                self.solid_program.attrib_vert as u32,
-                2,
-                ffi::FLOAT,
-                ffi::FALSE,
+            self.gl.GenTextures(1, &mut tex);
+            self.gl.BindTexture(ffi::TEXTURE_2D, tex);
+            self.gl.TexImage2D(
+                ffi::TEXTURE_2D,
                 0,
-                std::ptr::null(),
-            );
-
-            // Damage vertices.
-            let vertices = if self.supports_instancing {
-                Cow::Borrowed(damage)
-            } else {
-                let mut vertices = Vec::with_capacity(damage.len() * 6);
-                // Add the 4 f32s per damage rectangle for each of the 6 vertices.
-                for chunk in damage.chunks(4) {
-                    for _ in 0..6 {
-                        vertices.extend_from_slice(chunk);
-                    }
-                }
-                Cow::Owned(vertices)
-            };
-
-            // vert_position
-            self.gl
-                .EnableVertexAttribArray(self.tex_programs[tex.0.texture_kind].attrib_vert_position as u32);
-            self.gl.BindBuffer(ffi::ARRAY_BUFFER, self.vbos[1]);
-            self.gl.BufferData(
-                ffi::ARRAY_BUFFER,
-                (std::mem::size_of::<ffi::types::GLfloat>() * vertices.len()) as isize,
-                vertices.as_ptr() as *const _,
-                ffi::STREAM_DRAW,
-            );
-
-            self.gl.VertexAttribPointer(
-                self.tex_programs[tex.0.texture_kind].attrib_vert_position as u32,
-                4,
-                ffi::FLOAT,
-                ffi::FALSE,
+                internal_format as i32,
+                size.w,
+                size.h,
                 0,
+                pixel_format,
+                ffi::UNSIGNED_BYTE,
                 std::ptr::null(),
             );
+            self.gl.BindTexture(ffi::TEXTURE_2D, 0);
 
-            let damage_len = (damage.len() / 4) as i32;
-            if self.supports_instancing {
-                self.gl
-                    .VertexAttribDivisor(self.tex_programs[tex.0.texture_kind].attrib_vert as u32, 0);
-                self.gl.VertexAttribDivisor(
-                    self.tex_programs[tex.0.texture_kind].attrib_vert_position as u32,
-                    1,
-                );
-
-                self.gl.DrawArraysInstanced(ffi::TRIANGLE_STRIP, 0, 4, damage_len);
-            } else {
-                // When we have more than 10 rectangles, draw them in batches of 10.
-                for i in 0..(damage_len - 1) / 10 {
-                    self.gl.DrawArrays(ffi::TRIANGLES, 0, 6);
-
-                    // Set damage pointer to the next 10 rectangles.
-                    let offset = (i + 1) as usize * 6 * 4 * std::mem::size_of::<ffi::types::GLfloat>();
-                    self.gl.VertexAttribPointer(
-                        self.solid_program.attrib_position as u32,
-                        4,
-                        ffi::FLOAT,
-                        ffi::FALSE,
-                        0,
-                        offset as *const _,
-                    );
-                }
-
-                // Draw the up to 10 remaining rectangles.
-                let count = ((damage_len - 1) % 10 + 1) * 6;
-                self.gl.DrawArrays(ffi::TRIANGLES, 0, count);
-            }
-
-            self.gl.BindBuffer(ffi::ARRAY_BUFFER, 0);
-            self.gl.BindTexture(target, 0);
-            self.gl
-                .DisableVertexAttribArray(self.tex_programs[tex.0.texture_kind].attrib_vert as u32);
+            self.gl.GenFramebuffers(1, &mut fbo as *mut _);
+            self.gl.BindFramebuffer(ffi::FRAMEBUFFER, fbo);
             self.gl
-                .DisableVertexAttribArray(self.tex_programs[tex.0.texture_kind].attrib_vert_position as u32);
+                .FramebufferTexture2D(ffi::FRAMEBUFFER, ffi::COLOR_ATTACHMENT0, ffi::TEXTURE_2D, tex, 0);
+            let status = self.gl.CheckFramebufferStatus(ffi::FRAMEBUFFER);
+            if status != ffi::FRAMEBUFFER_COMPLETE {
+                self.gl.DeleteFramebuffers(1, &mut fbo as *mut _);
+                self.gl.DeleteTextures(1, &mut tex as *mut _);
+                return Err(Gles2Error::FramebufferBindingError);
+            }
         }
 
-        Ok(())
+        let texture = Gles2Texture(Rc::new(Gles2TextureInternal {
+            texture: tex,
+            texture_kind,
+            is_external: false,
+            y_inverted: false,
+            size,
+            srgb: self.linear_blending,
+            egl_images: None,
+            destruction_callback_sender: self.destruction_callback_sender.clone(),
+        }));
+        targets.push(OffscreenTarget {
+            texture: texture.clone(),
+            fbo,
+            format,
+            used: Cell::new(true),
+        });
+
+        Ok((texture, fbo))
     }
 
-    /// Projection matrix for this frame
-    pub fn projection(&self) -> &[f32; 9] {
-        self.current_projection.as_ref()
+    fn apply_blend_mode(&self) {
+        let (src, dst) = self.blend_mode.factors();
+        unsafe {
+            self.gl.BlendFunc(src, dst);
+        }
     }
 }
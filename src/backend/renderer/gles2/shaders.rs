@@ -35,10 +35,39 @@ pub const FRAGMENT_SHADER_ABGR: &str = r#"
 precision mediump float;
 uniform sampler2D tex;
 uniform float alpha;
+uniform vec4 tint;
+uniform bool decode_srgb;
+uniform bool apply_color_matrix;
+uniform mat3 color_matrix;
+uniform vec3 color_offset;
 varying vec2 v_tex_coords;
 
+vec4 srgb_to_linear(vec4 color) {
+    if (color.a == 0.0) {
+        return color;
+    }
+    vec3 c = color.rgb / color.a;
+    c = mix(c / 12.92, pow((c + 0.055) / 1.055, vec3(2.4)), step(0.04045, c));
+    return vec4(c * color.a, color.a);
+}
+
+vec4 transform_color(vec4 color) {
+    if (color.a == 0.0) {
+        return color;
+    }
+    vec3 c = clamp(color_matrix * (color.rgb / color.a) + color_offset, 0.0, 1.0);
+    return vec4(c * color.a, color.a);
+}
+
 void main() {
-    gl_FragColor = texture2D(tex, v_tex_coords) * alpha;
+    vec4 color = texture2D(tex, v_tex_coords);
+    if (decode_srgb) {
+        color = srgb_to_linear(color);
+    }
+    if (apply_color_matrix) {
+        color = transform_color(color);
+    }
+    gl_FragColor = color * alpha * tint;
 }
 "#;
 
@@ -48,10 +77,39 @@ pub const FRAGMENT_SHADER_XBGR: &str = r#"
 precision mediump float;
 uniform sampler2D tex;
 uniform float alpha;
+uniform vec4 tint;
+uniform bool decode_srgb;
+uniform bool apply_color_matrix;
+uniform mat3 color_matrix;
+uniform vec3 color_offset;
 varying vec2 v_tex_coords;
 
+vec4 srgb_to_linear(vec4 color) {
+    if (color.a == 0.0) {
+        return color;
+    }
+    vec3 c = color.rgb / color.a;
+    c = mix(c / 12.92, pow((c + 0.055) / 1.055, vec3(2.4)), step(0.04045, c));
+    return vec4(c * color.a, color.a);
+}
+
+vec4 transform_color(vec4 color) {
+    if (color.a == 0.0) {
+        return color;
+    }
+    vec3 c = clamp(color_matrix * (color.rgb / color.a) + color_offset, 0.0, 1.0);
+    return vec4(c * color.a, color.a);
+}
+
 void main() {
-    gl_FragColor = vec4(texture2D(tex, v_tex_coords).rgb, 1.0) * alpha;
+    vec4 color = vec4(texture2D(tex, v_tex_coords).rgb, 1.0);
+    if (decode_srgb) {
+        color = srgb_to_linear(color);
+    }
+    if (apply_color_matrix) {
+        color = transform_color(color);
+    }
+    gl_FragColor = color * alpha * tint;
 }
 "#;
 
@@ -62,10 +120,39 @@ pub const FRAGMENT_SHADER_EXTERNAL: &str = r#"
 precision mediump float;
 uniform samplerExternalOES tex;
 uniform float alpha;
+uniform vec4 tint;
+uniform bool decode_srgb;
+uniform bool apply_color_matrix;
+uniform mat3 color_matrix;
+uniform vec3 color_offset;
 varying vec2 v_tex_coords;
 
+vec4 srgb_to_linear(vec4 color) {
+    if (color.a == 0.0) {
+        return color;
+    }
+    vec3 c = color.rgb / color.a;
+    c = mix(c / 12.92, pow((c + 0.055) / 1.055, vec3(2.4)), step(0.04045, c));
+    return vec4(c * color.a, color.a);
+}
+
+vec4 transform_color(vec4 color) {
+    if (color.a == 0.0) {
+        return color;
+    }
+    vec3 c = clamp(color_matrix * (color.rgb / color.a) + color_offset, 0.0, 1.0);
+    return vec4(c * color.a, color.a);
+}
+
 void main() {
-    gl_FragColor = texture2D(tex, v_tex_coords) * alpha;
+    vec4 color = texture2D(tex, v_tex_coords);
+    if (decode_srgb) {
+        color = srgb_to_linear(color);
+    }
+    if (apply_color_matrix) {
+        color = transform_color(color);
+    }
+    gl_FragColor = color * alpha * tint;
 }
 "#;
 
@@ -101,3 +188,106 @@ void main() {
     gl_FragColor = color;
 }
 "#;
+
+pub const VERTEX_SHADER_BORDER: &str = r#"
+#version 100
+
+uniform mat3 matrix;
+attribute vec2 vert;
+attribute vec4 position;
+
+varying vec2 v_pos;
+
+mat2 scale(vec2 scale_vec){
+    return mat2(
+        scale_vec.x, 0.0,
+        0.0, scale_vec.y
+    );
+}
+
+void main() {
+    vec2 transform_translation = position.xy;
+    vec2 transform_scale = position.zw;
+    vec2 pos = vert * scale(transform_scale) + transform_translation;
+    v_pos = pos;
+    gl_Position = vec4(matrix * vec3(pos, 1.0), 1.0);
+}
+"#;
+
+pub const FRAGMENT_SHADER_BORDER: &str = r#"
+#version 100
+
+#ifdef GL_FRAGMENT_PRECISION_HIGH
+precision highp float;
+#else
+precision mediump float;
+#endif
+uniform vec4 color;
+// location and size of the outer edge
+uniform vec4 rect;
+uniform float thickness;
+// top-left, top-right, bottom-right, bottom-left
+uniform vec4 radius;
+varying vec2 v_pos;
+
+// signed distance to the edge of a rounded box centered at the origin
+float rounded_box(vec2 p, vec2 half_size, float r) {
+    vec2 q = abs(p) - half_size + r;
+    return length(max(q, 0.0)) + min(max(q.x, q.y), 0.0) - r;
+}
+
+void main() {
+    vec2 half_size = rect.zw * 0.5;
+    vec2 p = v_pos - rect.xy - half_size;
+    float r = p.x < 0.0 ? (p.y < 0.0 ? radius.x : radius.w) : (p.y < 0.0 ? radius.y : radius.z);
+    float outer = rounded_box(p, half_size, r);
+    vec2 inner_half_size = max(half_size - thickness, 0.0);
+    float inner = rounded_box(p, inner_half_size, max(r - thickness, 0.0));
+    float coverage = clamp(0.5 - outer, 0.0, 1.0) * clamp(0.5 + inner, 0.0, 1.0);
+    gl_FragColor = color * coverage;
+}
+"#;
+
+// drawn using VERTEX_SHADER_BORDER
+pub const FRAGMENT_SHADER_SHADOW: &str = r#"
+#version 100
+
+#ifdef GL_FRAGMENT_PRECISION_HIGH
+precision highp float;
+#else
+precision mediump float;
+#endif
+uniform vec4 color;
+// location and size of the shape casting the shadow
+uniform vec4 rect;
+// standard deviation of the gaussian blur
+uniform float sigma;
+// top-left, top-right, bottom-right, bottom-left
+uniform vec4 radius;
+varying vec2 v_pos;
+
+// signed distance to the edge of a rounded box centered at the origin
+float rounded_box(vec2 p, vec2 half_size, float r) {
+    vec2 q = abs(p) - half_size + r;
+    return length(max(q, 0.0)) + min(max(q.x, q.y), 0.0) - r;
+}
+
+// approximation of the error function, with a maximum error of 5e-4
+float erf_approx(float x) {
+    float s = sign(x);
+    float a = abs(x);
+    x = 1.0 + (0.278393 + (0.230389 + 0.078108 * (a * a)) * a) * a;
+    x *= x;
+    return s - s / (x * x);
+}
+
+void main() {
+    vec2 half_size = rect.zw * 0.5;
+    vec2 p = v_pos - rect.xy - half_size;
+    float r = p.x < 0.0 ? (p.y < 0.0 ? radius.x : radius.w) : (p.y < 0.0 ? radius.y : radius.z);
+    float distance = rounded_box(p, half_size, min(r, min(half_size.x, half_size.y)));
+    // exact for straight edges, an approximation close to the corners
+    float coverage = 0.5 - 0.5 * erf_approx(distance / (sigma * 1.41421356));
+    gl_FragColor = color * coverage;
+}
+"#;
@@ -0,0 +1,120 @@
+use std::{
+    fmt,
+    os::unix::io::{FromRawFd, OwnedFd},
+    sync::Arc,
+};
+
+use crate::backend::egl::{
+    display::EGLDisplayHandle,
+    ffi::egl::{self as ffi_egl, types::EGLSyncKHR},
+    wrap_egl_call, EGLError,
+};
+
+/// Point in the command stream of a [`Gles2Renderer`](super::Gles2Renderer), that is signaled
+/// once all rendering commands submitted before it have been completed by the gpu.
+///
+/// Created by [`Gles2Frame::finish_with_sync`](super::Gles2Frame::finish_with_sync).
+pub struct SyncPoint {
+    display: Arc<EGLDisplayHandle>,
+    sync: EGLSyncKHR,
+    native: bool,
+}
+
+// SAFETY: EGL sync objects belong to the display and may be used from any thread
+unsafe impl Send for SyncPoint {}
+unsafe impl Sync for SyncPoint {}
+
+impl fmt::Debug for SyncPoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SyncPoint")
+            .field("display", &self.display)
+            .field("native", &self.native)
+            .finish_non_exhaustive()
+    }
+}
+
+impl SyncPoint {
+    /// Insert a new fence into the command stream of the current context.
+    ///
+    /// Creates a native fence, if `native` is true. This requires `EGL_ANDROID_native_fence_sync`.
+    pub(super) fn create(display: Arc<EGLDisplayHandle>, native: bool) -> Result<SyncPoint, EGLError> {
+        let sync_type = if native {
+            ffi_egl::SYNC_NATIVE_FENCE_ANDROID
+        } else {
+            ffi_egl::SYNC_FENCE_KHR
+        };
+        let attributes = [ffi_egl::NONE as i32];
+        let sync =
+            wrap_egl_call(|| unsafe { ffi_egl::CreateSyncKHR(**display, sync_type, attributes.as_ptr()) })?;
+        Ok(SyncPoint {
+            display,
+            sync,
+            native,
+        })
+    }
+
+    /// Returns true, if the gpu has finished all commands submitted before this sync point.
+    ///
+    /// This does not block.
+    pub fn is_signaled(&self) -> bool {
+        let mut status = ffi_egl::UNSIGNALED_KHR as i32;
+        let res = wrap_egl_call(|| unsafe {
+            ffi_egl::GetSyncAttribKHR(
+                **self.display,
+                self.sync,
+                ffi_egl::SYNC_STATUS_KHR as i32,
+                &mut status,
+            )
+        });
+        // an invalid fence would never be signaled, better not to wait on it forever
+        res.map(|_| status == ffi_egl::SIGNALED_KHR as i32)
+            .unwrap_or(true)
+    }
+
+    /// Block until the sync point is signaled.
+    pub fn wait(&self) -> Result<(), EGLError> {
+        wrap_egl_call(|| unsafe {
+            ffi_egl::ClientWaitSyncKHR(
+                **self.display,
+                self.sync,
+                ffi_egl::SYNC_FLUSH_COMMANDS_BIT_KHR as i32,
+                ffi_egl::FOREVER_KHR,
+            )
+        })
+        .map(|_| ())
+    }
+
+    /// Returns true, if this sync point is backed by a native fence and may be exported.
+    pub fn is_exportable(&self) -> bool {
+        self.native
+    }
+
+    /// Export the sync point as a native fence file descriptor.
+    ///
+    /// The file descriptor can for example be passed as `IN_FENCE_FD` of a drm plane,
+    /// to let the kernel delay a page flip until rendering has finished.
+    ///
+    /// Returns `None`, if the sync point is not [exportable](SyncPoint::is_exportable)
+    /// or exporting failed.
+    pub fn export(&self) -> Option<OwnedFd> {
+        if !self.native {
+            return None;
+        }
+        let fd =
+            wrap_egl_call(|| unsafe { ffi_egl::DupNativeFenceFDANDROID(**self.display, self.sync) }).ok()?;
+        if fd < 0 {
+            return None;
+        }
+        // SAFETY: the returned fd is a new file descriptor owned by the caller
+        Some(unsafe { OwnedFd::from_raw_fd(fd) })
+    }
+}
+
+impl Drop for SyncPoint {
+    fn drop(&mut self) {
+        unsafe {
+            // ignore errors on drop
+            ffi_egl::DestroySyncKHR(**self.display, self.sync);
+        }
+    }
+}
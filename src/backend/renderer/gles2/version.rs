@@ -4,6 +4,7 @@ use scan_fmt::scan_fmt;
 
 use super::ffi::{self, Gles2};
 
+pub const GLES_3_2: GlVersion = GlVersion::new(3, 2);
 pub const GLES_3_0: GlVersion = GlVersion::new(3, 0);
 pub const GLES_2_0: GlVersion = GlVersion::new(2, 0);
 
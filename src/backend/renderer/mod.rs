@@ -7,6 +7,7 @@
 //!
 //! - Raw OpenGL ES 2
 
+use std::borrow::Cow;
 use std::collections::HashSet;
 use std::error::Error;
 
@@ -46,6 +47,47 @@ pub enum TextureFilter {
     Nearest,
 }
 
+/// Interpretation of the alpha channel of imported memory
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum AlphaMode {
+    /// The color channels are already multiplied with the alpha channel
+    ///
+    /// This is what the wayland protocol requires for client buffers.
+    Premultiplied,
+    /// The color channels are not multiplied with the alpha channel (straight alpha)
+    ///
+    /// Some clients and most image decoders produce data like this,
+    /// which needs to be premultiplied to blend correctly.
+    Straight,
+}
+
+impl Default for AlphaMode {
+    fn default() -> Self {
+        AlphaMode::Premultiplied
+    }
+}
+
+impl AlphaMode {
+    /// Convert RGBA8 data using this alpha mode into premultiplied data
+    ///
+    /// Premultiplied data is returned as is, straight alpha data is copied and converted.
+    pub fn premultiply<'a>(&self, data: &'a [u8]) -> Cow<'a, [u8]> {
+        match self {
+            AlphaMode::Premultiplied => Cow::Borrowed(data),
+            AlphaMode::Straight => {
+                let mut premultiplied = data.to_vec();
+                for pixel in premultiplied.chunks_exact_mut(4) {
+                    let alpha = pixel[3] as u16;
+                    for channel in &mut pixel[..3] {
+                        *channel = ((*channel as u16 * alpha + 127) / 255) as u8;
+                    }
+                }
+                Cow::Owned(premultiplied)
+            }
+        }
+    }
+}
+
 impl Transform {
     /// A projection matrix to apply this transformation
     pub fn matrix(&self) -> Matrix3<f32> {
@@ -183,6 +225,17 @@ pub trait Frame {
 
     /// Output transformation that is applied to this frame
     fn transformation(&self) -> Transform;
+
+    /// Override the texture filter used for up- and downscaling by subsequent draw operations
+    ///
+    /// This allows to e.g. draw pixel-art with [`TextureFilter::Nearest`], while using smooth filtering
+    /// for everything else. Passing `None` restores the filters set on the renderer using
+    /// [`Renderer::upscale_filter`] and [`Renderer::downscale_filter`].
+    ///
+    /// Renderers not supporting this ignore the override.
+    fn set_texture_filter(&mut self, filter: Option<TextureFilter>) {
+        let _ = filter;
+    }
 }
 
 /// Abstraction of commonly used rendering operations for compositors.
@@ -283,11 +336,16 @@ pub trait ImportMem: Renderer {
     ///
     /// The provided data slice needs to be in RGBA8 format, its length should thus be `size.w * size.h * 4`.
     /// Anything beyond will be truncated, if the buffer is too small an error will be returned.
+    ///
+    /// `alpha` describes how the alpha channel of the data is to be interpreted. Data with
+    /// [`AlphaMode::Straight`] is premultiplied during the import. Future uploads to the same texture
+    /// via [`ImportMem::update_memory`] are expected to be premultiplied (see [`AlphaMode::premultiply`]).
     fn import_memory(
         &mut self,
         data: &[u8],
         size: Size<i32, BufferCoord>,
         flipped: bool,
+        alpha: AlphaMode,
     ) -> Result<<Self as Renderer>::TextureId, <Self as Renderer>::Error>;
 
     /// Import a given chunk of memory into an existing texture.
@@ -668,3 +726,62 @@ pub fn buffer_dimensions(buffer: &wl_buffer::WlBuffer) -> Option<Size<i32, Buffe
         }
     }
 }
+
+/// A buffer consisting of a single color
+///
+/// Clients may attach a buffer containing just a single pixel and scale it up to the
+/// size of their surface using a viewport, to represent solid colors without allocating
+/// a full buffer. Such buffers are better drawn as filled rectangles than as tiny textures,
+/// see [`solid_color_buffer`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SolidColorBuffer {
+    color: [f32; 4],
+}
+
+impl SolidColorBuffer {
+    /// Create a new [`SolidColorBuffer`] from a premultiplied rgba color
+    pub fn new(color: [f32; 4]) -> Self {
+        SolidColorBuffer { color }
+    }
+
+    /// Returns the premultiplied rgba color of this buffer
+    pub fn color(&self) -> [f32; 4] {
+        self.color
+    }
+
+    /// Returns if the color of this buffer is fully opaque
+    pub fn is_opaque(&self) -> bool {
+        self.color[3] >= 1.0
+    }
+}
+
+/// Returns the color of a single-pixel buffer
+///
+/// Returns `None` if the buffer is not a shm buffer of exactly one pixel in one of the
+/// formats `Argb8888` or `Xrgb8888`, which every client can use.
+/// Buffers of other types or sizes have to be imported as usual.
+#[cfg(feature = "wayland_frontend")]
+pub fn solid_color_buffer(buffer: &wl_buffer::WlBuffer) -> Option<SolidColorBuffer> {
+    crate::wayland::shm::with_buffer_contents(buffer, |slice, data| {
+        if data.width != 1 || data.height != 1 {
+            return None;
+        }
+        let offset = data.offset as usize;
+        let pixel = slice.get(offset..offset + 4)?;
+        // both formats are little-endian, so the bytes are stored as b, g, r, a
+        let alpha = match data.format {
+            wl_shm::Format::Argb8888 => pixel[3],
+            wl_shm::Format::Xrgb8888 => u8::MAX,
+            _ => return None,
+        };
+        let channel = |value: u8| value as f32 / u8::MAX as f32;
+        Some(SolidColorBuffer::new([
+            channel(pixel[2]),
+            channel(pixel[1]),
+            channel(pixel[0]),
+            channel(alpha),
+        ]))
+    })
+    .ok()
+    .flatten()
+}
@@ -27,6 +27,10 @@
 //!
 //! Any `Export*`-implementations will reside on the render-gpu, if applicable.
 //!
+//! Frames are copied to the target-gpu by importing the framebuffer of the render-gpu as a dmabuf,
+//! falling back to a copy through system memory, if that fails. [`MultiRenderer::last_frame_copy`]
+//! tells you which of these paths was used for a frame.
+//!
 //! *Note*: This module will not keep you from selecting sub-optimal configurations.
 //! Any heuristics for which render-gpu to use for a given set of client buffers
 //! and desired target-gpu are up to be implemented by the compositor. The module only
@@ -221,6 +225,7 @@ impl<A: GraphicsApi> GpuManager<A> {
                 target: Some(target.remove(0)),
                 other_renderers: others,
                 proxy_framebuffer: std::marker::PhantomData,
+                last_frame_copy: None,
                 log: self.log.clone(),
             })
         } else {
@@ -230,6 +235,7 @@ impl<A: GraphicsApi> GpuManager<A> {
                 target: None,
                 other_renderers: others,
                 proxy_framebuffer: std::marker::PhantomData,
+                last_frame_copy: None,
                 log: self.log.clone(),
             })
         }
@@ -308,6 +314,7 @@ impl<A: GraphicsApi> GpuManager<A> {
                 target: Some(target),
                 other_renderers: others,
                 proxy_framebuffer: std::marker::PhantomData,
+                last_frame_copy: None,
                 log: render_api.log.clone(),
             })
         } else {
@@ -317,6 +324,7 @@ impl<A: GraphicsApi> GpuManager<A> {
                 target: None,
                 other_renderers: others,
                 proxy_framebuffer: std::marker::PhantomData,
+                last_frame_copy: None,
                 log: target_api.log.clone(),
             })
         }
@@ -610,9 +618,30 @@ pub struct MultiRenderer<'a, 'b, R: GraphicsApi, T: GraphicsApi, Target> {
     target: Option<&'b mut T::Device>,
     other_renderers: Vec<&'a mut R::Device>,
     proxy_framebuffer: std::marker::PhantomData<Target>,
+    last_frame_copy: Option<FrameCopy>,
     log: ::slog::Logger,
 }
 
+/// How the result of a frame rendered by a [`MultiRenderer`] reached the target-gpu
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FrameCopy {
+    /// The frame was rendered on the target-gpu directly, no copy was necessary
+    Direct,
+    /// The framebuffer of the render-gpu was imported as a dmabuf by the target-gpu (zero-copy)
+    Dmabuf,
+    /// The damaged parts of the framebuffer were copied through system memory
+    Cpu,
+}
+
+impl<'a, 'b, R: GraphicsApi, T: GraphicsApi, Target> MultiRenderer<'a, 'b, R, T, Target> {
+    /// Returns how the result of the last frame rendered by this renderer was transferred to the target-gpu
+    ///
+    /// Returns `None`, if no frame was rendered yet or rendering the last frame failed.
+    pub fn last_frame_copy(&self) -> Option<FrameCopy> {
+        self.last_frame_copy
+    }
+}
+
 impl<'a, 'b, R: GraphicsApi, T: GraphicsApi, Target> AsRef<<R::Device as ApiDevice>::Renderer>
     for MultiRenderer<'a, 'b, R, T, Target>
 {
@@ -830,6 +859,7 @@ where
     where
         F: FnOnce(&mut Self, &mut Self::Frame) -> Res,
     {
+        self.last_frame_copy = None;
         let buffer_size = size.to_logical(1).to_buffer(1, dst_transform);
         if self.target.is_some() {
             let render_buffer = Offscreen::<Target>::create_buffer(self.render.renderer_mut(), buffer_size)
@@ -861,6 +891,7 @@ where
                     target: target_ref.take(),
                     other_renderers: other_renderers_ref.drain(..).collect(),
                     proxy_framebuffer: std::marker::PhantomData,
+                    last_frame_copy: None,
                     log: log.clone(),
                 };
                 let mut frame = MultiFrame {
@@ -933,6 +964,7 @@ where
                                 .map_err(Error::Target)?;
 
                             can_import.insert((*self.render.node(), *target.node(), dmabuf.format()), true);
+                            self.last_frame_copy = Some(FrameCopy::Dmabuf);
                             return Ok(res);
                         }
                         Err(err) => {
@@ -1000,7 +1032,7 @@ where
                             .map_texture(&mapping.0)
                             .map_err(Error::Render::<R, T>)?;
                         let texture = target
-                            .import_memory(slice, mapping.1.size, false)
+                            .import_memory(slice, mapping.1.size, false, AlphaMode::Premultiplied)
                             .map_err(Error::Target)?;
                         let dst = mapping
                             .1
@@ -1021,6 +1053,9 @@ where
                 })
                 .map_err(Error::Target)
                 .and_then(std::convert::identity)?;
+            self.last_frame_copy = Some(FrameCopy::Cpu);
+        } else {
+            self.last_frame_copy = Some(FrameCopy::Direct);
         }
 
         Ok(res)
@@ -1238,6 +1273,10 @@ where
     fn transformation(&self) -> Transform {
         unsafe { &mut *self.frame }.transformation()
     }
+
+    fn set_texture_filter(&mut self, filter: Option<TextureFilter>) {
+        unsafe { &mut *self.frame }.set_texture_filter(filter)
+    }
 }
 
 #[cfg(feature = "wayland_frontend")]
@@ -1293,11 +1332,12 @@ where
         data: &[u8],
         size: Size<i32, BufferCoords>,
         flipped: bool,
+        alpha: AlphaMode,
     ) -> Result<<Self as Renderer>::TextureId, <Self as Renderer>::Error> {
         let mem_texture = self
             .render
             .renderer_mut()
-            .import_memory(data, size, flipped)
+            .import_memory(data, size, flipped, alpha)
             .map_err(Error::Render)?;
         let mut texture = MultiTexture::new(size);
         texture.insert_texture::<R>(*self.render.node(), mem_texture);
@@ -1605,7 +1645,7 @@ where
                     .map_err(Error::Target)?;
                 self.render
                     .renderer_mut()
-                    .import_memory(mapped, mapping.size(), false)
+                    .import_memory(mapped, mapping.size(), false, AlphaMode::Premultiplied)
                     .ok()
             } else if let Some(source) = self
                 .other_renderers
@@ -1630,7 +1670,7 @@ where
                     .map_err(Error::Render)?;
                 self.render
                     .renderer_mut()
-                    .import_memory(mapped, mapping.size(), false)
+                    .import_memory(mapped, mapping.size(), false, AlphaMode::Premultiplied)
                     .ok()
             } else {
                 None
@@ -0,0 +1,220 @@
+use crate::{
+    backend::renderer::gles2::{Gles2Error, Gles2Frame, Gles2Renderer},
+    desktop::space::{element::CommitTracker, ElementKind, RenderElement, RenderZindex, SpaceOutputTuple},
+    utils::{Logical, Physical, Point, Rectangle, Scale},
+};
+
+crate::utils::ids::id_gen!(next_border_id, BORDER_ID, BORDER_IDS);
+
+/// Placement of a border relative to the rectangle it surrounds
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BorderPlacement {
+    /// The border is drawn inside of the rectangle, covering its edges
+    Inside,
+    /// The border is drawn around the rectangle, leaving it uncovered
+    Outside,
+    /// The border is centered on the edges of the rectangle
+    Centered,
+}
+
+impl Default for BorderPlacement {
+    fn default() -> Self {
+        BorderPlacement::Outside
+    }
+}
+
+/// Custom element drawing a border around a rectangle
+///
+/// This is useful for focus rings or highlighting windows. Only the border itself is drawn,
+/// the area it surrounds is left untouched. The corners can be rounded individually.
+///
+/// Borders are usually drawn on top of other content and are often translucent,
+/// so this element never reports any opaque regions.
+#[derive(Debug)]
+pub struct BorderElement {
+    id: usize,
+    rect: Rectangle<i32, Logical>,
+    thickness: f64,
+    color: [f32; 4],
+    radius: [f64; 4],
+    placement: BorderPlacement,
+    z_index: u8,
+    commits: CommitTracker,
+}
+
+impl BorderElement {
+    /// Create a new [`BorderElement`] around `rect`
+    ///
+    /// The color is given with straight alpha. The border is placed outside of `rect`
+    /// and has square corners by default.
+    pub fn new(rect: impl Into<Rectangle<i32, Logical>>, thickness: f64, color: [f32; 4]) -> Self {
+        BorderElement {
+            id: next_border_id(),
+            rect: rect.into(),
+            thickness,
+            color,
+            radius: [0.0; 4],
+            placement: BorderPlacement::default(),
+            z_index: RenderZindex::Overlay as u8,
+            commits: CommitTracker::default(),
+        }
+    }
+
+    /// Set the rectangle surrounded by the border
+    pub fn set_rect(&mut self, rect: impl Into<Rectangle<i32, Logical>>) {
+        let rect = rect.into();
+        if rect.size != self.rect.size {
+            self.damage();
+        }
+        self.rect = rect;
+    }
+
+    /// Set the thickness of the border
+    pub fn set_thickness(&mut self, thickness: f64) {
+        if thickness != self.thickness {
+            self.thickness = thickness;
+            self.damage();
+        }
+    }
+
+    /// Set the color of the border, with straight alpha
+    pub fn set_color(&mut self, color: [f32; 4]) {
+        if color != self.color {
+            self.color = color;
+            self.damage();
+        }
+    }
+
+    /// Set the radii of the outer corners of the border
+    ///
+    /// The radii are given in the order top-left, top-right, bottom-right and bottom-left.
+    pub fn set_corner_radius(&mut self, radius: [f64; 4]) {
+        if radius != self.radius {
+            self.radius = radius;
+            self.damage();
+        }
+    }
+
+    /// Set the placement of the border relative to its rectangle
+    pub fn set_placement(&mut self, placement: BorderPlacement) {
+        if placement != self.placement {
+            self.placement = placement;
+            self.damage();
+        }
+    }
+
+    /// Set the z-index of this element
+    pub fn set_z_index(&mut self, z_index: u8) {
+        self.z_index = z_index;
+    }
+
+    /// Returns the rectangle surrounded by the border
+    pub fn rect(&self) -> Rectangle<i32, Logical> {
+        self.rect
+    }
+
+    /// Returns the thickness of the border
+    pub fn thickness(&self) -> f64 {
+        self.thickness
+    }
+
+    /// Returns the color of the border
+    pub fn color(&self) -> [f32; 4] {
+        self.color
+    }
+
+    /// Returns the radii of the outer corners of the border
+    pub fn corner_radius(&self) -> [f64; 4] {
+        self.radius
+    }
+
+    /// Returns the placement of the border
+    pub fn placement(&self) -> BorderPlacement {
+        self.placement
+    }
+
+    // area covered by the border, including the rectangle it surrounds
+    fn outer_rect(&self) -> Rectangle<f64, Logical> {
+        let outset = match self.placement {
+            BorderPlacement::Inside => 0.0,
+            BorderPlacement::Outside => self.thickness,
+            BorderPlacement::Centered => self.thickness / 2.0,
+        };
+        let mut rect = self.rect.to_f64();
+        rect.loc -= (outset, outset).into();
+        rect.size += (outset * 2.0, outset * 2.0).into();
+        rect
+    }
+
+    fn damage(&mut self) {
+        self.commits.increment();
+    }
+}
+
+impl RenderElement<Gles2Renderer> for BorderElement {
+    fn id(&self) -> usize {
+        self.id
+    }
+
+    fn location(&self, scale: impl Into<Scale<f64>>) -> Point<f64, Physical> {
+        self.outer_rect().loc.to_physical(scale)
+    }
+
+    fn geometry(&self, scale: impl Into<Scale<f64>>) -> Rectangle<i32, Physical> {
+        self.outer_rect().to_physical_precise_round_edges(scale)
+    }
+
+    fn accumulated_damage(
+        &self,
+        scale: impl Into<Scale<f64>>,
+        for_values: Option<SpaceOutputTuple<'_, '_>>,
+    ) -> Vec<Rectangle<i32, Physical>> {
+        // any change of the border affects its whole outline
+        if let Some(values) = for_values {
+            if !self.commits.changed_for(&values) {
+                return Vec::new();
+            }
+        }
+        vec![self.geometry(scale)]
+    }
+
+    fn opaque_regions(&self, _scale: impl Into<Scale<f64>>) -> Option<Vec<Rectangle<i32, Physical>>> {
+        None
+    }
+
+    fn draw(
+        &self,
+        _renderer: &mut Gles2Renderer,
+        frame: &mut Gles2Frame,
+        scale: impl Into<Scale<f64>>,
+        location: Point<f64, Physical>,
+        damage: &[Rectangle<i32, Physical>],
+        _log: &slog::Logger,
+    ) -> Result<(), Gles2Error> {
+        let scale = scale.into();
+        let size = self.outer_rect().size.to_physical(scale);
+        let geometry = Rectangle::from_loc_and_size(location, size).to_i32_round_edges();
+        let [r, g, b, a] = self.color;
+        frame.draw_border(
+            geometry,
+            (self.thickness * scale.x) as f32,
+            self.radius.map(|radius| (radius * scale.x) as f32),
+            [r * a, g * a, b * a, a],
+            damage,
+        )
+    }
+
+    fn z_index(&self) -> u8 {
+        self.z_index
+    }
+
+    fn kind(&self) -> ElementKind {
+        ElementKind::Overlay
+    }
+}
+
+impl Drop for BorderElement {
+    fn drop(&mut self) {
+        BORDER_IDS.lock().unwrap().remove(&self.id);
+    }
+}
@@ -0,0 +1,254 @@
+use crate::{
+    backend::{
+        allocator::Fourcc,
+        renderer::{
+            gles2::{Gles2Error, Gles2Frame, Gles2Renderer, Gles2Texture},
+            Frame, TextureFilter,
+        },
+    },
+    desktop::space::{ElementKind, RenderElement, SpaceOutputTuple},
+    utils::{Buffer, Physical, Point, Rectangle, Scale, Size, Transform},
+};
+use std::cell::{Cell, RefCell};
+
+#[derive(Debug)]
+struct Cache {
+    texture: Gles2Texture,
+    scale: Scale<f64>,
+    size: Size<i32, Physical>,
+}
+
+/// Wrapper caching the contents of another [`RenderElement`] in a texture
+///
+/// Some elements are expensive to draw, but change rarely (e.g. rasterized text or blurred panels).
+/// The wrapped element is rendered into an offscreen texture once, which is then drawn on subsequent
+/// frames instead of the element itself.
+///
+/// The cache is invalidated, whenever the element is accessed mutably through [`CachedElement::get_mut`]
+/// or [`CachedElement::invalidate`] is called, and if the size or scale of the element changes.
+/// It is released, once the `CachedElement` is dropped.
+///
+/// The damage reported by the wrapped element is passed through and also invalidates the cache,
+/// so only changes through interior mutability, that are not reported as damage, need to be signaled
+/// using [`CachedElement::invalidate`].
+#[derive(Debug)]
+pub struct CachedElement<E> {
+    element: E,
+    cache: RefCell<Option<Cache>>,
+    dirty: Cell<bool>,
+}
+
+impl<E> CachedElement<E> {
+    /// Wrap an element caching its contents
+    pub fn new(element: E) -> Self {
+        CachedElement {
+            element,
+            cache: RefCell::new(None),
+            dirty: Cell::new(true),
+        }
+    }
+
+    /// Re-render the wrapped element on the next frame
+    pub fn invalidate(&self) {
+        self.dirty.set(true);
+    }
+
+    /// Returns a reference to the wrapped element
+    pub fn get_ref(&self) -> &E {
+        &self.element
+    }
+
+    /// Returns a mutable reference to the wrapped element
+    ///
+    /// This invalidates the cache.
+    pub fn get_mut(&mut self) -> &mut E {
+        self.invalidate();
+        &mut self.element
+    }
+
+    /// Unwraps the element, releasing the cache
+    pub fn into_inner(self) -> E {
+        self.element
+    }
+}
+
+impl<E> RenderElement<Gles2Renderer> for CachedElement<E>
+where
+    E: RenderElement<Gles2Renderer>,
+{
+    fn id(&self) -> usize {
+        self.element.id()
+    }
+
+    fn location(&self, scale: impl Into<Scale<f64>>) -> Point<f64, Physical> {
+        self.element.location(scale)
+    }
+
+    fn geometry(&self, scale: impl Into<Scale<f64>>) -> Rectangle<i32, Physical> {
+        self.element.geometry(scale)
+    }
+
+    fn accumulated_damage(
+        &self,
+        scale: impl Into<Scale<f64>>,
+        for_values: Option<SpaceOutputTuple<'_, '_>>,
+    ) -> Vec<Rectangle<i32, Physical>> {
+        let damage = self.element.accumulated_damage(scale, for_values);
+        if !damage.is_empty() {
+            self.dirty.set(true);
+        }
+        damage
+    }
+
+    fn opaque_regions(&self, scale: impl Into<Scale<f64>>) -> Option<Vec<Rectangle<i32, Physical>>> {
+        self.element.opaque_regions(scale)
+    }
+
+    fn draw(
+        &self,
+        renderer: &mut Gles2Renderer,
+        frame: &mut Gles2Frame,
+        scale: impl Into<Scale<f64>>,
+        location: Point<f64, Physical>,
+        damage: &[Rectangle<i32, Physical>],
+        log: &slog::Logger,
+    ) -> Result<(), Gles2Error> {
+        let scale = scale.into();
+        let geometry = self.element.geometry(scale);
+        if geometry.is_empty() {
+            return Ok(());
+        }
+        // offset of the bounding box relative to the location of the element
+        let offset = geometry.loc.to_f64() - self.element.location(scale);
+
+        let mut cache = self.cache.borrow_mut();
+        let valid = cache
+            .as_ref()
+            .map(|cache| cache.scale == scale && cache.size == geometry.size)
+            .unwrap_or(false);
+        if self.dirty.get() || !valid {
+            let size = Size::<i32, Buffer>::from((geometry.size.w, geometry.size.h));
+            let full = Rectangle::from_loc_and_size((0, 0), geometry.size);
+            let (texture, result) = frame.with_offscreen(size, Fourcc::Abgr8888, |offscreen| {
+                offscreen.clear([0.0, 0.0, 0.0, 0.0], &[full])?;
+                self.element.draw(
+                    renderer,
+                    offscreen,
+                    scale,
+                    Point::from((0.0, 0.0)) - offset,
+                    &[full],
+                    log,
+                )
+            })?;
+            result?;
+            *cache = Some(Cache {
+                texture,
+                scale,
+                size: geometry.size,
+            });
+            self.dirty.set(false);
+        }
+        let cache = cache.as_ref().unwrap();
+
+        let dst = Rectangle::from_loc_and_size((location + offset).to_i32_round(), geometry.size);
+        let damage = damage
+            .iter()
+            .flat_map(|geo| geo.intersection(dst))
+            .map(|mut geo| {
+                geo.loc -= dst.loc;
+                geo
+            })
+            .collect::<Vec<_>>();
+        if damage.is_empty() {
+            return Ok(());
+        }
+
+        frame.render_texture_from_to(
+            &cache.texture,
+            Rectangle::from_loc_and_size((0, 0), Size::<i32, Buffer>::from((dst.size.w, dst.size.h)))
+                .to_f64(),
+            dst,
+            &damage,
+            Transform::Normal,
+            1.0,
+        )
+    }
+
+    fn z_index(&self) -> u8 {
+        self.element.z_index()
+    }
+
+    fn send_frame(&self, time: u32) {
+        self.element.send_frame(time)
+    }
+
+    fn texture_filter(&self) -> Option<TextureFilter> {
+        self.element.texture_filter()
+    }
+
+    fn kind(&self) -> ElementKind {
+        self.element.kind()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Default)]
+    struct DamagedElement {
+        damage: RefCell<Vec<Rectangle<i32, Physical>>>,
+    }
+
+    impl RenderElement<Gles2Renderer> for DamagedElement {
+        fn id(&self) -> usize {
+            0
+        }
+
+        fn location(&self, _scale: impl Into<Scale<f64>>) -> Point<f64, Physical> {
+            (0.0, 0.0).into()
+        }
+
+        fn geometry(&self, _scale: impl Into<Scale<f64>>) -> Rectangle<i32, Physical> {
+            Rectangle::from_loc_and_size((0, 0), (10, 10))
+        }
+
+        fn accumulated_damage(
+            &self,
+            _scale: impl Into<Scale<f64>>,
+            _for_values: Option<SpaceOutputTuple<'_, '_>>,
+        ) -> Vec<Rectangle<i32, Physical>> {
+            self.damage.take()
+        }
+
+        fn opaque_regions(&self, _scale: impl Into<Scale<f64>>) -> Option<Vec<Rectangle<i32, Physical>>> {
+            None
+        }
+
+        fn draw(
+            &self,
+            _renderer: &mut Gles2Renderer,
+            _frame: &mut Gles2Frame,
+            _scale: impl Into<Scale<f64>>,
+            _location: Point<f64, Physical>,
+            _damage: &[Rectangle<i32, Physical>],
+            _log: &slog::Logger,
+        ) -> Result<(), Gles2Error> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn damage_of_the_wrapped_element_invalidates_the_cache() {
+        let cached = CachedElement::new(DamagedElement::default());
+        cached.dirty.set(false);
+
+        assert!(cached.accumulated_damage(1.0, None).is_empty());
+        assert!(!cached.dirty.get());
+
+        let damage = Rectangle::from_loc_and_size((2, 2), (4, 4));
+        cached.get_ref().damage.replace(vec![damage]);
+        assert_eq!(cached.accumulated_damage(1.0, None), vec![damage]);
+        assert!(cached.dirty.get());
+    }
+}
@@ -0,0 +1,172 @@
+use crate::{
+    backend::renderer::{ImportAll, Renderer, TextureFilter},
+    desktop::space::{element::CommitTracker, ElementKind, RenderElement, SpaceOutputTuple},
+    utils::{Logical, Physical, Point, Rectangle, Scale},
+};
+
+/// Wrapper clipping another [`RenderElement`] to a set of rectangles
+///
+/// Only the parts of the wrapped element inside any of the clip rectangles are drawn,
+/// which is for example useful to constrain a window to a workspace region.
+/// The geometry, opaque regions and damage of the element are intersected with the clip,
+/// an element completely outside of the clip reports an empty geometry and is not drawn at all.
+///
+/// Clipping is done by restricting the damage passed to [`RenderElement::draw`],
+/// so the wrapped element needs to respect the damage it is given.
+#[derive(Debug)]
+pub struct ClipElement<E> {
+    element: E,
+    clip: Vec<Rectangle<i32, Logical>>,
+    commits: CommitTracker,
+}
+
+impl<E> ClipElement<E> {
+    /// Wrap an element clipping it to the given rectangles in space coordinates
+    pub fn new(element: E, clip: impl IntoIterator<Item = Rectangle<i32, Logical>>) -> Self {
+        ClipElement {
+            element,
+            clip: clip.into_iter().collect(),
+            commits: CommitTracker::default(),
+        }
+    }
+
+    /// Replace the rectangles the element is clipped to
+    pub fn set_clip(&mut self, clip: impl IntoIterator<Item = Rectangle<i32, Logical>>) {
+        let clip = clip.into_iter().collect::<Vec<_>>();
+        if clip != self.clip {
+            self.clip = clip;
+            self.commits.increment();
+        }
+    }
+
+    /// Returns the rectangles the element is clipped to
+    pub fn clip(&self) -> &[Rectangle<i32, Logical>] {
+        &self.clip
+    }
+
+    /// Returns a reference to the wrapped element
+    pub fn get_ref(&self) -> &E {
+        &self.element
+    }
+
+    /// Returns a mutable reference to the wrapped element
+    pub fn get_mut(&mut self) -> &mut E {
+        &mut self.element
+    }
+
+    /// Unwraps the element
+    pub fn into_inner(self) -> E {
+        self.element
+    }
+
+    fn clip_rects(
+        &self,
+        rects: Vec<Rectangle<i32, Physical>>,
+        scale: Scale<f64>,
+    ) -> Vec<Rectangle<i32, Physical>> {
+        let clip = self
+            .clip
+            .iter()
+            .map(|rect| rect.to_physical_precise_round_edges(scale))
+            .collect::<Vec<_>>();
+        rects
+            .into_iter()
+            .flat_map(|rect| clip.iter().flat_map(move |clip| rect.intersection(*clip)))
+            .filter(|rect| !rect.is_empty())
+            .collect()
+    }
+}
+
+impl<R, E> RenderElement<R> for ClipElement<E>
+where
+    R: Renderer + ImportAll,
+    E: RenderElement<R>,
+{
+    fn id(&self) -> usize {
+        self.element.id()
+    }
+
+    fn location(&self, scale: impl Into<Scale<f64>>) -> Point<f64, Physical> {
+        self.element.location(scale)
+    }
+
+    fn geometry(&self, scale: impl Into<Scale<f64>>) -> Rectangle<i32, Physical> {
+        let scale = scale.into();
+        let geometry = self.element.geometry(scale);
+        self.clip_rects(vec![geometry], scale)
+            .into_iter()
+            .reduce(|a, b| a.merge(b))
+            .unwrap_or_else(|| Rectangle::from_loc_and_size(geometry.loc, (0, 0)))
+    }
+
+    fn accumulated_damage(
+        &self,
+        scale: impl Into<Scale<f64>>,
+        for_values: Option<SpaceOutputTuple<'_, '_>>,
+    ) -> Vec<Rectangle<i32, Physical>> {
+        let scale = scale.into();
+        // a changed clip may reveal or hide parts of the element, so damage all of it
+        let clip_changed = for_values
+            .as_ref()
+            .map_or(false, |values| self.commits.changed_for(values));
+        let damage = self.element.accumulated_damage(scale, for_values);
+        if clip_changed {
+            vec![<Self as RenderElement<R>>::geometry(self, scale)]
+        } else {
+            self.clip_rects(damage, scale)
+        }
+    }
+
+    fn opaque_regions(&self, scale: impl Into<Scale<f64>>) -> Option<Vec<Rectangle<i32, Physical>>> {
+        let scale = scale.into();
+        self.element
+            .opaque_regions(scale)
+            .map(|regions| self.clip_rects(regions, scale))
+            .filter(|regions| !regions.is_empty())
+    }
+
+    fn draw(
+        &self,
+        renderer: &mut R,
+        frame: &mut <R as Renderer>::Frame,
+        scale: impl Into<Scale<f64>>,
+        location: Point<f64, Physical>,
+        damage: &[Rectangle<i32, Physical>],
+        log: &slog::Logger,
+    ) -> Result<(), <R as Renderer>::Error> {
+        let scale = scale.into();
+        // the damage is relative to the output, while the clip is in space coordinates
+        let offset = (self.element.location(scale) - location).to_i32_round();
+        let damage = self
+            .clip_rects(
+                damage
+                    .iter()
+                    .map(|rect| Rectangle::from_loc_and_size(rect.loc + offset, rect.size))
+                    .collect(),
+                scale,
+            )
+            .into_iter()
+            .map(|rect| Rectangle::from_loc_and_size(rect.loc - offset, rect.size))
+            .collect::<Vec<_>>();
+        if damage.is_empty() {
+            return Ok(());
+        }
+        self.element.draw(renderer, frame, scale, location, &damage, log)
+    }
+
+    fn z_index(&self) -> u8 {
+        self.element.z_index()
+    }
+
+    fn send_frame(&self, time: u32) {
+        self.element.send_frame(time)
+    }
+
+    fn texture_filter(&self) -> Option<TextureFilter> {
+        self.element.texture_filter()
+    }
+
+    fn kind(&self) -> ElementKind {
+        self.element.kind()
+    }
+}
@@ -0,0 +1,272 @@
+use crate::{
+    backend::renderer::{Frame, ImportAll, Renderer},
+    desktop::space::{element::draw_with_texture_filter, sort_render_elements, RenderElement, RenderError},
+    utils::{Physical, Rectangle, Scale, Size, Transform},
+};
+use std::{
+    any::TypeId,
+    collections::{HashMap, VecDeque},
+};
+
+type ElementId = (TypeId, usize);
+
+/// Damage-tracked rendering of a set of [`RenderElement`]s
+///
+/// This provides the same damage-tracking [`Space::render_output`](super::Space::render_output) does,
+/// but for an arbitrary list of elements, that are not managed by a [`Space`](super::Space).
+///
+/// The geometry of the elements is expected to be relative to the rendered output.
+///
+/// *Note*: Elements are queried for their damage without a [`SpaceOutputTuple`](super::SpaceOutputTuple),
+/// so they are unable to cache, that they already reported their damage.
+/// Depending on the element this might cause more damage than strictly necessary.
+#[derive(Debug)]
+pub struct DamageTrackedRenderer {
+    size: Size<i32, Physical>,
+    scale: Scale<f64>,
+    transform: Transform,
+    // damage from the last n render iterations, used for the different buffer ages
+    old_damage: VecDeque<Vec<Rectangle<i32, Physical>>>,
+    // z_index and geometry of the elements from the last render iteration
+    last_state: HashMap<ElementId, (usize, Rectangle<i32, Physical>)>,
+    // size, scale and transform of the last render iteration
+    last_mode: Option<(Size<i32, Physical>, Scale<f64>, Transform)>,
+    logger: ::slog::Logger,
+}
+
+impl DamageTrackedRenderer {
+    /// Create a new [`DamageTrackedRenderer`] for an output of the given size, scale and transform.
+    pub fn new<L>(
+        size: impl Into<Size<i32, Physical>>,
+        scale: impl Into<Scale<f64>>,
+        transform: Transform,
+        log: L,
+    ) -> Self
+    where
+        L: Into<Option<::slog::Logger>>,
+    {
+        DamageTrackedRenderer {
+            size: size.into(),
+            scale: scale.into(),
+            transform,
+            old_damage: VecDeque::new(),
+            last_state: HashMap::new(),
+            last_mode: None,
+            logger: crate::slog_or_fallback(log).new(slog::o!("smithay_module" => "damage_tracked_renderer")),
+        }
+    }
+
+    /// Update the size, scale and transform of the rendered output.
+    ///
+    /// Any change causes the next render to redraw everything.
+    pub fn set_mode(
+        &mut self,
+        size: impl Into<Size<i32, Physical>>,
+        scale: impl Into<Scale<f64>>,
+        transform: Transform,
+    ) {
+        self.size = size.into();
+        self.scale = scale.into();
+        self.transform = transform;
+    }
+
+    /// Reset the tracked damage, causing the next render to redraw everything.
+    ///
+    /// You need to call this, if you have rendered into the buffers without using this renderer.
+    pub fn reset(&mut self) {
+        self.old_damage.clear();
+        self.last_state.clear();
+        self.last_mode = None;
+    }
+
+    /// Render the given elements using a given [`Renderer`].
+    ///
+    /// Elements are rendered in the order given by [`sort_render_elements`] and only
+    /// the parts of the output, that have changed since the buffer was last rendered into, are redrawn.
+    /// `clear_color` will be used to fill all unoccupied regions.
+    ///
+    /// To facilitate this you need to provide the age of the buffer bound to the given `renderer`.
+    ///
+    /// Returns a list of updated regions relative to the rendered output
+    /// (or `None` if that list would be empty) in case of success.
+    pub fn render_output<R, E>(
+        &mut self,
+        renderer: &mut R,
+        age: usize,
+        clear_color: [f32; 4],
+        elements: &[E],
+    ) -> Result<Option<Vec<Rectangle<i32, Physical>>>, RenderError<R>>
+    where
+        R: Renderer + ImportAll,
+        R::TextureId: 'static,
+        E: RenderElement<R>,
+    {
+        let output_geo = Rectangle::from_loc_and_size((0, 0), self.size);
+        let scale = self.scale;
+
+        let mut elements = elements.iter().collect::<Vec<_>>();
+        sort_render_elements(&mut elements);
+
+        let opaque_regions = elements
+            .iter()
+            .enumerate()
+            .filter_map(|(zindex, element)| element.opaque_regions(scale).map(|regions| (zindex, regions)))
+            .collect::<Vec<_>>();
+
+        // This will hold all the damage we need for this rendering step
+        let mut damage = Vec::<Rectangle<i32, Physical>>::new();
+
+        // First add damage for elements gone
+        for (id, (_, old_geo)) in self.last_state.iter() {
+            if !elements.iter().any(|e| (e.type_of(), e.id()) == *id) {
+                slog::trace!(self.logger, "Removing element at: {:?}", old_geo);
+                damage.push(*old_geo);
+            }
+        }
+
+        // then figure out the damage of new, moved or updated elements
+        for (zindex, element) in elements.iter().enumerate() {
+            let geo = element.geometry(scale);
+            let old_state = self.last_state.get(&(element.type_of(), element.id())).copied();
+
+            let mut element_damage = element.accumulated_damage(scale, None);
+
+            // element was moved, resized or just appeared
+            if old_state
+                .map(|(old_zindex, old_geo)| old_geo != geo || zindex != old_zindex)
+                .unwrap_or(true)
+            {
+                if let Some((_, old_geo)) = old_state {
+                    element_damage.push(old_geo);
+                }
+                element_damage.push(geo);
+            }
+
+            damage.extend(
+                opaque_regions
+                    .iter()
+                    .filter(|(index, _)| *index > zindex)
+                    .flat_map(|(_, regions)| regions)
+                    .fold(element_damage, |damage, region| {
+                        damage
+                            .into_iter()
+                            .flat_map(|geo| geo.subtract_rect(*region))
+                            .collect::<Vec<_>>()
+                    }),
+            );
+        }
+
+        let mode = (self.size, self.scale, self.transform);
+        if self.last_mode.map(|last_mode| last_mode != mode).unwrap_or(true) {
+            // The output changed, so just damage everything
+            slog::trace!(self.logger, "Output mode changed, damaging whole output");
+            damage = vec![output_geo];
+        }
+
+        // That is all completely new damage, which we need to store for subsequent renders
+        let new_damage = damage.clone();
+        // We now add old damage states, if we have an age value
+        if age > 0 && self.old_damage.len() >= age {
+            // We do not need even older states anymore
+            self.old_damage.truncate(age);
+            damage.extend(self.old_damage.iter().flatten().copied());
+        } else {
+            // just damage everything, if we have no damage
+            damage = vec![output_geo];
+        }
+
+        // filter damage outside of the output and merge overlapping rectangles
+        damage.dedup();
+        damage.retain(|rect| !rect.is_empty());
+        damage = damage
+            .into_iter()
+            .filter_map(|rect| rect.intersection(output_geo))
+            .fold(Vec::new(), |new_damage, mut rect| {
+                let (overlapping, mut new_damage): (Vec<_>, Vec<_>) =
+                    new_damage.into_iter().partition(|other| other.overlaps(rect));
+
+                for overlap in overlapping {
+                    rect = rect.merge(overlap);
+                }
+                new_damage.push(rect);
+                new_damage
+            });
+
+        if damage.is_empty() {
+            return Ok(None);
+        }
+
+        let logger = &self.logger;
+        let res = renderer.render(
+            self.transform.transform_size(self.size),
+            self.transform,
+            |renderer, frame| {
+                let clear_damage = opaque_regions.iter().flat_map(|(_, regions)| regions).fold(
+                    damage.clone(),
+                    |damage, region| {
+                        damage
+                            .into_iter()
+                            .flat_map(|geo| geo.subtract_rect(*region))
+                            .collect::<Vec<_>>()
+                    },
+                );
+
+                slog::trace!(logger, "Clearing at {:#?}", clear_damage);
+                frame.clear(clear_color, &clear_damage)?;
+
+                for (zindex, element) in elements.iter().enumerate() {
+                    let geo = element.geometry(scale);
+
+                    let element_damage = opaque_regions
+                        .iter()
+                        .filter(|(index, _)| *index > zindex)
+                        .flat_map(|(_, regions)| regions)
+                        .fold(damage.clone(), |damage, region| {
+                            damage
+                                .into_iter()
+                                .flat_map(|geo| geo.subtract_rect(*region))
+                                .collect::<Vec<_>>()
+                        });
+
+                    if element_damage.iter().any(|d| d.overlaps(geo)) {
+                        slog::trace!(
+                            logger,
+                            "Rendering element with index {} at {:?} with damage {:#?}",
+                            zindex,
+                            geo,
+                            element_damage
+                        );
+                        draw_with_texture_filter(
+                            element,
+                            renderer,
+                            frame,
+                            scale,
+                            element.location(scale),
+                            &element_damage,
+                            logger,
+                        )?;
+                    }
+                }
+
+                Result::<(), R::Error>::Ok(())
+            },
+        );
+
+        if let Err(err) = res.and_then(std::convert::identity) {
+            // the buffer might have been partially updated and is now unusable,
+            // so we need to reset our state
+            self.reset();
+            return Err(RenderError::Rendering(err));
+        }
+
+        self.last_state = elements
+            .iter()
+            .enumerate()
+            .map(|(zindex, elem)| ((elem.type_of(), elem.id()), (zindex, elem.geometry(scale))))
+            .collect();
+        self.old_damage.push_front(new_damage.clone());
+        self.last_mode = Some(mode);
+
+        Ok(Some(new_damage))
+    }
+}
@@ -0,0 +1,131 @@
+use crate::{
+    backend::renderer::{ImportAll, Renderer},
+    desktop::space::RenderElement,
+    utils::{Physical, Rectangle, Scale},
+};
+use std::{any::TypeId, collections::HashMap};
+
+/// Identifies a [`RenderElement`] by its type and [id](RenderElement::id)
+pub type ElementId = (TypeId, usize);
+
+/// Changes of the rendered elements between two frames
+///
+/// This is useful to trigger animations, e.g. to fade in new elements and fade out removed ones.
+/// As removed elements are usually gone by the time the next frame is rendered, the elements of
+/// a frame are compared using a snapshot of their ids and geometries taken by [`ElementDiff::snapshot`].
+///
+/// ```ignore
+/// let current = ElementDiff::snapshot::<Gles2Renderer, _>(&elements, scale);
+/// let diff = ElementDiff::new(&last_frame, &current);
+/// for id in &diff.appeared {
+///     // start a fade-in animation
+/// }
+/// last_frame = current;
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ElementDiff {
+    /// Elements not present in the old frame, in the order of the new frame
+    pub appeared: Vec<ElementId>,
+    /// Elements not present in the new frame anymore, in the order of the old frame
+    pub removed: Vec<ElementId>,
+    /// Elements present in both frames with a changed geometry, together with the old and the new one
+    pub moved: Vec<(ElementId, Rectangle<i32, Physical>, Rectangle<i32, Physical>)>,
+}
+
+impl ElementDiff {
+    /// Take a snapshot of the ids and geometries of the given elements
+    ///
+    /// The renderer the elements are drawn with can not be inferred from the elements,
+    /// name it explicitly, e.g. `ElementDiff::snapshot::<Gles2Renderer, _>(&elements, scale)`.
+    pub fn snapshot<'a, R, E>(
+        elements: impl IntoIterator<Item = &'a E>,
+        scale: impl Into<Scale<f64>>,
+    ) -> Vec<(ElementId, Rectangle<i32, Physical>)>
+    where
+        R: Renderer + ImportAll,
+        E: RenderElement<R> + 'a,
+    {
+        let scale = scale.into();
+        elements
+            .into_iter()
+            .map(|element| ((element.type_of(), element.id()), element.geometry(scale)))
+            .collect()
+    }
+
+    /// Compare the snapshots of two frames
+    pub fn new(
+        old: &[(ElementId, Rectangle<i32, Physical>)],
+        new: &[(ElementId, Rectangle<i32, Physical>)],
+    ) -> Self {
+        let old_geometries = old.iter().copied().collect::<HashMap<_, _>>();
+        let new_geometries = new.iter().copied().collect::<HashMap<_, _>>();
+
+        let mut diff = ElementDiff::default();
+        for (id, geometry) in new {
+            match old_geometries.get(id) {
+                None => diff.appeared.push(*id),
+                Some(old_geometry) if old_geometry != geometry => {
+                    diff.moved.push((*id, *old_geometry, *geometry))
+                }
+                Some(_) => {}
+            }
+        }
+        diff.removed = old
+            .iter()
+            .map(|(id, _)| *id)
+            .filter(|id| !new_geometries.contains_key(id))
+            .collect();
+        diff
+    }
+
+    /// Returns true, if the elements did not change at all
+    pub fn is_empty(&self) -> bool {
+        self.appeared.is_empty() && self.removed.is_empty() && self.moved.is_empty()
+    }
+}
+
+#[cfg(all(test, feature = "renderer_gl"))]
+mod tests {
+    use super::*;
+    use crate::{
+        backend::renderer::{gles2::Gles2Renderer, SolidColorBuffer},
+        desktop::space::SolidColorElement,
+    };
+
+    fn element(x: i32) -> SolidColorElement {
+        SolidColorElement::new(
+            Rectangle::from_loc_and_size((x, 0), (10, 10)),
+            SolidColorBuffer::new([1.0; 4]),
+        )
+    }
+
+    fn id(element: &SolidColorElement) -> ElementId {
+        (
+            RenderElement::<Gles2Renderer>::type_of(element),
+            RenderElement::<Gles2Renderer>::id(element),
+        )
+    }
+
+    #[test]
+    fn unchanged_elements_produce_an_empty_diff() {
+        let elements = [element(0), element(20)];
+        let old = ElementDiff::snapshot::<Gles2Renderer, _>(&elements, 1.0);
+        let new = ElementDiff::snapshot::<Gles2Renderer, _>(&elements, 1.0);
+        assert!(ElementDiff::new(&old, &new).is_empty());
+    }
+
+    #[test]
+    fn diff_reports_appeared_removed_and_moved_elements() {
+        let (kept, removed, mut moved, appeared) = (element(0), element(20), element(40), element(60));
+        let old = ElementDiff::snapshot::<Gles2Renderer, _>([&kept, &removed, &moved], 1.0);
+        let old_geometry = RenderElement::<Gles2Renderer>::geometry(&moved, 1.0);
+        moved.set_rect(Rectangle::from_loc_and_size((50, 0), (10, 10)));
+        let new_geometry = RenderElement::<Gles2Renderer>::geometry(&moved, 1.0);
+        let new = ElementDiff::snapshot::<Gles2Renderer, _>([&appeared, &kept, &moved], 1.0);
+
+        let diff = ElementDiff::new(&old, &new);
+        assert_eq!(diff.appeared, vec![id(&appeared)]);
+        assert_eq!(diff.removed, vec![id(&removed)]);
+        assert_eq!(diff.moved, vec![(id(&moved), old_geometry, new_geometry)]);
+    }
+}
@@ -1,12 +1,14 @@
 use crate::desktop::space::popup::RenderPopup;
 use crate::{
-    backend::renderer::{ImportAll, Renderer, Texture},
+    backend::renderer::{Frame, ImportAll, Renderer, Texture, TextureFilter},
     desktop::{space::*, utils::*},
     utils::{Logical, Physical, Point, Rectangle, Scale},
     wayland::output::Output,
 };
 use std::{
     any::{Any, TypeId},
+    cell::RefCell,
+    collections::HashMap,
     hash::{Hash, Hasher},
 };
 use wayland_server::protocol::wl_surface::WlSurface;
@@ -30,6 +32,8 @@ pub enum RenderZindex {
     Overlay = 60,
     /// Default Layer for Overlay PopUp
     PopupsOverlay = 70,
+    /// Default zindex for drag and drop icons
+    DndIcon = 80,
 }
 
 impl From<RenderZindex> for u8 {
@@ -44,6 +48,32 @@ impl From<RenderZindex> for Option<u8> {
     }
 }
 
+/// Classification of a [`RenderElement`]
+///
+/// Allows to apply policies or gather statistics per kind of element without downcasting,
+/// e.g. to never assign cursors to the primary plane.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ElementKind {
+    /// The element did not specify its kind
+    Unspecified,
+    /// Contents of a window
+    Window,
+    /// A popup of a window or layer surface
+    Popup,
+    /// A cursor or anything attached to it, like a drag'n'drop icon
+    Cursor,
+    /// Element drawn on top of the desktop, like borders or on-screen displays
+    Overlay,
+    /// Element drawn behind the desktop, like a wallpaper
+    Background,
+}
+
+impl Default for ElementKind {
+    fn default() -> Self {
+        ElementKind::Unspecified
+    }
+}
+
 /// Trait for custom elements to be rendered during [`Space::render_output`].
 pub trait RenderElement<R>
 where
@@ -100,9 +130,69 @@ where
     ) -> Result<(), <R as Renderer>::Error>;
 
     /// Returns z_index of RenderElement, reverf too [`RenderZindex`] for default values
+    ///
+    /// Elements with the same z_index are drawn in a stable order, see [`sort_render_elements`].
     fn z_index(&self) -> u8 {
         RenderZindex::Overlay as u8
     }
+
+    /// Sends the frame callbacks of all surfaces drawn by this element with the given `time`.
+    ///
+    /// Called by [`ElementStack::send_frames`] for elements, that were not hidden this frame.
+    /// Elements not drawing any client surfaces don't need to implement this.
+    fn send_frame(&self, time: u32) {
+        let _ = time;
+    }
+
+    /// Returns the texture filter this element should be drawn with
+    ///
+    /// E.g. pixel-art is best scaled with [`TextureFilter::Nearest`], while smooth UI elements
+    /// need [`TextureFilter::Linear`]. The filter is applied using [`Frame::set_texture_filter`]
+    /// while the element is drawn. Returning `None` uses the filters of the renderer,
+    /// which default to [`TextureFilter::Linear`].
+    fn texture_filter(&self) -> Option<TextureFilter> {
+        None
+    }
+
+    /// Returns the kind of this element, see [`ElementKind`]
+    fn kind(&self) -> ElementKind {
+        ElementKind::Unspecified
+    }
+}
+
+/// Sort elements into the order they are drawn in
+///
+/// Elements are ordered by their [`z_index`](RenderElement::z_index). Ties are broken by their
+/// [`ElementId`], which for elements of the same type follows the order they were created in,
+/// while elements of different types are ordered by type. The resulting order does not depend on
+/// the order of `elements`, so overlapping elements sharing a z_index are drawn the same way
+/// every frame, even if the list of elements is rebuilt in between.
+pub fn sort_render_elements<R, E>(elements: &mut [&E])
+where
+    R: Renderer + ImportAll,
+    E: RenderElement<R>,
+{
+    elements.sort_by_key(|element| (element.z_index(), element.type_of(), element.id()));
+}
+
+// Draw an element applying its texture filter
+pub(crate) fn draw_with_texture_filter<R, E>(
+    element: &E,
+    renderer: &mut R,
+    frame: &mut <R as Renderer>::Frame,
+    scale: impl Into<Scale<f64>>,
+    location: Point<f64, Physical>,
+    damage: &[Rectangle<i32, Physical>],
+    log: &slog::Logger,
+) -> Result<(), <R as Renderer>::Error>
+where
+    R: Renderer + ImportAll,
+    E: RenderElement<R>,
+{
+    frame.set_texture_filter(element.texture_filter());
+    let result = element.draw(renderer, frame, scale, location, damage, log);
+    frame.set_texture_filter(None);
+    result
 }
 
 pub(crate) enum SpaceElement<'a, R, E>
@@ -202,7 +292,9 @@ where
             SpaceElement::Popup(popup) => {
                 popup.elem_draw(space_id, renderer, frame, scale, location, damage, log)
             }
-            SpaceElement::Custom(custom, _) => custom.draw(renderer, frame, scale, location, damage, log),
+            SpaceElement::Custom(custom, _) => {
+                draw_with_texture_filter(*custom, renderer, frame, scale, location, damage, log)
+            }
         }
     }
     pub fn z_index(&self, space_id: usize) -> u8 {
@@ -231,6 +323,33 @@ pub struct SurfaceTree {
     pub position: Point<i32, Logical>,
     /// Z-Index to draw at
     pub z_index: u8,
+    kind: ElementKind,
+}
+
+impl SurfaceTree {
+    /// Create a new [`SurfaceTree`] drawing `surface` at `position`
+    ///
+    /// `kind` classifies what the surface is used for, e.g. [`ElementKind::Cursor`] for cursor and
+    /// drag'n'drop icons or [`ElementKind::Window`] and [`ElementKind::Popup`] for shell surfaces
+    /// drawn outside of a [`Space`].
+    pub fn new(
+        surface: WlSurface,
+        position: impl Into<Point<i32, Logical>>,
+        z_index: u8,
+        kind: ElementKind,
+    ) -> Self {
+        SurfaceTree {
+            surface,
+            position: position.into(),
+            z_index,
+            kind,
+        }
+    }
+
+    /// Set the kind of this element, see [`ElementKind`]
+    pub fn set_kind(&mut self, kind: ElementKind) {
+        self.kind = kind;
+    }
 }
 
 impl<R> RenderElement<R> for SurfaceTree
@@ -293,6 +412,14 @@ where
     fn z_index(&self) -> u8 {
         self.z_index
     }
+
+    fn send_frame(&self, time: u32) {
+        send_frames_surface_tree(&self.surface, time)
+    }
+
+    fn kind(&self) -> ElementKind {
+        self.kind
+    }
 }
 
 /// Newtype for (&Space, &Output) to provide a `Hash` implementation for damage tracking
@@ -325,3 +452,82 @@ impl<'a, 'b> From<(&'a Space, &'b Output)> for SpaceOutputTuple<'a, 'b> {
 /// Type to use as an owned hashable value equal to [`SpaceOutputTuple`]
 #[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
 pub struct SpaceOutputHash(usize, usize);
+
+/// Tracks changes of an element per space and output
+///
+/// Every change increments the commit counter, [`CommitTracker::changed_for`] compares it
+/// with the commit last seen for a given space and output.
+#[derive(Debug, Default)]
+pub(super) struct CommitTracker {
+    commit: usize,
+    last_commits: RefCell<HashMap<SpaceOutputHash, usize>>,
+}
+
+impl CommitTracker {
+    /// Record a change of the element
+    pub fn increment(&mut self) {
+        self.commit = self.commit.wrapping_add(1);
+    }
+
+    /// Returns whether the element changed since the last call for the same space and output
+    pub fn changed_for(&self, values: &SpaceOutputTuple<'_, '_>) -> bool {
+        self.last_commits
+            .borrow_mut()
+            .insert(values.owned_hash(), self.commit)
+            != Some(self.commit)
+    }
+}
+
+#[cfg(all(test, feature = "renderer_gl"))]
+mod tests {
+    use super::*;
+    use crate::backend::renderer::{gles2::Gles2Renderer, SolidColorBuffer};
+
+    #[test]
+    fn sorting_does_not_depend_on_input_order() {
+        let mut elements = (0..6)
+            .map(|i| BorderElement::new(Rectangle::from_loc_and_size((i, i), (10, 10)), 1.0, [1.0; 4]))
+            .collect::<Vec<_>>();
+        elements[1].set_z_index(RenderZindex::Top as u8);
+        elements[4].set_z_index(RenderZindex::Background as u8);
+
+        let order = |elements: &mut Vec<&BorderElement>| {
+            sort_render_elements::<Gles2Renderer, _>(elements);
+            elements.iter().map(|element| element.id()).collect::<Vec<_>>()
+        };
+
+        let mut input = elements.iter().collect::<Vec<_>>();
+        let expected = order(&mut input);
+        assert_eq!(expected[0], elements[4].id());
+        assert_eq!(expected[5], elements[1].id());
+
+        for shift in 0..elements.len() {
+            let mut input = elements.iter().collect::<Vec<_>>();
+            input.rotate_left(shift);
+            assert_eq!(order(&mut input), expected);
+            input.reverse();
+            assert_eq!(order(&mut input), expected);
+        }
+    }
+
+    #[test]
+    fn adjacent_elements_abut_at_fractional_scale() {
+        let scale = 1.5;
+        for offset in 0..4 {
+            for width in [100, 101] {
+                let color = SolidColorBuffer::new([1.0; 4]);
+                let left =
+                    SolidColorElement::new(Rectangle::from_loc_and_size((offset, 0), (width, 50)), color);
+                let right = SolidColorElement::new(
+                    Rectangle::from_loc_and_size((offset + width, 0), (width, 50)),
+                    color,
+                );
+
+                let left = RenderElement::<Gles2Renderer>::geometry(&left, scale);
+                let right = RenderElement::<Gles2Renderer>::geometry(&right, scale);
+                assert_eq!(left.loc.x + left.size.w, right.loc.x);
+                assert!(left.intersection(right).map_or(true, |shared| shared.is_empty()));
+            }
+        }
+    }
+}
@@ -0,0 +1,121 @@
+use crate::{
+    backend::{
+        egl::{EGLContext, EGLDevice, EGLDisplay, Error as EGLError},
+        renderer::{
+            gles2::{Gles2Error, Gles2Renderbuffer, Gles2Renderer},
+            Bind, ExportMem, Frame, Offscreen, Renderer, TextureMapping,
+        },
+    },
+    desktop::space::{element::draw_with_texture_filter, sort_render_elements, RenderElement},
+    utils::{Buffer, Physical, Rectangle, Scale, Size, Transform},
+};
+
+/// Errors that can occur while creating a headless renderer
+#[derive(Debug, thiserror::Error)]
+pub enum HeadlessError {
+    /// No EGL device could be used for headless rendering
+    #[error("No EGL device supports headless rendering")]
+    NoDevice,
+    /// Enumerating the EGL devices failed
+    #[error("Failed to enumerate EGL devices")]
+    Egl(#[from] EGLError),
+    /// Initializing the renderer failed
+    #[error("Failed to initialize the renderer")]
+    Gles2(#[from] Gles2Error),
+}
+
+/// Create a [`Gles2Renderer`] not associated with any display
+///
+/// This renders using the first EGL device supporting it, without any window system or drm device,
+/// which makes it usable e.g. for automated tests. The renderer has no default framebuffer,
+/// use [`render_elements_to_memory`] or bind an offscreen target to render.
+///
+/// Requires the following EGL extensions:
+/// - `EGL_EXT_device_base`, `EGL_EXT_device_enumeration` and `EGL_EXT_device_query` to find a device
+/// - `EGL_EXT_platform_device` to create a display for the device
+/// - `EGL_KHR_surfaceless_context` to make the context current without any surface
+///
+/// Returns [`HeadlessError::NoDevice`], if no device supports all of these.
+pub fn headless_renderer<L>(logger: L) -> Result<Gles2Renderer, HeadlessError>
+where
+    L: Into<Option<::slog::Logger>>,
+{
+    let log = crate::slog_or_fallback(logger).new(slog::o!("smithay_module" => "desktop_headless"));
+
+    for device in EGLDevice::enumerate()? {
+        let display = match EGLDisplay::new(&device, log.clone()) {
+            Ok(display) => display,
+            Err(err) => {
+                slog::debug!(log, "Skipping EGL device: {}", err);
+                continue;
+            }
+        };
+        if !display
+            .extensions()
+            .iter()
+            .any(|ext| ext == "EGL_KHR_surfaceless_context")
+        {
+            slog::debug!(log, "Skipping EGL device without EGL_KHR_surfaceless_context");
+            continue;
+        }
+        let context = match EGLContext::new(&display, log.clone()) {
+            Ok(context) => context,
+            Err(err) => {
+                slog::debug!(log, "Skipping EGL device: {}", err);
+                continue;
+            }
+        };
+        // SAFETY: the context was just created and is not used anywhere else
+        return Ok(unsafe { Gles2Renderer::new(context, log.clone())? });
+    }
+
+    Err(HeadlessError::NoDevice)
+}
+
+/// Render a list of elements into memory
+///
+/// The elements are drawn ordered by their z-index into an offscreen framebuffer of the given size,
+/// as if they were placed on an output located at `(0, 0)` with the given scale. The framebuffer
+/// is cleared with `clear_color` first.
+///
+/// Returns the contents of the framebuffer as tightly packed RGBA pixels with 8 bits per channel,
+/// starting with the top row. This replaces the currently bound target of the renderer.
+pub fn render_elements_to_memory<E>(
+    renderer: &mut Gles2Renderer,
+    size: impl Into<Size<i32, Physical>>,
+    scale: impl Into<Scale<f64>>,
+    elements: &[E],
+    clear_color: [f32; 4],
+    log: &slog::Logger,
+) -> Result<Vec<u8>, Gles2Error>
+where
+    E: RenderElement<Gles2Renderer>,
+{
+    let size = size.into();
+    let scale = scale.into();
+    let buffer_size = Size::<i32, Buffer>::from((size.w, size.h));
+
+    let target: Gles2Renderbuffer = renderer.create_buffer(buffer_size)?;
+    renderer.bind(target)?;
+
+    let mut elements = elements.iter().collect::<Vec<_>>();
+    sort_render_elements(&mut elements);
+
+    let damage = [Rectangle::from_loc_and_size((0, 0), size)];
+    renderer.render(size, Transform::Normal, |renderer, frame| {
+        frame.clear(clear_color, &damage)?;
+        for element in elements {
+            let location = element.location(scale);
+            draw_with_texture_filter(element, renderer, frame, scale, location, &damage, log)?;
+        }
+        Ok::<(), Gles2Error>(())
+    })??;
+
+    let mapping = renderer.copy_framebuffer(Rectangle::from_loc_and_size((0, 0), buffer_size))?;
+    let pixels = renderer.map_texture(&mapping)?;
+    if !mapping.flipped() {
+        return Ok(pixels.to_vec());
+    }
+    let stride = size.w as usize * 4;
+    Ok(pixels.chunks_exact(stride).rev().flatten().copied().collect())
+}
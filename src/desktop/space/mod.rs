@@ -19,14 +19,58 @@ use indexmap::{IndexMap, IndexSet};
 use std::{collections::VecDeque, fmt};
 use wayland_server::{protocol::wl_surface::WlSurface, DisplayHandle, Resource};
 
+#[cfg(feature = "renderer_gl")]
+mod border;
+#[cfg(feature = "renderer_gl")]
+mod cached;
+mod clip;
+mod damage;
+mod diff;
 mod element;
+#[cfg(feature = "renderer_gl")]
+mod headless;
 mod layer;
+#[cfg(feature = "renderer_gl")]
+mod modulate;
 mod output;
+mod output_renderer;
 mod popup;
+#[cfg(feature = "renderer_gl")]
+mod shadow;
+#[cfg(feature = "renderer_gl")]
+mod snapshot;
+#[cfg(feature = "renderer_gl")]
+mod solid;
+mod stack;
+mod text;
+#[cfg(feature = "renderer_gl")]
+mod tiled;
 mod window;
 
+#[cfg(feature = "renderer_gl")]
+pub use self::border::{BorderElement, BorderPlacement};
+#[cfg(feature = "renderer_gl")]
+pub use self::cached::CachedElement;
+pub use self::clip::ClipElement;
+pub use self::damage::DamageTrackedRenderer;
+pub use self::diff::{ElementDiff, ElementId};
 pub use self::element::*;
+#[cfg(feature = "renderer_gl")]
+pub use self::headless::{headless_renderer, render_elements_to_memory, HeadlessError};
+#[cfg(feature = "renderer_gl")]
+pub use self::modulate::ModulateElement;
 use self::output::*;
+pub use self::output_renderer::{OutputRenderResult, OutputRenderer};
+#[cfg(feature = "renderer_gl")]
+pub use self::shadow::DropShadowElement;
+#[cfg(feature = "renderer_gl")]
+pub use self::snapshot::SnapshotElement;
+#[cfg(feature = "renderer_gl")]
+pub use self::solid::SolidColorElement;
+pub use self::stack::ElementStack;
+pub use self::text::{Glyph, TextElement};
+#[cfg(feature = "renderer_gl")]
+pub use self::tiled::TiledTextureElement;
 use self::window::*;
 
 use super::WindowSurfaceType;
@@ -446,8 +490,9 @@ impl Space {
     /// to avoid rendering artifacts.
     ///
     /// To add aditional elements without breaking damage-tracking implement the `RenderElement`
-    /// trait and use `custom_elements` to provide them to this function. `custom_elements are rendered
-    /// after every other element.
+    /// trait and use `custom_elements` to provide them to this function. `custom_elements` are rendered
+    /// before, and thus below, the windows, layers and popups of the same z-index. Among themselves they
+    /// are rendered in the order given by [`sort_render_elements`].
     ///
     /// Returns a list of updated regions relative to the rendered output
     /// (or `None` if that list would be empty) in case of success.
@@ -498,9 +543,11 @@ impl Space {
                 + layer_popups.len(),
         );
 
+        let mut custom_elements = custom_elements.iter().collect::<Vec<_>>();
+        sort_render_elements(&mut custom_elements);
         render_elements.extend(
             custom_elements
-                .iter()
+                .into_iter()
                 .map(|e| SpaceElement::Custom(e, std::marker::PhantomData)),
         );
         render_elements.extend(self.windows.iter().map(SpaceElement::Window));
@@ -877,6 +924,42 @@ macro_rules! custom_elements_internal {
                 Self::_GenericCatcher(_) => unreachable!(),
             }
         }
+
+        fn send_frame(&self, time: u32) {
+            match self {
+                $(
+                    $(
+                        #[$meta]
+                    )*
+                    Self::$body(x) => $crate::custom_elements_internal!(@call $renderer $(as $other_renderer)?; send_frame; x, time)
+                ),*,
+                Self::_GenericCatcher(_) => unreachable!(),
+            }
+        }
+
+        fn texture_filter(&self) -> Option<$crate::backend::renderer::TextureFilter> {
+            match self {
+                $(
+                    $(
+                        #[$meta]
+                    )*
+                    Self::$body(x) => $crate::custom_elements_internal!(@call $renderer $(as $other_renderer)?; texture_filter; x)
+                ),*,
+                Self::_GenericCatcher(_) => unreachable!(),
+            }
+        }
+
+        fn kind(&self) -> $crate::desktop::space::ElementKind {
+            match self {
+                $(
+                    $(
+                        #[$meta]
+                    )*
+                    Self::$body(x) => $crate::custom_elements_internal!(@call $renderer $(as $other_renderer)?; kind; x)
+                ),*,
+                Self::_GenericCatcher(_) => unreachable!(),
+            }
+        }
     };
     (@draw <$renderer:ty>; $($(#[$meta:meta])* $body:ident=$field:ty $(as <$other_renderer:ty>)?),* $(,)?) => {
         fn draw(
@@ -0,0 +1,140 @@
+use crate::{
+    backend::renderer::{
+        gles2::{Gles2Error, Gles2Frame, Gles2Renderer},
+        TextureFilter,
+    },
+    desktop::space::{element::CommitTracker, ElementKind, RenderElement, SpaceOutputTuple},
+    utils::{Physical, Point, Rectangle, Scale},
+};
+
+/// Wrapper multiplying the colors of another [`RenderElement`] with a color
+///
+/// This can be used to dim, tint or fade out arbitrary elements, e.g. to darken unfocused windows.
+/// The color is given with straight alpha, so `[0.5, 0.5, 0.5, 1.0]` darkens the element and
+/// `[1.0, 1.0, 1.0, 0.5]` makes it half transparent.
+///
+/// The modulation is applied using [`Gles2Frame::with_tint`] to everything the wrapped element draws.
+/// An element, that is not fully opaque anymore, does not report any opaque regions.
+#[derive(Debug)]
+pub struct ModulateElement<E> {
+    element: E,
+    color: [f32; 4],
+    commits: CommitTracker,
+}
+
+impl<E> ModulateElement<E> {
+    /// Wrap an element multiplying its colors with the given color
+    pub fn new(element: E, color: [f32; 4]) -> Self {
+        ModulateElement {
+            element,
+            color,
+            commits: CommitTracker::default(),
+        }
+    }
+
+    /// Set the color the element is multiplied with
+    pub fn set_color(&mut self, color: [f32; 4]) {
+        if color != self.color {
+            self.color = color;
+            self.commits.increment();
+        }
+    }
+
+    /// Returns the color the element is multiplied with
+    pub fn color(&self) -> [f32; 4] {
+        self.color
+    }
+
+    /// Returns a reference to the wrapped element
+    pub fn get_ref(&self) -> &E {
+        &self.element
+    }
+
+    /// Returns a mutable reference to the wrapped element
+    pub fn get_mut(&mut self) -> &mut E {
+        &mut self.element
+    }
+
+    /// Unwraps the element
+    pub fn into_inner(self) -> E {
+        self.element
+    }
+
+    fn premultiplied(&self) -> [f32; 4] {
+        let [r, g, b, a] = self.color;
+        [r * a, g * a, b * a, a]
+    }
+}
+
+impl<E> RenderElement<Gles2Renderer> for ModulateElement<E>
+where
+    E: RenderElement<Gles2Renderer>,
+{
+    fn id(&self) -> usize {
+        self.element.id()
+    }
+
+    fn location(&self, scale: impl Into<Scale<f64>>) -> Point<f64, Physical> {
+        self.element.location(scale)
+    }
+
+    fn geometry(&self, scale: impl Into<Scale<f64>>) -> Rectangle<i32, Physical> {
+        self.element.geometry(scale)
+    }
+
+    fn accumulated_damage(
+        &self,
+        scale: impl Into<Scale<f64>>,
+        for_values: Option<SpaceOutputTuple<'_, '_>>,
+    ) -> Vec<Rectangle<i32, Physical>> {
+        let scale = scale.into();
+        // a changed color affects the whole element
+        let color_changed = for_values
+            .as_ref()
+            .map_or(false, |values| self.commits.changed_for(values));
+        let damage = self.element.accumulated_damage(scale, for_values);
+        if color_changed {
+            vec![self.element.geometry(scale)]
+        } else {
+            damage
+        }
+    }
+
+    fn opaque_regions(&self, scale: impl Into<Scale<f64>>) -> Option<Vec<Rectangle<i32, Physical>>> {
+        if self.color[3] < 1.0 {
+            return None;
+        }
+        self.element.opaque_regions(scale)
+    }
+
+    fn draw(
+        &self,
+        renderer: &mut Gles2Renderer,
+        frame: &mut Gles2Frame,
+        scale: impl Into<Scale<f64>>,
+        location: Point<f64, Physical>,
+        damage: &[Rectangle<i32, Physical>],
+        log: &slog::Logger,
+    ) -> Result<(), Gles2Error> {
+        let scale = scale.into();
+        frame.with_tint(self.premultiplied(), |frame| {
+            self.element.draw(renderer, frame, scale, location, damage, log)
+        })
+    }
+
+    fn z_index(&self) -> u8 {
+        self.element.z_index()
+    }
+
+    fn send_frame(&self, time: u32) {
+        self.element.send_frame(time)
+    }
+
+    fn texture_filter(&self) -> Option<TextureFilter> {
+        self.element.texture_filter()
+    }
+
+    fn kind(&self) -> ElementKind {
+        self.element.kind()
+    }
+}
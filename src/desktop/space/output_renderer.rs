@@ -0,0 +1,350 @@
+use crate::{
+    backend::renderer::{ImportAll, Renderer},
+    desktop::space::{
+        sort_render_elements, DamageTrackedRenderer, ElementId, ElementStack, RenderElement, RenderError,
+    },
+    utils::{Physical, Rectangle, Transform},
+    wayland::output::Output,
+};
+
+/// Result of [`OutputRenderer::render`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutputRenderResult {
+    /// Regions of the output, that were redrawn, or `None` if nothing changed
+    ///
+    /// Pass these on when submitting the buffer, e.g. as damage of a `DrmSurface` commit.
+    pub damage: Option<Vec<Rectangle<i32, Physical>>>,
+    /// Elements at least partially visible on the output, ordered from top to bottom
+    ///
+    /// Elements completely hidden behind opaque elements are not included,
+    /// even if they were damaged.
+    pub presented: Vec<ElementId>,
+}
+
+/// Renders a list of [`RenderElement`]s to an [`Output`]
+///
+/// This ties together the steps needed to render every frame of an output:
+///
+/// - The size, scale and transform are taken from the current state of the output,
+///   changing any of them redraws the whole output.
+/// - Only damaged parts of the output are redrawn, using a [`DamageTrackedRenderer`].
+/// - Elements hidden behind opaque elements are culled.
+/// - Frame callbacks are only sent to elements, that are visible, see [`OutputRenderer::send_frames`].
+///
+/// The geometry of the elements is expected to be relative to the output.
+///
+/// ```ignore
+/// let result = output_renderer.render(&mut renderer, age, CLEAR_COLOR, &elements)?;
+/// // submit the buffer, passing `result.damage` ...
+/// output_renderer.send_frames::<Gles2Renderer, _>(&elements, time);
+/// ```
+#[derive(Debug)]
+pub struct OutputRenderer {
+    output: Output,
+    damage_tracker: DamageTrackedRenderer,
+    // elements at least partially visible in the last rendered frame
+    presented: Vec<ElementId>,
+}
+
+impl OutputRenderer {
+    /// Create a new [`OutputRenderer`] for the given output
+    pub fn new<L>(output: &Output, log: L) -> Self
+    where
+        L: Into<Option<::slog::Logger>>,
+    {
+        let transform: Transform = output.current_transform().into();
+        let size = output.current_mode().map(|mode| mode.size).unwrap_or_default();
+        OutputRenderer {
+            output: output.clone(),
+            damage_tracker: DamageTrackedRenderer::new(
+                size,
+                output.current_scale().fractional_scale(),
+                transform,
+                log,
+            ),
+            presented: Vec::new(),
+        }
+    }
+
+    /// Returns the output rendered to
+    pub fn output(&self) -> &Output {
+        &self.output
+    }
+
+    /// Reset the tracked damage, causing the next render to redraw everything
+    ///
+    /// You need to call this, if you have rendered into the buffers of the output without using
+    /// this renderer.
+    pub fn reset(&mut self) {
+        self.damage_tracker.reset();
+    }
+
+    /// Render the given elements into the buffer currently bound to `renderer`
+    ///
+    /// Elements are drawn in the order given by [`sort_render_elements`], `clear_color` fills all
+    /// regions not covered by any opaque element. `age` is the age of the bound buffer, as returned
+    /// e.g. by a `DrmSurface` or `EGLSurface`, `0` redraws everything.
+    pub fn render<R, E>(
+        &mut self,
+        renderer: &mut R,
+        age: usize,
+        clear_color: [f32; 4],
+        elements: &[E],
+    ) -> Result<OutputRenderResult, RenderError<R>>
+    where
+        R: Renderer + ImportAll,
+        R::TextureId: 'static,
+        E: RenderElement<R>,
+    {
+        let mode = self.output.current_mode().ok_or(RenderError::OutputNoMode)?;
+        let scale = self.output.current_scale().fractional_scale();
+        let transform: Transform = self.output.current_transform().into();
+        self.damage_tracker.set_mode(mode.size, scale, transform);
+
+        // find the elements, that are visible at all
+        let output_geo = Rectangle::from_loc_and_size((0, 0), mode.size);
+        let mut sorted = elements.iter().collect::<Vec<_>>();
+        sort_render_elements(&mut sorted);
+        let mut stack = ElementStack::<R, E>::new([output_geo], scale);
+        stack.extend(sorted.into_iter().rev());
+        let presented = stack
+            .presented()
+            .map(|element| (element.type_of(), element.id()))
+            .collect::<Vec<_>>();
+
+        let damage = self
+            .damage_tracker
+            .render_output(renderer, age, clear_color, elements)?;
+        // a failed frame was not presented, keep the elements of the last one for `send_frames`
+        self.presented = presented.clone();
+
+        Ok(OutputRenderResult { damage, presented })
+    }
+
+    /// Send the frame callbacks of the elements, that were visible in the last rendered frame
+    ///
+    /// Call this once the frame was presented, passing the presentation time. Elements completely
+    /// hidden behind opaque elements don't receive frame callbacks. See [`RenderElement::send_frame`].
+    pub fn send_frames<R, E>(&self, elements: &[E], time: u32)
+    where
+        R: Renderer + ImportAll,
+        E: RenderElement<R>,
+    {
+        for element in elements {
+            if self.presented.contains(&(element.type_of(), element.id())) {
+                element.send_frame(time);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use wayland_server::protocol::{wl_buffer, wl_output::Subpixel};
+
+    use super::*;
+    use crate::{
+        backend::{
+            renderer::{Frame, Texture, TextureFilter},
+            SwapBuffersError,
+        },
+        desktop::space::SpaceOutputTuple,
+        utils::{Buffer, Point, Scale, Size},
+        wayland::{
+            compositor::SurfaceData,
+            output::{Mode, PhysicalProperties},
+        },
+    };
+
+    // Renderer drawing nothing, whose frames fail on request
+    #[derive(Default)]
+    struct TestRenderer {
+        fail: bool,
+    }
+
+    struct TestFrame;
+
+    struct TestTexture;
+
+    impl Texture for TestTexture {
+        fn width(&self) -> u32 {
+            0
+        }
+
+        fn height(&self) -> u32 {
+            0
+        }
+    }
+
+    impl Frame for TestFrame {
+        type Error = SwapBuffersError;
+        type TextureId = TestTexture;
+
+        fn clear(&mut self, _color: [f32; 4], _at: &[Rectangle<i32, Physical>]) -> Result<(), Self::Error> {
+            Ok(())
+        }
+
+        fn render_texture_from_to(
+            &mut self,
+            _texture: &Self::TextureId,
+            _src: Rectangle<f64, Buffer>,
+            _dst: Rectangle<i32, Physical>,
+            _damage: &[Rectangle<i32, Physical>],
+            _src_transform: Transform,
+            _alpha: f32,
+        ) -> Result<(), Self::Error> {
+            Ok(())
+        }
+
+        fn transformation(&self) -> Transform {
+            Transform::Normal
+        }
+    }
+
+    impl Renderer for TestRenderer {
+        type Error = SwapBuffersError;
+        type TextureId = TestTexture;
+        type Frame = TestFrame;
+
+        fn id(&self) -> usize {
+            0
+        }
+
+        fn downscale_filter(&mut self, _filter: TextureFilter) -> Result<(), Self::Error> {
+            Ok(())
+        }
+
+        fn upscale_filter(&mut self, _filter: TextureFilter) -> Result<(), Self::Error> {
+            Ok(())
+        }
+
+        fn render<F, R>(
+            &mut self,
+            _size: Size<i32, Physical>,
+            _dst_transform: Transform,
+            rendering: F,
+        ) -> Result<R, Self::Error>
+        where
+            F: FnOnce(&mut Self, &mut Self::Frame) -> R,
+        {
+            if self.fail {
+                return Err(SwapBuffersError::AlreadySwapped);
+            }
+            Ok(rendering(self, &mut TestFrame))
+        }
+    }
+
+    impl ImportAll for TestRenderer {
+        fn import_buffer(
+            &mut self,
+            _buffer: &wl_buffer::WlBuffer,
+            _surface: Option<&SurfaceData>,
+            _damage: &[Rectangle<i32, Buffer>],
+        ) -> Option<Result<TestTexture, SwapBuffersError>> {
+            None
+        }
+    }
+
+    // Opaque element counting its frame callbacks
+    struct TestElement {
+        id: usize,
+        geometry: Rectangle<i32, Physical>,
+        frames: Cell<usize>,
+    }
+
+    impl TestElement {
+        fn new(id: usize, geometry: Rectangle<i32, Physical>) -> Self {
+            TestElement {
+                id,
+                geometry,
+                frames: Cell::new(0),
+            }
+        }
+    }
+
+    impl RenderElement<TestRenderer> for TestElement {
+        fn id(&self) -> usize {
+            self.id
+        }
+
+        fn location(&self, _scale: impl Into<Scale<f64>>) -> Point<f64, Physical> {
+            self.geometry.loc.to_f64()
+        }
+
+        fn geometry(&self, _scale: impl Into<Scale<f64>>) -> Rectangle<i32, Physical> {
+            self.geometry
+        }
+
+        fn accumulated_damage(
+            &self,
+            _scale: impl Into<Scale<f64>>,
+            _for_values: Option<SpaceOutputTuple<'_, '_>>,
+        ) -> Vec<Rectangle<i32, Physical>> {
+            vec![Rectangle::from_loc_and_size((0, 0), self.geometry.size)]
+        }
+
+        fn opaque_regions(&self, _scale: impl Into<Scale<f64>>) -> Option<Vec<Rectangle<i32, Physical>>> {
+            Some(vec![Rectangle::from_loc_and_size((0, 0), self.geometry.size)])
+        }
+
+        fn draw(
+            &self,
+            _renderer: &mut TestRenderer,
+            _frame: &mut TestFrame,
+            _scale: impl Into<Scale<f64>>,
+            _location: Point<f64, Physical>,
+            _damage: &[Rectangle<i32, Physical>],
+            _log: &slog::Logger,
+        ) -> Result<(), SwapBuffersError> {
+            Ok(())
+        }
+
+        fn send_frame(&self, _time: u32) {
+            self.frames.set(self.frames.get() + 1);
+        }
+    }
+
+    fn test_output() -> Output {
+        let output = Output::new(
+            "test".into(),
+            PhysicalProperties {
+                size: (0, 0).into(),
+                subpixel: Subpixel::Unknown,
+                make: "smithay".into(),
+                model: "test".into(),
+            },
+            None,
+        );
+        let mode = Mode {
+            size: (100, 100).into(),
+            refresh: 60_000,
+        };
+        output.change_current_state(Some(mode), None, None, None);
+        output
+    }
+
+    #[test]
+    fn failed_render_keeps_the_presented_elements() {
+        let output = test_output();
+        let mut output_renderer = OutputRenderer::new(&output, None);
+        let mut renderer = TestRenderer::default();
+        let window = TestElement::new(0, Rectangle::from_loc_and_size((10, 10), (50, 50)));
+        let result = output_renderer
+            .render(&mut renderer, 0, [0.0; 4], std::slice::from_ref(&window))
+            .unwrap();
+        assert_eq!(result.presented.len(), 1);
+
+        // a fullscreen element hides the window, but the frame is never presented
+        let fullscreen = TestElement::new(1, Rectangle::from_loc_and_size((0, 0), (100, 100)));
+        let elements = [fullscreen, window];
+        renderer.fail = true;
+        let result = output_renderer.render(&mut renderer, 0, [0.0; 4], &elements);
+        assert!(result.is_err());
+
+        output_renderer.send_frames::<TestRenderer, _>(&elements, 0);
+        assert_eq!(elements[0].frames.get(), 0);
+        assert_eq!(elements[1].frames.get(), 1);
+    }
+}
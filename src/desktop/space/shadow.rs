@@ -0,0 +1,209 @@
+use crate::{
+    backend::renderer::gles2::{Gles2Error, Gles2Frame, Gles2Renderer},
+    desktop::space::{element::CommitTracker, ElementKind, RenderElement, RenderZindex, SpaceOutputTuple},
+    utils::{Logical, Physical, Point, Rectangle, Scale},
+};
+
+crate::utils::ids::id_gen!(next_shadow_id, SHADOW_ID, SHADOW_IDS);
+
+/// Custom element drawing a drop shadow below a rectangle
+///
+/// The shadow has the shape of the casting rectangle, optionally with rounded corners,
+/// moved by an offset and blurred by a gaussian blur fading out within the blur radius.
+/// It is computed analytically from the geometry, so no offscreen rendering is involved.
+///
+/// Shadows are translucent, so this element never reports any opaque regions.
+/// By default it is placed right below windows.
+#[derive(Debug)]
+pub struct DropShadowElement {
+    id: usize,
+    caster: Rectangle<i32, Logical>,
+    blur_radius: f64,
+    offset: Point<i32, Logical>,
+    color: [f32; 4],
+    radius: [f64; 4],
+    z_index: u8,
+    commits: CommitTracker,
+}
+
+impl DropShadowElement {
+    /// Create a new [`DropShadowElement`] for the rectangle `caster`
+    ///
+    /// The color is given with straight alpha. The shadow has square corners by default.
+    pub fn new(
+        caster: impl Into<Rectangle<i32, Logical>>,
+        blur_radius: f64,
+        offset: impl Into<Point<i32, Logical>>,
+        color: [f32; 4],
+    ) -> Self {
+        DropShadowElement {
+            id: next_shadow_id(),
+            caster: caster.into(),
+            blur_radius: blur_radius.max(0.0),
+            offset: offset.into(),
+            color,
+            radius: [0.0; 4],
+            z_index: RenderZindex::Shell as u8 - 1,
+            commits: CommitTracker::default(),
+        }
+    }
+
+    /// Set the rectangle casting the shadow
+    pub fn set_caster(&mut self, caster: impl Into<Rectangle<i32, Logical>>) {
+        let caster = caster.into();
+        if caster.size != self.caster.size {
+            self.damage();
+        }
+        self.caster = caster;
+    }
+
+    /// Set the radius of the blur
+    pub fn set_blur_radius(&mut self, blur_radius: f64) {
+        let blur_radius = blur_radius.max(0.0);
+        if blur_radius != self.blur_radius {
+            self.blur_radius = blur_radius;
+            self.damage();
+        }
+    }
+
+    /// Set the offset of the shadow relative to the casting rectangle
+    pub fn set_offset(&mut self, offset: impl Into<Point<i32, Logical>>) {
+        self.offset = offset.into();
+    }
+
+    /// Set the color of the shadow, with straight alpha
+    pub fn set_color(&mut self, color: [f32; 4]) {
+        if color != self.color {
+            self.color = color;
+            self.damage();
+        }
+    }
+
+    /// Set the radii of the corners of the shadow
+    ///
+    /// These should match the corners of the casting surface, the blur rounds them further.
+    /// See [`BorderElement::set_corner_radius`](super::BorderElement::set_corner_radius) for the order.
+    pub fn set_corner_radius(&mut self, radius: [f64; 4]) {
+        if radius != self.radius {
+            self.radius = radius;
+            self.damage();
+        }
+    }
+
+    /// Set the z-index of the shadow
+    ///
+    /// Defaults to right below [`RenderZindex::Shell`], shadows of popups or layer surfaces
+    /// need to be placed below their respective z-index instead.
+    pub fn set_z_index(&mut self, z_index: u8) {
+        self.z_index = z_index;
+    }
+
+    /// Returns the rectangle casting the shadow
+    pub fn caster(&self) -> Rectangle<i32, Logical> {
+        self.caster
+    }
+
+    /// Returns the radius of the blur
+    pub fn blur_radius(&self) -> f64 {
+        self.blur_radius
+    }
+
+    /// Returns the offset of the shadow relative to the casting rectangle
+    pub fn offset(&self) -> Point<i32, Logical> {
+        self.offset
+    }
+
+    /// Returns the color of the shadow
+    pub fn color(&self) -> [f32; 4] {
+        self.color
+    }
+
+    /// Returns the radii of the corners of the shadow
+    pub fn corner_radius(&self) -> [f64; 4] {
+        self.radius
+    }
+
+    // area covered by the shadow, including the blur
+    fn outer_rect(&self) -> Rectangle<f64, Logical> {
+        let mut rect = self.caster.to_f64();
+        rect.loc += self.offset.to_f64();
+        rect.loc -= (self.blur_radius, self.blur_radius).into();
+        rect.size += (self.blur_radius * 2.0, self.blur_radius * 2.0).into();
+        rect
+    }
+
+    fn damage(&mut self) {
+        self.commits.increment();
+    }
+}
+
+impl RenderElement<Gles2Renderer> for DropShadowElement {
+    fn id(&self) -> usize {
+        self.id
+    }
+
+    fn location(&self, scale: impl Into<Scale<f64>>) -> Point<f64, Physical> {
+        self.outer_rect().loc.to_physical(scale)
+    }
+
+    fn geometry(&self, scale: impl Into<Scale<f64>>) -> Rectangle<i32, Physical> {
+        self.outer_rect().to_physical_precise_round_edges(scale)
+    }
+
+    fn accumulated_damage(
+        &self,
+        scale: impl Into<Scale<f64>>,
+        for_values: Option<SpaceOutputTuple<'_, '_>>,
+    ) -> Vec<Rectangle<i32, Physical>> {
+        // the blur spreads any change across the whole shadow
+        if let Some(values) = for_values {
+            if !self.commits.changed_for(&values) {
+                return Vec::new();
+            }
+        }
+        vec![self.geometry(scale)]
+    }
+
+    fn opaque_regions(&self, _scale: impl Into<Scale<f64>>) -> Option<Vec<Rectangle<i32, Physical>>> {
+        None
+    }
+
+    fn draw(
+        &self,
+        _renderer: &mut Gles2Renderer,
+        frame: &mut Gles2Frame,
+        scale: impl Into<Scale<f64>>,
+        location: Point<f64, Physical>,
+        damage: &[Rectangle<i32, Physical>],
+        _log: &slog::Logger,
+    ) -> Result<(), Gles2Error> {
+        let scale = scale.into();
+        // `location` is the corner of `outer_rect`, the frame expands the caster by the blur itself
+        let blur = self.blur_radius * scale.x;
+        let size = self.caster.size.to_f64().to_physical(scale);
+        let geometry =
+            Rectangle::from_loc_and_size(location + Point::from((blur, blur)), size).to_i32_round_edges();
+        let [r, g, b, a] = self.color;
+        frame.draw_shadow(
+            geometry,
+            blur as f32,
+            self.radius.map(|radius| (radius * scale.x) as f32),
+            [r * a, g * a, b * a, a],
+            damage,
+        )
+    }
+
+    fn z_index(&self) -> u8 {
+        self.z_index
+    }
+
+    fn kind(&self) -> ElementKind {
+        ElementKind::Background
+    }
+}
+
+impl Drop for DropShadowElement {
+    fn drop(&mut self) {
+        SHADOW_IDS.lock().unwrap().remove(&self.id);
+    }
+}
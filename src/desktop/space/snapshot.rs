@@ -0,0 +1,142 @@
+use crate::{
+    backend::renderer::{
+        gles2::{Gles2Error, Gles2Frame, Gles2Renderer, Gles2Texture},
+        Frame, Texture,
+    },
+    desktop::space::{RenderElement, RenderZindex, SpaceOutputTuple},
+    utils::{Logical, Physical, Point, Rectangle, Scale, Size, Transform},
+};
+
+crate::utils::ids::id_gen!(next_snapshot_id, SNAPSHOT_ID, SNAPSHOT_IDS);
+
+/// Custom element drawing a previously rendered frame
+///
+/// The contents of an output are captured at the end of rendering it and can be drawn at any
+/// location afterwards. This is useful for transitions, e.g. sliding out the contents of a previous
+/// workspace while the new one slides in, without having to render the old state again.
+///
+/// The snapshot keeps the scale it was captured with and is stretched on outputs with another scale.
+#[derive(Debug)]
+pub struct SnapshotElement {
+    id: usize,
+    texture: Gles2Texture,
+    transform: Transform,
+    location: Point<i32, Logical>,
+    size: Size<i32, Logical>,
+    z_index: u8,
+}
+
+impl SnapshotElement {
+    /// Capture everything drawn into the given frame so far
+    ///
+    /// `location` and `scale` are the location and scale of the output, the frame belongs to.
+    /// The element is initially placed at the same location, covering the output.
+    pub fn capture_current(
+        frame: &mut Gles2Frame,
+        location: impl Into<Point<i32, Logical>>,
+        scale: impl Into<Scale<f64>>,
+    ) -> Self {
+        let texture = frame.copy_to_texture();
+        let transform = frame.transformation();
+        let size = texture
+            .size()
+            .to_f64()
+            .to_logical(scale.into(), transform)
+            .to_i32_round();
+        SnapshotElement {
+            id: next_snapshot_id(),
+            texture,
+            transform,
+            location: location.into(),
+            size,
+            z_index: RenderZindex::Overlay as u8,
+        }
+    }
+
+    /// Set the location the snapshot is drawn at
+    pub fn set_location(&mut self, location: impl Into<Point<i32, Logical>>) {
+        self.location = location.into();
+    }
+
+    /// Set the z-index of this element
+    pub fn set_z_index(&mut self, z_index: u8) {
+        self.z_index = z_index;
+    }
+
+    /// Returns the area covered by this element
+    pub fn area(&self) -> Rectangle<i32, Logical> {
+        Rectangle::from_loc_and_size(self.location, self.size)
+    }
+
+    /// Returns the captured texture
+    pub fn texture(&self) -> &Gles2Texture {
+        &self.texture
+    }
+}
+
+impl RenderElement<Gles2Renderer> for SnapshotElement {
+    fn id(&self) -> usize {
+        self.id
+    }
+
+    fn location(&self, scale: impl Into<Scale<f64>>) -> Point<f64, Physical> {
+        self.location.to_f64().to_physical(scale)
+    }
+
+    fn geometry(&self, scale: impl Into<Scale<f64>>) -> Rectangle<i32, Physical> {
+        self.area().to_physical_precise_round_edges(scale)
+    }
+
+    fn accumulated_damage(
+        &self,
+        _scale: impl Into<Scale<f64>>,
+        _for_values: Option<SpaceOutputTuple<'_, '_>>,
+    ) -> Vec<Rectangle<i32, Physical>> {
+        // the captured texture is never updated
+        Vec::new()
+    }
+
+    fn draw(
+        &self,
+        _renderer: &mut Gles2Renderer,
+        frame: &mut Gles2Frame,
+        scale: impl Into<Scale<f64>>,
+        location: Point<f64, Physical>,
+        damage: &[Rectangle<i32, Physical>],
+        _log: &slog::Logger,
+    ) -> Result<(), Gles2Error> {
+        let scale = scale.into();
+        let dst = Rectangle::from_loc_and_size(location, self.size.to_f64().to_physical(scale))
+            .to_i32_round_edges();
+        let damage = damage
+            .iter()
+            .flat_map(|geo| geo.intersection(dst))
+            .map(|mut geo| {
+                geo.loc -= dst.loc;
+                geo
+            })
+            .collect::<Vec<_>>();
+        if damage.is_empty() {
+            return Ok(());
+        }
+
+        frame.render_texture_from_to(
+            &self.texture,
+            Rectangle::from_loc_and_size((0, 0), self.texture.size()).to_f64(),
+            dst,
+            &damage,
+            self.transform,
+            1.0,
+        )
+    }
+
+    fn z_index(&self) -> u8 {
+        self.z_index
+    }
+}
+
+impl Drop for SnapshotElement {
+    fn drop(&mut self) {
+        SNAPSHOT_IDS.lock().unwrap().remove(&self.id);
+    }
+}
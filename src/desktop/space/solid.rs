@@ -0,0 +1,157 @@
+use crate::{
+    backend::renderer::{
+        gles2::{Gles2Error, Gles2Frame, Gles2Renderer},
+        solid_color_buffer, SolidColorBuffer,
+    },
+    desktop::space::{element::CommitTracker, ElementKind, RenderElement, RenderZindex, SpaceOutputTuple},
+    utils::{Logical, Physical, Point, Rectangle, Scale},
+};
+use wayland_server::protocol::wl_buffer::WlBuffer;
+
+crate::utils::ids::id_gen!(next_solid_id, SOLID_ID, SOLID_IDS);
+
+/// Custom element filling a rectangle with a solid color
+///
+/// The color is drawn as a filled rectangle without any texture. Fully opaque colors
+/// report the whole rectangle as opaque, so content below can be skipped during rendering.
+///
+/// Use [`SolidColorElement::from_buffer`] to draw single-pixel client buffers this way
+/// instead of importing them as tiny textures.
+#[derive(Debug)]
+pub struct SolidColorElement {
+    id: usize,
+    rect: Rectangle<i32, Logical>,
+    buffer: SolidColorBuffer,
+    z_index: u8,
+    kind: ElementKind,
+    commits: CommitTracker,
+}
+
+impl SolidColorElement {
+    /// Create a new [`SolidColorElement`] filling `rect` with the color of `buffer`
+    pub fn new(rect: impl Into<Rectangle<i32, Logical>>, buffer: SolidColorBuffer) -> Self {
+        SolidColorElement {
+            id: next_solid_id(),
+            rect: rect.into(),
+            buffer,
+            z_index: RenderZindex::Shell as u8,
+            kind: ElementKind::Unspecified,
+            commits: CommitTracker::default(),
+        }
+    }
+
+    /// Create a new [`SolidColorElement`] filling `rect` with the color of a single-pixel `buffer`
+    ///
+    /// Returns `None` if `buffer` is not a single-pixel buffer, see [`solid_color_buffer`].
+    pub fn from_buffer(rect: impl Into<Rectangle<i32, Logical>>, buffer: &WlBuffer) -> Option<Self> {
+        solid_color_buffer(buffer).map(|buffer| SolidColorElement::new(rect, buffer))
+    }
+
+    /// Set the rectangle filled by this element
+    pub fn set_rect(&mut self, rect: impl Into<Rectangle<i32, Logical>>) {
+        let rect = rect.into();
+        if rect.size != self.rect.size {
+            self.damage();
+        }
+        self.rect = rect;
+    }
+
+    /// Set the color of this element
+    pub fn set_buffer(&mut self, buffer: SolidColorBuffer) {
+        if buffer != self.buffer {
+            self.buffer = buffer;
+            self.damage();
+        }
+    }
+
+    /// Set the z-index of this element
+    pub fn set_z_index(&mut self, z_index: u8) {
+        self.z_index = z_index;
+    }
+
+    /// Set the kind of this element, see [`ElementKind`]
+    ///
+    /// A solid color may be anything from a wallpaper to the contents of a window,
+    /// so the kind defaults to [`ElementKind::Unspecified`].
+    pub fn set_kind(&mut self, kind: ElementKind) {
+        self.kind = kind;
+    }
+
+    /// Returns the rectangle filled by this element
+    pub fn rect(&self) -> Rectangle<i32, Logical> {
+        self.rect
+    }
+
+    /// Returns the color of this element
+    pub fn buffer(&self) -> SolidColorBuffer {
+        self.buffer
+    }
+
+    fn damage(&mut self) {
+        self.commits.increment();
+    }
+}
+
+impl RenderElement<Gles2Renderer> for SolidColorElement {
+    fn id(&self) -> usize {
+        self.id
+    }
+
+    fn location(&self, scale: impl Into<Scale<f64>>) -> Point<f64, Physical> {
+        self.rect.loc.to_f64().to_physical(scale)
+    }
+
+    fn geometry(&self, scale: impl Into<Scale<f64>>) -> Rectangle<i32, Physical> {
+        self.rect.to_physical_precise_round_edges(scale)
+    }
+
+    fn accumulated_damage(
+        &self,
+        scale: impl Into<Scale<f64>>,
+        for_values: Option<SpaceOutputTuple<'_, '_>>,
+    ) -> Vec<Rectangle<i32, Physical>> {
+        // a new color or size affects every pixel of the rectangle
+        if let Some(values) = for_values {
+            if !self.commits.changed_for(&values) {
+                return Vec::new();
+            }
+        }
+        vec![self.geometry(scale)]
+    }
+
+    fn opaque_regions(&self, scale: impl Into<Scale<f64>>) -> Option<Vec<Rectangle<i32, Physical>>> {
+        if self.buffer.is_opaque() {
+            Some(vec![self.geometry(scale)])
+        } else {
+            None
+        }
+    }
+
+    fn draw(
+        &self,
+        _renderer: &mut Gles2Renderer,
+        frame: &mut Gles2Frame,
+        scale: impl Into<Scale<f64>>,
+        location: Point<f64, Physical>,
+        damage: &[Rectangle<i32, Physical>],
+        _log: &slog::Logger,
+    ) -> Result<(), Gles2Error> {
+        let size = self.rect.size.to_f64().to_physical(scale);
+        let geometry = Rectangle::from_loc_and_size(location, size).to_i32_round_edges();
+        frame.draw_solid_color(geometry, self.buffer.color(), damage)
+    }
+
+    fn z_index(&self) -> u8 {
+        self.z_index
+    }
+
+    fn kind(&self) -> ElementKind {
+        self.kind
+    }
+}
+
+impl Drop for SolidColorElement {
+    fn drop(&mut self) {
+        SOLID_IDS.lock().unwrap().remove(&self.id);
+    }
+}
@@ -0,0 +1,192 @@
+use crate::{
+    backend::renderer::{Frame, ImportAll, Renderer},
+    desktop::space::{element::draw_with_texture_filter, RenderElement},
+    utils::{Physical, Rectangle, Scale},
+};
+use std::{fmt, marker::PhantomData};
+
+/// Collection of [`RenderElement`]s culling elements hidden behind opaque ones
+///
+/// Elements are added from top to bottom. Every element is restricted to the parts of the
+/// given damage, that are not covered by the [opaque regions](RenderElement::opaque_regions)
+/// of the elements above it. Elements, that end up completely hidden, are skipped.
+///
+/// The geometry of the elements is expected to be relative to the rendered output,
+/// like for the [`DamageTrackedRenderer`](super::DamageTrackedRenderer).
+///
+/// Independent of the damage, the stack also keeps track of elements, that are at least partially
+/// visible. After rendering, [`ElementStack::send_frames`] uses this to only send frame callbacks
+/// to surfaces, that were actually presented.
+pub struct ElementStack<'a, R, E> {
+    scale: Scale<f64>,
+    // parts of the damage not covered by opaque regions so far
+    visible: Vec<Rectangle<i32, Physical>>,
+    // opaque regions of all elements so far
+    opaque: Vec<Rectangle<i32, Physical>>,
+    // top to bottom
+    elements: Vec<(&'a E, Vec<Rectangle<i32, Physical>>)>,
+    // elements not completely covered by opaque regions, top to bottom
+    presented: Vec<&'a E>,
+    _renderer: PhantomData<fn(&mut R)>,
+}
+
+impl<'a, R, E: fmt::Debug> fmt::Debug for ElementStack<'a, R, E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ElementStack")
+            .field("scale", &self.scale)
+            .field("visible", &self.visible)
+            .field("opaque", &self.opaque)
+            .field("elements", &self.elements)
+            .field("presented", &self.presented)
+            .finish()
+    }
+}
+
+impl<'a, R, E> ElementStack<'a, R, E>
+where
+    R: Renderer + ImportAll,
+    E: RenderElement<R>,
+{
+    /// Create a new stack redrawing the given damage at the given scale
+    pub fn new(
+        damage: impl IntoIterator<Item = Rectangle<i32, Physical>>,
+        scale: impl Into<Scale<f64>>,
+    ) -> Self {
+        ElementStack {
+            scale: scale.into(),
+            visible: damage.into_iter().filter(|rect| !rect.is_empty()).collect(),
+            opaque: Vec::new(),
+            elements: Vec::new(),
+            presented: Vec::new(),
+            _renderer: PhantomData,
+        }
+    }
+
+    /// Add an element below all previously added elements
+    ///
+    /// Returns `false`, if the element is completely hidden and will not be drawn.
+    pub fn push(&mut self, element: &'a E) -> bool {
+        let geometry = element.geometry(self.scale);
+        if !geometry.is_empty() && !subtract_all(vec![geometry], &self.opaque).is_empty() {
+            self.presented.push(element);
+        }
+
+        let damage = self
+            .visible
+            .iter()
+            .filter_map(|rect| rect.intersection(geometry))
+            .filter(|rect| !rect.is_empty())
+            .collect::<Vec<_>>();
+        let regions = element.opaque_regions(self.scale).unwrap_or_default();
+        if !damage.is_empty() {
+            self.visible = subtract_all(std::mem::take(&mut self.visible), &regions);
+        }
+        self.opaque.extend(regions);
+
+        if damage.is_empty() {
+            return false;
+        }
+        self.elements.push((element, damage));
+        true
+    }
+
+    /// Add multiple elements ordered from top to bottom
+    pub fn extend(&mut self, elements: impl IntoIterator<Item = &'a E>) {
+        for element in elements {
+            self.push(element);
+        }
+    }
+
+    /// Returns the parts of the damage not covered by any opaque element
+    ///
+    /// These need to be cleared before drawing the elements.
+    pub fn uncovered(&self) -> &[Rectangle<i32, Physical>] {
+        &self.visible
+    }
+
+    /// Returns true, if the opaque elements cover all of the damage
+    ///
+    /// Clearing the frame is unnecessary in that case.
+    pub fn is_covered(&self) -> bool {
+        self.visible.is_empty()
+    }
+
+    /// Clear the parts of the damage not covered by any opaque element
+    ///
+    /// The clear is skipped, if the damage is completely [covered](ElementStack::is_covered).
+    pub fn clear(
+        &self,
+        frame: &mut <R as Renderer>::Frame,
+        color: [f32; 4],
+    ) -> Result<(), <R as Renderer>::Error> {
+        if self.is_covered() {
+            return Ok(());
+        }
+        frame.clear(color, &self.visible)
+    }
+
+    /// Returns the elements to be drawn together with their restricted damage
+    ///
+    /// Elements are ordered from bottom to top, in the order they need to be drawn.
+    pub fn draw_list(&self) -> impl Iterator<Item = (&'a E, &[Rectangle<i32, Physical>])> {
+        self.elements
+            .iter()
+            .rev()
+            .map(|(element, damage)| (*element, &damage[..]))
+    }
+
+    /// Returns all elements, that are at least partially visible, including undamaged ones
+    ///
+    /// Elements are ordered from top to bottom.
+    pub fn presented(&self) -> impl Iterator<Item = &'a E> + '_ {
+        self.presented.iter().copied()
+    }
+
+    /// Send the frame callbacks of all elements, that are at least partially visible
+    ///
+    /// Call this once the frame was presented, passing the presentation time. Surfaces of elements
+    /// completely hidden behind opaque elements don't receive frame callbacks, so their clients do not
+    /// waste cycles rendering invisible content. Elements outside of the damage still receive them,
+    /// as they may have committed without any damage. See [`RenderElement::send_frame`].
+    pub fn send_frames(&self, time: u32) {
+        for element in &self.presented {
+            element.send_frame(time);
+        }
+    }
+
+    /// Draw all visible elements from bottom to top
+    ///
+    /// The frame is not cleared, use [`ElementStack::clear`] before drawing.
+    pub fn draw(
+        &self,
+        renderer: &mut R,
+        frame: &mut <R as Renderer>::Frame,
+        log: &slog::Logger,
+    ) -> Result<(), <R as Renderer>::Error> {
+        for (element, damage) in self.draw_list() {
+            draw_with_texture_filter(
+                element,
+                renderer,
+                frame,
+                self.scale,
+                element.location(self.scale),
+                damage,
+                log,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+// Subtract all `regions` from `rects`
+fn subtract_all(
+    rects: Vec<Rectangle<i32, Physical>>,
+    regions: &[Rectangle<i32, Physical>],
+) -> Vec<Rectangle<i32, Physical>> {
+    regions.iter().fold(rects, |rects, region| {
+        rects
+            .into_iter()
+            .flat_map(|rect| rect.subtract_rect(*region))
+            .collect()
+    })
+}
@@ -0,0 +1,243 @@
+use crate::{
+    backend::renderer::{Frame, ImportAll, Renderer, Texture},
+    desktop::space::{ElementKind, RenderElement, RenderZindex, SpaceOutputHash, SpaceOutputTuple},
+    utils::{Buffer, Logical, Physical, Point, Rectangle, Scale, Transform},
+};
+use std::{
+    cell::RefCell,
+    collections::{HashMap, VecDeque},
+};
+
+crate::utils::ids::id_gen!(next_text_id, TEXT_ID, TEXT_IDS);
+
+/// Maximum amount of changes to keep track of for damage tracking
+const MAX_DAMAGE_HISTORY: usize = 16;
+
+/// A single positioned glyph of a [`TextElement`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Glyph {
+    /// Geometry of the glyph relative to the origin of the element
+    pub geometry: Rectangle<i32, Logical>,
+    /// Region of the glyph inside the atlas texture
+    pub atlas_region: Rectangle<i32, Buffer>,
+}
+
+/// Custom element rendering text from a prerendered glyph atlas
+///
+/// Smithay does not shape or rasterize any text. You are expected to provide
+/// a texture containing all required glyphs and a run of already positioned [`Glyph`]s,
+/// referencing regions of that texture.
+///
+/// Changes to the glyphs or position of the element are tracked,
+/// so only the affected regions are redrawn.
+#[derive(Debug)]
+pub struct TextElement<T> {
+    id: usize,
+    atlas: T,
+    glyphs: Vec<Glyph>,
+    position: Point<i32, Logical>,
+    alpha: f32,
+    z_index: u8,
+    commit: usize,
+    damage: VecDeque<(usize, Vec<Rectangle<i32, Logical>>)>,
+    last_commits: RefCell<HashMap<SpaceOutputHash, usize>>,
+}
+
+impl<T: Texture> TextElement<T> {
+    /// Create a new [`TextElement`] from an atlas texture and a run of glyphs
+    pub fn new(atlas: T, glyphs: Vec<Glyph>, position: impl Into<Point<i32, Logical>>) -> Self {
+        TextElement {
+            id: next_text_id(),
+            atlas,
+            glyphs,
+            position: position.into(),
+            alpha: 1.0,
+            z_index: RenderZindex::Overlay as u8,
+            commit: 0,
+            damage: VecDeque::new(),
+            last_commits: RefCell::new(HashMap::new()),
+        }
+    }
+
+    /// Replace the displayed glyphs
+    ///
+    /// The `atlas` may be replaced as well, if new glyphs had to be rasterized.
+    pub fn set_glyphs(&mut self, atlas: Option<T>, glyphs: Vec<Glyph>) {
+        let mut damage = self.glyph_regions();
+        if let Some(atlas) = atlas {
+            self.atlas = atlas;
+        }
+        self.glyphs = glyphs;
+        damage.extend(self.glyph_regions());
+        self.add_damage(damage);
+    }
+
+    /// Move the element to a new position
+    pub fn set_position(&mut self, position: impl Into<Point<i32, Logical>>) {
+        let position = position.into();
+        if position != self.position {
+            let mut damage = self.glyph_regions();
+            self.position = position;
+            damage.extend(self.glyph_regions());
+            self.add_damage(damage);
+        }
+    }
+
+    /// Set the alpha value the glyphs are drawn with
+    pub fn set_alpha(&mut self, alpha: f32) {
+        if alpha != self.alpha {
+            self.alpha = alpha;
+            self.add_damage(self.glyph_regions());
+        }
+    }
+
+    /// Set the z-index of this element
+    pub fn set_z_index(&mut self, z_index: u8) {
+        self.z_index = z_index;
+    }
+
+    /// Returns the currently displayed glyphs
+    pub fn glyphs(&self) -> &[Glyph] {
+        &self.glyphs
+    }
+
+    /// Returns the position of this element
+    pub fn position(&self) -> Point<i32, Logical> {
+        self.position
+    }
+
+    fn glyph_regions(&self) -> Vec<Rectangle<i32, Logical>> {
+        self.glyphs
+            .iter()
+            .map(|glyph| {
+                let mut geo = glyph.geometry;
+                geo.loc += self.position;
+                geo
+            })
+            .collect()
+    }
+
+    fn bbox(&self) -> Rectangle<i32, Logical> {
+        self.glyph_regions()
+            .into_iter()
+            .reduce(|bbox, geo| bbox.merge(geo))
+            .unwrap_or_else(|| Rectangle::from_loc_and_size(self.position, (0, 0)))
+    }
+
+    fn add_damage(&mut self, damage: Vec<Rectangle<i32, Logical>>) {
+        self.commit = self.commit.wrapping_add(1);
+        self.damage.push_front((self.commit, damage));
+        self.damage.truncate(MAX_DAMAGE_HISTORY);
+    }
+
+    // Returns the damage since `commit` or `None` if that is no longer known
+    fn damage_since(&self, commit: usize) -> Option<Vec<Rectangle<i32, Logical>>> {
+        let mut damage = Vec::new();
+        for (entry_commit, entry_damage) in self.damage.iter() {
+            if *entry_commit == commit {
+                return Some(damage);
+            }
+            damage.extend(entry_damage.iter().copied());
+        }
+        None
+    }
+}
+
+impl<R> RenderElement<R> for TextElement<<R as Renderer>::TextureId>
+where
+    R: Renderer + ImportAll,
+    <R as Renderer>::TextureId: Texture + 'static,
+{
+    fn id(&self) -> usize {
+        self.id
+    }
+
+    fn location(&self, scale: impl Into<Scale<f64>>) -> Point<f64, Physical> {
+        self.position.to_f64().to_physical(scale)
+    }
+
+    fn geometry(&self, scale: impl Into<Scale<f64>>) -> Rectangle<i32, Physical> {
+        self.bbox().to_physical_precise_up(scale)
+    }
+
+    fn accumulated_damage(
+        &self,
+        scale: impl Into<Scale<f64>>,
+        for_values: Option<SpaceOutputTuple<'_, '_>>,
+    ) -> Vec<Rectangle<i32, Physical>> {
+        let scale = scale.into();
+        let damage = match for_values {
+            Some(values) => {
+                let last_commit = self
+                    .last_commits
+                    .borrow_mut()
+                    .insert(values.owned_hash(), self.commit);
+                last_commit
+                    .and_then(|commit| self.damage_since(commit))
+                    .unwrap_or_else(|| vec![self.bbox()])
+            }
+            None => vec![self.bbox()],
+        };
+        damage
+            .into_iter()
+            .map(|rect| rect.to_physical_precise_up(scale))
+            .collect()
+    }
+
+    fn opaque_regions(&self, _scale: impl Into<Scale<f64>>) -> Option<Vec<Rectangle<i32, Physical>>> {
+        None
+    }
+
+    fn draw(
+        &self,
+        _renderer: &mut R,
+        frame: &mut <R as Renderer>::Frame,
+        scale: impl Into<Scale<f64>>,
+        location: Point<f64, Physical>,
+        damage: &[Rectangle<i32, Physical>],
+        _log: &slog::Logger,
+    ) -> Result<(), <R as Renderer>::Error> {
+        let scale = scale.into();
+        for glyph in self.glyphs.iter() {
+            let glyph_location = glyph.geometry.loc.to_f64().to_physical(scale) + location;
+            let dst = Rectangle::from_extemities(
+                glyph_location.to_i32_round(),
+                (glyph.geometry.size.to_f64().to_physical(scale).to_point() + glyph_location).to_i32_round(),
+            );
+            let glyph_damage = damage
+                .iter()
+                .flat_map(|geo| geo.intersection(dst))
+                .map(|mut geo| {
+                    geo.loc -= dst.loc;
+                    geo
+                })
+                .collect::<Vec<_>>();
+            if glyph_damage.is_empty() {
+                continue;
+            }
+            frame.render_texture_from_to(
+                &self.atlas,
+                glyph.atlas_region.to_f64(),
+                dst,
+                &glyph_damage,
+                Transform::Normal,
+                self.alpha,
+            )?;
+        }
+        Ok(())
+    }
+
+    fn z_index(&self) -> u8 {
+        self.z_index
+    }
+
+    fn kind(&self) -> ElementKind {
+        ElementKind::Overlay
+    }
+}
+
+impl<T> Drop for TextElement<T> {
+    fn drop(&mut self) {
+        TEXT_IDS.lock().unwrap().remove(&self.id);
+    }
+}
@@ -0,0 +1,186 @@
+use crate::{
+    backend::renderer::gles2::{Gles2Error, Gles2Frame, Gles2Renderer, Gles2Texture},
+    desktop::space::{element::CommitTracker, ElementKind, RenderElement, RenderZindex, SpaceOutputTuple},
+    utils::{Logical, Physical, Point, Rectangle, Scale, Size},
+};
+
+crate::utils::ids::id_gen!(next_tiled_id, TILED_ID, TILED_IDS);
+
+/// Custom element repeating a texture to fill an area
+///
+/// This is useful for tiled wallpapers or patterned backgrounds, which would otherwise
+/// require a buffer of the size of the whole area.
+///
+/// The texture is scaled to `tile_size` and repeated starting at the top-left corner of the area,
+/// tiles at the right and bottom edge are cut off.
+/// See [`Gles2Frame::render_texture_tiled`] for restrictions regarding the texture.
+#[derive(Debug)]
+pub struct TiledTextureElement {
+    id: usize,
+    texture: Gles2Texture,
+    tile_size: Size<i32, Logical>,
+    area: Rectangle<i32, Logical>,
+    opaque: bool,
+    alpha: f32,
+    z_index: u8,
+    commits: CommitTracker,
+}
+
+impl TiledTextureElement {
+    /// Create a new [`TiledTextureElement`] filling `area` with tiles of the given texture
+    pub fn new(
+        texture: Gles2Texture,
+        tile_size: impl Into<Size<i32, Logical>>,
+        area: impl Into<Rectangle<i32, Logical>>,
+    ) -> Self {
+        TiledTextureElement {
+            id: next_tiled_id(),
+            texture,
+            tile_size: tile_size.into(),
+            area: area.into(),
+            opaque: false,
+            alpha: 1.0,
+            z_index: RenderZindex::Background as u8,
+            commits: CommitTracker::default(),
+        }
+    }
+
+    /// Replace the repeated texture
+    pub fn set_texture(&mut self, texture: Gles2Texture) {
+        self.texture = texture;
+        self.damage();
+    }
+
+    /// Set the size a single tile is drawn with
+    pub fn set_tile_size(&mut self, tile_size: impl Into<Size<i32, Logical>>) {
+        let tile_size = tile_size.into();
+        if tile_size != self.tile_size {
+            self.tile_size = tile_size;
+            self.damage();
+        }
+    }
+
+    /// Set the area filled by this element
+    pub fn set_area(&mut self, area: impl Into<Rectangle<i32, Logical>>) {
+        self.area = area.into();
+    }
+
+    /// Mark the texture as opaque
+    ///
+    /// Textures do not carry information about their alpha channel, so you need to tell,
+    /// if the texture is opaque. The whole area is reported as opaque in that case,
+    /// as long as the alpha value of the element is `1.0`.
+    pub fn set_opaque(&mut self, opaque: bool) {
+        self.opaque = opaque;
+    }
+
+    /// Set the alpha value the texture is drawn with
+    pub fn set_alpha(&mut self, alpha: f32) {
+        if alpha != self.alpha {
+            self.alpha = alpha;
+            self.damage();
+        }
+    }
+
+    /// Set the z-index of this element
+    pub fn set_z_index(&mut self, z_index: u8) {
+        self.z_index = z_index;
+    }
+
+    /// Returns the area filled by this element
+    pub fn area(&self) -> Rectangle<i32, Logical> {
+        self.area
+    }
+
+    /// Returns the size of a single tile
+    pub fn tile_size(&self) -> Size<i32, Logical> {
+        self.tile_size
+    }
+
+    fn damage(&mut self) {
+        self.commits.increment();
+    }
+}
+
+impl RenderElement<Gles2Renderer> for TiledTextureElement {
+    fn id(&self) -> usize {
+        self.id
+    }
+
+    fn location(&self, scale: impl Into<Scale<f64>>) -> Point<f64, Physical> {
+        self.area.loc.to_f64().to_physical(scale)
+    }
+
+    fn geometry(&self, scale: impl Into<Scale<f64>>) -> Rectangle<i32, Physical> {
+        self.area.to_physical_precise_round_edges(scale)
+    }
+
+    fn accumulated_damage(
+        &self,
+        scale: impl Into<Scale<f64>>,
+        for_values: Option<SpaceOutputTuple<'_, '_>>,
+    ) -> Vec<Rectangle<i32, Physical>> {
+        // the tiles repeat across the whole area, so any change affects all of them
+        if let Some(values) = for_values {
+            if !self.commits.changed_for(&values) {
+                return Vec::new();
+            }
+        }
+        vec![self.geometry(scale)]
+    }
+
+    fn opaque_regions(&self, scale: impl Into<Scale<f64>>) -> Option<Vec<Rectangle<i32, Physical>>> {
+        if self.opaque && self.alpha >= 1.0 {
+            Some(vec![self.geometry(scale)])
+        } else {
+            None
+        }
+    }
+
+    fn draw(
+        &self,
+        _renderer: &mut Gles2Renderer,
+        frame: &mut Gles2Frame,
+        scale: impl Into<Scale<f64>>,
+        location: Point<f64, Physical>,
+        damage: &[Rectangle<i32, Physical>],
+        _log: &slog::Logger,
+    ) -> Result<(), Gles2Error> {
+        let scale = scale.into();
+        let dst = Rectangle::from_loc_and_size(location, self.area.size.to_f64().to_physical(scale))
+            .to_i32_round_edges();
+        let damage = damage
+            .iter()
+            .flat_map(|geo| geo.intersection(dst))
+            .map(|mut geo| {
+                geo.loc -= dst.loc;
+                geo
+            })
+            .collect::<Vec<_>>();
+        if damage.is_empty() {
+            return Ok(());
+        }
+
+        frame.render_texture_tiled(
+            &self.texture,
+            self.tile_size.to_f64().to_physical(scale),
+            dst,
+            &damage,
+            self.alpha,
+        )
+    }
+
+    fn z_index(&self) -> u8 {
+        self.z_index
+    }
+
+    fn kind(&self) -> ElementKind {
+        ElementKind::Background
+    }
+}
+
+impl Drop for TiledTextureElement {
+    fn drop(&mut self) {
+        TILED_IDS.lock().unwrap().remove(&self.id);
+    }
+}
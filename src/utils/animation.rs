@@ -0,0 +1,337 @@
+//! Utilities for animating values over time
+//!
+//! An [`Animation`] interpolates between two values over a given [`Duration`],
+//! shaped by an [`EasingCurve`]. Any type implementing [`Lerp`] can be animated,
+//! which includes floats, the geometry types of smithay and colors (`[f32; 4]`).
+//!
+//! ```
+//! use std::time::{Duration, Instant};
+//! use smithay::utils::animation::{Animation, EasingCurve};
+//!
+//! let start = Instant::now();
+//! let fade = Animation::new(0.0f32, 1.0f32, start, Duration::from_millis(200), EasingCurve::EaseOut);
+//!
+//! // query the value of the animation, when rendering a frame
+//! let alpha = fade.value_at(start + Duration::from_millis(100));
+//! assert!(alpha > 0.5);
+//! assert_eq!(fade.value_at(start + Duration::from_millis(300)), 1.0);
+//! ```
+
+use std::time::{Duration, Instant};
+
+use super::{Coordinate, Point, Rectangle, Size};
+
+/// Easing curve shaping the progress of an [`Animation`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EasingCurve {
+    /// Constant speed
+    Linear,
+    /// Starts slow and accelerates (cubic)
+    EaseIn,
+    /// Starts fast and decelerates (cubic)
+    EaseOut,
+    /// Starts slow, accelerates and decelerates at the end (cubic)
+    EaseInOut,
+    /// Cubic bézier curve from `(0, 0)` to `(1, 1)` with the control points `(x1, y1)` and `(x2, y2)`,
+    /// like the `cubic-bezier` timing function of CSS
+    CubicBezier {
+        /// x coordinate of the first control point, needs to be within `[0, 1]`
+        x1: f64,
+        /// y coordinate of the first control point
+        y1: f64,
+        /// x coordinate of the second control point, needs to be within `[0, 1]`
+        x2: f64,
+        /// y coordinate of the second control point
+        y2: f64,
+    },
+}
+
+impl Default for EasingCurve {
+    fn default() -> Self {
+        EasingCurve::Linear
+    }
+}
+
+impl EasingCurve {
+    /// Map the linear progress `t` (clamped to `[0, 1]`) onto the curve
+    pub fn apply(&self, t: f64) -> f64 {
+        let t = t.clamp(0.0, 1.0);
+        match *self {
+            EasingCurve::Linear => t,
+            EasingCurve::EaseIn => t * t * t,
+            EasingCurve::EaseOut => 1.0 - (1.0 - t).powi(3),
+            EasingCurve::EaseInOut => {
+                if t < 0.5 {
+                    4.0 * t * t * t
+                } else {
+                    1.0 - (-2.0 * t + 2.0).powi(3) / 2.0
+                }
+            }
+            EasingCurve::CubicBezier { x1, y1, x2, y2 } => {
+                let x1 = x1.clamp(0.0, 1.0);
+                let x2 = x2.clamp(0.0, 1.0);
+                bezier(y1, y2, solve_bezier_x(x1, x2, t))
+            }
+        }
+    }
+}
+
+// One dimension of a cubic bézier curve from 0 to 1 with the given control points
+fn bezier(p1: f64, p2: f64, s: f64) -> f64 {
+    let inv = 1.0 - s;
+    3.0 * inv * inv * s * p1 + 3.0 * inv * s * s * p2 + s * s * s
+}
+
+fn bezier_derivative(p1: f64, p2: f64, s: f64) -> f64 {
+    let inv = 1.0 - s;
+    3.0 * inv * inv * p1 + 6.0 * inv * s * (p2 - p1) + 3.0 * s * s * (1.0 - p2)
+}
+
+// Find the curve parameter for a given x, which is monotonic for control points within [0, 1]
+fn solve_bezier_x(x1: f64, x2: f64, x: f64) -> f64 {
+    const EPSILON: f64 = 1e-7;
+
+    // Newton's method converges fast in most cases
+    let mut s = x;
+    for _ in 0..8 {
+        let error = bezier(x1, x2, s) - x;
+        if error.abs() < EPSILON {
+            return s;
+        }
+        let derivative = bezier_derivative(x1, x2, s);
+        if derivative.abs() < EPSILON {
+            break;
+        }
+        s -= error / derivative;
+    }
+
+    // fall back to bisection otherwise
+    let (mut low, mut high) = (0.0, 1.0);
+    s = x;
+    while high - low > EPSILON {
+        if bezier(x1, x2, s) < x {
+            low = s;
+        } else {
+            high = s;
+        }
+        s = (low + high) / 2.0;
+    }
+    s
+}
+
+/// Linear interpolation between two values
+pub trait Lerp {
+    /// Interpolate between `self` (at `t = 0.0`) and `other` (at `t = 1.0`)
+    fn lerp(&self, other: &Self, t: f64) -> Self;
+}
+
+impl Lerp for f32 {
+    fn lerp(&self, other: &Self, t: f64) -> Self {
+        self + (other - self) * t as f32
+    }
+}
+
+impl Lerp for f64 {
+    fn lerp(&self, other: &Self, t: f64) -> Self {
+        self + (other - self) * t
+    }
+}
+
+/// Colors in RGBA format
+impl Lerp for [f32; 4] {
+    fn lerp(&self, other: &Self, t: f64) -> Self {
+        [
+            self[0].lerp(&other[0], t),
+            self[1].lerp(&other[1], t),
+            self[2].lerp(&other[2], t),
+            self[3].lerp(&other[3], t),
+        ]
+    }
+}
+
+fn lerp_coordinate<N: Coordinate>(from: N, to: N, t: f64) -> N {
+    N::from_f64(from.to_f64().lerp(&to.to_f64(), t))
+}
+
+impl<N: Coordinate, Kind> Lerp for Point<N, Kind> {
+    fn lerp(&self, other: &Self, t: f64) -> Self {
+        Point::from((
+            lerp_coordinate(self.x, other.x, t),
+            lerp_coordinate(self.y, other.y, t),
+        ))
+    }
+}
+
+impl<N: Coordinate, Kind> Lerp for Size<N, Kind> {
+    fn lerp(&self, other: &Self, t: f64) -> Self {
+        Size::from((
+            lerp_coordinate(self.w, other.w, t),
+            lerp_coordinate(self.h, other.h, t),
+        ))
+    }
+}
+
+impl<N: Coordinate, Kind> Lerp for Rectangle<N, Kind> {
+    fn lerp(&self, other: &Self, t: f64) -> Self {
+        Rectangle {
+            loc: self.loc.lerp(&other.loc, t),
+            size: self.size.lerp(&other.size, t),
+        }
+    }
+}
+
+/// Interpolation between two values over a period of time
+#[derive(Debug, Clone, PartialEq)]
+pub struct Animation<T> {
+    from: T,
+    to: T,
+    start: Instant,
+    duration: Duration,
+    curve: EasingCurve,
+}
+
+impl<T: Lerp + Clone> Animation<T> {
+    /// Create a new animation from `from` to `to` starting at `start`
+    pub fn new(from: T, to: T, start: Instant, duration: Duration, curve: EasingCurve) -> Self {
+        Animation {
+            from,
+            to,
+            start,
+            duration,
+            curve,
+        }
+    }
+
+    /// Linear progress of the animation at the given time in the range `[0, 1]`
+    pub fn progress(&self, now: Instant) -> f64 {
+        if self.duration.is_zero() {
+            return 1.0;
+        }
+        let elapsed = now.saturating_duration_since(self.start);
+        (elapsed.as_secs_f64() / self.duration.as_secs_f64()).min(1.0)
+    }
+
+    /// Value of the animation at the given time
+    ///
+    /// Returns the start value before the animation started and the end value after it is finished.
+    pub fn value_at(&self, now: Instant) -> T {
+        let progress = self.progress(now);
+        if progress >= 1.0 {
+            return self.to.clone();
+        }
+        self.from.lerp(&self.to, self.curve.apply(progress))
+    }
+
+    /// Returns true, if the animation has reached its end value at the given time
+    pub fn is_finished(&self, now: Instant) -> bool {
+        self.progress(now) >= 1.0
+    }
+
+    /// The value the animation starts with
+    pub fn from(&self) -> &T {
+        &self.from
+    }
+
+    /// The value the animation ends with
+    pub fn to(&self) -> &T {
+        &self.to
+    }
+
+    /// Time the animation starts at
+    pub fn start(&self) -> Instant {
+        self.start
+    }
+
+    /// Duration of the animation
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+
+    /// Easing curve of the animation
+    pub fn curve(&self) -> EasingCurve {
+        self.curve
+    }
+
+    /// Start a new animation from the current value towards a new target
+    ///
+    /// This allows retargeting an animation, that is still in progress, without a jump.
+    pub fn retarget(&mut self, to: T, now: Instant, duration: Duration) {
+        self.from = self.value_at(now);
+        self.to = to;
+        self.start = now;
+        self.duration = duration;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Animation, EasingCurve, Lerp};
+    use crate::utils::{Logical, Point, Rectangle};
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn curves_start_and_end() {
+        let curves = [
+            EasingCurve::Linear,
+            EasingCurve::EaseIn,
+            EasingCurve::EaseOut,
+            EasingCurve::EaseInOut,
+            EasingCurve::CubicBezier {
+                x1: 0.25,
+                y1: 0.1,
+                x2: 0.25,
+                y2: 1.0,
+            },
+        ];
+        for curve in curves {
+            assert!(curve.apply(0.0).abs() < 1e-6, "{:?}", curve);
+            assert!((curve.apply(1.0) - 1.0).abs() < 1e-6, "{:?}", curve);
+        }
+    }
+
+    #[test]
+    fn linear_bezier_is_linear() {
+        let curve = EasingCurve::CubicBezier {
+            x1: 0.0,
+            y1: 0.0,
+            x2: 1.0,
+            y2: 1.0,
+        };
+        for i in 0..=10 {
+            let t = i as f64 / 10.0;
+            assert!((curve.apply(t) - t).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn lerp_rectangle() {
+        let from = Rectangle::<i32, Logical>::from_loc_and_size((0, 0), (10, 10));
+        let to = Rectangle::<i32, Logical>::from_loc_and_size((100, 50), (20, 30));
+        assert_eq!(
+            from.lerp(&to, 0.5),
+            Rectangle::<i32, Logical>::from_loc_and_size((50, 25), (15, 20))
+        );
+    }
+
+    #[test]
+    fn animation_clamps() {
+        let start = Instant::now();
+        let animation = Animation::new(
+            Point::<f64, Logical>::from((0.0, 0.0)),
+            Point::from((10.0, 20.0)),
+            start,
+            Duration::from_millis(100),
+            EasingCurve::Linear,
+        );
+        assert_eq!(animation.value_at(start), Point::from((0.0, 0.0)));
+        assert_eq!(
+            animation.value_at(start + Duration::from_millis(50)),
+            Point::from((5.0, 10.0))
+        );
+        assert_eq!(
+            animation.value_at(start + Duration::from_millis(200)),
+            Point::from((10.0, 20.0))
+        );
+        assert!(animation.is_finished(start + Duration::from_millis(100)));
+    }
+}
@@ -1089,6 +1089,15 @@ impl<Kind> Rectangle<f64, Kind> {
         }
     }
 
+    /// Convert to i32 by rounding the edges of the float-based rectangle
+    ///
+    /// Unlike [`Rectangle::to_i32_round`] the size is derived from the rounded edges instead of being
+    /// rounded on its own, so rectangles sharing an edge still share it after the conversion.
+    #[inline]
+    pub fn to_i32_round_edges<N: Coordinate>(self) -> Rectangle<N, Kind> {
+        Rectangle::from_extemities(self.loc.to_i32_round(), (self.loc + self.size).to_i32_round())
+    }
+
     /// Convert to i32 by returning the largest integer-space rectangle fitting into the float-based rectangle
     #[inline]
     pub fn to_i32_down<N: Coordinate>(self) -> Rectangle<N, Kind> {
@@ -1292,6 +1301,20 @@ impl<N: Coordinate> Rectangle<N, Logical> {
         self.to_f64().to_physical(scale.into().to_f64()).to_i32_round()
     }
 
+    /// Convert this logical rectangle to physical coordinate space according to given scale factor
+    /// and round its edges
+    ///
+    /// Adjacent rectangles stay adjacent at fractional scales, see [`Rectangle::to_i32_round_edges`].
+    #[inline]
+    pub fn to_physical_precise_round_edges<S: Coordinate, R: Coordinate>(
+        self,
+        scale: impl Into<Scale<S>>,
+    ) -> Rectangle<R, Physical> {
+        self.to_f64()
+            .to_physical(scale.into().to_f64())
+            .to_i32_round_edges()
+    }
+
     /// Convert this logical rectangle to physical coordinate space according to given scale factor,
     /// returning the largest N-space rectangle fitting into the N-based rectangle
     ///
@@ -149,6 +149,21 @@ pub struct SurfaceUserData {
     alive_tracker: AliveTracker,
 }
 
+/// Create a `wl_surface` for the given client, as if it had sent `wl_compositor.create_surface`
+#[cfg(test)]
+pub(crate) fn create_test_surface<D>(dh: &DisplayHandle, client: &wayland_server::Client) -> WlSurface
+where
+    D: Dispatch<WlSurface, SurfaceUserData> + 'static,
+{
+    let data = SurfaceUserData {
+        inner: PrivateSurfaceData::new(),
+        alive_tracker: Default::default(),
+    };
+    let surface = client.create_resource::<WlSurface, _, D>(dh, 5, data).unwrap();
+    PrivateSurfaceData::init(&surface);
+    surface
+}
+
 impl<D> Dispatch<WlSurface, SurfaceUserData, D> for CompositorState
 where
     D: Dispatch<WlSurface, SurfaceUserData>,
@@ -106,6 +106,8 @@ mod transaction;
 mod tree;
 
 pub use self::cache::{Cacheable, MultiCache};
+#[cfg(test)]
+pub(crate) use self::handlers::create_test_surface;
 pub use self::handlers::{RegionUserData, SubsurfaceCachedState, SubsurfaceUserData, SurfaceUserData};
 use self::tree::PrivateSurfaceData;
 pub use self::tree::{AlreadyHasRole, TraversalAction};
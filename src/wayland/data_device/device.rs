@@ -1,19 +1,25 @@
-use std::cell::RefCell;
+use std::{cell::RefCell, sync::Mutex};
 
 use slog::debug;
 use wayland_server::{
+    backend::ClientId,
     protocol::{
         wl_data_device::{self, WlDataDevice},
+        wl_data_source::WlDataSource,
         wl_seat::WlSeat,
+        wl_surface::WlSurface,
     },
     Client, DataInit, Dispatch, DisplayHandle, Resource,
 };
 
-use crate::wayland::{
-    compositor,
-    data_device::seat_data::{SeatData, Selection},
-    seat::{Focus, Seat},
-    Serial,
+use crate::{
+    utils::{Logical, Point},
+    wayland::{
+        compositor::{self, SurfaceAttributes},
+        data_device::seat_data::{SeatData, Selection},
+        seat::{Focus, Seat},
+        Serial,
+    },
 };
 
 use super::{dnd_grab, DataDeviceHandler, DataDeviceState};
@@ -21,6 +27,33 @@ use super::{dnd_grab, DataDeviceHandler, DataDeviceState};
 /// WlSurface role of drag and drop icon
 pub const DND_ICON_ROLE: &str = "dnd_icon";
 
+/// Data associated with a drag and drop icon
+///
+/// It is stored in the data map of the icon surface as a `Mutex<DnDIconAttributes>`.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct DnDIconAttributes {
+    /// Location of the icon relative to the pointer location
+    ///
+    /// Accumulated from the offsets the client provided, when attaching buffers to the icon
+    /// during the current drag.
+    pub offset: Point<i32, Logical>,
+}
+
+fn dnd_icon_commit_hook(_dh: &DisplayHandle, surface: &WlSurface) {
+    compositor::with_states(surface, |states| {
+        let delta = states.cached_state.pending::<SurfaceAttributes>().buffer_delta;
+        if let Some(delta) = delta {
+            states
+                .data_map
+                .get::<Mutex<DnDIconAttributes>>()
+                .unwrap()
+                .lock()
+                .unwrap()
+                .offset += delta;
+        }
+    });
+}
+
 #[doc(hidden)]
 #[derive(Debug)]
 pub struct DataDeviceUserData {
@@ -52,7 +85,6 @@ where
                     icon,
                     serial,
                 } => {
-                    /* TODO: handle the icon */
                     let serial = Serial::from(serial);
                     if let Some(pointer) = seat.get_pointer() {
                         if pointer.has_grab(serial) {
@@ -64,15 +96,44 @@ where
                                     );
                                     return;
                                 }
+                                let initial = compositor::with_states(icon, |states| {
+                                    let initial = states.data_map.insert_if_missing_threadsafe(|| {
+                                        Mutex::new(DnDIconAttributes::default())
+                                    });
+                                    // the offset is relative to the start of this drag
+                                    *states
+                                        .data_map
+                                        .get::<Mutex<DnDIconAttributes>>()
+                                        .unwrap()
+                                        .lock()
+                                        .unwrap() = DnDIconAttributes::default();
+                                    initial
+                                });
+                                // the surface keeps its role, so it may be reused for later drags
+                                if initial {
+                                    compositor::add_pre_commit_hook(icon, dnd_icon_commit_hook);
+                                }
                             }
                             // The StartDrag is in response to a pointer implicit grab, all is good
-                            handler.started(source.clone(), icon.clone(), seat.clone());
                             let start_data = pointer.grab_start_data().unwrap();
+                            // replacing a previous drag clears its icon, so set the new one afterwards
                             pointer.set_grab(
-                                dnd_grab::DnDGrab::new(start_data, source, origin, seat, icon),
+                                dnd_grab::DnDGrab::new(
+                                    start_data,
+                                    source.clone(),
+                                    origin,
+                                    seat.clone(),
+                                    icon.clone(),
+                                ),
                                 serial,
                                 Focus::Clear,
                             );
+                            seat.user_data()
+                                .get::<RefCell<SeatData>>()
+                                .unwrap()
+                                .borrow_mut()
+                                .set_dnd_icon(icon.clone());
+                            handler.started(source, icon, seat);
                             return;
                         }
                     }
@@ -84,14 +145,8 @@ where
                 wl_data_device::Request::SetSelection { source, .. } => {
                     if let Some(keyboard) = seat.get_keyboard() {
                         if keyboard.client_of_object_has_focus(&resource.id()) {
-                            let seat_data = seat.user_data().get::<RefCell<SeatData>>().unwrap();
-
-                            handler.new_selection(dh, source.clone());
                             // The client has kbd focus, it can set the selection
-                            seat_data.borrow_mut().set_selection::<D>(
-                                dh,
-                                source.map(Selection::Client).unwrap_or(Selection::Empty),
-                            );
+                            set_client_selection(handler, dh, &seat, source);
                             return;
                         }
                     }
@@ -113,3 +168,33 @@ where
         }
     }
 }
+
+/// Apply a selection set by a client, that is allowed to set the selection of `seat`
+pub(super) fn set_client_selection<D>(
+    handler: &mut D,
+    dh: &DisplayHandle,
+    seat: &Seat<D>,
+    source: Option<WlDataSource>,
+) where
+    D: DataDeviceHandler,
+    D: 'static,
+{
+    let seat_data = seat.user_data().get::<RefCell<SeatData>>().unwrap();
+
+    handler.new_selection(dh, source.clone());
+    seat.add_client_cleanup("data_device", cleanup_client::<D>);
+    seat_data
+        .borrow_mut()
+        .set_selection::<D>(dh, source.map(Selection::Client).unwrap_or(Selection::Empty));
+}
+
+// Clear the selection a client set on `seat`, see `SeatState::cleanup_client`
+fn cleanup_client<D>(dh: &DisplayHandle, seat: &Seat<D>, client: &ClientId)
+where
+    D: DataDeviceHandler,
+    D: 'static,
+{
+    if let Some(seat_data) = seat.user_data().get::<RefCell<SeatData>>() {
+        seat_data.borrow_mut().clear_client_selection::<D>(dh, client);
+    }
+}
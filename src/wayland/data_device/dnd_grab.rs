@@ -23,7 +23,7 @@ use crate::{
 
 use super::{seat_data::SeatData, with_source_metadata, ClientDndGrabHandler, DataDeviceHandler};
 
-pub(crate) struct DnDGrab<D> {
+pub(crate) struct DnDGrab<D: 'static> {
     start_data: PointerGrabStartData,
     data_source: Option<wl_data_source::WlDataSource>,
     current_focus: Option<wl_surface::WlSurface>,
@@ -34,7 +34,7 @@ pub(crate) struct DnDGrab<D> {
     seat: Seat<D>,
 }
 
-impl<D> DnDGrab<D> {
+impl<D: 'static> DnDGrab<D> {
     pub(crate) fn new(
         start_data: PointerGrabStartData,
         source: Option<wl_data_source::WlDataSource>,
@@ -174,7 +174,7 @@ where
     ) {
         if handle.current_pressed().is_empty() {
             // the user dropped, proceed to the drop
-            let seat_data = self
+            let mut seat_data = self
                 .seat
                 .user_data()
                 .get::<RefCell<SeatData>>()
@@ -212,6 +212,7 @@ where
 
             ClientDndGrabHandler::dropped(handler, self.seat.clone());
             self.icon = None;
+            seat_data.set_dnd_icon(None);
             // in all cases abandon the drop
             // no more buttons are pressed, release the grab
             if let Some(ref surface) = self.current_focus {
@@ -241,6 +242,23 @@ where
     }
 }
 
+impl<D: 'static> Drop for DnDGrab<D> {
+    fn drop(&mut self) {
+        // the grab may also end without a drop, e.g. if it is replaced or its client is cleaned up
+        if let Some(icon) = self.icon.take() {
+            let mut seat_data = self
+                .seat
+                .user_data()
+                .get::<RefCell<SeatData>>()
+                .unwrap()
+                .borrow_mut();
+            if seat_data.dnd_icon() == Some(&icon) {
+                seat_data.set_dnd_icon(None);
+            }
+        }
+    }
+}
+
 #[derive(Debug)]
 struct OfferData {
     active: bool,
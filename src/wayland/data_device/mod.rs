@@ -55,7 +55,7 @@
 //! // You're now ready to go!
 //! ```
 
-use std::{cell::RefCell, os::unix::prelude::RawFd};
+use std::{cell::RefCell, os::unix::prelude::RawFd, sync::Mutex};
 
 use wayland_server::{
     backend::GlobalId,
@@ -67,7 +67,12 @@ use wayland_server::{
     Client, DisplayHandle, GlobalDispatch,
 };
 
+#[cfg(feature = "desktop")]
+use crate::desktop::space::{ElementKind, RenderZindex, SurfaceTree};
+use crate::utils::{Logical, Point};
+
 use super::{
+    compositor,
     seat::{Focus, PointerGrabStartData, Seat},
     Serial,
 };
@@ -77,8 +82,10 @@ mod dnd_grab;
 mod seat_data;
 mod server_dnd_grab;
 mod source;
+#[cfg(test)]
+mod tests;
 
-pub use device::{DataDeviceUserData, DND_ICON_ROLE};
+pub use device::{DataDeviceUserData, DnDIconAttributes, DND_ICON_ROLE};
 pub use source::{with_source_metadata, DataSourceUserData, SourceMetadata};
 
 use seat_data::{SeatData, Selection};
@@ -269,6 +276,53 @@ pub fn start_dnd<D, C>(
     }
 }
 
+/// Returns the icon of the drag'n'drop operation currently running on this seat
+///
+/// Returns `None`, if there is no ongoing drag'n'drop, the client did not provide an icon,
+/// or the icon surface was destroyed.
+pub fn dnd_icon<D>(seat: &Seat<D>) -> Option<WlSurface> {
+    let seat_data = seat.user_data().get::<RefCell<SeatData>>()?;
+    seat_data.borrow().dnd_icon().cloned()
+}
+
+/// Returns the location of the current drag'n'drop icon of this seat
+///
+/// The location is the current pointer location offset by what the client requested
+/// through the offsets of the buffers attached to the icon.
+///
+/// Returns `None` in the same cases as [`dnd_icon`] or if the seat has no pointer.
+pub fn dnd_icon_location<D>(seat: &Seat<D>) -> Option<Point<i32, Logical>> {
+    let icon = dnd_icon(seat)?;
+    let pointer = seat.get_pointer()?;
+    let offset = compositor::with_states(&icon, |states| {
+        states
+            .data_map
+            .get::<Mutex<DnDIconAttributes>>()
+            .map(|attributes| attributes.lock().unwrap().offset)
+            .unwrap_or_default()
+    });
+    Some(pointer.current_location().to_i32_round() + offset)
+}
+
+/// Returns the current drag'n'drop icon of this seat as a [`RenderElement`](crate::desktop::space::RenderElement)
+///
+/// The element is positioned at [`dnd_icon_location`] and draws on top of windows and layers.
+/// As the element is re-created by every call, new commits of the icon surface are picked up
+/// automatically, when calling this once per frame.
+///
+/// Returns `None` in the same cases as [`dnd_icon_location`].
+#[cfg(feature = "desktop")]
+pub fn dnd_icon_element<D>(seat: &Seat<D>) -> Option<SurfaceTree> {
+    let position = dnd_icon_location(seat)?;
+    let surface = dnd_icon(seat)?;
+    Some(SurfaceTree::new(
+        surface,
+        position,
+        RenderZindex::DndIcon as u8,
+        ElementKind::Cursor,
+    ))
+}
+
 mod handlers {
     use std::cell::RefCell;
 
@@ -7,6 +7,7 @@ use wayland_server::{
         wl_data_device::WlDataDevice,
         wl_data_offer::{self, WlDataOffer},
         wl_data_source::WlDataSource,
+        wl_surface::WlSurface,
     },
     Client, DisplayHandle, Resource,
 };
@@ -25,6 +26,7 @@ pub struct SeatData {
     known_devices: Vec<WlDataDevice>,
     selection: Selection,
     current_focus: Option<Client>,
+    dnd_icon: Option<WlSurface>,
 }
 
 impl Default for SeatData {
@@ -33,6 +35,7 @@ impl Default for SeatData {
             known_devices: Vec::new(),
             selection: Selection::Empty,
             current_focus: None,
+            dnd_icon: None,
         }
     }
 }
@@ -50,6 +53,14 @@ impl SeatData {
         self.known_devices.push(device);
     }
 
+    pub fn dnd_icon(&self) -> Option<&WlSurface> {
+        self.dnd_icon.as_ref().filter(|icon| icon.alive())
+    }
+
+    pub fn set_dnd_icon(&mut self, icon: Option<WlSurface>) {
+        self.dnd_icon = icon;
+    }
+
     pub fn retain_devices<F>(&mut self, f: F)
     where
         F: FnMut(&WlDataDevice) -> bool,
@@ -74,6 +85,18 @@ impl SeatData {
         self.send_selection::<D>(dh);
     }
 
+    /// Clear the selection, if it was set by the given client
+    pub fn clear_client_selection<D>(&mut self, dh: &DisplayHandle, client: &ClientId)
+    where
+        D: DataDeviceHandler,
+        D: 'static,
+    {
+        let set_by_client = |source: &WlDataSource| source.client_id().as_ref() == Some(client);
+        if matches!(self.selection, Selection::Client(ref source) if set_by_client(source)) {
+            self.set_selection::<D>(dh, Selection::Empty);
+        }
+    }
+
     pub fn set_focus<D>(&mut self, dh: &DisplayHandle, new_focus: Option<Client>)
     where
         D: DataDeviceHandler,
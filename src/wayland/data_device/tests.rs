@@ -0,0 +1,156 @@
+// In-process tests of the regular selection, see `wayland::test_client`
+
+use std::cell::RefCell;
+
+use wayland_server::{
+    protocol::{wl_data_device::WlDataDevice, wl_data_source::WlDataSource},
+    Client, Display, DisplayHandle, Resource,
+};
+
+use crate::wayland::{
+    seat::{Seat, SeatHandler, SeatState},
+    test_client::{Event, TestClient},
+};
+
+use super::{
+    device::set_client_selection, seat_data::SeatData, set_data_device_focus, ClientDndGrabHandler,
+    DataDeviceHandler, DataDeviceState, DataDeviceUserData, DataSourceUserData, ServerDndGrabHandler,
+};
+
+// opcodes of the events used by the tests
+const DEVICE_SELECTION: u16 = 5;
+const SOURCE_CANCELLED: u16 = 2;
+
+struct TestState {
+    seat_state: SeatState<TestState>,
+    data_device_state: DataDeviceState,
+}
+
+impl SeatHandler for TestState {
+    fn seat_state(&mut self) -> &mut SeatState<Self> {
+        &mut self.seat_state
+    }
+}
+
+impl DataDeviceHandler for TestState {
+    fn data_device_state(&self) -> &DataDeviceState {
+        &self.data_device_state
+    }
+}
+
+impl ClientDndGrabHandler for TestState {}
+impl ServerDndGrabHandler for TestState {}
+
+crate::delegate_seat!(TestState);
+crate::delegate_data_device!(TestState);
+
+// A compositor with a single seat supporting the regular selection
+struct Harness {
+    display: Display<TestState>,
+    state: TestState,
+    seat: Seat<TestState>,
+}
+
+impl Harness {
+    fn new() -> Self {
+        let display = Display::<TestState>::new().unwrap();
+        let dh = display.handle();
+        let mut state = TestState {
+            seat_state: SeatState::new(),
+            data_device_state: DataDeviceState::new::<TestState, _>(&dh, None),
+        };
+        let seat = Seat::new(&dh, "seat-0", None);
+        // as if a client bound the seat
+        state.seat_state.add_seat(&seat);
+        Harness { display, state, seat }
+    }
+
+    fn dh(&self) -> DisplayHandle {
+        self.display.handle()
+    }
+
+    // Bind a data device like `get_data_device` would
+    fn new_device(&mut self, client: &Client) -> WlDataDevice {
+        let dh = self.dh();
+        let wl_seat = self.seat.create_test_resource(&dh, client);
+        let device = client
+            .create_resource::<WlDataDevice, _, TestState>(&dh, 3, DataDeviceUserData { wl_seat })
+            .unwrap();
+        self.seat
+            .user_data()
+            .insert_if_missing(|| RefCell::new(SeatData::new()));
+        let seat_data = self.seat.user_data().get::<RefCell<SeatData>>().unwrap();
+        seat_data.borrow_mut().add_device(device.clone());
+        device
+    }
+
+    fn new_source(&mut self, client: &Client) -> WlDataSource {
+        client
+            .create_resource::<WlDataSource, _, TestState>(&self.dh(), 3, DataSourceUserData::new())
+            .unwrap()
+    }
+
+    // Set the selection like a client with keyboard focus would
+    fn set_selection(&mut self, source: Option<WlDataSource>) {
+        let dh = self.dh();
+        set_client_selection(&mut self.state, &dh, &self.seat, source);
+    }
+
+    fn cleanup(&mut self, client: &Client) {
+        let dh = self.dh();
+        self.state.seat_state.cleanup_client(&dh, client);
+    }
+
+    fn events(&mut self, client: &mut TestClient) -> Vec<Event> {
+        self.display.flush_clients().unwrap();
+        client.events()
+    }
+}
+
+fn selections(events: &[Event], device: &WlDataDevice) -> Vec<u32> {
+    events
+        .iter()
+        .filter(|event| event.object == device.id().protocol_id() && event.opcode == DEVICE_SELECTION)
+        .map(Event::uint_arg)
+        .collect()
+}
+
+#[test]
+fn cleanup_clears_the_selection_of_the_client() {
+    let mut harness = Harness::new();
+    let mut owner = TestClient::new(&harness.dh());
+    let mut other = TestClient::new(&harness.dh());
+    let device = harness.new_device(&other.client);
+    set_data_device_focus(&harness.dh(), &harness.seat, Some(other.client.clone()));
+
+    let source = harness.new_source(&owner.client);
+    harness.set_selection(Some(source.clone()));
+    let events = harness.events(&mut other);
+    assert!(matches!(selections(&events, &device)[..], [offer] if offer != 0));
+
+    harness.cleanup(&owner.client);
+    let events = harness.events(&mut other);
+    // the focused client is told, that the selection is gone
+    assert_eq!(selections(&events, &device), vec![0]);
+    let events = harness.events(&mut owner);
+    assert!(events
+        .iter()
+        .any(|event| event.object == source.id().protocol_id() && event.opcode == SOURCE_CANCELLED));
+}
+
+#[test]
+fn cleanup_keeps_the_selection_of_other_clients() {
+    let mut harness = Harness::new();
+    let owner = TestClient::new(&harness.dh());
+    let mut other = TestClient::new(&harness.dh());
+    harness.new_device(&other.client);
+    set_data_device_focus(&harness.dh(), &harness.seat, Some(other.client.clone()));
+
+    let source = harness.new_source(&other.client);
+    harness.set_selection(Some(source));
+    harness.events(&mut other);
+
+    harness.cleanup(&owner.client);
+    // neither the selection nor its source are touched
+    assert!(harness.events(&mut other).is_empty());
+}
@@ -1,6 +1,8 @@
 use std::sync::{atomic::AtomicBool, Mutex};
 
-use wayland_protocols::wp::linux_dmabuf::zv1::server::{zwp_linux_buffer_params_v1, zwp_linux_dmabuf_v1};
+use wayland_protocols::wp::linux_dmabuf::zv1::server::{
+    zwp_linux_buffer_params_v1, zwp_linux_dmabuf_feedback_v1, zwp_linux_dmabuf_v1,
+};
 use wayland_server::{
     protocol::wl_buffer, Client, DataInit, Dispatch, DisplayHandle, GlobalDispatch, New, Resource,
 };
@@ -11,8 +13,8 @@ use crate::{
 };
 
 use super::{
-    DmabufData, DmabufGlobal, DmabufGlobalData, DmabufHandler, DmabufParamsData, DmabufState, ImportError,
-    Modifier,
+    DmabufData, DmabufFeedbackData, DmabufGlobal, DmabufGlobalData, DmabufHandler, DmabufParamsData,
+    DmabufState, ImportError, Modifier,
 };
 
 impl<D> Dispatch<wl_buffer::WlBuffer, Dmabuf, D> for DmabufState
@@ -42,6 +44,7 @@ impl<D> Dispatch<zwp_linux_dmabuf_v1::ZwpLinuxDmabufV1, DmabufData, D> for Dmabu
 where
     D: Dispatch<zwp_linux_dmabuf_v1::ZwpLinuxDmabufV1, DmabufData>
         + Dispatch<zwp_linux_buffer_params_v1::ZwpLinuxBufferParamsV1, DmabufParamsData>
+        + Dispatch<zwp_linux_dmabuf_feedback_v1::ZwpLinuxDmabufFeedbackV1, DmabufFeedbackData>
         + 'static,
 {
     fn request(
@@ -69,9 +72,19 @@ where
                 );
             }
 
-            zwp_linux_dmabuf_v1::Request::GetDefaultFeedback { id: _ } => unimplemented!("v4"),
-
-            zwp_linux_dmabuf_v1::Request::GetSurfaceFeedback { id: _, surface: _ } => unimplemented!("v4"),
+            // There is no per-surface feedback yet, so surfaces receive the default feedback as well
+            zwp_linux_dmabuf_v1::Request::GetDefaultFeedback { id }
+            | zwp_linux_dmabuf_v1::Request::GetSurfaceFeedback { id, .. } => {
+                let feedback = data_init.init(
+                    id,
+                    DmabufFeedbackData {
+                        default_feedback: data.default_feedback.clone(),
+                    },
+                );
+                if let Some(default_feedback) = data.default_feedback.as_ref() {
+                    default_feedback.add(feedback);
+                }
+            }
 
             _ => unreachable!(),
         }
@@ -83,6 +96,7 @@ where
     D: GlobalDispatch<zwp_linux_dmabuf_v1::ZwpLinuxDmabufV1, DmabufGlobalData>
         + Dispatch<zwp_linux_dmabuf_v1::ZwpLinuxDmabufV1, DmabufData>
         + Dispatch<zwp_linux_buffer_params_v1::ZwpLinuxBufferParamsV1, DmabufParamsData>
+        + Dispatch<zwp_linux_dmabuf_feedback_v1::ZwpLinuxDmabufFeedbackV1, DmabufFeedbackData>
         + 'static,
 {
     fn bind(
@@ -95,6 +109,7 @@ where
     ) {
         let data = DmabufData {
             formats: global_data.formats.clone(),
+            default_feedback: global_data.default_feedback.clone(),
             id: global_data.id,
             logger: global_data.logger.clone(),
         };
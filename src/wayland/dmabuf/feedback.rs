@@ -0,0 +1,284 @@
+use std::{
+    collections::HashMap,
+    ffi::CString,
+    fs::File,
+    io::Write,
+    os::unix::io::{AsRawFd, FromRawFd},
+    sync::{Arc, Mutex},
+};
+
+use nix::{
+    fcntl::{FcntlArg, SealFlag},
+    sys::{memfd::MemFdCreateFlag, stat::dev_t},
+};
+use wayland_protocols::wp::linux_dmabuf::zv1::server::zwp_linux_dmabuf_feedback_v1::{
+    self, ZwpLinuxDmabufFeedbackV1,
+};
+use wayland_server::{
+    backend::{ClientId, ObjectId},
+    Client, DataInit, Dispatch, DisplayHandle, Resource,
+};
+
+use crate::backend::allocator::Format;
+
+use super::DmabufState;
+
+pub use zwp_linux_dmabuf_feedback_v1::TrancheFlags;
+
+/// Builder for [`DmabufFeedback`]
+///
+/// Dmabuf feedback tells clients which device to allocate their buffers on and which formats
+/// to prefer. The formats are grouped into tranches, which are sent to clients in the order they
+/// were added, so the most preferred tranche (e.g. formats suitable for direct scanout) should be
+/// added first. A final tranche targeting the main device with all formats given to
+/// [`DmabufFeedbackBuilder::new`] is always appended.
+#[derive(Debug)]
+pub struct DmabufFeedbackBuilder {
+    main_device: dev_t,
+    main_formats: Vec<Format>,
+    tranches: Vec<(dev_t, TrancheFlags, Vec<Format>)>,
+}
+
+impl DmabufFeedbackBuilder {
+    /// Create a new builder for the given main device
+    ///
+    /// The main device is the device used by the compositor for rendering,
+    /// `formats` should contain all formats the compositor is able to import.
+    pub fn new(main_device: dev_t, formats: impl IntoIterator<Item = Format>) -> Self {
+        DmabufFeedbackBuilder {
+            main_device,
+            main_formats: formats.into_iter().collect(),
+            tranches: Vec::new(),
+        }
+    }
+
+    /// Add a preference tranche
+    ///
+    /// The `target_device` is the device buffers with the given formats should be allocated on
+    /// to benefit from this tranche. Use [`TrancheFlags::Scanout`] for formats suitable for direct scanout.
+    ///
+    /// Formats not contained in the formats given to [`DmabufFeedbackBuilder::new`] are ignored,
+    /// as the compositor would not be able to import them.
+    pub fn add_preference_tranche(
+        mut self,
+        target_device: dev_t,
+        flags: TrancheFlags,
+        formats: impl IntoIterator<Item = Format>,
+    ) -> Self {
+        let formats = formats
+            .into_iter()
+            .filter(|format| self.main_formats.contains(format))
+            .collect::<Vec<_>>();
+        if !formats.is_empty() {
+            self.tranches.push((target_device, flags, formats));
+        }
+        self
+    }
+
+    /// Build the [`DmabufFeedback`]
+    ///
+    /// This creates the format table shared with clients, which may fail.
+    pub fn build(self) -> std::io::Result<DmabufFeedback> {
+        let mut table = Vec::new();
+        let mut indices = HashMap::new();
+        for format in self.main_formats.iter() {
+            indices.entry(*format).or_insert_with(|| {
+                table.push(*format);
+                (table.len() - 1) as u16
+            });
+        }
+
+        let tranches = self
+            .tranches
+            .into_iter()
+            .chain(std::iter::once((
+                self.main_device,
+                TrancheFlags::empty(),
+                self.main_formats,
+            )))
+            .map(|(target_device, flags, formats)| {
+                let mut format_indices = formats.iter().map(|format| indices[format]).collect::<Vec<_>>();
+                format_indices.sort_unstable();
+                format_indices.dedup();
+                DmabufFeedbackTranche {
+                    target_device,
+                    flags,
+                    indices: format_indices,
+                }
+            })
+            .collect();
+
+        Ok(DmabufFeedback(Arc::new(DmabufFeedbackInner {
+            format_table: FormatTable::new(&table)?,
+            main_device: self.main_device,
+            formats: table,
+            tranches,
+        })))
+    }
+}
+
+/// Dmabuf feedback sent to clients
+///
+/// Created using a [`DmabufFeedbackBuilder`], cheap to clone.
+#[derive(Debug, Clone)]
+pub struct DmabufFeedback(Arc<DmabufFeedbackInner>);
+
+#[derive(Debug)]
+struct DmabufFeedbackInner {
+    format_table: FormatTable,
+    main_device: dev_t,
+    formats: Vec<Format>,
+    tranches: Vec<DmabufFeedbackTranche>,
+}
+
+#[derive(Debug)]
+struct DmabufFeedbackTranche {
+    target_device: dev_t,
+    flags: TrancheFlags,
+    indices: Vec<u16>,
+}
+
+impl DmabufFeedback {
+    /// The main device of this feedback
+    pub fn main_device(&self) -> dev_t {
+        self.0.main_device
+    }
+
+    /// All formats contained in this feedback
+    pub fn formats(&self) -> &[Format] {
+        &self.0.formats
+    }
+
+    fn send(&self, feedback: &ZwpLinuxDmabufFeedbackV1) {
+        feedback.format_table(
+            self.0.format_table.file.as_raw_fd(),
+            self.0.format_table.size as u32,
+        );
+        feedback.main_device(self.0.main_device.to_ne_bytes().to_vec());
+        for tranche in self.0.tranches.iter() {
+            feedback.tranche_target_device(tranche.target_device.to_ne_bytes().to_vec());
+            feedback.tranche_flags(tranche.flags);
+            feedback.tranche_formats(
+                tranche
+                    .indices
+                    .iter()
+                    .flat_map(|index| index.to_ne_bytes())
+                    .collect::<Vec<_>>(),
+            );
+            feedback.tranche_done();
+        }
+        feedback.done();
+    }
+}
+
+// The format table is shared with all clients, so it is sealed to prevent any modifications
+#[derive(Debug)]
+struct FormatTable {
+    file: File,
+    size: usize,
+}
+
+impl FormatTable {
+    fn new(formats: &[Format]) -> std::io::Result<Self> {
+        let name = CString::new("smithay-dmabuf-format-table")
+            .expect("File name should not contain interior nul byte");
+        let fd = nix::sys::memfd::memfd_create(
+            &name,
+            MemFdCreateFlag::MFD_CLOEXEC | MemFdCreateFlag::MFD_ALLOW_SEALING,
+        )?;
+        let mut file = unsafe { File::from_raw_fd(fd) };
+
+        // every entry consists of the format, 4 bytes padding and the modifier
+        let mut table = Vec::with_capacity(formats.len() * 16);
+        for format in formats {
+            table.extend_from_slice(&(format.code as u32).to_ne_bytes());
+            table.extend_from_slice(&[0; 4]);
+            table.extend_from_slice(&Into::<u64>::into(format.modifier).to_ne_bytes());
+        }
+        file.write_all(&table)?;
+        file.flush()?;
+
+        nix::fcntl::fcntl(
+            file.as_raw_fd(),
+            FcntlArg::F_ADD_SEALS(
+                SealFlag::F_SEAL_SEAL
+                    | SealFlag::F_SEAL_SHRINK
+                    | SealFlag::F_SEAL_GROW
+                    | SealFlag::F_SEAL_WRITE,
+            ),
+        )?;
+
+        Ok(FormatTable {
+            file,
+            size: table.len(),
+        })
+    }
+}
+
+/// Default feedback of a dmabuf global and the feedback objects it was sent to
+#[derive(Debug)]
+pub(super) struct DefaultFeedback {
+    feedback: Mutex<DmabufFeedback>,
+    known_feedbacks: Mutex<Vec<ZwpLinuxDmabufFeedbackV1>>,
+}
+
+impl DefaultFeedback {
+    pub(super) fn new(feedback: DmabufFeedback) -> Self {
+        DefaultFeedback {
+            feedback: Mutex::new(feedback),
+            known_feedbacks: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub(super) fn formats(&self) -> Vec<Format> {
+        self.feedback.lock().unwrap().formats().to_vec()
+    }
+
+    pub(super) fn add(&self, feedback: ZwpLinuxDmabufFeedbackV1) {
+        self.feedback.lock().unwrap().send(&feedback);
+        self.known_feedbacks.lock().unwrap().push(feedback);
+    }
+
+    pub(super) fn update(&self, feedback: DmabufFeedback) {
+        for known in self.known_feedbacks.lock().unwrap().iter() {
+            feedback.send(known);
+        }
+        *self.feedback.lock().unwrap() = feedback;
+    }
+}
+
+/// Data associated with a dmabuf feedback protocol object.
+#[derive(Debug)]
+pub struct DmabufFeedbackData {
+    pub(super) default_feedback: Option<Arc<DefaultFeedback>>,
+}
+
+impl<D> Dispatch<ZwpLinuxDmabufFeedbackV1, DmabufFeedbackData, D> for DmabufState
+where
+    D: Dispatch<ZwpLinuxDmabufFeedbackV1, DmabufFeedbackData>,
+{
+    fn request(
+        _state: &mut D,
+        _client: &Client,
+        _resource: &ZwpLinuxDmabufFeedbackV1,
+        request: zwp_linux_dmabuf_feedback_v1::Request,
+        _data: &DmabufFeedbackData,
+        _dh: &DisplayHandle,
+        _data_init: &mut DataInit<'_, D>,
+    ) {
+        match request {
+            zwp_linux_dmabuf_feedback_v1::Request::Destroy => {}
+            _ => unreachable!(),
+        }
+    }
+
+    fn destroyed(_state: &mut D, _client: ClientId, object_id: ObjectId, data: &DmabufFeedbackData) {
+        if let Some(default_feedback) = data.default_feedback.as_ref() {
+            default_feedback
+                .known_feedbacks
+                .lock()
+                .unwrap()
+                .retain(|feedback| feedback.id() != object_id);
+        }
+    }
+}
@@ -15,6 +15,13 @@
 //! support. You can typically receive a list of supported formats for one renderer by calling
 //! [`ImportDma::dmabuf_formats`](crate::backend::renderer::ImportDma::dmabuf_formats).
 //!
+//! Clients supporting version 4 of the protocol can additionally receive [dmabuf feedback](DmabufFeedback),
+//! telling them which device to allocate their buffers on and which formats to prefer (e.g. because they
+//! are suitable for direct scanout). To make use of this, create the global with
+//! [`DmabufState::create_global_with_default_feedback`] using a feedback built by a [`DmabufFeedbackBuilder`].
+//! The feedback can be updated at any time, e.g. if outputs were added or removed,
+//! using [`DmabufState::set_default_feedback`].
+//!
 //! Accessing a [`Dmabuf`] associated with a [`WlBuffer`](wayland_server::protocol::wl_buffer::WlBuffer)
 //! may be achieved using [`get_dmabuf`].
 //!
@@ -90,9 +97,12 @@
 //! ```
 
 mod dispatch;
+mod feedback;
+
+pub use feedback::{DmabufFeedback, DmabufFeedbackBuilder, DmabufFeedbackData, TrancheFlags};
 
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     convert::TryFrom,
     os::unix::io::IntoRawFd,
     sync::{
@@ -116,6 +126,7 @@ use crate::{
 };
 
 use super::buffer::BufferHandler;
+use feedback::DefaultFeedback;
 
 /// Delegate type for all dmabuf globals.
 ///
@@ -124,6 +135,8 @@ use super::buffer::BufferHandler;
 pub struct DmabufState {
     /// Globals managed by the dmabuf handler.
     globals: HashMap<usize, GlobalId>,
+    /// Default feedback of the globals supporting feedback.
+    default_feedbacks: HashMap<usize, Arc<DefaultFeedback>>,
 }
 
 impl DmabufState {
@@ -132,6 +145,7 @@ impl DmabufState {
     pub fn new() -> DmabufState {
         DmabufState {
             globals: HashMap::new(),
+            default_feedbacks: HashMap::new(),
         }
     }
 
@@ -164,6 +178,78 @@ impl DmabufState {
         filter: F,
         logger: L,
     ) -> DmabufGlobal
+    where
+        D: GlobalDispatch<zwp_linux_dmabuf_v1::ZwpLinuxDmabufV1, DmabufGlobalData>
+            + BufferHandler
+            + DmabufHandler
+            + 'static,
+        F: for<'c> Fn(&'c Client) -> bool + Send + Sync + 'static,
+        L: Into<Option<::slog::Logger>>,
+    {
+        self.create_global_inner::<D, _, L>(display, formats, None, filter, logger)
+    }
+
+    /// Creates a dmabuf global supporting dmabuf feedback.
+    ///
+    /// The supported formats are the formats contained in the given feedback.
+    /// Clients binding version 4 of the global receive the feedback instead of the list of formats.
+    pub fn create_global_with_default_feedback<D, L>(
+        &mut self,
+        display: &DisplayHandle,
+        default_feedback: &DmabufFeedback,
+        logger: L,
+    ) -> DmabufGlobal
+    where
+        D: GlobalDispatch<zwp_linux_dmabuf_v1::ZwpLinuxDmabufV1, DmabufGlobalData>
+            + BufferHandler
+            + DmabufHandler
+            + 'static,
+        L: Into<Option<::slog::Logger>>,
+    {
+        self.create_global_with_filter_and_default_feedback::<D, _, L>(
+            display,
+            default_feedback,
+            |_| true,
+            logger,
+        )
+    }
+
+    /// Creates a dmabuf global supporting dmabuf feedback.
+    ///
+    /// See [`DmabufState::create_global_with_default_feedback`] and [`DmabufState::create_global_with_filter`].
+    pub fn create_global_with_filter_and_default_feedback<D, F, L>(
+        &mut self,
+        display: &DisplayHandle,
+        default_feedback: &DmabufFeedback,
+        filter: F,
+        logger: L,
+    ) -> DmabufGlobal
+    where
+        D: GlobalDispatch<zwp_linux_dmabuf_v1::ZwpLinuxDmabufV1, DmabufGlobalData>
+            + BufferHandler
+            + DmabufHandler
+            + 'static,
+        F: for<'c> Fn(&'c Client) -> bool + Send + Sync + 'static,
+        L: Into<Option<::slog::Logger>>,
+    {
+        let formats = default_feedback.formats().to_vec();
+        self.create_global_inner::<D, _, L>(
+            display,
+            formats,
+            Some(Arc::new(DefaultFeedback::new(default_feedback.clone()))),
+            filter,
+            logger,
+        )
+    }
+
+    fn create_global_inner<D, F, L>(
+        &mut self,
+        display: &DisplayHandle,
+        formats: Vec<Format>,
+        default_feedback: Option<Arc<DefaultFeedback>>,
+        filter: F,
+        logger: L,
+    ) -> DmabufGlobal
     where
         D: GlobalDispatch<zwp_linux_dmabuf_v1::ZwpLinuxDmabufV1, DmabufGlobalData>
             + BufferHandler
@@ -176,20 +262,51 @@ impl DmabufState {
         let logger = crate::slog_or_fallback(logger)
             .new(slog::o!("smithay_module" => "wayland_dmabuf", "global" => id));
         let formats = Arc::new(formats);
+        let version = if default_feedback.is_some() {
+            FEEDBACK_GLOBAL_VERSION
+        } else {
+            GLOBAL_VERSION
+        };
+        if let Some(default_feedback) = default_feedback.as_ref() {
+            self.default_feedbacks.insert(id, default_feedback.clone());
+        }
         let data = DmabufGlobalData {
             filter: Box::new(filter),
             formats,
+            default_feedback,
             id,
             logger,
         };
 
-        let global =
-            display.create_global::<D, zwp_linux_dmabuf_v1::ZwpLinuxDmabufV1, _>(GLOBAL_VERSION, data);
+        let global = display.create_global::<D, zwp_linux_dmabuf_v1::ZwpLinuxDmabufV1, _>(version, data);
         self.globals.insert(id, global);
 
         DmabufGlobal { id }
     }
 
+    /// Update the default feedback of a dmabuf global.
+    ///
+    /// The new feedback is sent to all clients, that requested feedback from this global.
+    /// The formats supported by the global do not change, so the new feedback may
+    /// only regroup the formats of the initial feedback, e.g. into different tranches.
+    /// Returns an error and keeps the current feedback otherwise.
+    ///
+    /// Does nothing, if the global was not created with a default feedback.
+    pub fn set_default_feedback(
+        &mut self,
+        global: &DmabufGlobal,
+        feedback: &DmabufFeedback,
+    ) -> Result<(), FeedbackFormatsMismatch> {
+        if let Some(default_feedback) = self.default_feedbacks.get(&global.id) {
+            let formats = default_feedback.formats().into_iter().collect::<HashSet<_>>();
+            if feedback.formats().iter().copied().collect::<HashSet<_>>() != formats {
+                return Err(FeedbackFormatsMismatch);
+            }
+            default_feedback.update(feedback.clone());
+        }
+        Ok(())
+    }
+
     /// Disables a dmabuf global.
     ///
     /// This operation is permanent and there is no way to re-enable a global.
@@ -204,6 +321,7 @@ impl DmabufState {
     pub fn destroy_global<D: 'static>(&mut self, display: &DisplayHandle, global: DmabufGlobal) {
         if DMABUF_GLOBAL_IDS.lock().unwrap().remove(&global.id) {
             display.remove_global::<D>(self.globals.remove(&global.id).unwrap());
+            self.default_feedbacks.remove(&global.id);
         }
     }
 }
@@ -213,6 +331,7 @@ impl DmabufState {
 pub struct DmabufGlobalData {
     filter: Box<dyn for<'c> Fn(&'c Client) -> bool + Send + Sync>,
     formats: Arc<Vec<Format>>,
+    default_feedback: Option<Arc<DefaultFeedback>>,
     id: usize,
     logger: slog::Logger,
 }
@@ -221,6 +340,7 @@ pub struct DmabufGlobalData {
 #[derive(Debug)]
 pub struct DmabufData {
     formats: Arc<Vec<Format>>,
+    default_feedback: Option<Arc<DefaultFeedback>>,
     id: usize,
     logger: slog::Logger,
 }
@@ -291,6 +411,11 @@ pub enum ImportError {
     InvalidFormat,
 }
 
+/// The formats of a feedback differ from the formats supported by the dmabuf global.
+#[derive(Debug, thiserror::Error)]
+#[error("the formats of the feedback differ from the formats of the dmabuf global")]
+pub struct FeedbackFormatsMismatch;
+
 /// Gets the contents of a [`Dmabuf`] backed [`WlBuffer`].
 ///
 /// If the buffer is managed by the dmabuf handler, the [`Dmabuf`] is returned.
@@ -325,6 +450,9 @@ macro_rules! delegate_dmabuf {
         $crate::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
             __ZwpLinuxBufferParamsV1: $crate::wayland::dmabuf::DmabufParamsData
         ] => $crate::wayland::dmabuf::DmabufState);
+        $crate::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            $crate::reexports::wayland_protocols::wp::linux_dmabuf::zv1::server::zwp_linux_dmabuf_feedback_v1::ZwpLinuxDmabufFeedbackV1: $crate::wayland::dmabuf::DmabufFeedbackData
+        ] => $crate::wayland::dmabuf::DmabufState);
         $crate::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
             $crate::reexports::wayland_server::protocol::wl_buffer::WlBuffer: $crate::backend::allocator::dmabuf::Dmabuf
         ] => $crate::wayland::dmabuf::DmabufState);
@@ -332,6 +460,7 @@ macro_rules! delegate_dmabuf {
 }
 
 const GLOBAL_VERSION: u32 = 3;
+const FEEDBACK_GLOBAL_VERSION: u32 = 4;
 
 impl DmabufParamsData {
     /// Emits a protocol error if the params have already been used to create a dmabuf.
@@ -58,6 +58,8 @@ pub mod shell;
 pub mod shm;
 pub mod socket;
 pub mod tablet_manager;
+#[cfg(test)]
+pub(crate) mod test_client;
 pub mod viewporter;
 pub mod xdg_activation;
 
@@ -5,7 +5,7 @@ use wayland_protocols::wp::primary_selection::zv1::server::zwp_primary_selection
     self as primary_device, ZwpPrimarySelectionDeviceV1 as PrimaryDevice,
 };
 use wayland_server::{
-    protocol::wl_seat::WlSeat, Client, DataInit, Dispatch, DisplayHandle, Resource,
+    backend::ClientId, protocol::wl_seat::WlSeat, Client, DataInit, Dispatch, DisplayHandle, Resource,
 };
 
 use crate::wayland::{
@@ -13,7 +13,10 @@ use crate::wayland::{
     seat::Seat,
 };
 
-use super::{PrimarySelectionHandler, PrimarySelectionState};
+use super::{
+    flush_primary_selection, PrimarySelectionHandler, PrimarySelectionState, PrimarySource,
+    PrimarySourceUserData, StatCounters,
+};
 
 #[doc(hidden)]
 #[derive(Debug)]
@@ -38,22 +41,17 @@ where
     ) {
         let primary_selection_state = handler.primary_selection_state();
 
-        if let Some(seat) = Seat::<D>::from_resource(&data.wl_seat) {
+        if let Some(seat) = Seat::<D>::from_resource(&data.wl_seat).map(super::migrated_seat) {
             match request {
                 primary_device::Request::SetSelection { source, .. } => {
                     if let Some(keyboard) = seat.get_keyboard() {
                         if keyboard.client_of_object_has_focus(&resource.id()) {
-                            let seat_data = seat.user_data().get::<RefCell<SeatData>>().unwrap();
-
-                            PrimarySelectionHandler::new_selection(handler, dh, source.clone());
-                            // The client has kbd focus, it can set the selection
-                            seat_data.borrow_mut().set_selection::<D>(
-                                dh,
-                                source.map(Selection::Client).unwrap_or(Selection::Empty),
-                            );
+                            // The client has kbd focus, it can set the selection.
+                            set_client_selection(handler, dh, &seat, source);
                             return;
                         }
                     }
+                    StatCounters::add(&primary_selection_state.counters.rejected_requests, 1);
                     debug!(
                         &primary_selection_state.log,
                         "denying setting selection by a non-focused client"
@@ -72,3 +70,65 @@ where
         }
     }
 }
+
+/// Apply a selection set by a client, that is allowed to set the selection of `seat`
+pub(super) fn set_client_selection<D>(
+    handler: &mut D,
+    dh: &DisplayHandle,
+    seat: &Seat<D>,
+    source: Option<PrimarySource>,
+) where
+    D: PrimarySelectionHandler,
+    D: 'static,
+{
+    let counters = handler.primary_selection_state().counters.clone();
+    if !handler.allow_selection(dh, seat, source.as_ref()) {
+        StatCounters::add(&counters.rejected_requests, 1);
+        debug!(
+            &handler.primary_selection_state().log,
+            "selection rejected by the compositor"
+        );
+        if let Some(source) = source {
+            source.cancelled();
+        }
+        return;
+    }
+
+    if let Some(source) = source.as_ref() {
+        if let Some(data) = source.data::<PrimarySourceUserData>() {
+            data.set_seat(source, seat);
+        }
+    }
+
+    StatCounters::add(&counters.client_selections, 1);
+    seat.add_client_cleanup("primary_selection", cleanup_client::<D>);
+    let seat_data = seat.user_data().get::<RefCell<SeatData>>().unwrap();
+    seat_data.borrow_mut().set_counters(&counters);
+
+    PrimarySelectionHandler::new_selection(handler, dh, source.clone());
+    let schedule_flush = seat_data
+        .borrow_mut()
+        .queue_selection(source.map(Selection::Client).unwrap_or(Selection::Empty));
+    match handler.primary_selection_state().idle_scheduler.as_ref() {
+        // Offers are only created once the event loop is idle,
+        // so rapid changes within one dispatch cycle are coalesced.
+        Some(scheduler) => {
+            if schedule_flush {
+                let (dh, seat) = (dh.clone(), seat.clone());
+                (scheduler.0)(Box::new(move || flush_primary_selection(&dh, &seat)));
+            }
+        }
+        None => seat_data.borrow_mut().flush::<D>(dh),
+    }
+}
+
+// Clear the selections a client set on `seat`, see `SeatState::cleanup_client`
+fn cleanup_client<D>(dh: &DisplayHandle, seat: &Seat<D>, client: &ClientId)
+where
+    D: PrimarySelectionHandler,
+    D: 'static,
+{
+    if let Some(seat_data) = seat.user_data().get::<RefCell<SeatData>>() {
+        seat_data.borrow_mut().clear_client_selection::<D>(dh, client);
+    }
+}
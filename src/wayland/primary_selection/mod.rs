@@ -15,6 +15,29 @@
 //!
 //! - the freestanding function [`set_primary_selection`]
 //!   allows you to set the contents of the selection for your clients
+//! - the freestanding function [`set_primary_selection_bytes`]
+//!   allows you to set the selection from data held in memory, which smithay sends to clients directly
+//! - the freestanding function [`set_primary_selection_reader`]
+//!   allows you to set the selection from readers created on demand, which smithay streams to clients
+//! - the freestanding function [`seed_primary_selection_data`]
+//!   allows you to provide the data of some mime types of a selection set by [`set_primary_selection`] upfront
+//! - the freestanding functions [`set_primary_scope`] and [`remove_primary_scope`]
+//!   allow you to keep separate selections, e.g. per workspace
+//! - the freestanding function [`primary_selection_origin`]
+//!   tells you whether the current selection is empty, or was set by a client or the compositor
+//! - the freestanding function [`primary_selection_owner_is`]
+//!   tells you whether the current selection was set by the client of a given surface
+//! - [`PrimarySelectionState::snapshot_compositor_selection`] and [`PrimarySelectionState::restore`]
+//!   allow you to persist a selection set from memory, e.g. across restarts of the compositor
+//! - [`PrimarySelectionState::migrate_seat`] moves the selection and devices of a seat to another one,
+//!   e.g. when a seat is recreated after a configuration change
+//! - [`PrimarySelectionState::stats`] returns counters of the selections set and transferred,
+//!   e.g. to export them as metrics
+//!
+//! Once an event loop was set by [`PrimarySelectionState::set_loop_handle`], selections set by
+//! clients are not advertised to other clients right away. Instead the offers are created from an
+//! idle callback of the event loop, so that only the last selection set during a dispatch cycle
+//! generates any protocol traffic. [`flush_primary_selection`] advertises them earlier.
 //!
 //! ## Initialization
 //!
@@ -48,24 +71,45 @@
 //! // You're now ready to go!
 //! ```
 
-use std::{cell::RefCell, os::unix::prelude::RawFd};
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    io::Read,
+    os::unix::prelude::RawFd,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+};
 
+use calloop::LoopHandle;
 use wayland_protocols::wp::primary_selection::zv1::server::{
     zwp_primary_selection_device_manager_v1::ZwpPrimarySelectionDeviceManagerV1 as PrimaryDeviceManager,
     zwp_primary_selection_source_v1::ZwpPrimarySelectionSourceV1 as PrimarySource,
 };
-use wayland_server::{backend::GlobalId, Client, DisplayHandle, GlobalDispatch};
+use wayland_server::{
+    backend::{ClientId, GlobalId},
+    protocol::wl_surface::WlSurface,
+    Client, DisplayHandle, GlobalDispatch, Resource,
+};
 
 use crate::wayland::seat::Seat;
 
 mod device;
 mod seat_data;
 mod source;
+#[cfg(test)]
+mod tests;
 
 pub use device::PrimaryDeviceUserData;
 pub use source::{with_source_metadata, PrimarySourceUserData, SourceMetadata};
 
-use seat_data::{SeatData, Selection};
+use seat_data::{
+    CompositorSelection, IdleScheduler, ReaderSelection, SeatData, Selection, TransferScheduler,
+};
+
+/// Factory producing a reader for the data of a mime type, see [`set_primary_selection_reader`]
+pub type SelectionReaderFactory = Arc<dyn Fn(String) -> Box<dyn Read + Send> + Send + Sync>;
 
 /// Events that are generated by interactions of the clients with the data device
 pub trait PrimarySelectionHandler: Sized {
@@ -76,12 +120,102 @@ pub trait PrimarySelectionHandler: Sized {
     #[allow(unused_variables)]
     fn new_selection(&mut self, dh: &DisplayHandle, source: Option<PrimarySource>) {}
 
+    /// A client with keyboard focus wants to set the selection of the given seat
+    ///
+    /// Returning `false` rejects the selection and cancels the source.
+    /// The selection is applied to the active scope (see [`set_primary_scope`]).
+    #[allow(unused_variables)]
+    fn allow_selection(
+        &mut self,
+        dh: &DisplayHandle,
+        seat: &Seat<Self>,
+        source: Option<&PrimarySource>,
+    ) -> bool {
+        true
+    }
+
     /// A client requested to read the server-set selection
     ///
     /// * `mime_type` - the requested mime type
     /// * `fd` - the fd to write into
     #[allow(unused_variables)]
     fn send_selection(&mut self, dh: &DisplayHandle, mime_type: String, fd: RawFd) {}
+
+    /// A client destroyed a source, that was set as the selection of the given seat
+    ///
+    /// This is called, even if the source was already replaced by another selection,
+    /// and allows to release resources associated with it.
+    /// Sources destroyed by disconnecting clients are reported as well.
+    #[allow(unused_variables)]
+    fn source_destroyed(&mut self, source: PrimarySource, seat: Seat<Self>) {}
+}
+
+/// Origin of the current primary selection of a seat
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SelectionOrigin {
+    /// There is no selection
+    Empty,
+    /// The selection was set by a client
+    Client,
+    /// The selection was set by the compositor
+    Compositor,
+}
+
+/// Snapshot of a compositor-provided primary selection
+///
+/// This is plain data, that can be stored by the compositor, e.g. to persist the selection
+/// across restarts, and be restored using [`PrimarySelectionState::restore`].
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct SerializableSelection {
+    /// Mime types of the selection and the data offered for them
+    pub entries: Vec<(String, Vec<u8>)>,
+}
+
+/// Counters of the primary selection activity
+///
+/// Returned by [`PrimarySelectionState::stats`]. All counters start at zero, when the
+/// [`PrimarySelectionState`] is created, and only ever increase.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PrimarySelectionStats {
+    /// Selections set by clients, including clearing the selection
+    pub client_selections: u64,
+    /// Selections set by the compositor using [`set_primary_selection`] or [`set_primary_selection_bytes`]
+    ///
+    /// Only selections of seats, that a client has bound a primary selection device for,
+    /// or that were handled by [`PrimarySelectionState::restore`] or
+    /// [`PrimarySelectionState::migrate_seat`] before, are counted.
+    pub compositor_selections: u64,
+    /// `receive` requests of clients, that were passed on to the source of the selection
+    pub transfers: u64,
+    /// Bytes written to clients by smithay for selections set using [`set_primary_selection_bytes`]
+    /// or [`set_primary_selection_reader`], or seeded using [`seed_primary_selection_data`]
+    ///
+    /// The data of other selections is transferred by their source directly and not accounted.
+    pub bytes_transferred: u64,
+    /// Requests of clients, that were denied or ignored
+    ///
+    /// This includes selections set by unfocused clients or rejected by
+    /// [`PrimarySelectionHandler::allow_selection`], mime types exceeding the
+    /// [maximum per source](PrimarySelectionState::set_max_mime_types), as well as `receive` requests
+    /// for invalid mime types or exceeding the
+    /// [maximum transfers in flight](PrimarySelectionState::set_max_inflight_transfers).
+    pub rejected_requests: u64,
+}
+
+// Counters shared between the state and the seats it handles
+#[derive(Debug, Default)]
+struct StatCounters {
+    client_selections: AtomicU64,
+    compositor_selections: AtomicU64,
+    transfers: AtomicU64,
+    bytes_transferred: AtomicU64,
+    rejected_requests: AtomicU64,
+}
+
+impl StatCounters {
+    fn add(counter: &AtomicU64, value: u64) {
+        counter.fetch_add(value, Ordering::Relaxed);
+    }
 }
 
 /// State of data device
@@ -89,8 +223,42 @@ pub trait PrimarySelectionHandler: Sized {
 pub struct PrimarySelectionState {
     log: slog::Logger,
     manager_global: GlobalId,
+    max_mime_types: usize,
+    max_inflight_transfers: usize,
+    // transfers of every client, that are still in flight
+    transfers: Arc<Mutex<HashMap<ClientId, usize>>>,
+    counters: Arc<StatCounters>,
+    // writes the data of compositor selections from the event loop set by the compositor
+    scheduler: Option<TransferScheduler>,
+    // flushes the selections set by clients from the event loop set by the compositor
+    idle_scheduler: Option<IdleScheduler>,
 }
 
+// A transfer of a client in flight, released once dropped
+#[derive(Debug)]
+struct TransferSlot {
+    transfers: Arc<Mutex<HashMap<ClientId, usize>>>,
+    client: ClientId,
+}
+
+impl Drop for TransferSlot {
+    fn drop(&mut self) {
+        let mut transfers = self.transfers.lock().unwrap();
+        if let Some(count) = transfers.get_mut(&self.client) {
+            *count = count.saturating_sub(1);
+            if *count == 0 {
+                transfers.remove(&self.client);
+            }
+        }
+    }
+}
+
+/// Default maximum amount of mime types a single source may offer
+pub const DEFAULT_MAX_MIME_TYPES: usize = 512;
+
+/// Default maximum amount of transfers a single client may have in flight
+pub const DEFAULT_MAX_INFLIGHT_TRANSFERS: usize = 16;
+
 impl PrimarySelectionState {
     /// Regiseter new [ZwpPrimarySelectionDeviceManagerV1] global
     pub fn new<D, L>(display: &DisplayHandle, logger: L) -> Self
@@ -103,13 +271,179 @@ impl PrimarySelectionState {
 
         let manager_global = display.create_global::<D, PrimaryDeviceManager, _>(1, ());
 
-        Self { log, manager_global }
+        Self {
+            log,
+            manager_global,
+            max_mime_types: DEFAULT_MAX_MIME_TYPES,
+            max_inflight_transfers: DEFAULT_MAX_INFLIGHT_TRANSFERS,
+            transfers: Arc::new(Mutex::new(HashMap::new())),
+            counters: Arc::new(StatCounters::default()),
+            scheduler: None,
+            idle_scheduler: None,
+        }
     }
 
     /// [ZwpPrimarySelectionDeviceManagerV1] GlobalId getter
     pub fn global(&self) -> GlobalId {
         self.manager_global.clone()
     }
+
+    /// Set the maximum amount of mime types a single source may offer
+    ///
+    /// Further mime types offered by a client are ignored, to protect against clients
+    /// exhausting memory or flooding other clients with offers.
+    /// Defaults to [`DEFAULT_MAX_MIME_TYPES`].
+    pub fn set_max_mime_types(&mut self, max: usize) {
+        self.max_mime_types = max;
+    }
+
+    /// Returns the maximum amount of mime types a single source may offer
+    pub fn max_mime_types(&self) -> usize {
+        self.max_mime_types
+    }
+
+    /// Set the maximum amount of transfers a single client may have in flight
+    ///
+    /// Every `receive` request of a client on an offer of the primary selection counts as a transfer
+    /// in flight, until its file descriptor was handed to the source of the selection or all data was
+    /// written to it. Further requests are ignored and their file descriptor is closed right away,
+    /// to protect against clients exhausting file descriptors.
+    ///
+    /// Defaults to [`DEFAULT_MAX_INFLIGHT_TRANSFERS`].
+    pub fn set_max_inflight_transfers(&mut self, max: usize) {
+        self.max_inflight_transfers = max;
+    }
+
+    /// Returns the maximum amount of transfers a single client may have in flight
+    pub fn max_inflight_transfers(&self) -> usize {
+        self.max_inflight_transfers
+    }
+
+    /// Set the event loop used to write the data of selections provided by the compositor
+    ///
+    /// Data held by smithay, like selections set by [`set_primary_selection_bytes`], is written without
+    /// blocking on the client. Whatever the client does not accept right away is written by this event
+    /// loop once the client reads, transfers stalled for a few seconds are dropped. Without an event loop
+    /// only the data fitting into the file descriptor is written. `receive` requests for selections
+    /// set by [`set_primary_selection_reader`] are denied until an event loop was set.
+    ///
+    /// Selections set by clients are advertised from an idle callback of this event loop, so only
+    /// the last selection set during a dispatch cycle generates any protocol traffic. Without an event
+    /// loop they are advertised right away.
+    ///
+    /// The data of the event loop may be of any type, it does not need to be the type implementing
+    /// [`PrimarySelectionHandler`].
+    pub fn set_loop_handle<L: 'static>(&mut self, handle: LoopHandle<'static, L>) {
+        self.scheduler = Some(TransferScheduler::new(handle.clone(), self.log.clone()));
+        self.idle_scheduler = Some(IdleScheduler::new(handle));
+    }
+
+    /// Returns the current counters of the primary selection activity
+    pub fn stats(&self) -> PrimarySelectionStats {
+        let counters = &self.counters;
+        PrimarySelectionStats {
+            client_selections: counters.client_selections.load(Ordering::Relaxed),
+            compositor_selections: counters.compositor_selections.load(Ordering::Relaxed),
+            transfers: counters.transfers.load(Ordering::Relaxed),
+            bytes_transferred: counters.bytes_transferred.load(Ordering::Relaxed),
+            rejected_requests: counters.rejected_requests.load(Ordering::Relaxed),
+        }
+    }
+
+    // Account a new transfer of a client, returns `None` if it has too many in flight
+    fn start_transfer(&self, client: &ClientId) -> Option<TransferSlot> {
+        let mut transfers = self.transfers.lock().unwrap();
+        let count = transfers.entry(client.clone()).or_default();
+        if *count >= self.max_inflight_transfers {
+            return None;
+        }
+        *count += 1;
+        Some(TransferSlot {
+            transfers: self.transfers.clone(),
+            client: client.clone(),
+        })
+    }
+
+    /// Take a snapshot of the current primary selection of a seat
+    ///
+    /// Only selections set by the compositor from memory using [`set_primary_selection_bytes`]
+    /// can be persisted. Returns `None` for selections of clients, whose data is only available
+    /// as long as the client is, and for selections set using [`set_primary_selection`].
+    pub fn snapshot_compositor_selection<D: 'static>(&self, seat: &Seat<D>) -> Option<SerializableSelection> {
+        let seat_data = seat.user_data().get::<RefCell<SeatData>>()?;
+        let seat_data = seat_data.borrow();
+        seat_data.compositor_bytes().map(|entries| SerializableSelection {
+            entries: entries.to_vec(),
+        })
+    }
+
+    /// Restore a snapshot taken by [`PrimarySelectionState::snapshot_compositor_selection`]
+    ///
+    /// The snapshot replaces the current primary selection of the seat like [`set_primary_selection_bytes`].
+    /// Persisted selections can be large, their data is written using the event loop set by
+    /// [`PrimarySelectionState::set_loop_handle`] without blocking on the client.
+    pub fn restore<D>(&self, dh: &DisplayHandle, seat: &Seat<D>, snapshot: SerializableSelection)
+    where
+        D: PrimarySelectionHandler,
+        D: 'static,
+    {
+        seat.user_data()
+            .insert_if_missing(|| RefCell::new(SeatData::new()));
+        let seat_data = seat.user_data().get::<RefCell<SeatData>>().unwrap();
+        seat_data.borrow_mut().set_counters(&self.counters);
+        set_primary_selection_bytes(dh, seat, snapshot.entries);
+    }
+
+    /// Move the primary selection state of a seat to another seat
+    ///
+    /// The current selection, the selections of all scopes and the focus of `old` are moved to `new`,
+    /// replacing any selection `new` had. All devices bound to `old` are moved to `new` as well
+    /// and receive a new offer of the selection, if they belong to the focused client.
+    /// `old` is left without any selection afterwards.
+    ///
+    /// Devices can not be rebound by the compositor. Devices created by clients for the `wl_seat`
+    /// of `old` keep working and their requests are forwarded to `new`, as long as `old` is alive.
+    /// Clients are expected to create new devices for the `wl_seat` of `new` once it is advertised
+    /// and destroy their old ones, when the global of `old` is removed.
+    pub fn migrate_seat<D>(&self, dh: &DisplayHandle, old: &Seat<D>, new: &Seat<D>)
+    where
+        D: PrimarySelectionHandler,
+        D: 'static,
+    {
+        let old_data = match old.user_data().get::<RefCell<SeatData>>() {
+            Some(data) => data,
+            None => return,
+        };
+        if old == new {
+            return;
+        }
+        new.user_data()
+            .insert_if_missing(|| RefCell::new(SeatData::new()));
+        let new_data = new.user_data().get::<RefCell<SeatData>>().unwrap();
+        new_data.borrow_mut().set_counters(&self.counters);
+        old_data
+            .borrow_mut()
+            .migrate_to::<D>(dh, new, &mut new_data.borrow_mut());
+    }
+}
+
+/// Follow the migrations of a seat to the seat currently holding its state
+fn migrated_seat<D: 'static>(mut seat: Seat<D>) -> Seat<D> {
+    let mut visited = vec![seat.clone()];
+    loop {
+        let next = match seat.user_data().get::<RefCell<SeatData>>() {
+            Some(data) => data.borrow().migrated_to::<D>(),
+            None => None,
+        };
+        match next {
+            // stop at a cycle of migrations, instead of following it forever
+            Some(next) if !visited.contains(&next) => {
+                visited.push(next.clone());
+                seat = next;
+            }
+            _ => return seat,
+        }
+    }
 }
 
 /// Set the primary selection focus to a certain client for a given seat
@@ -138,9 +472,149 @@ where
     seat.user_data()
         .insert_if_missing(|| RefCell::new(SeatData::new()));
     let seat_data = seat.user_data().get::<RefCell<SeatData>>().unwrap();
+    let selection = CompositorSelection::new(mime_types);
+    seat_data
+        .borrow_mut()
+        .set_selection::<D>(dh, Selection::Compositor(Arc::new(selection)));
+}
+
+/// Set a compositor-provided primary selection for this seat, whose data is read on demand
+///
+/// Whenever a client requests to read the selection, `factory` is called with the requested
+/// mime type to create a reader for its data. Smithay copies the data from the reader to the client
+/// without blocking on the client, using the event loop set by [`PrimarySelectionState::set_loop_handle`].
+/// If the reader fails, the transfer is aborted and the client receives the data read so far.
+///
+/// This avoids holding large payloads, like a rendered screenshot, in memory, compared to
+/// [`set_primary_selection_bytes`]. The reader is read from the event loop, so it should not block
+/// for long periods of time. The factory is dropped once the selection is replaced.
+pub fn set_primary_selection_reader<D>(
+    dh: &DisplayHandle,
+    seat: &Seat<D>,
+    mime_types: Vec<String>,
+    factory: SelectionReaderFactory,
+) where
+    D: PrimarySelectionHandler,
+    D: 'static,
+{
+    seat.user_data()
+        .insert_if_missing(|| RefCell::new(SeatData::new()));
+    let seat_data = seat.user_data().get::<RefCell<SeatData>>().unwrap();
+    let selection = ReaderSelection { mime_types, factory };
     seat_data
         .borrow_mut()
-        .set_selection::<D>(dh, Selection::Compositor(SourceMetadata { mime_types }));
+        .set_selection::<D>(dh, Selection::CompositorReader(Arc::new(selection)));
+}
+
+/// Provide the data of a mime type of the compositor-provided primary selection of this seat upfront
+///
+/// Requests of clients to read `mime_type` from the selection set by [`set_primary_selection`]
+/// are answered with `data` by smithay directly, without generating a
+/// [`PrimarySelectionHandler::send_selection`] event. Other mime types are still forwarded to your
+/// callback. The data is written without blocking on the client like the data of
+/// [`set_primary_selection_bytes`] and is dropped once the selection is replaced.
+///
+/// This is useful for small payloads already known to the compositor, like a copied url,
+/// that would otherwise be requested from your callback for every paste.
+///
+/// Returns `false` if the current selection was not set by [`set_primary_selection`]
+/// or does not offer `mime_type`.
+pub fn seed_primary_selection_data<D: 'static>(seat: &Seat<D>, mime_type: String, data: Vec<u8>) -> bool {
+    seat.user_data()
+        .get::<RefCell<SeatData>>()
+        .map(|seat_data| seat_data.borrow().seed_compositor_data(mime_type, data))
+        .unwrap_or(false)
+}
+
+/// Set a compositor-provided primary selection for this seat from in-memory data
+///
+/// Every entry consists of a mime type and the data offered for it.
+/// Requests of clients to read the selection are answered by smithay directly,
+/// without generating a [`PrimarySelectionHandler::send_selection`] event.
+/// The data is freed once the selection is replaced.
+///
+/// The data is written without blocking on the client, larger payloads require an event loop
+/// set by [`PrimarySelectionState::set_loop_handle`] to be transferred completely.
+pub fn set_primary_selection_bytes<D>(dh: &DisplayHandle, seat: &Seat<D>, entries: Vec<(String, Vec<u8>)>)
+where
+    D: PrimarySelectionHandler,
+    D: 'static,
+{
+    seat.user_data()
+        .insert_if_missing(|| RefCell::new(SeatData::new()));
+    let seat_data = seat.user_data().get::<RefCell<SeatData>>().unwrap();
+    seat_data
+        .borrow_mut()
+        .set_selection::<D>(dh, Selection::CompositorBytes(Arc::new(entries)));
+}
+
+/// Switch the active selection scope of this seat
+///
+/// Every seat keeps a separate selection per scope, identified by a key chosen by the compositor,
+/// e.g. to implement a separate selection per workspace. Selections set by clients or via
+/// [`set_primary_selection`] apply to the active scope, which is `0` initially.
+///
+/// Switching the scope advertises the selection of the new scope to the focused client,
+/// the selection of the previous scope is kept until it becomes active again.
+pub fn set_primary_scope<D>(dh: &DisplayHandle, seat: &Seat<D>, scope: u64)
+where
+    D: PrimarySelectionHandler,
+    D: 'static,
+{
+    seat.user_data()
+        .insert_if_missing(|| RefCell::new(SeatData::new()));
+    let seat_data = seat.user_data().get::<RefCell<SeatData>>().unwrap();
+    seat_data.borrow_mut().set_scope::<D>(dh, scope);
+}
+
+/// Drop the stored selection of a scope of this seat
+///
+/// Use this once a scope is not used anymore, e.g. if a workspace was removed.
+/// Removing the active scope clears the current selection, the scope itself stays active.
+pub fn remove_primary_scope<D>(dh: &DisplayHandle, seat: &Seat<D>, scope: u64)
+where
+    D: PrimarySelectionHandler,
+    D: 'static,
+{
+    if let Some(seat_data) = seat.user_data().get::<RefCell<SeatData>>() {
+        seat_data.borrow_mut().remove_scope::<D>(dh, scope);
+    }
+}
+
+/// Query the origin of the current primary selection of this seat
+///
+/// Selections of clients, that were destroyed, are reported as [`SelectionOrigin::Empty`].
+pub fn primary_selection_origin<D: 'static>(seat: &Seat<D>) -> SelectionOrigin {
+    seat.user_data()
+        .get::<RefCell<SeatData>>()
+        .map(|seat_data| seat_data.borrow().selection_origin())
+        .unwrap_or(SelectionOrigin::Empty)
+}
+
+/// Check if the current primary selection of this seat is owned by the client of `surface`
+///
+/// Returns `false` for empty selections, selections set by the compositor
+/// and selections of clients, whose source was destroyed.
+pub fn primary_selection_owner_is<D: 'static>(seat: &Seat<D>, surface: &WlSurface) -> bool {
+    seat.user_data()
+        .get::<RefCell<SeatData>>()
+        .map(|seat_data| seat_data.borrow().selection_owned_by(&surface.id()))
+        .unwrap_or(false)
+}
+
+/// Advertise pending selection changes of clients on this seat
+///
+/// Selections set by clients are coalesced until the idle callback scheduled in the event loop set by
+/// [`PrimarySelectionState::set_loop_handle`] runs. Call this function to advertise them before,
+/// e.g. if you flush your clients before the idle callbacks of your event loop are dispatched.
+pub fn flush_primary_selection<D>(dh: &DisplayHandle, seat: &Seat<D>)
+where
+    D: PrimarySelectionHandler,
+    D: 'static,
+{
+    if let Some(seat_data) = seat.user_data().get::<RefCell<SeatData>>() {
+        seat_data.borrow_mut().flush::<D>(dh);
+    }
 }
 
 mod handlers {
@@ -159,7 +633,7 @@ mod handlers {
     use crate::wayland::seat::Seat;
 
     use super::{device::PrimaryDeviceUserData, seat_data::SeatData, source::PrimarySourceUserData};
-    use super::{PrimarySelectionHandler, PrimarySelectionState};
+    use super::{migrated_seat, PrimarySelectionHandler, PrimarySelectionState};
 
     impl<D> GlobalDispatch<PrimaryDeviceManager, (), D> for PrimarySelectionState
     where
@@ -196,7 +670,7 @@ mod handlers {
             _resource: &PrimaryDeviceManager,
             request: primary_device_manager::Request,
             _data: &(),
-            _dhandle: &DisplayHandle,
+            dhandle: &DisplayHandle,
             data_init: &mut wayland_server::DataInit<'_, D>,
         ) {
             let primary_selection_state = state.primary_selection_state();
@@ -206,7 +680,7 @@ mod handlers {
                     data_init.init(id, PrimarySourceUserData::new());
                 }
                 primary_device_manager::Request::GetDevice { id, seat: wl_seat } => {
-                    match Seat::<D>::from_resource(&wl_seat) {
+                    match Seat::<D>::from_resource(&wl_seat).map(migrated_seat) {
                         Some(seat) => {
                             seat.user_data()
                                 .insert_if_missing(|| RefCell::new(SeatData::new()));
@@ -214,7 +688,9 @@ mod handlers {
                             let device = data_init.init(id, PrimaryDeviceUserData { wl_seat });
 
                             let seat_data = seat.user_data().get::<RefCell<SeatData>>().unwrap();
-                            seat_data.borrow_mut().add_device(device);
+                            let mut seat_data = seat_data.borrow_mut();
+                            seat_data.set_counters(&primary_selection_state.counters);
+                            seat_data.add_device::<D>(dhandle, device);
                         }
                         None => {
                             error!(
@@ -1,5 +1,22 @@
-use std::sync::Arc;
+use std::{
+    any::Any,
+    cell::Cell,
+    collections::HashMap,
+    fmt,
+    fs::File,
+    io::{self, Cursor, Read, Write},
+    os::unix::io::{AsRawFd, FromRawFd, RawFd},
+    rc::Rc,
+    sync::{Arc, Mutex, Weak},
+    time::{Duration, Instant},
+};
 
+use calloop::{
+    generic::Generic,
+    timer::{TimeoutAction, Timer},
+    Interest, LoopHandle, Mode, PostAction,
+};
+use nix::fcntl::{fcntl, FcntlArg, OFlag};
 use slog::debug;
 use wayland_protocols::wp::primary_selection::zv1::server::{
     zwp_primary_selection_device_v1::ZwpPrimarySelectionDeviceV1 as PrimaryDevice,
@@ -11,20 +28,60 @@ use wayland_server::{
     Client, DisplayHandle, Resource,
 };
 
-use crate::utils::IsAlive;
+use crate::{utils::IsAlive, wayland::seat::Seat};
+
+use super::{
+    source::{current_source, PrimarySourceUserData},
+    with_source_metadata, PrimarySelectionHandler, PrimarySelectionState, SelectionOrigin,
+    SelectionReaderFactory, SourceMetadata, StatCounters, TransferSlot,
+};
 
-use super::{with_source_metadata, PrimarySelectionHandler, SourceMetadata};
+// Size of the chunks read from the readers of compositor selections
+const READER_CHUNK_SIZE: usize = 64 * 1024;
+// Time a transfer may not make any progress, before it is dropped
+const TRANSFER_TIMEOUT: Duration = Duration::from_secs(5);
 
 pub enum Selection {
     Empty,
     Client(PrimarySource),
-    Compositor(SourceMetadata),
+    // shared by the offers of all devices
+    Compositor(Arc<CompositorSelection>),
+    CompositorBytes(Arc<Vec<(String, Vec<u8>)>>),
+    CompositorReader(Arc<ReaderSelection>),
+}
+
+pub struct CompositorSelection {
+    pub meta: SourceMetadata,
+    // data seeded by the compositor, sent without involving the handler
+    pub cache: Mutex<HashMap<String, Arc<[u8]>>>,
+}
+
+pub struct ReaderSelection {
+    pub mime_types: Vec<String>,
+    pub factory: SelectionReaderFactory,
+}
+
+impl CompositorSelection {
+    pub fn new(mime_types: Vec<String>) -> Self {
+        Self {
+            meta: SourceMetadata { mime_types },
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
 }
 
 pub struct SeatData {
     known_devices: Vec<PrimaryDevice>,
     selection: Selection,
     current_focus: Option<Client>,
+    pending_offers: bool,
+    scope: u64,
+    // selections of all inactive scopes
+    scoped_selections: HashMap<u64, Selection>,
+    // the `Seat<D>` this data was moved to, devices still bound to the old seat are forwarded
+    migrated_to: Option<Box<dyn Any>>,
+    // counters of the `PrimarySelectionState` handling this seat, once known
+    counters: Option<Arc<StatCounters>>,
 }
 
 impl Default for SeatData {
@@ -33,6 +90,11 @@ impl Default for SeatData {
             known_devices: Vec::new(),
             selection: Selection::Empty,
             current_focus: None,
+            pending_offers: false,
+            scope: 0,
+            scoped_selections: HashMap::new(),
+            migrated_to: None,
+            counters: None,
         }
     }
 }
@@ -42,10 +104,34 @@ impl SeatData {
         Self::default()
     }
 
-    pub fn add_device(&mut self, device: PrimaryDevice) {
+    /// Add a new device, advertising the current selection to it right away,
+    /// if it belongs to the focused client.
+    pub fn add_device<D>(&mut self, dh: &DisplayHandle, device: PrimaryDevice)
+    where
+        D: PrimarySelectionHandler,
+        D: 'static,
+    {
+        // a queued selection is sent to all devices on the next flush anyway
+        if !self.pending_offers {
+            if let Some(client) = self.current_focus.as_ref() {
+                if dh.get_client(device.id()).map(|c| &c == client).unwrap_or(false) {
+                    if matches!(self.selection, Selection::Client(ref source) if !source.alive()) {
+                        self.selection = Selection::Empty;
+                    }
+                    self.offer_selection::<D>(dh, client, &device);
+                }
+            }
+        }
         self.known_devices.push(device);
     }
 
+    /// Account selections of this seat in the given counters
+    pub(super) fn set_counters(&mut self, counters: &Arc<StatCounters>) {
+        if self.counters.is_none() {
+            self.counters = Some(counters.clone());
+        }
+    }
+
     pub fn retain_devices<F>(&mut self, f: F)
     where
         F: FnMut(&PrimaryDevice) -> bool,
@@ -53,6 +139,103 @@ impl SeatData {
         self.known_devices.retain(f)
     }
 
+    pub fn selection_origin(&self) -> SelectionOrigin {
+        match &self.selection {
+            Selection::Empty => SelectionOrigin::Empty,
+            Selection::Client(source) if !source.alive() => SelectionOrigin::Empty,
+            Selection::Client(_) => SelectionOrigin::Client,
+            Selection::Compositor(_) | Selection::CompositorBytes(_) | Selection::CompositorReader(_) => {
+                SelectionOrigin::Compositor
+            }
+        }
+    }
+
+    /// Whether the current selection was set by the client owning the given object
+    pub fn selection_owned_by(&self, id: &ObjectId) -> bool {
+        match &self.selection {
+            Selection::Client(source) => source.alive() && source.id().same_client_as(id),
+            _ => false,
+        }
+    }
+
+    /// Clear the selections of all scopes, that were set by the given client
+    pub fn clear_client_selection<D>(&mut self, dh: &DisplayHandle, client: &ClientId)
+    where
+        D: PrimarySelectionHandler,
+        D: 'static,
+    {
+        let set_by_client = |source: &PrimarySource| source.client_id().as_ref() == Some(client);
+        self.scoped_selections.retain(|_, selection| match selection {
+            Selection::Client(source) if set_by_client(source) => {
+                source.cancelled();
+                false
+            }
+            _ => true,
+        });
+        if matches!(self.selection, Selection::Client(ref source) if set_by_client(source)) {
+            self.replace_selection(Selection::Empty);
+            self.send_selection::<D>(dh);
+        }
+    }
+
+    /// In-memory data of the current selection, if it was set from bytes by the compositor
+    pub fn compositor_bytes(&self) -> Option<&[(String, Vec<u8>)]> {
+        match &self.selection {
+            Selection::CompositorBytes(entries) => Some(entries),
+            _ => None,
+        }
+    }
+
+    /// Seed the data of a mime type of the current selection, if it was set by the compositor
+    pub fn seed_compositor_data(&self, mime_type: String, data: Vec<u8>) -> bool {
+        match &self.selection {
+            Selection::Compositor(selection) if selection.meta.mime_types.contains(&mime_type) => {
+                selection.cache.lock().unwrap().insert(mime_type, data.into());
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Move the devices and selections of this seat to another seat.
+    ///
+    /// Any selection of the other seat is replaced, its devices are kept.
+    /// This seat is left empty and remembers the new seat to forward its remaining devices.
+    pub fn migrate_to<D>(&mut self, dh: &DisplayHandle, new_seat: &Seat<D>, new_data: &mut SeatData)
+    where
+        D: PrimarySelectionHandler,
+        D: 'static,
+    {
+        let old = std::mem::take(self);
+        self.migrated_to = Some(Box::new(new_seat.clone()));
+        // the new seat may have been migrated to this one before
+        new_data.migrated_to = None;
+
+        let mut known_devices = old.known_devices;
+        known_devices.append(&mut new_data.known_devices);
+        new_data.known_devices = known_devices;
+        new_data.replace_selection(old.selection);
+        for (scope, selection) in old.scoped_selections {
+            if let Some(Selection::Client(source)) = new_data.scoped_selections.insert(scope, selection) {
+                source.cancelled();
+            }
+        }
+        new_data.scope = old.scope;
+        if new_data.counters.is_none() {
+            new_data.counters = old.counters;
+        }
+        if old.current_focus.is_some() {
+            new_data.current_focus = old.current_focus;
+        }
+        // re-issue the offers under the new seat
+        new_data.send_selection::<D>(dh);
+    }
+
+    /// The seat this data was moved to by [`SeatData::migrate_to`], if any
+    pub fn migrated_to<D: 'static>(&self) -> Option<Seat<D>> {
+        self.migrated_to.as_ref()?.downcast_ref::<Seat<D>>().cloned()
+    }
+
     pub fn set_focus<D>(&mut self, dh: &DisplayHandle, new_focus: Option<Client>)
     where
         D: PrimarySelectionHandler,
@@ -67,16 +250,91 @@ impl SeatData {
         D: PrimarySelectionHandler,
         D: 'static,
     {
-        if let Selection::Client(source) = &self.selection {
-            match &new_selection {
+        if let Some(counters) = self.counters.as_ref() {
+            StatCounters::add(&counters.compositor_selections, 1);
+        }
+        self.replace_selection(new_selection);
+        self.send_selection::<D>(dh);
+    }
+
+    /// Replace the selection, but defer sending the offers until the next `flush`.
+    ///
+    /// Only the last selection queued before a flush is advertised to the clients.
+    /// A client source with the same metadata as the already advertised one takes over
+    /// its offers instead, as re-issuing them would not change anything the clients can observe.
+    ///
+    /// Returns `true`, if the offers were up to date before, so a flush needs to be scheduled.
+    pub fn queue_selection(&mut self, new_selection: Selection) -> bool {
+        if !self.pending_offers {
+            if let (Selection::Client(old), Selection::Client(new)) = (&self.selection, &new_selection) {
+                if old != new && old.alive() && same_metadata(old, new) {
+                    if let Some(data) = old.data::<PrimarySourceUserData>() {
+                        data.set_replaced_by(new);
+                        self.replace_selection(new_selection);
+                        return false;
+                    }
+                }
+            }
+        }
+        self.replace_selection(new_selection);
+        !std::mem::replace(&mut self.pending_offers, true)
+    }
+
+    pub fn flush<D>(&mut self, dh: &DisplayHandle)
+    where
+        D: PrimarySelectionHandler,
+        D: 'static,
+    {
+        if self.pending_offers {
+            self.send_selection::<D>(dh);
+        }
+    }
+
+    /// Activate the selection of another scope, keeping the current selection for later
+    pub fn set_scope<D>(&mut self, dh: &DisplayHandle, scope: u64)
+    where
+        D: PrimarySelectionHandler,
+        D: 'static,
+    {
+        if scope == self.scope {
+            return;
+        }
+        let new_selection = self.scoped_selections.remove(&scope).unwrap_or(Selection::Empty);
+        let old_selection = std::mem::replace(&mut self.selection, new_selection);
+        if !matches!(old_selection, Selection::Empty) {
+            self.scoped_selections.insert(self.scope, old_selection);
+        }
+        self.scope = scope;
+        self.send_selection::<D>(dh);
+    }
+
+    /// Drop the selection of a scope, clearing the current selection if the scope is active
+    pub fn remove_scope<D>(&mut self, dh: &DisplayHandle, scope: u64)
+    where
+        D: PrimarySelectionHandler,
+        D: 'static,
+    {
+        if scope == self.scope {
+            self.replace_selection(Selection::Empty);
+            self.send_selection::<D>(dh);
+        } else if let Some(Selection::Client(source)) = self.scoped_selections.remove(&scope) {
+            source.cancelled();
+        }
+    }
+
+    fn replace_selection(&mut self, new_selection: Selection) {
+        match &self.selection {
+            Selection::Client(source) => match &new_selection {
                 Selection::Client(new_source) if new_source == source => {}
                 _ => {
                     source.cancelled();
                 }
-            }
+            },
+            // offers of the old selection must not be answered with its seeded data anymore
+            Selection::Compositor(selection) => selection.cache.lock().unwrap().clear(),
+            _ => {}
         }
         self.selection = new_selection;
-        self.send_selection::<D>(dh);
     }
 
     pub fn send_selection<D>(&mut self, dh: &DisplayHandle)
@@ -84,6 +342,7 @@ impl SeatData {
         D: PrimarySelectionHandler,
         D: 'static,
     {
+        self.pending_offers = false;
         let client = match self.current_focus.as_ref() {
             Some(c) => c,
             None => return,
@@ -99,77 +358,121 @@ impl SeatData {
             self.selection = Selection::Empty;
         }
 
-        // then send it if appropriate
+        // then send it to all devices of the focused client
+        for pd in &self.known_devices {
+            // skip data devices not belonging to our client
+            if dh.get_client(pd.id()).map(|c| &c != client).unwrap_or(true) {
+                continue;
+            }
+            self.offer_selection::<D>(dh, client, pd);
+        }
+    }
+
+    // Advertise the current selection to a single device of the focused client
+    fn offer_selection<D>(&self, dh: &DisplayHandle, client: &Client, pd: &PrimaryDevice)
+    where
+        D: PrimarySelectionHandler,
+        D: 'static,
+    {
         match self.selection {
             Selection::Empty => {
                 // send an empty selection
-                for pd in &self.known_devices {
-                    // skip data devices not belonging to our client
-                    if dh.get_client(pd.id()).map(|c| &c != client).unwrap_or(true) {
-                        continue;
-                    }
-                    pd.selection(None);
-                }
+                pd.selection(None);
             }
             Selection::Client(ref source) => {
-                for pd in &self.known_devices {
-                    // skip data devices not belonging to our client
-                    if dh.get_client(pd.id()).map(|c| &c != client).unwrap_or(true) {
-                        continue;
-                    }
-                    let source_clone = source.clone();
-
-                    let handle = dh.backend_handle();
-                    // create a data offer
-                    let offer = handle
-                        .create_object::<D>(
-                            client.id(),
-                            PrimaryOffer::interface(),
-                            pd.version(),
-                            Arc::new(ClientSelection { source: source_clone }),
-                        )
-                        .unwrap();
-                    let offer = PrimaryOffer::from_id(dh, offer).unwrap();
-
-                    // advertize the offer to the client
-                    pd.data_offer(&offer);
-                    with_source_metadata(source, |meta| {
-                        for mime_type in meta.mime_types.iter().cloned() {
-                            offer.offer(mime_type);
-                        }
-                    })
+                let source_clone = source.clone();
+
+                let handle = dh.backend_handle();
+                // create a data offer
+                let offer = handle
+                    .create_object::<D>(
+                        client.id(),
+                        PrimaryOffer::interface(),
+                        pd.version(),
+                        Arc::new(ClientSelection { source: source_clone }),
+                    )
                     .unwrap();
-                    pd.selection(Some(&offer));
-                }
-            }
-            Selection::Compositor(ref meta) => {
-                for pd in &self.known_devices {
-                    // skip data devices not belonging to our client
-                    if dh.get_client(pd.id()).map(|c| &c != client).unwrap_or(true) {
-                        continue;
-                    }
+                let offer = PrimaryOffer::from_id(dh, offer).unwrap();
 
-                    let offer_meta = meta.clone();
-
-                    let handle = dh.backend_handle();
-                    // create a data offer
-                    let offer = handle
-                        .create_object::<D>(
-                            client.id(),
-                            PrimaryOffer::interface(),
-                            pd.version(),
-                            Arc::new(ServerSelection { offer_meta }),
-                        )
-                        .unwrap();
-                    let offer = PrimaryOffer::from_id(dh, offer).unwrap();
-
-                    // advertize the offer to the client
-                    pd.data_offer(&offer);
+                // advertize the offer to the client
+                pd.data_offer(&offer);
+                with_source_metadata(source, |meta| {
                     for mime_type in meta.mime_types.iter().cloned() {
                         offer.offer(mime_type);
                     }
-                    pd.selection(Some(&offer));
+                })
+                .unwrap();
+                pd.selection(Some(&offer));
+            }
+            Selection::Compositor(ref selection) => {
+                let offer_selection = selection.clone();
+
+                let handle = dh.backend_handle();
+                // create a data offer
+                let offer = handle
+                    .create_object::<D>(
+                        client.id(),
+                        PrimaryOffer::interface(),
+                        pd.version(),
+                        Arc::new(ServerSelection {
+                            selection: offer_selection,
+                        }),
+                    )
+                    .unwrap();
+                let offer = PrimaryOffer::from_id(dh, offer).unwrap();
+
+                // advertize the offer to the client
+                pd.data_offer(&offer);
+                for mime_type in selection.meta.mime_types.iter().cloned() {
+                    offer.offer(mime_type);
+                }
+                pd.selection(Some(&offer));
+            }
+            Selection::CompositorBytes(ref entries) => {
+                let handle = dh.backend_handle();
+                // create a data offer, only holding a weak reference, so the data
+                // is freed as soon as the selection is replaced
+                let offer = handle
+                    .create_object::<D>(
+                        client.id(),
+                        PrimaryOffer::interface(),
+                        pd.version(),
+                        Arc::new(ServerBytesSelection {
+                            entries: Arc::downgrade(entries),
+                        }),
+                    )
+                    .unwrap();
+                let offer = PrimaryOffer::from_id(dh, offer).unwrap();
+
+                // advertize the offer to the client
+                pd.data_offer(&offer);
+                for (mime_type, _) in entries.iter() {
+                    offer.offer(mime_type.clone());
                 }
+                pd.selection(Some(&offer));
+            }
+            Selection::CompositorReader(ref selection) => {
+                let handle = dh.backend_handle();
+                // create a data offer, only holding a weak reference, so the
+                // factory is freed as soon as the selection is replaced
+                let offer = handle
+                    .create_object::<D>(
+                        client.id(),
+                        PrimaryOffer::interface(),
+                        pd.version(),
+                        Arc::new(ServerReaderSelection {
+                            selection: Arc::downgrade(selection),
+                        }),
+                    )
+                    .unwrap();
+                let offer = PrimaryOffer::from_id(dh, offer).unwrap();
+
+                // advertize the offer to the client
+                pd.data_offer(&offer);
+                for mime_type in selection.mime_types.iter().cloned() {
+                    offer.offer(mime_type);
+                }
+                pd.selection(Some(&offer));
             }
         }
     }
@@ -187,12 +490,17 @@ where
         self: Arc<Self>,
         dh: &Handle,
         handler: &mut D,
-        _client_id: ClientId,
+        client_id: ClientId,
         msg: Message<ObjectId>,
     ) -> Option<Arc<dyn ObjectData<D>>> {
         let dh = DisplayHandle::from(dh.clone());
-        if let Ok((_resource, request)) = PrimaryOffer::parse_request(&dh, msg) {
-            handle_client_selection(handler, request, &self.source);
+        // selection data offers only care about the `receive` event
+        if let Ok((_resource, primary_offer::Request::Receive { fd, mime_type })) =
+            PrimaryOffer::parse_request(&dh, msg)
+        {
+            if let Some(slot) = accept_transfer(handler.primary_selection_state(), &client_id, fd) {
+                handle_client_selection(handler, fd, mime_type, slot, &self.source);
+            }
         }
 
         None
@@ -201,50 +509,225 @@ where
     fn destroyed(&self, _data: &mut D, _client_id: ClientId, _object_id: ObjectId) {}
 }
 
-fn handle_client_selection<D>(state: &mut D, request: primary_offer::Request, source: &PrimarySource)
-where
+// Account a `receive` request on an offer, closing its fd if the client has too many transfers in flight
+fn accept_transfer(state: &PrimarySelectionState, client_id: &ClientId, fd: RawFd) -> Option<TransferSlot> {
+    let slot = state.start_transfer(client_id);
+    if slot.is_none() {
+        StatCounters::add(&state.counters.rejected_requests, 1);
+        debug!(
+            state.log,
+            "Denying a zwp_primary_selection_offer_v1.receive, the client has too many transfers in flight."
+        );
+        let _ = ::nix::unistd::close(fd);
+    }
+    slot
+}
+
+// The transfer ends once the fd is handed to the source, `_slot` is released on return
+fn handle_client_selection<D>(
+    state: &mut D,
+    fd: RawFd,
+    mime_type: String,
+    _slot: TransferSlot,
+    source: &PrimarySource,
+) where
     D: PrimarySelectionHandler,
 {
     let primary_selection_state = state.primary_selection_state();
+    // the offer might have been taken over by an identical source
+    let source = &current_source(source);
+
+    // check if the source and associated mime type is still valid
+    let valid = with_source_metadata(source, |meta| meta.mime_types.contains(&mime_type)).unwrap_or(false);
+    // TODO:?
+    // && source.as_ref().is_alive();
+    if !valid {
+        // deny the receive
+        StatCounters::add(&primary_selection_state.counters.rejected_requests, 1);
+        debug!(
+            primary_selection_state.log,
+            "Denying a zwp_primary_selection_offer_v1.receive with invalid source."
+        );
+    } else {
+        StatCounters::add(&primary_selection_state.counters.transfers, 1);
+        source.send(mime_type, fd);
+    }
+    let _ = ::nix::unistd::close(fd);
+}
+
+fn same_metadata(a: &PrimarySource, b: &PrimarySource) -> bool {
+    match (
+        with_source_metadata(a, SourceMetadata::clone),
+        with_source_metadata(b, SourceMetadata::clone),
+    ) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+struct ServerSelection {
+    selection: Arc<CompositorSelection>,
+}
+
+impl<D> ObjectData<D> for ServerSelection
+where
+    D: PrimarySelectionHandler,
+{
+    fn request(
+        self: Arc<Self>,
+        dh: &Handle,
+        handler: &mut D,
+        client_id: ClientId,
+        msg: Message<ObjectId>,
+    ) -> Option<Arc<dyn ObjectData<D>>> {
+        let dh = DisplayHandle::from(dh.clone());
+        // selection data offers only care about the `receive` event
+        if let Ok((_resource, primary_offer::Request::Receive { fd, mime_type })) =
+            PrimaryOffer::parse_request(&dh, msg)
+        {
+            if let Some(slot) = accept_transfer(handler.primary_selection_state(), &client_id, fd) {
+                handle_server_selection(handler, &dh, fd, mime_type, slot, &self.selection);
+            }
+        }
+
+        None
+    }
+
+    fn destroyed(&self, _data: &mut D, _client_id: ClientId, _object_id: ObjectId) {}
+}
 
-    // selection data offers only care about the `receive` event
-    if let primary_offer::Request::Receive { fd, mime_type } = request {
-        // check if the source and associated mime type is still valid
-        let valid =
-            with_source_metadata(source, |meta| meta.mime_types.contains(&mime_type)).unwrap_or(false);
-        // TODO:?
-        // && source.as_ref().is_alive();
-        if !valid {
+// The transfer ends once the fd is handed to the handler or the seeded data was written
+fn handle_server_selection<D>(
+    handler: &mut D,
+    dh: &DisplayHandle,
+    fd: RawFd,
+    mime_type: String,
+    slot: TransferSlot,
+    selection: &CompositorSelection,
+) where
+    D: PrimarySelectionHandler,
+{
+    let primary_selection_state = handler.primary_selection_state();
+
+    let cached = selection.cache.lock().unwrap().get(&mime_type).cloned();
+    // check if the associated mime type is valid
+    if !selection.meta.mime_types.contains(&mime_type) {
+        // deny the receive
+        StatCounters::add(&primary_selection_state.counters.rejected_requests, 1);
+        debug!(
+            primary_selection_state.log,
+            "Denying a zwp_primary_selection_offer_v1.receive with invalid source."
+        );
+        let _ = ::nix::unistd::close(fd);
+    } else if let Some(data) = cached {
+        // the file takes ownership of the fd and closes it once dropped
+        let file = unsafe { File::from_raw_fd(fd) };
+        start_transfer(primary_selection_state, file, Box::new(Cursor::new(data)), slot);
+    } else {
+        StatCounters::add(&primary_selection_state.counters.transfers, 1);
+        handler.send_selection(dh, mime_type, fd);
+    }
+}
+
+struct ServerBytesSelection {
+    entries: Weak<Vec<(String, Vec<u8>)>>,
+}
+
+impl<D> ObjectData<D> for ServerBytesSelection
+where
+    D: PrimarySelectionHandler,
+{
+    fn request(
+        self: Arc<Self>,
+        dh: &Handle,
+        handler: &mut D,
+        client_id: ClientId,
+        msg: Message<ObjectId>,
+    ) -> Option<Arc<dyn ObjectData<D>>> {
+        let dh = DisplayHandle::from(dh.clone());
+        // selection data offers only care about the `receive` event
+        if let Ok((_resource, primary_offer::Request::Receive { fd, mime_type })) =
+            PrimaryOffer::parse_request(&dh, msg)
+        {
+            if let Some(slot) = accept_transfer(handler.primary_selection_state(), &client_id, fd) {
+                handle_server_bytes_selection(handler, fd, mime_type, slot, &self.entries);
+            }
+        }
+
+        None
+    }
+
+    fn destroyed(&self, _data: &mut D, _client_id: ClientId, _object_id: ObjectId) {}
+}
+
+// The transfer ends once all data was written, `slot` is moved into the transfer writing it
+fn handle_server_bytes_selection<D>(
+    handler: &mut D,
+    fd: RawFd,
+    mime_type: String,
+    slot: TransferSlot,
+    entries: &Weak<Vec<(String, Vec<u8>)>>,
+) where
+    D: PrimarySelectionHandler,
+{
+    let primary_selection_state = handler.primary_selection_state();
+
+    // the file takes ownership of the fd and closes it once dropped
+    let file = unsafe { File::from_raw_fd(fd) };
+    // check if the selection is still current and the mime type is valid
+    let entry = entries.upgrade().and_then(|entries| {
+        let index = entries.iter().position(|(mime, _)| *mime == mime_type)?;
+        Some(EntryData { entries, index })
+    });
+    match entry {
+        Some(entry) => start_transfer(primary_selection_state, file, Box::new(Cursor::new(entry)), slot),
+        None => {
             // deny the receive
+            StatCounters::add(&primary_selection_state.counters.rejected_requests, 1);
             debug!(
                 primary_selection_state.log,
                 "Denying a zwp_primary_selection_offer_v1.receive with invalid source."
             );
-        } else {
-            source.send(mime_type, fd);
         }
-        let _ = ::nix::unistd::close(fd);
     }
 }
 
-struct ServerSelection {
-    offer_meta: SourceMetadata,
+// Data of an entry of an in-memory selection, kept alive until its transfer ended
+struct EntryData {
+    entries: Arc<Vec<(String, Vec<u8>)>>,
+    index: usize,
 }
 
-impl<D> ObjectData<D> for ServerSelection
+impl AsRef<[u8]> for EntryData {
+    fn as_ref(&self) -> &[u8] {
+        &self.entries[self.index].1
+    }
+}
+
+struct ServerReaderSelection {
+    selection: Weak<ReaderSelection>,
+}
+
+impl<D> ObjectData<D> for ServerReaderSelection
 where
     D: PrimarySelectionHandler,
+    D: 'static,
 {
     fn request(
         self: Arc<Self>,
         dh: &Handle,
         handler: &mut D,
-        _client_id: ClientId,
+        client_id: ClientId,
         msg: Message<ObjectId>,
     ) -> Option<Arc<dyn ObjectData<D>>> {
         let dh = DisplayHandle::from(dh.clone());
-        if let Ok((_resource, request)) = PrimaryOffer::parse_request(&dh, msg) {
-            handle_server_selection(handler, &dh, request, &self.offer_meta);
+        // selection data offers only care about the `receive` event
+        if let Ok((_resource, primary_offer::Request::Receive { fd, mime_type })) =
+            PrimaryOffer::parse_request(&dh, msg)
+        {
+            if let Some(slot) = accept_transfer(handler.primary_selection_state(), &client_id, fd) {
+                handle_server_reader_selection(handler, fd, mime_type, slot, &self.selection);
+            }
         }
 
         None
@@ -253,28 +736,231 @@ where
     fn destroyed(&self, _data: &mut D, _client_id: ClientId, _object_id: ObjectId) {}
 }
 
-pub fn handle_server_selection<D>(
+// The transfer ends once all data was written, `slot` is moved into the event source writing it
+fn handle_server_reader_selection<D>(
     handler: &mut D,
-    dh: &DisplayHandle,
-    request: primary_offer::Request,
-    offer_meta: &SourceMetadata,
+    fd: RawFd,
+    mime_type: String,
+    slot: TransferSlot,
+    selection: &Weak<ReaderSelection>,
 ) where
     D: PrimarySelectionHandler,
+    D: 'static,
 {
     let primary_selection_state = handler.primary_selection_state();
 
-    // selection data offers only care about the `receive` event
-    if let primary_offer::Request::Receive { fd, mime_type } = request {
-        // check if the associated mime type is valid
-        if !offer_meta.mime_types.contains(&mime_type) {
+    // the file takes ownership of the fd and closes it once dropped
+    let file = unsafe { File::from_raw_fd(fd) };
+    // check if the selection is still current and the mime type is valid
+    let selection = match selection
+        .upgrade()
+        .filter(|selection| selection.mime_types.contains(&mime_type))
+    {
+        Some(selection) => selection,
+        None => {
             // deny the receive
+            StatCounters::add(&primary_selection_state.counters.rejected_requests, 1);
             debug!(
                 primary_selection_state.log,
                 "Denying a zwp_primary_selection_offer_v1.receive with invalid source."
             );
-            let _ = ::nix::unistd::close(fd);
-        } else {
-            handler.send_selection(dh, mime_type, fd);
+            return;
+        }
+    };
+    if primary_selection_state.scheduler.is_none() {
+        StatCounters::add(&primary_selection_state.counters.rejected_requests, 1);
+        debug!(
+            primary_selection_state.log,
+            "Denying a zwp_primary_selection_offer_v1.receive, no event loop was set to write the data."
+        );
+        return;
+    }
+    start_transfer(
+        primary_selection_state,
+        file,
+        (selection.factory)(mime_type),
+        slot,
+    );
+}
+
+// Write the data of `reader` into the fd of a `receive` request without blocking on the client
+//
+// As much data as the fd accepts is written right away, the rest is written by the event loop,
+// once the client reads. The transfer is dropped, closing the fd and releasing `slot`, once all data
+// was written, writing failed, e.g. because the client closed its end, or the client did not read
+// for `TRANSFER_TIMEOUT`.
+fn start_transfer(
+    primary_selection_state: &PrimarySelectionState,
+    mut file: File,
+    reader: Box<dyn Read + Send>,
+    slot: TransferSlot,
+) {
+    let log = primary_selection_state.log.clone();
+    if let Err(err) = fcntl(file.as_raw_fd(), FcntlArg::F_SETFL(OFlag::O_NONBLOCK)) {
+        debug!(
+            log,
+            "Failed to make the primary selection fd non-blocking: {}", err
+        );
+        return;
+    }
+
+    StatCounters::add(&primary_selection_state.counters.transfers, 1);
+    let mut transfer = ReaderTransfer {
+        reader,
+        buffer: vec![0; READER_CHUNK_SIZE],
+        filled: 0,
+        written: 0,
+        counters: primary_selection_state.counters.clone(),
+        _slot: slot,
+    };
+    match transfer.write_to(&mut file) {
+        Ok(true) => return,
+        Ok(false) => {}
+        Err(err) => {
+            debug!(log, "Failed to write primary selection data: {}", err);
+            return;
+        }
+    }
+    match primary_selection_state.scheduler.as_ref() {
+        Some(scheduler) => (scheduler.0)(file, transfer),
+        None => debug!(
+            log,
+            "Dropping a primary selection transfer, no event loop was set to write the remaining data."
+        ),
+    }
+}
+
+// Inserts transfers into the event loop set by the compositor, independent of the data of the loop
+pub(super) struct TransferScheduler(Box<dyn Fn(File, ReaderTransfer)>);
+
+impl fmt::Debug for TransferScheduler {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("TransferScheduler").finish()
+    }
+}
+
+impl TransferScheduler {
+    pub(super) fn new<L: 'static>(loop_handle: LoopHandle<'static, L>, log: slog::Logger) -> Self {
+        TransferScheduler(Box::new(move |file, transfer| {
+            schedule_transfer(&loop_handle, file, transfer, &log)
+        }))
+    }
+}
+
+// Inserts idle callbacks into the event loop set by the compositor, independent of the data of the loop
+pub(super) struct IdleScheduler(Box<dyn Fn(Box<dyn FnOnce()>)>);
+
+impl fmt::Debug for IdleScheduler {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("IdleScheduler").finish()
+    }
+}
+
+impl IdleScheduler {
+    pub(super) fn new<L: 'static>(loop_handle: LoopHandle<'static, L>) -> Self {
+        IdleScheduler(Box::new(move |callback| {
+            loop_handle.insert_idle(move |_| callback());
+        }))
+    }
+}
+
+// Write the rest of the data of a transfer from the event loop, once the client reads
+fn schedule_transfer<L: 'static>(
+    loop_handle: &LoopHandle<'static, L>,
+    file: File,
+    mut transfer: ReaderTransfer,
+    log: &slog::Logger,
+) {
+    let progress = Rc::new(Cell::new(Instant::now()));
+    let timer = Rc::new(Cell::new(None));
+    let source = Generic::new(file, Interest::WRITE, Mode::Level);
+    // removing the source closes the fd, signaling the end of the data to the client
+    let result = loop_handle.insert_source(source, {
+        let (handle, progress, timer, log) =
+            (loop_handle.clone(), progress.clone(), timer.clone(), log.clone());
+        move |_, file, _| {
+            let done = transfer.write_to(file).unwrap_or_else(|err| {
+                debug!(log, "Failed to write primary selection data: {}", err);
+                true
+            });
+            if !done {
+                progress.set(Instant::now());
+                return Ok(PostAction::Continue);
+            }
+            if let Some(timer) = timer.take() {
+                handle.remove(timer);
+            }
+            Ok(PostAction::Remove)
+        }
+    });
+    let token = match result {
+        Ok(token) => token,
+        Err(err) => {
+            debug!(
+                log,
+                "Failed to start writing primary selection data: {}", err.error
+            );
+            return;
+        }
+    };
+
+    // drop the transfer, if the client stopped reading
+    let result = loop_handle.insert_source(Timer::from_duration(TRANSFER_TIMEOUT), {
+        let (handle, log) = (loop_handle.clone(), log.clone());
+        move |_, _, _| {
+            let idle = progress.get().elapsed();
+            if idle < TRANSFER_TIMEOUT {
+                return TimeoutAction::ToDuration(TRANSFER_TIMEOUT - idle);
+            }
+            debug!(log, "Dropping a stalled primary selection transfer.");
+            handle.remove(token);
+            TimeoutAction::Drop
+        }
+    });
+    match result {
+        Ok(token) => timer.set(Some(token)),
+        Err(err) => debug!(
+            log,
+            "Failed to watch the primary selection transfer: {}", err.error
+        ),
+    }
+}
+
+// Copy of the data of a reader into the fd of a `receive` request, driven by the event loop
+pub(super) struct ReaderTransfer {
+    reader: Box<dyn Read + Send>,
+    buffer: Vec<u8>,
+    // bytes of `buffer` read from the reader and written to the fd
+    filled: usize,
+    written: usize,
+    counters: Arc<StatCounters>,
+    // released once the transfer ends
+    _slot: TransferSlot,
+}
+
+impl ReaderTransfer {
+    // Write as much data as possible without blocking, returns true once all data was written
+    fn write_to(&mut self, file: &mut File) -> io::Result<bool> {
+        loop {
+            if self.written == self.filled {
+                self.filled = match self.reader.read(&mut self.buffer) {
+                    Ok(0) => return Ok(true),
+                    Ok(len) => len,
+                    Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                    Err(err) => return Err(err),
+                };
+                self.written = 0;
+            }
+            match file.write(&self.buffer[self.written..self.filled]) {
+                Ok(0) => return Err(io::ErrorKind::WriteZero.into()),
+                Ok(len) => {
+                    self.written += len;
+                    StatCounters::add(&self.counters.bytes_transferred, len as u64);
+                }
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => return Ok(false),
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => return Err(err),
+            }
         }
     }
 }
@@ -1,4 +1,6 @@
-use std::sync::Mutex;
+use std::{any::Any, sync::Mutex};
+
+use slog::debug;
 
 use wayland_protocols::wp::primary_selection::zv1::server::zwp_primary_selection_source_v1::{
     self as primary_source, ZwpPrimarySelectionSourceV1 as PrimarySource,
@@ -8,12 +10,15 @@ use wayland_server::{
     Dispatch, DisplayHandle, Resource,
 };
 
-use crate::utils::{alive_tracker::AliveTracker, IsAlive};
+use crate::{
+    utils::{alive_tracker::AliveTracker, IsAlive},
+    wayland::seat::Seat,
+};
 
-use super::{PrimarySelectionHandler, PrimarySelectionState};
+use super::{PrimarySelectionHandler, PrimarySelectionState, StatCounters};
 
 /// The metadata describing a data source
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
 pub struct SourceMetadata {
     /// The MIME types supported by this source
     pub mime_types: Vec<String>,
@@ -23,6 +28,10 @@ pub struct SourceMetadata {
 #[derive(Debug)]
 pub struct PrimarySourceUserData {
     inner: Mutex<SourceMetadata>,
+    // this source and the `Seat<D>` it was last set as selection of, until the source is destroyed
+    seat: Mutex<Option<(PrimarySource, Box<dyn Any + Send>)>>,
+    // identical source, that replaced this one as selection without re-issuing its offers
+    replaced_by: Mutex<Option<PrimarySource>>,
     alive_tracker: AliveTracker,
 }
 
@@ -30,9 +39,41 @@ impl PrimarySourceUserData {
     pub(super) fn new() -> Self {
         Self {
             inner: Default::default(),
+            seat: Default::default(),
+            replaced_by: Default::default(),
             alive_tracker: Default::default(),
         }
     }
+
+    #[cfg(test)]
+    pub(super) fn with_mime_types(mime_types: Vec<String>) -> Self {
+        Self {
+            inner: Mutex::new(SourceMetadata { mime_types }),
+            seat: Default::default(),
+            replaced_by: Default::default(),
+            alive_tracker: Default::default(),
+        }
+    }
+
+    pub(super) fn set_seat<D: 'static>(&self, source: &PrimarySource, seat: &Seat<D>) {
+        *self.seat.lock().unwrap() = Some((source.clone(), Box::new(seat.clone())));
+    }
+
+    pub(super) fn set_replaced_by(&self, source: &PrimarySource) {
+        *self.replaced_by.lock().unwrap() = Some(source.clone());
+    }
+}
+
+// follow the chain of sources, that took over the offers of the given one
+pub(super) fn current_source(source: &PrimarySource) -> PrimarySource {
+    let mut source = source.clone();
+    while let Some(next) = source
+        .data::<PrimarySourceUserData>()
+        .and_then(|data| data.replaced_by.lock().unwrap().clone())
+    {
+        source = next;
+    }
+    source
 }
 
 impl<D> Dispatch<PrimarySource, PrimarySourceUserData, D> for PrimarySelectionState
@@ -50,11 +91,20 @@ where
         _dhandle: &DisplayHandle,
         _data_init: &mut wayland_server::DataInit<'_, D>,
     ) {
-        let _primary_selection_state = state.primary_selection_state();
-        let mut data = data.inner.lock().unwrap();
-
         match request {
             primary_source::Request::Offer { mime_type } => {
+                let primary_selection_state = state.primary_selection_state();
+                let mut data = data.inner.lock().unwrap();
+                if data.mime_types.len() >= primary_selection_state.max_mime_types {
+                    StatCounters::add(&primary_selection_state.counters.rejected_requests, 1);
+                    debug!(
+                        primary_selection_state.log,
+                        "Ignoring mime type {:?} exceeding the maximum of {} mime types per source",
+                        mime_type,
+                        primary_selection_state.max_mime_types
+                    );
+                    return;
+                }
                 data.mime_types.push(mime_type);
             }
             primary_source::Request::Destroy => {}
@@ -62,8 +112,16 @@ where
         }
     }
 
-    fn destroyed(_state: &mut D, _client: ClientId, _resource: ObjectId, data: &PrimarySourceUserData) {
+    fn destroyed(state: &mut D, _client: ClientId, _resource: ObjectId, data: &PrimarySourceUserData) {
         data.alive_tracker.destroy_notify();
+
+        // taking the source out of its own user data also breaks the reference cycle
+        let selection = data.seat.lock().unwrap().take();
+        if let Some((source, seat)) = selection {
+            if let Ok(seat) = seat.downcast::<Seat<D>>() {
+                state.source_destroyed(source, *seat);
+            }
+        }
     }
 }
 
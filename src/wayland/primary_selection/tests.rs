@@ -0,0 +1,1046 @@
+// In-process test harness for the primary selection, see `wayland::test_client`
+
+use std::{
+    cell::RefCell,
+    fs::File,
+    io::{Cursor, ErrorKind, Read, Write},
+    ops::{Deref, DerefMut},
+    os::unix::io::{AsRawFd, FromRawFd, RawFd},
+    sync::Arc,
+    time::Duration,
+};
+
+use calloop::EventLoop;
+use nix::{
+    fcntl::{fcntl, FcntlArg, OFlag},
+    sys::{
+        socket::{sendmsg, ControlMessage, MsgFlags},
+        uio::IoVec,
+    },
+    unistd::{close, pipe},
+};
+use wayland_protocols::wp::primary_selection::zv1::server::zwp_primary_selection_device_v1::ZwpPrimarySelectionDeviceV1 as PrimaryDevice;
+use wayland_server::{Client, Display, DisplayHandle, Resource};
+
+use crate::wayland::{
+    seat::{Seat, SeatHandler, SeatState},
+    test_client::{request, string_request, Event, TestClient},
+};
+
+use super::{
+    device::{set_client_selection, PrimaryDeviceUserData},
+    flush_primary_selection, primary_selection_origin, remove_primary_scope,
+    seat_data::SeatData,
+    seed_primary_selection_data, set_primary_focus, set_primary_scope, set_primary_selection,
+    set_primary_selection_bytes, set_primary_selection_reader,
+    source::PrimarySourceUserData,
+    with_source_metadata, PrimarySelectionHandler, PrimarySelectionState, PrimarySelectionStats,
+    PrimarySource, SelectionOrigin, SerializableSelection,
+};
+
+// opcodes of the events and requests used by the tests
+const DEVICE_DATA_OFFER: u16 = 0;
+const DEVICE_SELECTION: u16 = 1;
+const DEVICE_DESTROY: u16 = 1;
+const OFFER_OFFER: u16 = 0;
+const OFFER_RECEIVE: u16 = 0;
+const SOURCE_OFFER: u16 = 0;
+const SOURCE_SEND: u16 = 0;
+const SOURCE_CANCELLED: u16 = 1;
+const SOURCE_DESTROY: u16 = 1;
+
+struct TestState {
+    seat_state: SeatState<TestState>,
+    primary_selection_state: PrimarySelectionState,
+    destroyed_sources: Vec<PrimarySource>,
+    // mime types requested from the compositor selection
+    sent_selections: Vec<String>,
+}
+
+impl SeatHandler for TestState {
+    fn seat_state(&mut self) -> &mut SeatState<Self> {
+        &mut self.seat_state
+    }
+}
+
+impl PrimarySelectionHandler for TestState {
+    fn primary_selection_state(&self) -> &PrimarySelectionState {
+        &self.primary_selection_state
+    }
+
+    fn source_destroyed(&mut self, source: PrimarySource, _seat: Seat<Self>) {
+        self.destroyed_sources.push(source);
+    }
+
+    fn send_selection(&mut self, _dh: &DisplayHandle, mime_type: String, fd: RawFd) {
+        let mut file = unsafe { File::from_raw_fd(fd) };
+        file.write_all(b"from handler").unwrap();
+        self.sent_selections.push(mime_type);
+    }
+}
+
+crate::delegate_seat!(TestState);
+crate::delegate_primary_selection!(TestState);
+
+// A compositor with a single seat supporting the primary selection
+struct Harness {
+    display: Display<TestState>,
+    state: TestState,
+    seat: Seat<TestState>,
+}
+
+// A client owning a primary selection device
+struct FakeClient {
+    inner: TestClient,
+    device: PrimaryDevice,
+}
+
+impl Deref for FakeClient {
+    type Target = TestClient;
+
+    fn deref(&self) -> &TestClient {
+        &self.inner
+    }
+}
+
+impl DerefMut for FakeClient {
+    fn deref_mut(&mut self) -> &mut TestClient {
+        &mut self.inner
+    }
+}
+
+impl Harness {
+    fn new() -> Self {
+        let display = Display::<TestState>::new().unwrap();
+        let dh = display.handle();
+        let mut state = TestState {
+            seat_state: SeatState::new(),
+            primary_selection_state: PrimarySelectionState::new::<TestState, _>(&dh, None),
+            destroyed_sources: Vec::new(),
+            sent_selections: Vec::new(),
+        };
+        let seat = Seat::new(&dh, "seat-0", None);
+        // as if a client bound the seat
+        state.seat_state.add_seat(&seat);
+        Harness { display, state, seat }
+    }
+
+    fn dh(&self) -> DisplayHandle {
+        self.display.handle()
+    }
+
+    fn new_client(&mut self) -> FakeClient {
+        let inner = TestClient::new(&self.dh());
+        let device = self.new_device(&inner.client);
+        FakeClient { inner, device }
+    }
+
+    // Bind a primary selection device like `get_device` would
+    fn new_device(&mut self, client: &Client) -> PrimaryDevice {
+        let dh = self.dh();
+        let wl_seat = self.seat.create_test_resource(&dh, client);
+        let device = client
+            .create_resource::<PrimaryDevice, _, TestState>(&dh, 1, PrimaryDeviceUserData { wl_seat })
+            .unwrap();
+        self.seat
+            .user_data()
+            .insert_if_missing(|| RefCell::new(SeatData::new()));
+        let seat_data = self.seat.user_data().get::<RefCell<SeatData>>().unwrap();
+        seat_data
+            .borrow_mut()
+            .add_device::<TestState>(&dh, device.clone());
+        device
+    }
+
+    fn new_source(&mut self, client: &FakeClient, mime_types: &[&str]) -> PrimarySource {
+        let data = PrimarySourceUserData::with_mime_types(mime_types.iter().map(|m| m.to_string()).collect());
+        client
+            .client
+            .create_resource::<PrimarySource, _, TestState>(&self.dh(), 1, data)
+            .unwrap()
+    }
+
+    fn set_focus(&mut self, client: Option<&FakeClient>) {
+        set_primary_focus(&self.dh(), &self.seat, client.map(|c| c.client.clone()));
+    }
+
+    // Set the selection like a client with keyboard focus would
+    fn set_selection(&mut self, source: Option<PrimarySource>) {
+        let dh = self.dh();
+        set_client_selection(&mut self.state, &dh, &self.seat, source);
+    }
+
+    fn flush(&mut self) {
+        flush_primary_selection(&self.dh(), &self.seat);
+        self.display.flush_clients().unwrap();
+    }
+
+    fn dispatch(&mut self) {
+        self.display.dispatch_clients(&mut self.state).unwrap();
+    }
+}
+
+impl FakeClient {
+    // Send a request without arguments to the given object
+    fn request(&mut self, harness: &mut Harness, object: u32, opcode: u16) {
+        self.send(&request(object, opcode));
+        harness.dispatch();
+    }
+
+    // Send an offer request for the given source
+    fn offer(&mut self, harness: &mut Harness, source: &PrimarySource, mime_type: &str) {
+        let message = string_request(source.id().protocol_id(), SOURCE_OFFER, mime_type);
+        self.send(&message);
+        harness.dispatch();
+    }
+
+    // Send a receive request for the given offer and return the data written by the compositor
+    fn receive(&mut self, harness: &mut Harness, offer: u32, mime_type: &str) -> Vec<u8> {
+        let mut data = Vec::new();
+        self.request_receive(harness, offer, mime_type)
+            .read_to_end(&mut data)
+            .unwrap();
+        data
+    }
+
+    // Send a receive request for the given offer and return the read end of the pipe
+    fn request_receive(&mut self, harness: &mut Harness, offer: u32, mime_type: &str) -> File {
+        let message = string_request(offer, OFFER_RECEIVE, mime_type);
+
+        let (read_fd, write_fd) = pipe().unwrap();
+        sendmsg(
+            self.stream.as_raw_fd(),
+            &[IoVec::from_slice(&message)],
+            &[ControlMessage::ScmRights(&[write_fd])],
+            MsgFlags::empty(),
+            None,
+        )
+        .unwrap();
+        close(write_fd).unwrap();
+        harness.dispatch();
+
+        unsafe { File::from_raw_fd(read_fd) }
+    }
+}
+
+// Returns the id of the offer last advertised as selection of the given device
+fn selection_offer(events: &[Event], device: &PrimaryDevice) -> Option<u32> {
+    let device = device.id().protocol_id();
+    events
+        .iter()
+        .filter(|event| event.object == device && event.opcode == DEVICE_SELECTION)
+        .last()
+        .map(Event::uint_arg)
+        .filter(|offer| *offer != 0)
+}
+
+// Read a selection written by the event loop until the compositor closes the pipe
+fn read_streamed<L>(event_loop: &mut EventLoop<'static, L>, data: &mut L, mut pipe: File) -> Vec<u8> {
+    fcntl(pipe.as_raw_fd(), FcntlArg::F_SETFL(OFlag::O_NONBLOCK)).unwrap();
+    let mut received = Vec::new();
+    let mut buffer = [0; 4096];
+    loop {
+        event_loop.dispatch(Some(Duration::ZERO), data).unwrap();
+        match pipe.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(len) => received.extend_from_slice(&buffer[..len]),
+            Err(err) if err.kind() == ErrorKind::WouldBlock => {}
+            Err(err) => panic!("failed to read the selection: {}", err),
+        }
+    }
+    received
+}
+
+fn offered_mime_types(events: &[Event], offer: u32) -> Vec<String> {
+    events
+        .iter()
+        .filter(|event| event.object == offer && event.opcode == OFFER_OFFER)
+        .map(Event::string_arg)
+        .collect()
+}
+
+#[test]
+fn compositor_selection_is_sent_on_receive() {
+    let mut harness = Harness::new();
+    let mut client = harness.new_client();
+    harness.set_focus(Some(&client));
+
+    set_primary_selection_bytes(
+        &harness.dh(),
+        &harness.seat,
+        vec![("text/plain".into(), b"smithay".to_vec())],
+    );
+    harness.flush();
+    assert_eq!(
+        primary_selection_origin(&harness.seat),
+        SelectionOrigin::Compositor
+    );
+
+    let events = client.events();
+    let device = client.device.id().protocol_id();
+    let offer = selection_offer(&events, &client.device).expect("no selection advertised");
+    assert!(events.iter().any(|event| event.object == device
+        && event.opcode == DEVICE_DATA_OFFER
+        && event.uint_arg() == offer));
+    assert_eq!(offered_mime_types(&events, offer), vec!["text/plain".to_string()]);
+
+    assert_eq!(client.receive(&mut harness, offer, "text/plain"), b"smithay");
+    assert!(client.receive(&mut harness, offer, "text/html").is_empty());
+}
+
+#[test]
+fn client_selection_is_only_offered_to_focused_client() {
+    let mut harness = Harness::new();
+    let mut owner = harness.new_client();
+    let mut other = harness.new_client();
+
+    harness.set_focus(Some(&owner));
+    let source = harness.new_source(&owner, &["text/plain", "UTF8_STRING"]);
+    harness.set_selection(Some(source));
+    assert_eq!(primary_selection_origin(&harness.seat), SelectionOrigin::Client);
+    harness.set_focus(Some(&other));
+    harness.flush();
+
+    let events = other.events();
+    let offer = selection_offer(&events, &other.device).expect("no selection advertised");
+    assert_eq!(
+        offered_mime_types(&events, offer),
+        vec!["text/plain".to_string(), "UTF8_STRING".to_string()]
+    );
+    assert!(selection_offer(&owner.events(), &owner.device).is_none());
+}
+
+#[test]
+fn replaced_client_selection_is_cancelled() {
+    let mut harness = Harness::new();
+    let mut client = harness.new_client();
+    harness.set_focus(Some(&client));
+    let mut event_loop = EventLoop::<TestState>::try_new().unwrap();
+    harness
+        .state
+        .primary_selection_state
+        .set_loop_handle(event_loop.handle());
+    harness.flush();
+    client.events();
+
+    let first = harness.new_source(&client, &["text/plain"]);
+    let second = harness.new_source(&client, &["text/plain"]);
+    harness.set_selection(Some(first.clone()));
+    harness.set_selection(Some(second.clone()));
+    // the offers are created once the event loop is idle
+    event_loop
+        .dispatch(Some(Duration::ZERO), &mut harness.state)
+        .unwrap();
+    harness.display.flush_clients().unwrap();
+
+    let events = client.events();
+    let cancelled = |source: &PrimarySource| {
+        events
+            .iter()
+            .any(|event| event.object == source.id().protocol_id() && event.opcode == SOURCE_CANCELLED)
+    };
+    assert!(cancelled(&first));
+    assert!(!cancelled(&second));
+    // only the last selection of a dispatch cycle is advertised
+    assert_eq!(
+        events
+            .iter()
+            .filter(
+                |event| event.opcode == DEVICE_SELECTION && event.object == client.device.id().protocol_id()
+            )
+            .count(),
+        1
+    );
+}
+
+#[test]
+fn client_selection_is_advertised_right_away_without_event_loop() {
+    let mut harness = Harness::new();
+    let mut client = harness.new_client();
+    harness.set_focus(Some(&client));
+
+    let source = harness.new_source(&client, &["text/plain"]);
+    harness.set_selection(Some(source));
+    harness.display.flush_clients().unwrap();
+    assert!(selection_offer(&client.events(), &client.device).is_some());
+}
+
+#[test]
+fn identical_client_selection_keeps_offers() {
+    let mut harness = Harness::new();
+    let mut client = harness.new_client();
+    harness.set_focus(Some(&client));
+
+    let first = harness.new_source(&client, &["text/plain"]);
+    harness.set_selection(Some(first.clone()));
+    harness.flush();
+    let offer = selection_offer(&client.events(), &client.device).expect("no selection advertised");
+
+    let second = harness.new_source(&client, &["text/plain"]);
+    harness.set_selection(Some(second.clone()));
+    harness.flush();
+    let events = client.events();
+    // the replaced source is cancelled, but no new offer is sent
+    assert_eq!(selection_offer(&events, &client.device), None);
+    assert!(events
+        .iter()
+        .any(|event| event.object == first.id().protocol_id() && event.opcode == SOURCE_CANCELLED));
+
+    // the existing offer is served by the new source
+    let _pipe = client.request_receive(&mut harness, offer, "text/plain");
+    harness.flush();
+    let sent = |events: &[Event], source: &PrimarySource| {
+        events
+            .iter()
+            .any(|event| event.object == source.id().protocol_id() && event.opcode == SOURCE_SEND)
+    };
+    let events = client.events();
+    assert!(sent(&events, &second));
+    assert!(!sent(&events, &first));
+}
+
+#[test]
+fn unsupported_mime_type_is_not_forwarded_to_client_source() {
+    let mut harness = Harness::new();
+    let mut owner = harness.new_client();
+    let mut other = harness.new_client();
+
+    harness.set_focus(Some(&owner));
+    let source = harness.new_source(&owner, &["text/plain"]);
+    harness.set_selection(Some(source.clone()));
+    harness.set_focus(Some(&other));
+    harness.flush();
+    owner.events();
+
+    let events = other.events();
+    let offer = selection_offer(&events, &other.device).expect("no selection advertised");
+    // the requester sees EOF right away instead of waiting on the source
+    assert!(other.receive(&mut harness, offer, "text/html").is_empty());
+
+    harness.flush();
+    assert!(!owner
+        .events()
+        .iter()
+        .any(|event| event.object == source.id().protocol_id() && event.opcode == SOURCE_SEND));
+}
+
+#[test]
+fn new_device_receives_current_selection() {
+    let mut harness = Harness::new();
+    let mut client = harness.new_client();
+    harness.set_focus(Some(&client));
+    set_primary_selection_bytes(
+        &harness.dh(),
+        &harness.seat,
+        vec![("text/plain".into(), b"smithay".to_vec())],
+    );
+    harness.flush();
+    client.events();
+
+    let device = harness.new_device(&client.client);
+    harness.display.flush_clients().unwrap();
+
+    let events = client.events();
+    let offer = selection_offer(&events, &device).expect("no selection advertised to the new device");
+    assert_eq!(offered_mime_types(&events, offer), vec!["text/plain".to_string()]);
+    assert_eq!(client.receive(&mut harness, offer, "text/plain"), b"smithay");
+}
+
+#[test]
+fn destroyed_selection_source_is_reported() {
+    let mut harness = Harness::new();
+    let mut owner = harness.new_client();
+    harness.set_focus(Some(&owner));
+
+    let unused = harness.new_source(&owner, &["text/plain"]);
+    owner.request(&mut harness, unused.id().protocol_id(), SOURCE_DESTROY);
+    // the source was never set as a selection
+    assert!(harness.state.destroyed_sources.is_empty());
+
+    let source = harness.new_source(&owner, &["text/plain"]);
+    harness.set_selection(Some(source.clone()));
+    harness.set_selection(None);
+    owner.request(&mut harness, source.id().protocol_id(), SOURCE_DESTROY);
+    assert_eq!(harness.state.destroyed_sources, vec![source.clone()]);
+
+    // sources of disconnecting clients are reported as well
+    let other = harness.new_source(&owner, &["text/plain"]);
+    harness.set_selection(Some(other.clone()));
+    drop(owner);
+    // the closed connection is noticed by the first dispatch, its objects are destroyed by the next one
+    harness.dispatch();
+    harness.dispatch();
+    assert_eq!(harness.state.destroyed_sources, vec![source, other]);
+}
+
+#[test]
+fn excess_mime_types_are_ignored() {
+    let mut harness = Harness::new();
+    let mut owner = harness.new_client();
+    harness.state.primary_selection_state.set_max_mime_types(2);
+
+    let source = harness.new_source(&owner, &[]);
+    for mime_type in ["text/plain", "text/html", "image/png"] {
+        owner.offer(&mut harness, &source, mime_type);
+    }
+    let mime_types = with_source_metadata(&source, |meta| meta.mime_types.clone()).unwrap();
+    assert_eq!(
+        mime_types,
+        vec!["text/plain".to_string(), "text/html".to_string()]
+    );
+}
+
+#[test]
+fn every_device_of_a_client_gets_its_own_offer() {
+    let mut harness = Harness::new();
+    let mut client = harness.new_client();
+    let second_device = harness.new_device(&client.client);
+    harness.set_focus(Some(&client));
+
+    set_primary_selection(&harness.dh(), &harness.seat, vec!["text/plain".into()]);
+    harness.flush();
+
+    let events = client.events();
+    let first_offer = selection_offer(&events, &client.device).expect("no selection advertised");
+    let second_offer = selection_offer(&events, &second_device).expect("no selection advertised");
+    assert_ne!(first_offer, second_offer);
+    for (device, offer) in [(&client.device, first_offer), (&second_device, second_offer)] {
+        let device = device.id().protocol_id();
+        assert!(events.iter().any(|event| event.object == device
+            && event.opcode == DEVICE_DATA_OFFER
+            && event.uint_arg() == offer));
+        assert_eq!(offered_mime_types(&events, offer), vec!["text/plain".to_string()]);
+    }
+}
+
+#[test]
+fn compositor_selection_can_be_restored_from_snapshot() {
+    let mut harness = Harness::new();
+    let owner = harness.new_client();
+    harness.set_focus(Some(&owner));
+    let source = harness.new_source(&owner, &["text/plain"]);
+    harness.set_selection(Some(source));
+    let state = &harness.state.primary_selection_state;
+    assert!(state.snapshot_compositor_selection(&harness.seat).is_none());
+
+    let entries = vec![("text/plain".to_string(), b"smithay".to_vec())];
+    set_primary_selection_bytes(&harness.dh(), &harness.seat, entries.clone());
+    let snapshot = harness
+        .state
+        .primary_selection_state
+        .snapshot_compositor_selection(&harness.seat)
+        .expect("no snapshot of the compositor selection");
+    assert_eq!(snapshot, SerializableSelection { entries });
+
+    let mut restored = Harness::new();
+    let mut client = restored.new_client();
+    restored.set_focus(Some(&client));
+    let dh = restored.dh();
+    restored
+        .state
+        .primary_selection_state
+        .restore(&dh, &restored.seat, snapshot);
+    restored.flush();
+
+    let events = client.events();
+    let offer = selection_offer(&events, &client.device).expect("no selection advertised");
+    assert_eq!(client.receive(&mut restored, offer, "text/plain"), b"smithay");
+}
+
+#[test]
+fn restored_selection_is_written_without_blocking() {
+    let mut harness = Harness::new();
+    let mut client = harness.new_client();
+    harness.set_focus(Some(&client));
+    let mut event_loop = EventLoop::<TestState>::try_new().unwrap();
+    harness
+        .state
+        .primary_selection_state
+        .set_loop_handle(event_loop.handle());
+
+    // larger than the pipe buffer, so the transfer has to wait for the client to read
+    let payload = (0..200_000u32).map(|i| i as u8).collect::<Vec<_>>();
+    let snapshot = SerializableSelection {
+        entries: vec![("image/png".into(), payload.clone())],
+    };
+    let dh = harness.dh();
+    harness
+        .state
+        .primary_selection_state
+        .restore(&dh, &harness.seat, snapshot);
+    harness.flush();
+    let offer = selection_offer(&client.events(), &client.device).expect("no selection advertised");
+
+    let pipe = client.request_receive(&mut harness, offer, "image/png");
+    assert_eq!(read_streamed(&mut event_loop, &mut harness.state, pipe), payload);
+}
+
+#[test]
+fn migrated_seat_keeps_selection_and_devices() {
+    let mut harness = Harness::new();
+    let mut client = harness.new_client();
+    harness.set_focus(Some(&client));
+    let entries = vec![("text/plain".to_string(), b"smithay".to_vec())];
+    set_primary_selection_bytes(&harness.dh(), &harness.seat, entries);
+    harness.flush();
+    let first_offer = selection_offer(&client.events(), &client.device).expect("no selection advertised");
+
+    let dh = harness.dh();
+    let new_seat = Seat::new(&dh, "seat-1", None);
+    harness
+        .state
+        .primary_selection_state
+        .migrate_seat(&dh, &harness.seat, &new_seat);
+    let old_seat = std::mem::replace(&mut harness.seat, new_seat);
+    assert_eq!(primary_selection_origin(&old_seat), SelectionOrigin::Empty);
+    assert_eq!(
+        primary_selection_origin(&harness.seat),
+        SelectionOrigin::Compositor
+    );
+    harness.flush();
+
+    let offer = selection_offer(&client.events(), &client.device).expect("selection not offered again");
+    assert_ne!(offer, first_offer);
+    assert_eq!(client.receive(&mut harness, offer, "text/plain"), b"smithay");
+}
+
+#[test]
+fn seat_can_be_migrated_back() {
+    let mut harness = Harness::new();
+    let mut client = harness.new_client();
+    harness.set_focus(Some(&client));
+    let entries = vec![("text/plain".to_string(), b"smithay".to_vec())];
+    set_primary_selection_bytes(&harness.dh(), &harness.seat, entries);
+    harness.flush();
+    client.events();
+
+    let dh = harness.dh();
+    let other_seat = Seat::new(&dh, "seat-1", None);
+    let state = &harness.state.primary_selection_state;
+    state.migrate_seat(&dh, &harness.seat, &other_seat);
+    state.migrate_seat(&dh, &other_seat, &harness.seat);
+    assert_eq!(primary_selection_origin(&other_seat), SelectionOrigin::Empty);
+    assert_eq!(
+        primary_selection_origin(&harness.seat),
+        SelectionOrigin::Compositor
+    );
+    harness.flush();
+
+    let offer = selection_offer(&client.events(), &client.device).expect("selection not offered again");
+    assert_eq!(client.receive(&mut harness, offer, "text/plain"), b"smithay");
+    // requests of the device are forwarded to the seat holding the state, without looping
+    let device = client.device.id().protocol_id();
+    client.request(&mut harness, device, DEVICE_DESTROY);
+}
+
+#[test]
+fn inflight_transfers_are_limited_per_client() {
+    let mut harness = Harness::new();
+    let mut client = harness.new_client();
+    harness.set_focus(Some(&client));
+    harness
+        .state
+        .primary_selection_state
+        .set_max_inflight_transfers(1);
+    let mut event_loop = EventLoop::<TestState>::try_new().unwrap();
+    harness
+        .state
+        .primary_selection_state
+        .set_loop_handle(event_loop.handle());
+
+    // larger than the pipe buffer, so the first transfer stays in flight until the client reads
+    let payload = vec![0x42; 200_000];
+    let data = payload.clone();
+    set_primary_selection_reader(
+        &harness.dh(),
+        &harness.seat,
+        vec!["text/plain".into()],
+        Arc::new(move |_| Box::new(Cursor::new(data.clone()))),
+    );
+    let offer = selection_offer(&client.events(), &client.device).expect("no selection advertised");
+
+    let pending = client.request_receive(&mut harness, offer, "text/plain");
+    // the second transfer is rejected, so the requester sees EOF right away
+    assert!(client.receive(&mut harness, offer, "text/plain").is_empty());
+    assert_eq!(harness.state.primary_selection_state.stats().rejected_requests, 1);
+
+    // finishing the first transfer releases its slot
+    assert_eq!(
+        read_streamed(&mut event_loop, &mut harness.state, pending),
+        payload
+    );
+    let pipe = client.request_receive(&mut harness, offer, "text/plain");
+    assert_eq!(read_streamed(&mut event_loop, &mut harness.state, pipe), payload);
+    assert_eq!(harness.state.primary_selection_state.stats().rejected_requests, 1);
+}
+
+#[test]
+fn finished_transfers_release_their_slot() {
+    let mut harness = Harness::new();
+    let mut owner = harness.new_client();
+    let mut other = harness.new_client();
+    harness
+        .state
+        .primary_selection_state
+        .set_max_inflight_transfers(1);
+
+    harness.set_focus(Some(&other));
+    set_primary_selection(&harness.dh(), &harness.seat, vec!["text/plain".into()]);
+    let offer = selection_offer(&other.events(), &other.device).expect("no selection advertised");
+    for _ in 0..3 {
+        assert_eq!(other.receive(&mut harness, offer, "text/plain"), b"from handler");
+    }
+
+    // transfers from client sources end once the fd was handed to the source
+    harness.set_focus(Some(&owner));
+    let source = harness.new_source(&owner, &["text/plain"]);
+    harness.set_selection(Some(source.clone()));
+    harness.set_focus(Some(&other));
+    harness.flush();
+    owner.events();
+    let offer = selection_offer(&other.events(), &other.device).expect("no selection advertised");
+    for _ in 0..3 {
+        drop(other.request_receive(&mut harness, offer, "text/plain"));
+    }
+    harness.flush();
+    let sends = owner
+        .events()
+        .iter()
+        .filter(|event| event.object == source.id().protocol_id() && event.opcode == SOURCE_SEND)
+        .count();
+    assert_eq!(sends, 3);
+    assert_eq!(harness.state.primary_selection_state.stats().rejected_requests, 0);
+}
+
+#[test]
+fn stats_count_selections_and_transfers() {
+    let mut harness = Harness::new();
+    let mut owner = harness.new_client();
+    let mut other = harness.new_client();
+
+    harness.set_focus(Some(&owner));
+    let source = harness.new_source(&owner, &["text/plain"]);
+    harness.set_selection(Some(source));
+    harness.set_focus(Some(&other));
+    harness.flush();
+
+    let offer = selection_offer(&other.events(), &other.device).expect("no selection advertised");
+    drop(other.request_receive(&mut harness, offer, "text/plain"));
+    assert!(other.receive(&mut harness, offer, "image/png").is_empty());
+
+    set_primary_selection_bytes(
+        &harness.dh(),
+        &harness.seat,
+        vec![("text/plain".into(), b"hello".to_vec())],
+    );
+    harness.flush();
+    let offer = selection_offer(&other.events(), &other.device).expect("no selection advertised");
+    assert_eq!(other.receive(&mut harness, offer, "text/plain"), b"hello");
+
+    assert_eq!(
+        harness.state.primary_selection_state.stats(),
+        PrimarySelectionStats {
+            client_selections: 1,
+            compositor_selections: 1,
+            transfers: 2,
+            bytes_transferred: 5,
+            rejected_requests: 1,
+        }
+    );
+}
+
+#[test]
+fn selection_owner_is_the_client_of_the_source() {
+    let mut harness = Harness::new();
+    let owner = harness.new_client();
+    let other = harness.new_client();
+    let owned_by = |harness: &Harness, client: &FakeClient| {
+        harness
+            .seat
+            .user_data()
+            .get::<RefCell<SeatData>>()
+            .unwrap()
+            .borrow()
+            .selection_owned_by(&client.device.id())
+    };
+
+    harness.set_focus(Some(&owner));
+    let source = harness.new_source(&owner, &["text/plain"]);
+    harness.set_selection(Some(source));
+    assert!(owned_by(&harness, &owner));
+    assert!(!owned_by(&harness, &other));
+
+    set_primary_selection(&harness.dh(), &harness.seat, vec!["text/plain".into()]);
+    assert!(!owned_by(&harness, &owner));
+}
+
+#[test]
+fn seeded_data_is_sent_without_handler() {
+    let mut harness = Harness::new();
+    let mut client = harness.new_client();
+    harness.set_focus(Some(&client));
+
+    set_primary_selection(
+        &harness.dh(),
+        &harness.seat,
+        vec!["text/plain".into(), "text/html".into()],
+    );
+    assert!(seed_primary_selection_data(
+        &harness.seat,
+        "text/plain".into(),
+        b"https://smithay.rs".to_vec()
+    ));
+    assert!(!seed_primary_selection_data(
+        &harness.seat,
+        "image/png".into(),
+        Vec::new()
+    ));
+    let offer = selection_offer(&client.events(), &client.device).expect("no selection advertised");
+
+    for _ in 0..3 {
+        assert_eq!(
+            client.receive(&mut harness, offer, "text/plain"),
+            b"https://smithay.rs"
+        );
+    }
+    assert!(harness.state.sent_selections.is_empty());
+    assert_eq!(client.receive(&mut harness, offer, "text/html"), b"from handler");
+    assert_eq!(harness.state.sent_selections, vec!["text/html".to_string()]);
+
+    // replacing the selection drops the seeded data
+    set_primary_selection(&harness.dh(), &harness.seat, vec!["text/plain".into()]);
+    assert_eq!(client.receive(&mut harness, offer, "text/plain"), b"from handler");
+    let offer = selection_offer(&client.events(), &client.device).expect("no selection advertised");
+    assert_eq!(client.receive(&mut harness, offer, "text/plain"), b"from handler");
+}
+
+#[test]
+fn seeded_data_is_written_without_blocking() {
+    let mut harness = Harness::new();
+    let mut client = harness.new_client();
+    harness.set_focus(Some(&client));
+    let mut event_loop = EventLoop::<TestState>::try_new().unwrap();
+    harness
+        .state
+        .primary_selection_state
+        .set_loop_handle(event_loop.handle());
+
+    // larger than the pipe buffer, so the transfer has to wait for the client to read
+    let payload = (0..200_000u32).map(|i| i as u8).collect::<Vec<_>>();
+    set_primary_selection(&harness.dh(), &harness.seat, vec!["image/png".into()]);
+    assert!(seed_primary_selection_data(
+        &harness.seat,
+        "image/png".into(),
+        payload.clone()
+    ));
+    let offer = selection_offer(&client.events(), &client.device).expect("no selection advertised");
+
+    let pipe = client.request_receive(&mut harness, offer, "image/png");
+    assert_eq!(read_streamed(&mut event_loop, &mut harness.state, pipe), payload);
+    assert!(harness.state.sent_selections.is_empty());
+}
+
+#[test]
+fn reader_selection_is_streamed_by_the_event_loop() {
+    let mut harness = Harness::new();
+    let mut client = harness.new_client();
+    harness.set_focus(Some(&client));
+
+    // larger than the pipe buffer, so the transfer has to wait for the client to read
+    let payload = (0..200_000u32).map(|i| i as u8).collect::<Vec<_>>();
+    let data = payload.clone();
+    set_primary_selection_reader(
+        &harness.dh(),
+        &harness.seat,
+        vec!["image/png".into()],
+        Arc::new(move |_| Box::new(Cursor::new(data.clone()))),
+    );
+    let offer = selection_offer(&client.events(), &client.device).expect("no selection advertised");
+
+    // without an event loop the request is denied
+    assert!(client.receive(&mut harness, offer, "image/png").is_empty());
+    assert_eq!(harness.state.primary_selection_state.stats().rejected_requests, 1);
+
+    let mut event_loop = EventLoop::<TestState>::try_new().unwrap();
+    harness
+        .state
+        .primary_selection_state
+        .set_loop_handle(event_loop.handle());
+
+    let pipe = client.request_receive(&mut harness, offer, "image/png");
+    assert_eq!(read_streamed(&mut event_loop, &mut harness.state, pipe), payload);
+    assert_eq!(
+        harness.state.primary_selection_state.stats().bytes_transferred,
+        payload.len() as u64
+    );
+}
+
+#[test]
+fn bytes_selection_is_written_without_blocking() {
+    let mut harness = Harness::new();
+    let mut client = harness.new_client();
+    harness.set_focus(Some(&client));
+    harness
+        .state
+        .primary_selection_state
+        .set_max_inflight_transfers(1);
+    let mut event_loop = EventLoop::<TestState>::try_new().unwrap();
+    harness
+        .state
+        .primary_selection_state
+        .set_loop_handle(event_loop.handle());
+
+    // larger than the pipe buffer, so the transfer has to wait for the client to read
+    let payload = (0..200_000u32).map(|i| i as u8).collect::<Vec<_>>();
+    set_primary_selection_bytes(
+        &harness.dh(),
+        &harness.seat,
+        vec![("image/png".into(), payload.clone())],
+    );
+    let offer = selection_offer(&client.events(), &client.device).expect("no selection advertised");
+
+    let pipe = client.request_receive(&mut harness, offer, "image/png");
+    assert_eq!(read_streamed(&mut event_loop, &mut harness.state, pipe), payload);
+
+    // a client closing its end drops the transfer and releases its slot
+    drop(client.request_receive(&mut harness, offer, "image/png"));
+    event_loop
+        .dispatch(Some(Duration::ZERO), &mut harness.state)
+        .unwrap();
+    let pipe = client.request_receive(&mut harness, offer, "image/png");
+    assert_eq!(read_streamed(&mut event_loop, &mut harness.state, pipe), payload);
+    assert_eq!(harness.state.primary_selection_state.stats().rejected_requests, 0);
+}
+
+#[test]
+fn loop_data_may_differ_from_the_handler() {
+    let mut harness = Harness::new();
+    let mut client = harness.new_client();
+    harness.set_focus(Some(&client));
+
+    // compositors usually wrap their state in the data of the event loop
+    struct LoopData;
+    let mut event_loop = EventLoop::<LoopData>::try_new().unwrap();
+    harness
+        .state
+        .primary_selection_state
+        .set_loop_handle(event_loop.handle());
+
+    let payload = vec![0x42; 200_000];
+    let data = payload.clone();
+    set_primary_selection_reader(
+        &harness.dh(),
+        &harness.seat,
+        vec!["text/plain".into()],
+        Arc::new(move |_| Box::new(Cursor::new(data.clone()))),
+    );
+    let offer = selection_offer(&client.events(), &client.device).expect("no selection advertised");
+
+    let pipe = client.request_receive(&mut harness, offer, "text/plain");
+    assert_eq!(read_streamed(&mut event_loop, &mut LoopData, pipe), payload);
+    assert_eq!(harness.state.primary_selection_state.stats().rejected_requests, 0);
+}
+
+#[test]
+fn cleanup_client_clears_its_selection() {
+    let mut harness = Harness::new();
+    let mut owner = harness.new_client();
+    let other = harness.new_client();
+
+    harness.set_focus(Some(&owner));
+    let source = harness.new_source(&owner, &["text/plain"]);
+    harness.set_selection(Some(source.clone()));
+    harness.flush();
+    owner.events();
+
+    // other clients keep the selection of the owner
+    let dh = harness.dh();
+    harness.state.seat_state.cleanup_client(&dh, &other.client);
+    assert_eq!(primary_selection_origin(&harness.seat), SelectionOrigin::Client);
+
+    harness.state.seat_state.cleanup_client(&dh, &owner.client);
+    assert_eq!(primary_selection_origin(&harness.seat), SelectionOrigin::Empty);
+    harness.display.flush_clients().unwrap();
+    let source = source.id().protocol_id();
+    assert!(owner
+        .events()
+        .iter()
+        .any(|event| event.object == source && event.opcode == SOURCE_CANCELLED));
+}
+
+#[test]
+fn switching_scope_reissues_offers() {
+    let mut harness = Harness::new();
+    let mut client = harness.new_client();
+    harness.set_focus(Some(&client));
+
+    let plain = harness.new_source(&client, &["text/plain"]);
+    harness.set_selection(Some(plain));
+    harness.flush();
+    let first_offer = selection_offer(&client.events(), &client.device).expect("no selection advertised");
+
+    // the new scope starts out empty
+    set_primary_scope(&harness.dh(), &harness.seat, 1);
+    harness.flush();
+    assert_eq!(selection_offer(&client.events(), &client.device), None);
+    let html = harness.new_source(&client, &["text/html"]);
+    harness.set_selection(Some(html));
+    harness.flush();
+    let events = client.events();
+    let offer = selection_offer(&events, &client.device).expect("no selection advertised");
+    assert_eq!(offered_mime_types(&events, offer), vec!["text/html"]);
+
+    // the selection of the previous scope is offered again
+    set_primary_scope(&harness.dh(), &harness.seat, 0);
+    harness.flush();
+    let events = client.events();
+    let offer = selection_offer(&events, &client.device).expect("no selection advertised");
+    assert_ne!(offer, first_offer);
+    assert_eq!(offered_mime_types(&events, offer), vec!["text/plain"]);
+}
+
+#[test]
+fn removing_the_active_scope_hides_its_offers() {
+    let mut harness = Harness::new();
+    let mut client = harness.new_client();
+    harness.set_focus(Some(&client));
+    set_primary_scope(&harness.dh(), &harness.seat, 1);
+
+    let source = harness.new_source(&client, &["text/plain"]);
+    harness.set_selection(Some(source.clone()));
+    harness.flush();
+    assert!(selection_offer(&client.events(), &client.device).is_some());
+
+    remove_primary_scope(&harness.dh(), &harness.seat, 1);
+    harness.flush();
+    let events = client.events();
+    let device = client.device.id().protocol_id();
+    assert!(events
+        .iter()
+        .any(|event| event.object == device && event.opcode == DEVICE_SELECTION));
+    assert_eq!(selection_offer(&events, &client.device), None);
+    let source = source.id().protocol_id();
+    assert!(events
+        .iter()
+        .any(|event| event.object == source && event.opcode == SOURCE_CANCELLED));
+    assert_eq!(primary_selection_origin(&harness.seat), SelectionOrigin::Empty);
+}
+
+#[test]
+fn removing_an_inactive_scope_drops_its_selection() {
+    let mut harness = Harness::new();
+    let mut client = harness.new_client();
+    harness.set_focus(Some(&client));
+
+    let source = harness.new_source(&client, &["text/plain"]);
+    harness.set_selection(Some(source));
+    harness.flush();
+    set_primary_scope(&harness.dh(), &harness.seat, 1);
+    remove_primary_scope(&harness.dh(), &harness.seat, 0);
+    harness.flush();
+    client.events();
+
+    set_primary_scope(&harness.dh(), &harness.seat, 0);
+    harness.flush();
+    assert_eq!(selection_offer(&client.events(), &client.device), None);
+}
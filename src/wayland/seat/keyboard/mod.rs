@@ -1,12 +1,17 @@
 use crate::backend::input::KeyState;
 use crate::utils::IsAlive;
-use crate::wayland::Serial;
+use crate::wayland::{
+    compositor::{self, SurfaceData},
+    seat::{InputActivity, KeyEvent, SharedModifiers},
+    Serial,
+};
 use slog::{debug, error, info, o, trace, warn};
 use std::{
+    cell::RefCell,
     default::Default,
     ffi::CString,
     fmt, io,
-    sync::{Arc, Mutex},
+    sync::{Arc, Mutex, Weak},
 };
 use thiserror::Error;
 use wayland_server::{
@@ -43,12 +48,15 @@ struct KbdInternal {
     pending_focus: Option<WlSurface>,
     pressed_keys: Vec<u32>,
     mods_state: ModifiersState,
+    mods_override: Option<ModifiersState>,
     keymap: xkb::Keymap,
     state: xkb::State,
     repeat_rate: i32,
     repeat_delay: i32,
     focus_hook: Box<dyn FnMut(Option<&WlSurface>)>,
     grab: GrabStatus,
+    // the keyboard itself, to be notified when the focused surface is destroyed
+    me: Weak<KbdRc>,
 }
 
 // focus_hook does not implement debug, so we have to impl Debug manually
@@ -59,6 +67,7 @@ impl fmt::Debug for KbdInternal {
             .field("focus", &self.focus)
             .field("pressed_keys", &self.pressed_keys)
             .field("mods_state", &self.mods_state)
+            .field("mods_override", &self.mods_override)
             .field("keymap", &self.keymap.get_raw_ptr())
             .field("state", &self.state.get_raw_ptr())
             .field("repeat_rate", &self.repeat_rate)
@@ -103,12 +112,14 @@ impl KbdInternal {
             pending_focus: None,
             pressed_keys: Vec::new(),
             mods_state: ModifiersState::default(),
+            mods_override: None,
             keymap,
             state,
             repeat_rate,
             repeat_delay,
             focus_hook,
             grab: GrabStatus::None,
+            me: Weak::new(),
         })
     }
 
@@ -140,6 +151,10 @@ impl KbdInternal {
     }
 
     fn serialize_modifiers(&self) -> (u32, u32, u32, u32) {
+        if let Some(mods) = self.mods_override {
+            return self.serialize_override(&mods);
+        }
+
         let mods_depressed = self.state.serialize_mods(xkb::STATE_MODS_DEPRESSED);
         let mods_latched = self.state.serialize_mods(xkb::STATE_MODS_LATCHED);
         let mods_locked = self.state.serialize_mods(xkb::STATE_MODS_LOCKED);
@@ -148,6 +163,26 @@ impl KbdInternal {
         (mods_depressed, mods_latched, mods_locked, layout_locked)
     }
 
+    // serialize the given modifiers in place of the real ones, keeping the active layout
+    fn serialize_override(&self, mods: &ModifiersState) -> (u32, u32, u32, u32) {
+        let mask = |active: bool, name: &str| {
+            let index = self.keymap.mod_get_index(name);
+            if active && index != xkb::MOD_INVALID {
+                1 << index
+            } else {
+                0
+            }
+        };
+        let mods_depressed = mask(mods.ctrl, xkb::MOD_NAME_CTRL)
+            | mask(mods.alt, xkb::MOD_NAME_ALT)
+            | mask(mods.shift, xkb::MOD_NAME_SHIFT)
+            | mask(mods.logo, xkb::MOD_NAME_LOGO);
+        let mods_locked = mask(mods.caps_lock, xkb::MOD_NAME_CAPS) | mask(mods.num_lock, xkb::MOD_NAME_NUM);
+        let layout_locked = self.state.serialize_layout(xkb::STATE_LAYOUT_LOCKED);
+
+        (mods_depressed, 0, mods_locked, layout_locked)
+    }
+
     fn serialize_pressed_keys(&self) -> Vec<u8> {
         let serialized = unsafe {
             ::std::slice::from_raw_parts(
@@ -158,6 +193,13 @@ impl KbdInternal {
         serialized.into()
     }
 
+    fn send_modifiers(&self, serial: Serial) {
+        let (dep, la, lo, gr) = self.serialize_modifiers();
+        self.with_focused_kbds(|kbd, _| {
+            kbd.modifiers(serial.into(), dep, la, lo, gr);
+        });
+    }
+
     fn with_focused_kbds<F>(&self, mut f: F)
     where
         F: FnMut(&WlKeyboard, &WlSurface),
@@ -174,6 +216,37 @@ impl KbdInternal {
         }
     }
 
+    // get notified once the newly focused surface is destroyed
+    fn track_focus(&self, surface: &WlSurface) {
+        if !surface.alive() {
+            return;
+        }
+        let inserted = compositor::with_states(surface, |states| {
+            let inserted = states.data_map.insert_if_missing(FocusedKeyboards::default);
+            let mut keyboards = states.data_map.get::<FocusedKeyboards>().unwrap().0.borrow_mut();
+            keyboards.retain(|kbd| kbd.strong_count() > 0);
+            if !keyboards.iter().any(|kbd| kbd.ptr_eq(&self.me)) {
+                keyboards.push(self.me.clone());
+            }
+            inserted
+        });
+        if inserted {
+            compositor::add_destruction_hook(surface, clear_destroyed_focus);
+        }
+    }
+
+    // drop the focus, if the focused surface was destroyed
+    fn clear_destroyed_focus(&mut self) {
+        if matches!(self.pending_focus, Some(ref surface) if !surface.alive()) {
+            self.pending_focus = None;
+        }
+        if matches!(self.focus, Some((ref surface, _)) if !surface.alive()) {
+            // there is nobody left to send a leave event to
+            self.focus = None;
+            (self.focus_hook)(None);
+        }
+    }
+
     fn with_grab<F>(&mut self, f: F, logger: ::slog::Logger)
     where
         F: FnOnce(KeyboardInnerHandle<'_>, &mut dyn KeyboardGrab),
@@ -204,6 +277,21 @@ impl KbdInternal {
     }
 }
 
+// keyboards focusing a surface, stored in its data map
+#[derive(Debug, Default)]
+struct FocusedKeyboards(RefCell<Vec<Weak<KbdRc>>>);
+
+// destruction hook of focused surfaces
+fn clear_destroyed_focus(states: &SurfaceData) {
+    let keyboards = match states.data_map.get::<FocusedKeyboards>() {
+        Some(keyboards) => keyboards.0.take(),
+        None => return,
+    };
+    for kbd in keyboards.iter().filter_map(Weak::upgrade) {
+        kbd.internal.lock().unwrap().clear_destroyed_focus();
+    }
+}
+
 /// Errors that can be encountered when creating a keyboard handler
 #[derive(Debug, Error)]
 pub enum Error {
@@ -218,6 +306,8 @@ pub enum Error {
 #[derive(Debug)]
 struct KbdRc {
     internal: Mutex<KbdInternal>,
+    activity: Arc<InputActivity>,
+    modifiers: Arc<SharedModifiers>,
     keymap: KeymapFile,
     logger: ::slog::Logger,
 }
@@ -321,6 +411,92 @@ pub trait KeyboardGrab {
     fn start_data(&self) -> &GrabStartData;
 }
 
+/// Handler of a [`CompositorKeyboardGrab`]
+///
+/// Receives all key input while the grab is active, without any of it reaching clients.
+/// Call [`KeyboardInnerHandle::unset_grab`] with `restore_focus` to end the grab from within the handler.
+pub trait CompositorKeyboardHandler {
+    /// A key was pressed or released
+    fn input(&mut self, dh: &DisplayHandle, handle: &mut KeyboardInnerHandle<'_>, event: &KeyEvent);
+}
+
+/// A keyboard grab delivering all key input to the compositor instead of any client
+///
+/// This is useful for user interfaces drawn by the compositor itself, like an overview mode.
+/// No surface has keyboard focus while the grab is active, all keys are passed to the
+/// [`CompositorKeyboardHandler`] instead. Focus changes requested in the meantime are applied,
+/// once the grab ends.
+///
+/// Use [`KeyboardHandle::set_compositor_grab`] to start such a grab.
+#[derive(Debug)]
+pub struct CompositorKeyboardGrab<H> {
+    handler: H,
+    start_data: GrabStartData,
+}
+
+impl<H> CompositorKeyboardGrab<H> {
+    /// Create a new grab passing all key input to the given handler
+    pub fn new(handler: H) -> Self {
+        CompositorKeyboardGrab {
+            handler,
+            start_data: GrabStartData { focus: None },
+        }
+    }
+
+    /// Returns a reference to the handler of this grab
+    pub fn handler(&self) -> &H {
+        &self.handler
+    }
+
+    /// Returns a mutable reference to the handler of this grab
+    pub fn handler_mut(&mut self) -> &mut H {
+        &mut self.handler
+    }
+}
+
+impl<H: CompositorKeyboardHandler> KeyboardGrab for CompositorKeyboardGrab<H> {
+    fn input(
+        &mut self,
+        dh: &DisplayHandle,
+        handle: &mut KeyboardInnerHandle<'_>,
+        keycode: u32,
+        key_state: WlKeyState,
+        _modifiers: Option<(u32, u32, u32, u32)>,
+        serial: Serial,
+        time: u32,
+    ) {
+        let event = KeyEvent {
+            keycode,
+            // Offset the keycode by 8, as the evdev XKB rules reflect X's
+            // broken keycode system, which starts at 8.
+            keysym: handle.inner.state.key_get_one_sym(keycode + 8),
+            state: if key_state == WlKeyState::Pressed {
+                KeyState::Pressed
+            } else {
+                KeyState::Released
+            },
+            modifiers: handle.inner.mods_state,
+            serial,
+            time,
+        };
+        self.handler.input(dh, handle, &event);
+    }
+
+    fn set_focus(
+        &mut self,
+        _dh: &DisplayHandle,
+        _handle: &mut KeyboardInnerHandle<'_>,
+        _focus: Option<&WlSurface>,
+        _serial: Serial,
+    ) {
+        // the requested focus is remembered and restored, once the grab ends
+    }
+
+    fn start_data(&self) -> &GrabStartData {
+        &self.start_data
+    }
+}
+
 /// An handle to a keyboard handler
 ///
 /// It can be cloned and all clones manipulate the same internal state.
@@ -344,6 +520,8 @@ impl KeyboardHandle {
         repeat_delay: i32,
         repeat_rate: i32,
         cb: F,
+        activity: Arc<InputActivity>,
+        modifiers: Arc<SharedModifiers>,
         logger: &::slog::Logger,
     ) -> Result<Self, Error>
     where
@@ -365,13 +543,17 @@ impl KeyboardHandle {
         let keymap = internal.keymap.get_as_string(xkb::KEYMAP_FORMAT_TEXT_V1);
         let keymap = CString::new(keymap).expect("Keymap should not contain interior nul bytes");
 
-        Ok(Self {
-            arc: Arc::new(KbdRc {
-                internal: Mutex::new(internal),
-                keymap: KeymapFile::new(keymap, log.clone()),
-                logger: log,
-            }),
-        })
+        // a new keyboard starts without any modifiers held
+        modifiers.set(internal.mods_state);
+        let arc = Arc::new(KbdRc {
+            internal: Mutex::new(internal),
+            activity,
+            modifiers,
+            keymap: KeymapFile::new(keymap, log.clone()),
+            logger: log,
+        });
+        arc.internal.lock().unwrap().me = Arc::downgrade(&arc);
+        Ok(Self { arc })
     }
 
     /// Change the current grab on this keyboard to the provided grab
@@ -386,6 +568,37 @@ impl KeyboardHandle {
         self.arc.internal.lock().unwrap().grab = GrabStatus::None;
     }
 
+    /// Start a grab passing all key input to the given compositor handler
+    ///
+    /// The current focus is cleared and no client receives key events until the grab ends
+    /// through [`KeyboardHandle::unset_compositor_grab`]. See [`CompositorKeyboardGrab`] for details.
+    ///
+    /// Overwrites any current grab.
+    pub fn set_compositor_grab<H>(&self, handler: H, serial: Serial)
+    where
+        H: CompositorKeyboardHandler + 'static,
+    {
+        let mut guard = self.arc.internal.lock().unwrap();
+        guard.grab = GrabStatus::Active(serial, Box::new(CompositorKeyboardGrab::new(handler)));
+        KeyboardInnerHandle {
+            inner: &mut guard,
+            logger: self.arc.logger.clone(),
+        }
+        .set_focus(None, serial);
+    }
+
+    /// Remove any current grab on this keyboard and restore the focus
+    ///
+    /// The keyboard is focused on the surface last passed to [`KeyboardHandle::set_focus`].
+    pub fn unset_compositor_grab(&self, serial: Serial) {
+        let mut guard = self.arc.internal.lock().unwrap();
+        KeyboardInnerHandle {
+            inner: &mut guard,
+            logger: self.arc.logger.clone(),
+        }
+        .unset_grab(serial, true);
+    }
+
     /// Check if this keyboard is currently grabbed with this serial
     pub fn has_grab(&self, serial: Serial) -> bool {
         let guard = self.arc.internal.lock().unwrap();
@@ -401,6 +614,15 @@ impl KeyboardHandle {
         !matches!(guard.grab, GrabStatus::None)
     }
 
+    /// Returns the serial the current grab was started with, if any.
+    pub fn current_grab_serial(&self) -> Option<Serial> {
+        let guard = self.arc.internal.lock().unwrap();
+        match guard.grab {
+            GrabStatus::Active(serial, _) => Some(serial),
+            _ => None,
+        }
+    }
+
     /// Returns the start data for the grab, if any.
     pub fn grab_start_data(&self) -> Option<GrabStartData> {
         let guard = self.arc.internal.lock().unwrap();
@@ -436,8 +658,12 @@ impl KeyboardHandle {
         F: FnOnce(&ModifiersState, KeysymHandle<'_>) -> FilterResult<T>,
     {
         trace!(self.arc.logger, "Handling keystroke"; "keycode" => keycode, "state" => format_args!("{:?}", state));
+        self.arc.activity.notify();
         let mut guard = self.arc.internal.lock().unwrap();
         let mods_changed = guard.key_input(keycode, state);
+        if mods_changed {
+            self.arc.modifiers.set(guard.mods_state);
+        }
         let handle = KeysymHandle {
             // Offset the keycode by 8, as the evdev XKB rules reflect X's
             // broken keycode system, which starts at 8.
@@ -457,7 +683,8 @@ impl KeyboardHandle {
         }
 
         // forward to client if no keybinding is triggered
-        let modifiers = if mods_changed {
+        // while overridden, changes of the real modifiers are not visible to clients
+        let modifiers = if mods_changed && guard.mods_override.is_none() {
             Some(guard.serialize_modifiers())
         } else {
             None
@@ -487,6 +714,9 @@ impl KeyboardHandle {
     /// will be sent a [`wl_keyboard::Event::Leave`](wayland_server::protocol::wl_keyboard::Event::Leave)
     /// event, and if the new focus is not `None`,
     /// a [`wl_keyboard::Event::Enter`](wayland_server::protocol::wl_keyboard::Event::Enter) event will be sent.
+    ///
+    /// Once the focused surface is destroyed, the focus is cleared and the focus hook
+    /// is called with `None`.
     pub fn set_focus(&self, dh: &DisplayHandle, focus: Option<&WlSurface>, serial: Serial) {
         let mut guard = self.arc.internal.lock().unwrap();
         guard.pending_focus = focus.cloned();
@@ -528,6 +758,55 @@ impl KeyboardHandle {
         self.arc.internal.lock().unwrap().focus.is_some()
     }
 
+    /// Query the keysyms a key would produce under the current keymap state
+    ///
+    /// The `keycode` is expected in the same format as for [`KeyboardHandle::input`].
+    /// If `include_modifiers` is true, the currently active modifiers are applied,
+    /// otherwise the syms of the first level of the active layout are returned.
+    ///
+    /// This does not alter the keymap state and can be used e.g. to display keybindings
+    /// matching the active layout.
+    pub fn keysym_for(&self, keycode: u32, include_modifiers: bool) -> Vec<Keysym> {
+        let guard = self.arc.internal.lock().unwrap();
+        // Offset the keycode by 8, as the evdev XKB rules reflect X's
+        // broken keycode system, which starts at 8.
+        let handle = KeysymHandle {
+            keycode: keycode + 8,
+            state: &guard.state,
+            keymap: &guard.keymap,
+        };
+        if include_modifiers {
+            handle.modified_syms().to_vec()
+        } else {
+            handle.raw_syms().to_vec()
+        }
+    }
+
+    /// Remove the focus and grab of this keyboard from the surfaces of the given client
+    pub(crate) fn cleanup_client(&self, client: &ClientId, serial: Serial) {
+        let owned_by_client = |surface: &WlSurface| surface.client_id().as_ref() == Some(client);
+        let mut guard = self.arc.internal.lock().unwrap();
+        if matches!(guard.pending_focus, Some(ref surface) if owned_by_client(surface)) {
+            guard.pending_focus = None;
+        }
+        if matches!(guard.grab, GrabStatus::Active(_, ref grab)
+            if matches!(grab.start_data().focus, Some(ref surface) if owned_by_client(surface)))
+        {
+            KeyboardInnerHandle {
+                inner: &mut guard,
+                logger: self.arc.logger.clone(),
+            }
+            .unset_grab(serial, true);
+        }
+        if matches!(guard.focus, Some((ref surface, _)) if owned_by_client(surface)) {
+            KeyboardInnerHandle {
+                inner: &mut guard,
+                logger: self.arc.logger.clone(),
+            }
+            .set_focus(None, serial);
+        }
+    }
+
     /// Register a new keyboard to this handler
     ///
     /// The keymap will automatically be sent to it
@@ -565,15 +844,51 @@ impl KeyboardHandle {
         guard.known_kbds.push(kbd);
     }
 
+    /// Override the modifiers sent to clients
+    ///
+    /// The focused client immediately receives the given modifiers and all clients receive them
+    /// instead of the real modifiers, when gaining focus, until the override is
+    /// [cleared](KeyboardHandle::clear_modifier_override). This can be used to hide modifiers
+    /// of a chord consumed by the compositor, so they do not get stuck when focus changes.
+    ///
+    /// The modifiers tracked by the keyboard, as passed to the filter of [`KeyboardHandle::input`],
+    /// are not affected.
+    pub fn send_modifier_override(&self, mods: ModifiersState, serial: Serial) {
+        let mut guard = self.arc.internal.lock().unwrap();
+        guard.mods_override = Some(mods);
+        guard.send_modifiers(serial);
+    }
+
+    /// Clear a modifier override set with [`KeyboardHandle::send_modifier_override`]
+    ///
+    /// The real modifiers are sent to the focused client again.
+    pub fn clear_modifier_override(&self, serial: Serial) {
+        let mut guard = self.arc.internal.lock().unwrap();
+        if guard.mods_override.take().is_some() {
+            guard.send_modifiers(serial);
+        }
+    }
+
     /// Change the repeat info configured for this keyboard
+    ///
+    /// The new repeat rate and delay are sent to all bound keyboards supporting
+    /// `wl_keyboard.repeat_info` (version 4 and newer), keyboards bound later receive them on bind.
     pub fn change_repeat_info(&self, rate: i32, delay: i32) {
         let mut guard = self.arc.internal.lock().unwrap();
         guard.repeat_delay = delay;
         guard.repeat_rate = rate;
         for kbd in &guard.known_kbds {
-            kbd.repeat_info(rate, delay);
+            if kbd.version() >= 4 {
+                kbd.repeat_info(rate, delay);
+            }
         }
     }
+
+    /// Returns the repeat rate and delay configured for this keyboard
+    pub fn repeat_info(&self) -> (i32, i32) {
+        let guard = self.arc.internal.lock().unwrap();
+        (guard.repeat_rate, guard.repeat_delay)
+    }
 }
 
 /// User data for keyboard
@@ -685,6 +1000,9 @@ impl<'a> KeyboardInnerHandle<'a> {
             });
 
             // set new focus
+            if let Some(surface) = focus {
+                self.inner.track_focus(surface);
+            }
             self.inner.focus = focus.cloned().map(|f| (f, serial));
             let (dep, la, lo, gr) = self.inner.serialize_modifiers();
             let keys = self.inner.serialize_pressed_keys();
@@ -743,3 +1061,74 @@ impl KeyboardGrab for DefaultGrab {
         unreachable!()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use wayland_server::{protocol::wl_keyboard::WlKeyboard, Client, Display, DisplayHandle, Resource};
+
+    use crate::wayland::{
+        seat::Seat,
+        test_client::{TestClient, TestState},
+    };
+
+    use super::{KeyboardHandle, KeyboardUserData, XkbConfig};
+
+    // opcode of `wl_keyboard.repeat_info`
+    const KEYBOARD_REPEAT_INFO: u16 = 5;
+
+    // Bind a keyboard like `wl_seat.get_keyboard` would
+    fn bind_keyboard(
+        dh: &DisplayHandle,
+        client: &Client,
+        handle: &KeyboardHandle,
+        version: u32,
+    ) -> WlKeyboard {
+        let kbd = client
+            .create_resource::<WlKeyboard, _, TestState>(
+                dh,
+                version,
+                KeyboardUserData {
+                    handle: Some(handle.clone()),
+                },
+            )
+            .unwrap();
+        handle.new_kbd(kbd.clone());
+        kbd
+    }
+
+    // Returns the rate and delay of the `repeat_info` events sent to the given keyboard so far
+    fn repeat_infos(client: &mut TestClient, kbd: &WlKeyboard) -> Vec<(i32, i32)> {
+        client
+            .events()
+            .into_iter()
+            .filter(|event| event.object == kbd.id().protocol_id() && event.opcode == KEYBOARD_REPEAT_INFO)
+            .map(|event| (event.int_arg(0), event.int_arg(1)))
+            .collect()
+    }
+
+    #[test]
+    fn bound_keyboard_receives_repeat_info() {
+        let mut display = Display::<TestState>::new().unwrap();
+        let dh = display.handle();
+        let mut seat = Seat::<TestState>::new(&dh, "seat-0", None);
+        let keyboard = seat
+            .add_keyboard(XkbConfig::default(), 200, 25, |_, _| {})
+            .unwrap();
+
+        let mut client = TestClient::new(&dh);
+
+        let kbd = bind_keyboard(&dh, &client.client, &keyboard, 4);
+        display.flush_clients().unwrap();
+        assert_eq!(repeat_infos(&mut client, &kbd), vec![(25, 200)]);
+
+        keyboard.change_repeat_info(40, 300);
+        display.flush_clients().unwrap();
+        assert_eq!(repeat_infos(&mut client, &kbd), vec![(40, 300)]);
+
+        // the event was only added in version 4
+        let kbd = bind_keyboard(&dh, &client.client, &keyboard, 3);
+        keyboard.change_repeat_info(25, 200);
+        display.flush_clients().unwrap();
+        assert!(repeat_infos(&mut client, &kbd).is_empty());
+    }
+}
@@ -48,29 +48,44 @@
 //!
 //! This module further defines the `"cursor_image"` role, that is assigned to surfaces used by clients
 //! to change the cursor icon.
+//!
+//! Once a client is gone or about to be killed, [`SeatState::cleanup_client`] removes every
+//! focus, grab and selection it holds in the seats.
+//!
+//! The seat keeps track of the time of the last input event sent through any of its handles
+//! (see [`Seat::last_input_time`]), which can be used to implement idle notifications or
+//! to blank outputs after a period of inactivity.
 
 mod keyboard;
 mod pointer;
+#[cfg(test)]
+mod tests;
 mod touch;
 
 use std::{
-    marker::PhantomData,
-    sync::{Arc, Mutex},
+    cell::RefCell,
+    sync::{Arc, Mutex, Weak},
+    time::{Duration, Instant},
 };
 
-use crate::utils::user_data::UserDataMap;
+use crate::{
+    utils::user_data::UserDataMap,
+    wayland::{Serial, SERIAL_COUNTER},
+};
 
 // TODO: Just make the keyboard, pointer and touch modules public.
+#[cfg(feature = "xcursor")]
+pub use self::pointer::{load_xcursor_theme, XCursorError, XCursorImage, XCursorTheme};
 pub use self::{
     keyboard::{
-        keysyms, Error as KeyboardError, FilterResult, GrabStartData as KeyboardGrabStartData, KeyboardGrab,
-        KeyboardHandle, KeyboardInnerHandle, KeyboardUserData, Keysym, KeysymHandle, ModifiersState,
-        XkbConfig,
+        keysyms, CompositorKeyboardGrab, CompositorKeyboardHandler, Error as KeyboardError, FilterResult,
+        GrabStartData as KeyboardGrabStartData, KeyboardGrab, KeyboardHandle, KeyboardInnerHandle,
+        KeyboardUserData, Keysym, KeysymHandle, ModifiersState, XkbConfig,
     },
     pointer::{
-        AxisFrame, ButtonEvent, CursorImageAttributes, CursorImageStatus, Focus,
-        GrabStartData as PointerGrabStartData, MotionEvent, PointerGrab, PointerHandle, PointerInnerHandle,
-        PointerUserData, CURSOR_IMAGE_ROLE,
+        AccelProfile, AxisFrame, ButtonEvent, CompositorPointerGrab, CompositorPointerHandler,
+        CursorImageAttributes, CursorImageStatus, Focus, GrabStartData as PointerGrabStartData, KeyEvent,
+        MotionEvent, PointerGrab, PointerHandle, PointerInnerHandle, PointerUserData, CURSOR_IMAGE_ROLE,
     },
     touch::{TouchHandle, TouchUserData},
 };
@@ -84,7 +99,7 @@ use wayland_server::{
         wl_surface,
         wl_touch::WlTouch,
     },
-    DataInit, Dispatch, DisplayHandle, GlobalDispatch, New, Resource,
+    Client, DataInit, Dispatch, DisplayHandle, GlobalDispatch, New, Resource,
 };
 
 #[derive(Debug)]
@@ -100,10 +115,44 @@ struct Inner<D> {
 struct SeatRc<D> {
     name: String,
     inner: Mutex<Inner<D>>,
+    activity: Arc<InputActivity>,
+    modifiers: Arc<SharedModifiers>,
     user_data_map: UserDataMap,
     log: ::slog::Logger,
 }
 
+// Time of the last input event, shared by a seat and all of its handles
+#[derive(Debug)]
+pub(crate) struct InputActivity(Mutex<Instant>);
+
+impl InputActivity {
+    fn new() -> Arc<Self> {
+        Arc::new(InputActivity(Mutex::new(Instant::now())))
+    }
+
+    pub(crate) fn notify(&self) {
+        *self.0.lock().unwrap() = Instant::now();
+    }
+
+    fn last_input(&self) -> Instant {
+        *self.0.lock().unwrap()
+    }
+}
+
+// Modifier state of the keyboard of a seat, shared with its pointer
+#[derive(Debug, Default)]
+pub(crate) struct SharedModifiers(Mutex<ModifiersState>);
+
+impl SharedModifiers {
+    pub(crate) fn set(&self, modifiers: ModifiersState) {
+        *self.0.lock().unwrap() = modifiers;
+    }
+
+    pub(crate) fn get(&self) -> ModifiersState {
+        *self.0.lock().unwrap()
+    }
+}
+
 impl<D> Inner<D> {
     fn compute_caps(&self) -> wl_seat::Capability {
         let mut caps = wl_seat::Capability::empty();
@@ -127,6 +176,12 @@ impl<D> Inner<D> {
     }
 }
 
+// Callback of another module, clearing the state a client holds in a seat
+type ClientCleanup<D> = fn(&DisplayHandle, &Seat<D>, &ClientId);
+
+// Callbacks registered in the user data of a seat, see `SeatState::cleanup_client`
+struct ClientCleanups<D>(RefCell<Vec<(&'static str, ClientCleanup<D>)>>);
+
 /// Handler trait for WlSeat
 pub trait SeatHandler: Sized {
     /// [SeatState] getter
@@ -190,6 +245,8 @@ impl<D: 'static> Seat<D> {
                 known_seats: Default::default(),
                 global_id: None,
             }),
+            activity: InputActivity::new(),
+            modifiers: Arc::new(SharedModifiers::default()),
             user_data_map: UserDataMap::new(),
             log,
         });
@@ -222,6 +279,79 @@ impl<D: 'static> Seat<D> {
     pub fn global(&self) -> GlobalId {
         self.arc.inner.lock().unwrap().global_id.as_ref().unwrap().clone()
     }
+
+    /// Time of the last input event of this seat
+    ///
+    /// This is updated by every key, pointer motion, button, axis and touch event passed to
+    /// the handles of this seat, including synthetic ones. Before the first input event it is
+    /// the time the seat was created.
+    pub fn last_input_time(&self) -> Instant {
+        self.arc.activity.last_input()
+    }
+
+    /// Time elapsed since the last input event of this seat
+    ///
+    /// See [`Seat::last_input_time`].
+    pub fn idle_time(&self) -> Duration {
+        self.last_input_time().elapsed()
+    }
+
+    /// Mark this seat as active
+    ///
+    /// Resets the [idle time](Seat::idle_time) for input not passed through the handles
+    /// of this seat, e.g. from tablets or switches.
+    pub fn notify_activity(&self) {
+        self.arc.activity.notify();
+    }
+
+    /// Register a callback clearing the state a client holds in this seat
+    ///
+    /// Called by [`SeatState::cleanup_client`], registering another callback with the same
+    /// `name` has no effect.
+    pub(crate) fn add_client_cleanup(&self, name: &'static str, cleanup: ClientCleanup<D>) {
+        self.user_data()
+            .insert_if_missing(|| ClientCleanups::<D>(RefCell::new(Vec::new())));
+        let cleanups = self.user_data().get::<ClientCleanups<D>>().unwrap();
+        let mut cleanups = cleanups.0.borrow_mut();
+        if !cleanups.iter().any(|(registered, _)| *registered == name) {
+            cleanups.push((name, cleanup));
+        }
+    }
+
+    // clear the input state of this seat and the state registered by other modules
+    fn cleanup_client(&self, dh: &DisplayHandle, client: &ClientId, serial: Serial) {
+        if let Some(keyboard) = self.get_keyboard() {
+            keyboard.cleanup_client(client, serial);
+        }
+        if let Some(pointer) = self.get_pointer() {
+            pointer.cleanup_client(client, serial);
+        }
+        if let Some(touch) = self.get_touch() {
+            touch.cleanup_client(client);
+        }
+        let cleanups = self
+            .user_data()
+            .get::<ClientCleanups<D>>()
+            .map(|cleanups| cleanups.0.borrow().clone())
+            .unwrap_or_default();
+        for (_, cleanup) in cleanups {
+            cleanup(dh, self, client);
+        }
+    }
+
+    /// Create a `wl_seat` of this seat for the given client, as if it had bound the global
+    #[cfg(test)]
+    pub(crate) fn create_test_resource(&self, dh: &DisplayHandle, client: &wayland_server::Client) -> WlSeat
+    where
+        D: Dispatch<WlSeat, SeatUserData<D>>,
+    {
+        let data = SeatUserData {
+            arc: self.arc.clone(),
+        };
+        let seat = client.create_resource::<WlSeat, _, D>(dh, 7, data).unwrap();
+        self.arc.inner.lock().unwrap().known_seats.push(seat.clone());
+        seat
+    }
 }
 
 // Pointer
@@ -255,7 +385,8 @@ impl<D> Seat<D> {
         F: FnMut(CursorImageStatus) + Send + Sync + 'static,
     {
         let mut inner = self.arc.inner.lock().unwrap();
-        let pointer = self::pointer::PointerHandle::new(cb);
+        let pointer =
+            self::pointer::PointerHandle::new(cb, self.arc.activity.clone(), self.arc.modifiers.clone());
         if inner.pointer.is_some() {
             // there is already a pointer, remove it and notify the clients
             // of the change
@@ -338,6 +469,8 @@ impl<D: 'static> Seat<D> {
             repeat_delay,
             repeat_rate,
             move |focus| focus_hook(&me, focus),
+            self.arc.activity.clone(),
+            self.arc.modifiers.clone(),
             &self.arc.log,
         )?;
         if inner.keyboard.is_some() {
@@ -365,6 +498,7 @@ impl<D: 'static> Seat<D> {
             inner.keyboard = None;
             inner.send_all_caps();
         }
+        self.arc.modifiers.set(ModifiersState::default());
     }
 }
 
@@ -390,7 +524,7 @@ impl<D> Seat<D> {
     /// ```
     pub fn add_touch(&mut self) -> TouchHandle {
         let mut inner = self.arc.inner.lock().unwrap();
-        let touch = TouchHandle::new();
+        let touch = TouchHandle::new(self.arc.activity.clone());
         if inner.touch.is_some() {
             // If there's already a tocuh device, remove it notify the clients about the change.
             inner.touch = None;
@@ -429,14 +563,48 @@ impl<D> ::std::cmp::PartialEq for Seat<D> {
 /// Events will be forwarded to an instance of the Seat global.
 #[derive(Debug)]
 pub struct SeatState<D> {
-    pd: PhantomData<D>,
+    // seats bound by any client
+    seats: Vec<Weak<SeatRc<D>>>,
 }
 
 impl<D> SeatState<D> {
     /// Create new delegate SeatState
     #[allow(clippy::new_without_default)]
     pub fn new() -> Self {
-        Self { pd: PhantomData }
+        Self { seats: Vec::new() }
+    }
+
+    /// Remember a seat bound by a client, to clean it up in [`SeatState::cleanup_client`]
+    pub(crate) fn add_seat(&mut self, seat: &Seat<D>) {
+        self.seats.retain(|known| known.strong_count() > 0);
+        let ptr = Arc::as_ptr(&seat.arc);
+        if !self.seats.iter().any(|known| known.as_ptr() == ptr) {
+            self.seats.push(Arc::downgrade(&seat.arc));
+        }
+    }
+}
+
+impl<D: 'static> SeatState<D> {
+    /// Remove all references to a client from the seats
+    ///
+    /// For every seat bound by any client, this
+    /// - removes the keyboard, pointer and touch focus from the surfaces of the client,
+    /// - ends keyboard and pointer grabs started on a surface of the client and
+    /// - clears the primary and regular selection, if they were set by the client.
+    ///
+    /// Grabs end like they do when being unset, so they are dropped and restore the focus they
+    /// held back. Otherwise the focus is not moved to another surface, keyboards and pointers
+    /// stay unfocused until the next call to [`KeyboardHandle::set_focus`] or pointer motion.
+    ///
+    /// Use this once a client disconnected or before killing an unresponsive client, so no
+    /// stale state is left behind.
+    pub fn cleanup_client(&mut self, dh: &DisplayHandle, client: &Client) {
+        self.seats.retain(|seat| seat.strong_count() > 0);
+        let client = client.id();
+        let serial = SERIAL_COUNTER.next_serial();
+        for arc in self.seats.iter().filter_map(Weak::upgrade) {
+            Seat { arc }.cleanup_client(dh, &client, serial);
+        }
     }
 }
 
@@ -565,7 +733,7 @@ where
     D: 'static,
 {
     fn bind(
-        _state: &mut D,
+        state: &mut D,
         _dh: &DisplayHandle,
         _client: &wayland_server::Client,
         resource: New<WlSeat>,
@@ -586,5 +754,8 @@ where
         resource.capabilities(inner.compute_caps());
 
         inner.known_seats.push(resource);
+        state.seat_state().add_seat(&Seat {
+            arc: global_data.arc.clone(),
+        });
     }
 }
@@ -0,0 +1,72 @@
+use crate::utils::{Logical, Point};
+
+/// Velocity (in logical pixels per millisecond) up to which the adaptive profile does not accelerate
+const ADAPTIVE_THRESHOLD: f64 = 0.4;
+/// Increase of the acceleration factor per logical pixel per millisecond above the threshold
+const ADAPTIVE_INCLINE: f64 = 1.1;
+/// Maximum acceleration factor of the adaptive profile
+const ADAPTIVE_MAX_FACTOR: f64 = 3.0;
+/// Upper bound for the time between two motion events considered for velocity calculations
+const MAX_EVENT_DELTA_MS: u32 = 100;
+
+/// Acceleration profile applied to relative pointer motion
+///
+/// The `speed` of each profile is clamped to the range `[-1.0, 1.0]`, with `0.0` being the default speed,
+/// similar to the configuration options of libinput.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AccelProfile {
+    /// Relative motion is passed through unmodified
+    None,
+    /// Relative motion is scaled by a constant factor
+    Flat {
+        /// Speed adjustment of the profile
+        speed: f64,
+    },
+    /// Relative motion is accelerated depending on the velocity of the pointer
+    Adaptive {
+        /// Speed adjustment of the profile
+        speed: f64,
+    },
+}
+
+impl Default for AccelProfile {
+    fn default() -> Self {
+        AccelProfile::None
+    }
+}
+
+#[derive(Debug, Default)]
+pub(super) struct PointerAccel {
+    pub(super) profile: AccelProfile,
+    last_time: Option<u32>,
+}
+
+impl PointerAccel {
+    pub(super) fn accelerate(&mut self, delta: Point<f64, Logical>, time: u32) -> Point<f64, Logical> {
+        let elapsed = self
+            .last_time
+            .replace(time)
+            .map(|last| time.wrapping_sub(last).clamp(1, MAX_EVENT_DELTA_MS))
+            .unwrap_or(MAX_EVENT_DELTA_MS);
+
+        let factor = match self.profile {
+            AccelProfile::None => 1.0,
+            AccelProfile::Flat { speed } => speed_factor(speed),
+            AccelProfile::Adaptive { speed } => {
+                let velocity = (delta.x * delta.x + delta.y * delta.y).sqrt() / elapsed as f64;
+                let accel = if velocity <= ADAPTIVE_THRESHOLD {
+                    1.0
+                } else {
+                    (1.0 + (velocity - ADAPTIVE_THRESHOLD) * ADAPTIVE_INCLINE).min(ADAPTIVE_MAX_FACTOR)
+                };
+                accel * speed_factor(speed)
+            }
+        };
+
+        delta.upscale(factor)
+    }
+}
+
+fn speed_factor(speed: f64) -> f64 {
+    1.0 + speed.clamp(-1.0, 1.0)
+}
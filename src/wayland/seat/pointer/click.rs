@@ -0,0 +1,75 @@
+use std::time::Duration;
+
+use crate::utils::{Logical, Point};
+
+/// Default maximum time between two presses to count as a multi-click
+const DEFAULT_DOUBLE_CLICK_SPEED: Duration = Duration::from_millis(400);
+/// Default distance (in logical pixels) the pointer may move between two presses of a multi-click
+const DEFAULT_CLICK_SLOP: f64 = 4.0;
+
+#[derive(Debug, Clone, Copy)]
+struct LastClick {
+    button: u32,
+    time: u32,
+    location: Point<f64, Logical>,
+}
+
+#[derive(Debug)]
+pub(super) struct ClickTracker {
+    pub(super) double_click_speed: Duration,
+    pub(super) slop: f64,
+    count: u32,
+    last: Option<LastClick>,
+}
+
+impl Default for ClickTracker {
+    fn default() -> Self {
+        ClickTracker {
+            double_click_speed: DEFAULT_DOUBLE_CLICK_SPEED,
+            slop: DEFAULT_CLICK_SLOP,
+            count: 0,
+            last: None,
+        }
+    }
+}
+
+impl ClickTracker {
+    /// Register a button press and return the resulting click count
+    pub(super) fn press(&mut self, button: u32, time: u32, location: Point<f64, Logical>) -> u32 {
+        let continued = self.last.map_or(false, |last| {
+            last.button == button
+                && (time.wrapping_sub(last.time) as u128) <= self.double_click_speed.as_millis()
+                && self.within_slop(last.location, location)
+        });
+        self.count = if continued { self.count + 1 } else { 1 };
+        self.last = Some(LastClick {
+            button,
+            time,
+            location,
+        });
+        self.count
+    }
+
+    /// Reset the click count, if the pointer moved too far away from the last press
+    pub(super) fn motion(&mut self, location: Point<f64, Logical>) {
+        if let Some(last) = self.last {
+            if !self.within_slop(last.location, location) {
+                self.reset();
+            }
+        }
+    }
+
+    pub(super) fn reset(&mut self) {
+        self.count = 0;
+        self.last = None;
+    }
+
+    pub(super) fn count(&self) -> u32 {
+        self.count
+    }
+
+    fn within_slop(&self, a: Point<f64, Logical>, b: Point<f64, Logical>) -> bool {
+        let delta = b - a;
+        delta.x * delta.x + delta.y * delta.y <= self.slop * self.slop
+    }
+}
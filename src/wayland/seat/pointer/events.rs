@@ -4,8 +4,12 @@ use wayland_server::protocol::{
 };
 
 use crate::{
+    backend::input::KeyState,
     utils::{Logical, Point},
-    wayland::Serial,
+    wayland::{
+        seat::{Keysym, ModifiersState},
+        Serial,
+    },
 };
 
 /// Pointer motion event
@@ -21,6 +25,26 @@ pub struct MotionEvent {
     pub time: u32,
 }
 
+/// Keyboard key event offered to a pointer grab or a compositor keyboard grab
+///
+/// See [`PointerHandle::key`](super::PointerHandle::key) and
+/// [`CompositorKeyboardHandler`](crate::wayland::seat::CompositorKeyboardHandler) for details.
+#[derive(Debug, Clone, Copy)]
+pub struct KeyEvent {
+    /// Keycode of the key, as reported by the input backend
+    pub keycode: u32,
+    /// Keysym of the key, with the currently active modifiers applied
+    pub keysym: Keysym,
+    /// Physical state of the key
+    pub state: KeyState,
+    /// Modifiers active after the key event was processed
+    pub modifiers: ModifiersState,
+    /// Serial of the event
+    pub serial: Serial,
+    /// Timestamp with millisecond granularity, with an undefined base.
+    pub time: u32,
+}
+
 /// Pointer button event
 
 /// Mouse button click and release notifications.
@@ -135,4 +159,18 @@ impl AxisFrame {
         };
         self
     }
+
+    // Invert the values of the (horizontal, vertical) axes, discrete steps need
+    // to be inverted as well, so clients do not see mismatched signs
+    pub(super) fn invert(mut self, (horizontal, vertical): (bool, bool)) -> Self {
+        if horizontal {
+            self.axis.0 = -self.axis.0;
+            self.discrete.0 = -self.discrete.0;
+        }
+        if vertical {
+            self.axis.1 = -self.axis.1;
+            self.discrete.1 = -self.discrete.1;
+        }
+        self
+    }
 }
@@ -11,7 +11,7 @@ use crate::{
 };
 
 use super::{
-    events::{ButtonEvent, MotionEvent},
+    events::{ButtonEvent, KeyEvent, MotionEvent},
     AxisFrame, Focus, PointerInnerHandle,
 };
 
@@ -75,6 +75,23 @@ pub trait PointerGrab<D>: Send + Sync {
         handle: &mut PointerInnerHandle<'_, D>,
         details: AxisFrame,
     );
+    /// A keyboard key was offered to this grab
+    ///
+    /// Keys are only offered to the grab, if the compositor routes them through
+    /// [`PointerHandle::key`](super::PointerHandle::key). This allows interactive operations
+    /// to observe keys, e.g. to cancel a move or resize when Escape is pressed.
+    ///
+    /// Return `true` to consume the key, in which case it should not be forwarded to the client
+    /// with keyboard focus. The default implementation ignores all keys.
+    fn key(
+        &mut self,
+        _data: &mut D,
+        _dh: &DisplayHandle,
+        _handle: &mut PointerInnerHandle<'_, D>,
+        _event: &KeyEvent,
+    ) -> bool {
+        false
+    }
     /// The data about the event that started the grab.
     fn start_data(&self) -> &GrabStartData;
 }
@@ -92,6 +109,113 @@ pub struct GrabStartData {
     pub location: Point<f64, Logical>,
 }
 
+/// Handler of a [`CompositorPointerGrab`]
+///
+/// Receives all pointer input while the grab is active, without any of it reaching clients.
+/// Any interactions with the pointer should be done using the given [`PointerInnerHandle`],
+/// e.g. call [`PointerInnerHandle::unset_grab`] to end the grab and restore the regular focus.
+pub trait CompositorPointerHandler<D>: Send + Sync {
+    /// A motion was reported
+    ///
+    /// The focus of the event is the surface that would be focused without the grab.
+    fn motion(
+        &mut self,
+        data: &mut D,
+        dh: &DisplayHandle,
+        handle: &mut PointerInnerHandle<'_, D>,
+        event: &MotionEvent,
+    );
+    /// A button press or release was reported
+    fn button(
+        &mut self,
+        data: &mut D,
+        dh: &DisplayHandle,
+        handle: &mut PointerInnerHandle<'_, D>,
+        event: &ButtonEvent,
+    );
+    /// An axis scroll was reported
+    fn axis(
+        &mut self,
+        data: &mut D,
+        dh: &DisplayHandle,
+        handle: &mut PointerInnerHandle<'_, D>,
+        details: AxisFrame,
+    );
+}
+
+/// A pointer grab delivering all input to the compositor instead of any client
+///
+/// This is useful for user interfaces drawn by the compositor itself, like an overview mode.
+/// No surface has pointer focus while the grab is active, all events are passed to the
+/// [`CompositorPointerHandler`] instead. Once the grab ends, the surface under the pointer
+/// is focused again.
+///
+/// Use [`PointerHandle::set_compositor_grab`](super::PointerHandle::set_compositor_grab) to start
+/// such a grab, or set it with [`Focus::Clear`] yourself.
+#[derive(Debug)]
+pub struct CompositorPointerGrab<H> {
+    handler: H,
+    start_data: GrabStartData,
+}
+
+impl<H> CompositorPointerGrab<H> {
+    /// Create a new grab passing all pointer input to the given handler
+    pub fn new(handler: H, start_data: GrabStartData) -> Self {
+        CompositorPointerGrab { handler, start_data }
+    }
+
+    /// Returns a reference to the handler of this grab
+    pub fn handler(&self) -> &H {
+        &self.handler
+    }
+
+    /// Returns a mutable reference to the handler of this grab
+    pub fn handler_mut(&mut self) -> &mut H {
+        &mut self.handler
+    }
+}
+
+impl<D, H> PointerGrab<D> for CompositorPointerGrab<H>
+where
+    H: CompositorPointerHandler<D>,
+{
+    fn motion(
+        &mut self,
+        data: &mut D,
+        dh: &DisplayHandle,
+        handle: &mut PointerInnerHandle<'_, D>,
+        event: &MotionEvent,
+    ) {
+        // keep the location up to date, while no surface is focused
+        handle.motion(event.location, None, event.serial, event.time);
+        self.handler.motion(data, dh, handle, event);
+    }
+
+    fn button(
+        &mut self,
+        data: &mut D,
+        dh: &DisplayHandle,
+        handle: &mut PointerInnerHandle<'_, D>,
+        event: &ButtonEvent,
+    ) {
+        self.handler.button(data, dh, handle, event);
+    }
+
+    fn axis(
+        &mut self,
+        data: &mut D,
+        dh: &DisplayHandle,
+        handle: &mut PointerInnerHandle<'_, D>,
+        details: AxisFrame,
+    ) {
+        self.handler.axis(data, dh, handle, details);
+    }
+
+    fn start_data(&self) -> &GrabStartData {
+        &self.start_data
+    }
+}
+
 pub(super) enum GrabStatus<D> {
     None,
     Active(Serial, Box<dyn PointerGrab<D>>),
@@ -0,0 +1,272 @@
+use std::time::Duration;
+
+use wayland_server::protocol::wl_pointer::ButtonState;
+
+use super::ButtonEvent;
+
+const BTN_LEFT: u32 = 0x110;
+const BTN_RIGHT: u32 = 0x111;
+const BTN_MIDDLE: u32 = 0x112;
+
+/// Default maximum time between the presses of left and right to emulate a middle button press
+const DEFAULT_TIMEOUT: Duration = Duration::from_millis(50);
+
+#[derive(Debug, Clone, Copy)]
+enum State {
+    // neither left nor right are held
+    Idle,
+    // left or right was pressed, its press is held back waiting for the other one
+    Pending(ButtonEvent),
+    // both were pressed in time, the emulated middle button is held until one of them is released
+    Middle { released: bool },
+    // left or right are held and passed through as they are
+    Passthrough,
+}
+
+#[derive(Debug)]
+pub(super) struct MiddleButtonEmulation {
+    pub(super) enabled: bool,
+    pub(super) timeout: Duration,
+    state: State,
+    // physically held left and right buttons
+    held: [bool; 2],
+}
+
+impl Default for MiddleButtonEmulation {
+    fn default() -> Self {
+        MiddleButtonEmulation {
+            enabled: false,
+            timeout: DEFAULT_TIMEOUT,
+            state: State::Idle,
+            held: [false; 2],
+        }
+    }
+}
+
+impl MiddleButtonEmulation {
+    /// Filter a button event, returning the events to process in its place
+    pub(super) fn button(&mut self, event: &ButtonEvent) -> Vec<ButtonEvent> {
+        let side = match event.button {
+            BTN_LEFT => 0,
+            BTN_RIGHT => 1,
+            _ => {
+                // other buttons end the wait for the second button
+                let mut events = self.flush().into_iter().collect::<Vec<_>>();
+                events.push(*event);
+                return events;
+            }
+        };
+        let pressed = event.state == ButtonState::Pressed;
+        self.held[side] = pressed;
+
+        let events = match (self.state, pressed) {
+            (State::Idle, true) if self.enabled => {
+                self.state = State::Pending(*event);
+                Vec::new()
+            }
+            (State::Pending(pending), true) if self.enabled && pending.button != event.button => {
+                if event.time.wrapping_sub(pending.time) as u128 <= self.timeout.as_millis() {
+                    self.state = State::Middle { released: false };
+                    vec![ButtonEvent {
+                        button: BTN_MIDDLE,
+                        ..*event
+                    }]
+                } else {
+                    // the first button was held too long, fall back to the real buttons
+                    self.state = State::Passthrough;
+                    vec![pending, *event]
+                }
+            }
+            (State::Pending(pending), _) => {
+                self.state = State::Passthrough;
+                vec![pending, *event]
+            }
+            (State::Middle { released: false }, false) => {
+                self.state = State::Middle { released: true };
+                vec![ButtonEvent {
+                    button: BTN_MIDDLE,
+                    ..*event
+                }]
+            }
+            // left and right belong to the emulated button until both are released
+            (State::Middle { .. }, _) => Vec::new(),
+            (_, true) => {
+                self.state = State::Passthrough;
+                vec![*event]
+            }
+            (_, false) => vec![*event],
+        };
+
+        if self.held == [false; 2] && !matches!(self.state, State::Pending(_)) {
+            self.state = State::Idle;
+        }
+        events
+    }
+
+    /// Stop waiting for the second button, returning the held back press
+    pub(super) fn flush(&mut self) -> Option<ButtonEvent> {
+        match self.state {
+            State::Pending(pending) => {
+                self.state = State::Passthrough;
+                Some(pending)
+            }
+            _ => None,
+        }
+    }
+
+    /// Stop waiting for the second button, if the timeout expired at the given time
+    pub(super) fn flush_expired(&mut self, time: u32) -> Option<ButtonEvent> {
+        match self.state {
+            State::Pending(pending) if time.wrapping_sub(pending.time) as u128 > self.timeout.as_millis() => {
+                self.flush()
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BTN_EXTRA: u32 = 0x114;
+
+    fn event(button: u32, pressed: bool, time: u32) -> ButtonEvent {
+        ButtonEvent {
+            serial: time.into(),
+            time,
+            button,
+            state: if pressed {
+                ButtonState::Pressed
+            } else {
+                ButtonState::Released
+            },
+        }
+    }
+
+    fn emulation() -> MiddleButtonEmulation {
+        MiddleButtonEmulation {
+            enabled: true,
+            ..Default::default()
+        }
+    }
+
+    // the buttons and states of the given events
+    fn buttons(events: Vec<ButtonEvent>) -> Vec<(u32, ButtonState)> {
+        events
+            .into_iter()
+            .map(|event| (event.button, event.state))
+            .collect()
+    }
+
+    #[test]
+    fn presses_within_the_timeout_emulate_the_middle_button() {
+        let mut middle = emulation();
+        assert!(middle.button(&event(BTN_LEFT, true, 0)).is_empty());
+        assert_eq!(
+            buttons(middle.button(&event(BTN_RIGHT, true, 20))),
+            vec![(BTN_MIDDLE, ButtonState::Pressed)]
+        );
+        assert_eq!(
+            buttons(middle.button(&event(BTN_LEFT, false, 100))),
+            vec![(BTN_MIDDLE, ButtonState::Released)]
+        );
+        assert!(middle.button(&event(BTN_RIGHT, false, 110)).is_empty());
+        assert!(matches!(middle.state, State::Idle));
+    }
+
+    #[test]
+    fn press_held_past_the_timeout_is_passed_through() {
+        let mut middle = emulation();
+        assert!(middle.button(&event(BTN_LEFT, true, 0)).is_empty());
+        assert_eq!(
+            buttons(middle.button(&event(BTN_RIGHT, true, 80))),
+            vec![
+                (BTN_LEFT, ButtonState::Pressed),
+                (BTN_RIGHT, ButtonState::Pressed)
+            ]
+        );
+        assert_eq!(
+            buttons(middle.button(&event(BTN_LEFT, false, 100))),
+            vec![(BTN_LEFT, ButtonState::Released)]
+        );
+        assert_eq!(
+            buttons(middle.button(&event(BTN_RIGHT, false, 110))),
+            vec![(BTN_RIGHT, ButtonState::Released)]
+        );
+        assert!(matches!(middle.state, State::Idle));
+    }
+
+    #[test]
+    fn expired_press_is_flushed() {
+        let mut middle = emulation();
+        assert!(middle.button(&event(BTN_LEFT, true, 0)).is_empty());
+        assert!(middle.flush_expired(50).is_none());
+        let press = middle.flush_expired(51).expect("press not flushed");
+        assert_eq!(press.button, BTN_LEFT);
+        assert_eq!(
+            buttons(middle.button(&event(BTN_LEFT, false, 100))),
+            vec![(BTN_LEFT, ButtonState::Released)]
+        );
+        assert!(matches!(middle.state, State::Idle));
+    }
+
+    #[test]
+    fn release_while_pending_passes_on_the_click() {
+        let mut middle = emulation();
+        assert!(middle.button(&event(BTN_RIGHT, true, 0)).is_empty());
+        assert_eq!(
+            buttons(middle.button(&event(BTN_RIGHT, false, 10))),
+            vec![
+                (BTN_RIGHT, ButtonState::Pressed),
+                (BTN_RIGHT, ButtonState::Released)
+            ]
+        );
+        assert!(matches!(middle.state, State::Idle));
+    }
+
+    #[test]
+    fn other_button_while_pending_passes_on_the_press() {
+        let mut middle = emulation();
+        assert!(middle.button(&event(BTN_LEFT, true, 0)).is_empty());
+        assert_eq!(
+            buttons(middle.button(&event(BTN_EXTRA, true, 10))),
+            vec![
+                (BTN_LEFT, ButtonState::Pressed),
+                (BTN_EXTRA, ButtonState::Pressed)
+            ]
+        );
+        // the held left button is no longer emulating anything
+        assert_eq!(
+            buttons(middle.button(&event(BTN_RIGHT, true, 20))),
+            vec![(BTN_RIGHT, ButtonState::Pressed)]
+        );
+    }
+
+    #[test]
+    fn disabling_while_pending_passes_on_the_press() {
+        let mut middle = emulation();
+        assert!(middle.button(&event(BTN_LEFT, true, 0)).is_empty());
+        middle.enabled = false;
+        assert_eq!(
+            buttons(middle.button(&event(BTN_RIGHT, true, 10))),
+            vec![
+                (BTN_LEFT, ButtonState::Pressed),
+                (BTN_RIGHT, ButtonState::Pressed)
+            ]
+        );
+        assert_eq!(
+            buttons(middle.button(&event(BTN_LEFT, false, 20))),
+            vec![(BTN_LEFT, ButtonState::Released)]
+        );
+        assert_eq!(
+            buttons(middle.button(&event(BTN_RIGHT, false, 30))),
+            vec![(BTN_RIGHT, ButtonState::Released)]
+        );
+        assert!(matches!(middle.state, State::Idle));
+        assert_eq!(
+            buttons(middle.button(&event(BTN_LEFT, true, 40))),
+            vec![(BTN_LEFT, ButtonState::Pressed)]
+        );
+    }
+}
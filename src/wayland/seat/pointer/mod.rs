@@ -1,6 +1,7 @@
 use std::{
     fmt,
     sync::{Arc, Mutex},
+    time::Duration,
 };
 
 use wayland_server::{
@@ -14,20 +15,35 @@ use wayland_server::{
 
 use crate::{
     utils::{IsAlive, Logical, Point},
-    wayland::{compositor, Serial},
+    wayland::{compositor, output::Output, Serial},
 };
 
-use super::{SeatHandler, SeatState};
+use super::{FilterResult, InputActivity, ModifiersState, SeatHandler, SeatState, SharedModifiers};
+
+mod accel;
+pub use accel::AccelProfile;
+use accel::PointerAccel;
+
+mod click;
+use click::ClickTracker;
+
+mod middle;
+use middle::MiddleButtonEmulation;
 
 mod grab;
+pub use grab::{CompositorPointerGrab, CompositorPointerHandler, GrabStartData, PointerGrab};
 use grab::{DefaultGrab, GrabStatus};
-pub use grab::{GrabStartData, PointerGrab};
 
 mod cursor_image;
 pub use cursor_image::{CursorImageAttributes, CursorImageStatus};
 
 mod events;
-pub use events::{AxisFrame, ButtonEvent, MotionEvent};
+pub use events::{AxisFrame, ButtonEvent, KeyEvent, MotionEvent};
+
+#[cfg(feature = "xcursor")]
+mod xcursor;
+#[cfg(feature = "xcursor")]
+pub use self::xcursor::{load_xcursor_theme, XCursorError, XCursorImage, XCursorTheme};
 
 struct PointerInternal<D> {
     known_pointers: Vec<WlPointer>,
@@ -36,9 +52,23 @@ struct PointerInternal<D> {
     location: Point<f64, Logical>,
     grab: GrabStatus<D>,
     pressed_buttons: Vec<u32>,
+    accel: PointerAccel,
+    clicks: ClickTracker,
+    middle: MiddleButtonEmulation,
+    natural_scroll: (bool, bool),
+    cursor_surface: Option<WlSurface>,
+    cursor_output: Option<Output>,
     image_callback: Box<dyn FnMut(CursorImageStatus) + Send + Sync>,
+    axis_filter: Option<AxisFilter<D>>,
+    // surface the clients were last sent an enter event for
+    announced_focus: Option<WlSurface>,
+    coalesce_focus: bool,
+    // serial and time of the last motion, while enter and leave events are held back
+    coalesced_focus: Option<(Serial, u32)>,
 }
 
+type AxisFilter<D> = Box<dyn FnMut(&mut D, &ModifiersState, &AxisFrame) -> FilterResult<()> + Send + Sync>;
+
 // image_callback does not implement debug, so we have to impl Debug manually
 impl<D> fmt::Debug for PointerInternal<D> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -49,7 +79,17 @@ impl<D> fmt::Debug for PointerInternal<D> {
             .field("location", &self.location)
             .field("grab", &self.grab)
             .field("pressed_buttons", &self.pressed_buttons)
+            .field("accel", &self.accel)
+            .field("clicks", &self.clicks)
+            .field("middle", &self.middle)
+            .field("natural_scroll", &self.natural_scroll)
+            .field("cursor_surface", &self.cursor_surface)
+            .field("cursor_output", &self.cursor_output)
             .field("image_callback", &"...")
+            .field("axis_filter", &self.axis_filter.as_ref().map(|_| "..."))
+            .field("announced_focus", &self.announced_focus)
+            .field("coalesce_focus", &self.coalesce_focus)
+            .field("coalesced_focus", &self.coalesced_focus)
             .finish()
     }
 }
@@ -72,7 +112,17 @@ impl<D> PointerInternal<D> {
             location: (0.0, 0.0).into(),
             grab: GrabStatus::None,
             pressed_buttons: Vec::new(),
+            accel: PointerAccel::default(),
+            clicks: ClickTracker::default(),
+            middle: MiddleButtonEmulation::default(),
+            natural_scroll: (false, false),
+            cursor_surface: None,
+            cursor_output: None,
             image_callback,
+            axis_filter: None,
+            announced_focus: None,
+            coalesce_focus: false,
+            coalesced_focus: None,
         }
     }
 
@@ -99,6 +149,7 @@ impl<D> PointerInternal<D> {
         serial: Serial,
         time: u32,
     ) {
+        self.drop_destroyed_focus();
         // do we leave a surface ?
         let mut leave = true;
         self.location = location;
@@ -110,12 +161,9 @@ impl<D> PointerInternal<D> {
             }
         }
         if leave {
-            self.with_focused_pointers(|pointer, surface| {
-                pointer.leave(serial.into(), surface);
-                if pointer.version() >= 5 {
-                    pointer.frame();
-                }
-            });
+            if !self.coalesce_focus {
+                self.send_leave(serial);
+            }
             self.focus = None;
             (self.image_callback)(CursorImageStatus::Default);
         }
@@ -126,38 +174,147 @@ impl<D> PointerInternal<D> {
             // in all cases, update the focus, the coordinates of the surface
             // might have changed
             self.focus = Some((surface, surface_location));
-            let (x, y) = (location - surface_location.to_f64()).into();
-            if entered {
-                self.with_focused_pointers(|pointer, surface| {
-                    pointer.enter(serial.into(), surface, x, y);
-                    if pointer.version() >= 5 {
-                        pointer.frame();
-                    }
-                })
+            if self.coalesce_focus && (entered || self.coalesced_focus.is_some()) {
+                // hold back the enter until the focus settled
+                self.coalesced_focus = Some((serial, time));
+            } else if entered {
+                self.send_enter(serial);
             } else {
                 // we were on top of a surface and remained on it
-                self.with_focused_pointers(|pointer, _| {
-                    pointer.motion(time, x, y);
-                    if pointer.version() >= 5 {
-                        pointer.frame();
-                    }
-                })
+                self.send_motion(time);
             }
+        } else if leave && self.coalesce_focus {
+            self.coalesced_focus = Some((serial, time));
+        }
+    }
+
+    // Send the enter and leave events held back by focus coalescing
+    fn flush_focus(&mut self) {
+        if let Some((serial, time)) = self.coalesced_focus.take() {
+            self.drop_destroyed_focus();
+            let focus = self.focus.as_ref().map(|(surface, _)| surface.clone());
+            if self.announced_focus == focus {
+                // the pointer only passed over other surfaces
+                self.send_motion(time);
+            } else {
+                self.send_leave(serial);
+                self.send_enter(serial);
+            }
+        }
+    }
+
+    fn send_leave(&mut self, serial: Serial) {
+        if let Some(surface) = self.announced_focus.take() {
+            self.with_pointers_of(&surface, |pointer, surface| {
+                pointer.leave(serial.into(), surface);
+                if pointer.version() >= 5 {
+                    pointer.frame();
+                }
+            });
+        }
+    }
+
+    fn send_enter(&mut self, serial: Serial) {
+        if let Some((surface, surface_location)) = self.focus.clone() {
+            let (x, y) = (self.location - surface_location.to_f64()).into();
+            self.with_pointers_of(&surface, |pointer, surface| {
+                pointer.enter(serial.into(), surface, x, y);
+                if pointer.version() >= 5 {
+                    pointer.frame();
+                }
+            });
+            self.announced_focus = Some(surface);
+        }
+    }
+
+    fn send_motion(&self, time: u32) {
+        if let Some((_, surface_location)) = self.focus {
+            let (x, y) = (self.location - surface_location.to_f64()).into();
+            self.with_focused_pointers(|pointer, _| {
+                pointer.motion(time, x, y);
+                if pointer.version() >= 5 {
+                    pointer.frame();
+                }
+            })
+        }
+    }
+
+    fn button(&mut self, data: &mut D, dh: &DisplayHandle, event: &ButtonEvent) {
+        // the client needs to know the focus before receiving buttons
+        self.flush_focus();
+        match event.state {
+            ButtonState::Pressed => {
+                self.pressed_buttons.push(event.button);
+                let location = self.location;
+                self.clicks.press(event.button, event.time, location);
+            }
+            ButtonState::Released => {
+                self.pressed_buttons.retain(|b| *b != event.button);
+            }
+            _ => unreachable!(),
+        }
+        self.with_grab(dh, |dh, mut handle, grab| {
+            grab.button(data, dh, &mut handle, event);
+        });
+    }
+
+    // forget the surfaces of the given client
+    fn cleanup_client(&mut self, client: &ClientId, serial: Serial) {
+        let owned_by_client = |surface: &WlSurface| surface.client_id().as_ref() == Some(client);
+        if matches!(self.pending_focus, Some((ref surface, _)) if owned_by_client(surface)) {
+            self.pending_focus = None;
+        }
+        if matches!(self.grab, GrabStatus::Active(_, ref grab)
+            if matches!(grab.start_data().focus, Some((ref surface, _)) if owned_by_client(surface)))
+        {
+            self.unset_grab(serial, 0);
+        }
+        if matches!(self.focus, Some((ref surface, _)) if owned_by_client(surface)) {
+            self.motion(self.location, None, serial, 0);
+        }
+        // the leave may have been held back by focus coalescing
+        if matches!(self.announced_focus, Some(ref surface) if owned_by_client(surface)) {
+            self.send_leave(serial);
+        }
+        if matches!(self.cursor_surface, Some(ref surface) if owned_by_client(surface)) {
+            self.cursor_surface = None;
+        }
+    }
+
+    // forget the focus, if the focused surface was destroyed
+    fn drop_destroyed_focus(&mut self) {
+        if matches!(self.pending_focus, Some((ref surface, _)) if !surface.alive()) {
+            self.pending_focus = None;
+        }
+        if matches!(self.focus, Some((ref surface, _)) if !surface.alive()) {
+            // there is nobody left to send a leave event to
+            self.focus = None;
+            (self.image_callback)(CursorImageStatus::Default);
+        }
+        if matches!(self.announced_focus, Some(ref surface) if !surface.alive()) {
+            self.announced_focus = None;
         }
     }
 
-    fn with_focused_pointers<F>(&self, mut f: F)
+    fn with_focused_pointers<F>(&self, f: F)
     where
         F: FnMut(&WlPointer, &WlSurface),
     {
         if let Some((ref focus, _)) = self.focus {
-            if !focus.alive() {
-                return;
-            }
-            for ptr in &self.known_pointers {
-                if ptr.id().same_client_as(&focus.id()) {
-                    f(ptr, focus)
-                }
+            self.with_pointers_of(focus, f);
+        }
+    }
+
+    fn with_pointers_of<F>(&self, surface: &WlSurface, mut f: F)
+    where
+        F: FnMut(&WlPointer, &WlSurface),
+    {
+        if !surface.alive() {
+            return;
+        }
+        for ptr in &self.known_pointers {
+            if ptr.id().same_client_as(&surface.id()) {
+                f(ptr, surface)
             }
         }
     }
@@ -166,6 +323,7 @@ impl<D> PointerInternal<D> {
     where
         F: FnOnce(&DisplayHandle, PointerInnerHandle<'_, D>, &mut dyn PointerGrab<D>),
     {
+        self.drop_destroyed_focus();
         let mut grab = ::std::mem::replace(&mut self.grab, GrabStatus::Borrowed);
         match grab {
             GrabStatus::Borrowed => panic!("Accessed a pointer grab from within a pointer grab access."),
@@ -207,23 +365,33 @@ pub const CURSOR_IMAGE_ROLE: &str = "cursor_image";
 #[derive(Debug)]
 pub struct PointerHandle<D> {
     inner: Arc<Mutex<PointerInternal<D>>>,
+    activity: Arc<InputActivity>,
+    modifiers: Arc<SharedModifiers>,
 }
 
 impl<D> Clone for PointerHandle<D> {
     fn clone(&self) -> Self {
         Self {
             inner: self.inner.clone(),
+            activity: self.activity.clone(),
+            modifiers: self.modifiers.clone(),
         }
     }
 }
 
 impl<D> PointerHandle<D> {
-    pub(crate) fn new<F>(cb: F) -> PointerHandle<D>
+    pub(crate) fn new<F>(
+        cb: F,
+        activity: Arc<InputActivity>,
+        modifiers: Arc<SharedModifiers>,
+    ) -> PointerHandle<D>
     where
         F: FnMut(CursorImageStatus) + Send + Sync + 'static,
     {
         PointerHandle {
             inner: Arc::new(Mutex::new(PointerInternal::new(Box::new(cb)))),
+            activity,
+            modifiers,
         }
     }
 
@@ -232,6 +400,11 @@ impl<D> PointerHandle<D> {
         guard.known_pointers.push(pointer);
     }
 
+    /// Remove the focus and grab of this pointer from the surfaces of the given client
+    pub(crate) fn cleanup_client(&self, client: &ClientId, serial: Serial) {
+        self.inner.lock().unwrap().cleanup_client(client, serial);
+    }
+
     /// Change the current grab on this pointer to the provided grab
     ///
     /// If focus is set to [`Focus::Clear`] any currently focused surface will be unfocused.
@@ -246,6 +419,29 @@ impl<D> PointerHandle<D> {
         self.inner.lock().unwrap().unset_grab(serial, time);
     }
 
+    /// Start a grab passing all pointer input to the given compositor handler
+    ///
+    /// The current focus is cleared and no client receives pointer events until the grab ends.
+    /// See [`CompositorPointerGrab`] for details.
+    ///
+    /// Overwrites any current grab.
+    pub fn set_compositor_grab<H>(&self, handler: H, serial: Serial)
+    where
+        H: CompositorPointerHandler<D> + 'static,
+    {
+        let mut inner = self.inner.lock().unwrap();
+        let start_data = GrabStartData {
+            focus: None,
+            button: 0,
+            location: inner.location,
+        };
+        inner.set_grab(
+            serial,
+            CompositorPointerGrab::new(handler, start_data),
+            Focus::Clear,
+        );
+    }
+
     /// Check if this pointer is currently grabbed with this serial
     pub fn has_grab(&self, serial: Serial) -> bool {
         let guard = self.inner.lock().unwrap();
@@ -261,6 +457,15 @@ impl<D> PointerHandle<D> {
         !matches!(guard.grab, GrabStatus::None)
     }
 
+    /// Returns the serial the current grab was started with, if any.
+    pub fn current_grab_serial(&self) -> Option<Serial> {
+        let guard = self.inner.lock().unwrap();
+        match guard.grab {
+            GrabStatus::Active(serial, _) => Some(serial),
+            _ => None,
+        }
+    }
+
     /// Returns the start data for the grab, if any.
     pub fn grab_start_data(&self) -> Option<GrabStartData> {
         let guard = self.inner.lock().unwrap();
@@ -282,8 +487,13 @@ impl<D> PointerHandle<D> {
     /// This will internally take care of notifying the appropriate client objects
     /// of enter/motion/leave events.
     pub fn motion(&self, data: &mut D, dh: &DisplayHandle, event: &MotionEvent) {
+        self.activity.notify();
         let mut inner = self.inner.lock().unwrap();
+        if let Some(press) = inner.middle.flush_expired(event.time) {
+            inner.button(data, dh, &press);
+        }
         inner.pending_focus = event.focus.clone();
+        inner.clicks.motion(event.location);
         inner.with_grab(dh, move |dh, mut handle, grab| {
             grab.motion(data, dh, &mut handle, event);
         });
@@ -293,35 +503,338 @@ impl<D> PointerHandle<D> {
     ///
     /// This will internally send the appropriate button event to the client
     /// objects matching with the currently focused surface.
+    ///
+    /// Presses of the left and right button may be held back or replaced,
+    /// if [middle button emulation](PointerHandle::set_middle_button_emulation) is enabled.
     pub fn button(&self, data: &mut D, dh: &DisplayHandle, event: &ButtonEvent) {
+        self.activity.notify();
         let mut inner = self.inner.lock().unwrap();
-        match event.state {
-            ButtonState::Pressed => {
-                inner.pressed_buttons.push(event.button);
-            }
-            ButtonState::Released => {
-                inner.pressed_buttons.retain(|b| *b != event.button);
-            }
-            _ => unreachable!(),
+        for event in inner.middle.button(event) {
+            inner.button(data, dh, &event);
         }
-        inner.with_grab(dh, |dh, mut handle, grab| {
-            grab.button(data, dh, &mut handle, event);
-        });
     }
 
     /// Start an axis frame
     ///
     /// A single frame will group multiple scroll events as if they happened in the same instance.
+    ///
+    /// The frame is offered to the [axis filter](PointerHandle::set_axis_filter) first, if any,
+    /// and is not sent to the client or the active grab, if the filter intercepts it.
     pub fn axis(&self, data: &mut D, dh: &DisplayHandle, details: AxisFrame) {
-        self.inner.lock().unwrap().with_grab(dh, |dh, mut handle, grab| {
+        self.activity.notify();
+        let mut inner = self.inner.lock().unwrap();
+        if let Some(press) = inner.middle.flush_expired(details.time) {
+            inner.button(data, dh, &press);
+        }
+        inner.flush_focus();
+        if let Some(filter) = inner.axis_filter.as_mut() {
+            let modifiers = self.modifiers.get();
+            if let FilterResult::Intercept(()) = filter(data, &modifiers, &details) {
+                return;
+            }
+        }
+        inner.with_grab(dh, |dh, mut handle, grab| {
             grab.axis(data, dh, &mut handle, details);
         });
     }
 
+    /// Enable or disable coalescing of enter and leave events
+    ///
+    /// When the pointer skims across many surfaces quickly, every surface receives an enter and a
+    /// leave event, flooding the clients. With coalescing enabled, [`PointerHandle::motion`] only
+    /// updates the focus, the enter and leave events are held back until
+    /// [`PointerHandle::flush_focus`] is called. Only the surface focused at that point receives
+    /// an enter event and only the previously entered surface a leave event, surfaces the pointer
+    /// merely passed over receive neither. Button and axis events flush the held back events first.
+    ///
+    /// This changes the number of events clients observe, which is fine as long as clients don't
+    /// rely on being entered while the pointer passes through. Disabling coalescing flushes
+    /// any held back events. Disabled by default.
+    pub fn set_focus_coalescing(&self, enabled: bool) {
+        let mut inner = self.inner.lock().unwrap();
+        if !enabled {
+            inner.flush_focus();
+        }
+        inner.coalesce_focus = enabled;
+    }
+
+    /// Returns true, if enter and leave events are coalesced
+    pub fn focus_coalescing(&self) -> bool {
+        self.inner.lock().unwrap().coalesce_focus
+    }
+
+    /// Send the enter and leave events held back by [focus coalescing](PointerHandle::set_focus_coalescing)
+    ///
+    /// You should call this once per dispatch of your event loop, after processing the input events
+    /// and before flushing the clients.
+    pub fn flush_focus(&self) {
+        self.inner.lock().unwrap().flush_focus();
+    }
+
+    /// Set a filter deciding which axis frames are delivered to clients
+    ///
+    /// The filter is called for every frame passed to [`PointerHandle::axis`], together with the
+    /// current modifier state of the keyboard of the seat. If it returns [`FilterResult::Intercept`],
+    /// the frame is handled by the compositor and neither sent to the focused client nor to the active
+    /// grab. This can be used to implement modifier gated scroll gestures, like zooming with Ctrl+scroll:
+    ///
+    /// ```ignore
+    /// pointer.set_axis_filter(|state: &mut State, modifiers, frame| {
+    ///     if modifiers.ctrl {
+    ///         state.zoom(frame);
+    ///         FilterResult::Intercept(())
+    ///     } else {
+    ///         FilterResult::Forward
+    ///     }
+    /// });
+    /// ```
+    ///
+    /// The modifiers are the ones of the keyboard ignoring any
+    /// [override](crate::wayland::seat::KeyboardHandle::send_modifier_override),
+    /// and are empty, if the seat has no keyboard. The pointer is locked while the filter runs,
+    /// so the filter must not access this [`PointerHandle`], or this will deadlock.
+    pub fn set_axis_filter<F>(&self, filter: F)
+    where
+        F: FnMut(&mut D, &ModifiersState, &AxisFrame) -> FilterResult<()> + Send + Sync + 'static,
+    {
+        self.inner.lock().unwrap().axis_filter = Some(Box::new(filter));
+    }
+
+    /// Remove the axis filter, delivering all axis frames to clients again
+    pub fn clear_axis_filter(&self) {
+        self.inner.lock().unwrap().axis_filter = None;
+    }
+
+    /// Offer a keyboard key to the active pointer grab
+    ///
+    /// Keyboard input is not routed through pointer grabs by default, so the client with keyboard
+    /// focus keeps receiving keys while e.g. an interactive resize is in progress. Call this from the
+    /// filter passed to [`KeyboardHandle::input`](crate::wayland::seat::KeyboardHandle::input)
+    /// to let the grab observe the key first:
+    ///
+    /// ```ignore
+    /// keyboard.input(dh, keycode, state, serial, time, |modifiers, handle| {
+    ///     let event = KeyEvent {
+    ///         keycode,
+    ///         keysym: handle.modified_sym(),
+    ///         state,
+    ///         modifiers: *modifiers,
+    ///         serial,
+    ///         time,
+    ///     };
+    ///     if pointer.key(data, dh, &event) {
+    ///         FilterResult::Intercept(())
+    ///     } else {
+    ///         FilterResult::Forward
+    ///     }
+    /// });
+    /// ```
+    ///
+    /// Returns `true`, if the grab consumed the key. Without an active grab this always returns `false`.
+    ///
+    /// The keyboard focus is not changed by pointer grabs, so keys not consumed by the grab still
+    /// go to the focused client. As the keyboard is locked while the filter runs, the grab must not
+    /// access the [`KeyboardHandle`](crate::wayland::seat::KeyboardHandle) from within
+    /// [`PointerGrab::key`], or this will deadlock.
+    pub fn key(&self, data: &mut D, dh: &DisplayHandle, event: &KeyEvent) -> bool {
+        let mut inner = self.inner.lock().unwrap();
+        if matches!(inner.grab, GrabStatus::None) {
+            return false;
+        }
+        let mut consumed = false;
+        inner.with_grab(dh, |dh, mut handle, grab| {
+            consumed = grab.key(data, dh, &mut handle, event);
+        });
+        consumed
+    }
+
     /// Access the current location of this pointer in the global space
     pub fn current_location(&self) -> Point<f64, Logical> {
         self.inner.lock().unwrap().location
     }
+
+    /// Returns the surface currently focused by this pointer and the pointer location relative to it
+    ///
+    /// This is the surface receiving pointer events, as determined by the [`MotionEvent`]s passed to
+    /// [`PointerHandle::motion`] and the active grab. The location is given in the surface-local
+    /// coordinate space. Returns `None`, if no surface is focused, e.g. while a grab cleared the focus
+    /// or after the focused surface was destroyed.
+    pub fn current_focus(&self) -> Option<(WlSurface, Point<f64, Logical>)> {
+        let mut inner = self.inner.lock().unwrap();
+        inner.drop_destroyed_focus();
+        inner
+            .focus
+            .as_ref()
+            .map(|(surface, location)| (surface.clone(), inner.location - location.to_f64()))
+    }
+
+    /// The currently physically pressed buttons
+    ///
+    /// Buttons are tracked before they are passed to any grab, so this still includes buttons
+    /// the current grab has intercepted and releases swallowed by a grab are taken into account.
+    /// With [middle button emulation](PointerHandle::set_middle_button_emulation) the emulated
+    /// middle button is included instead of left and right, and a press held back by the emulation
+    /// is only included once it is passed on.
+    pub fn pressed_buttons(&self) -> impl Iterator<Item = u32> {
+        self.inner.lock().unwrap().pressed_buttons.clone().into_iter()
+    }
+
+    /// Check if the given button is currently pressed
+    ///
+    /// See [`PointerHandle::pressed_buttons`] for details.
+    pub fn is_button_pressed(&self, button: u32) -> bool {
+        self.inner.lock().unwrap().pressed_buttons.contains(&button)
+    }
+
+    /// Click count of the last button press
+    ///
+    /// Presses of the same button are counted as a multi-click (e.g. `2` for a double-click),
+    /// if they follow each other within the [double-click speed](PointerHandle::set_double_click_speed)
+    /// and the pointer did not move further than the [click slop](PointerHandle::set_click_slop)
+    /// in the meantime. The count is already updated, when the grab receives the button press,
+    /// see also [`PointerInnerHandle::click_count`].
+    ///
+    /// Returns `0`, if the pointer moved too far since the last press.
+    pub fn last_click_count(&self) -> u32 {
+        self.inner.lock().unwrap().clicks.count()
+    }
+
+    /// Set the maximum time between two button presses to count as a multi-click
+    ///
+    /// Defaults to 400 milliseconds.
+    pub fn set_double_click_speed(&self, speed: Duration) {
+        self.inner.lock().unwrap().clicks.double_click_speed = speed;
+    }
+
+    /// Maximum time between two button presses to count as a multi-click
+    pub fn double_click_speed(&self) -> Duration {
+        self.inner.lock().unwrap().clicks.double_click_speed
+    }
+
+    /// Set the distance in logical pixels the pointer may move between two presses of a multi-click
+    ///
+    /// Moving the pointer any further resets the click count. Defaults to 4 logical pixels.
+    pub fn set_click_slop(&self, slop: f64) {
+        self.inner.lock().unwrap().clicks.slop = slop.max(0.0);
+    }
+
+    /// Distance in logical pixels the pointer may move between two presses of a multi-click
+    pub fn click_slop(&self) -> f64 {
+        self.inner.lock().unwrap().clicks.slop
+    }
+
+    /// Enable or disable the emulation of a middle button by pressing left and right simultaneously
+    ///
+    /// This is useful for devices like trackpoints or touchpads without a physical middle button,
+    /// e.g. to paste the primary selection. If left and right are pressed within `timeout` of each
+    /// other, both are suppressed and a press of the middle button is sent instead. The emulated
+    /// button is released as soon as one of them is released.
+    ///
+    /// To detect this, the press of the first button is held back until the second one is pressed,
+    /// the first one is released, another button is pressed or a motion or axis event arrives
+    /// after the timeout expired. If the first button is held longer than `timeout`, both are passed
+    /// on as they are. As smithay has no timers, call [`PointerHandle::flush_middle_button_emulation`]
+    /// once the timeout expired to not delay long presses without any motion.
+    ///
+    /// Disabled by default, the default timeout is 50 milliseconds.
+    pub fn set_middle_button_emulation(&self, enabled: bool, timeout: Duration) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.middle.enabled = enabled;
+        inner.middle.timeout = timeout;
+    }
+
+    /// Returns true, if the emulation of a middle button is enabled
+    pub fn middle_button_emulation(&self) -> bool {
+        self.inner.lock().unwrap().middle.enabled
+    }
+
+    /// Maximum time between the presses of left and right to emulate a middle button
+    pub fn middle_button_emulation_timeout(&self) -> Duration {
+        self.inner.lock().unwrap().middle.timeout
+    }
+
+    /// Pass on a button press held back by the middle button emulation, if its timeout expired
+    ///
+    /// `time` is the current time with the same base and millisecond granularity as the timestamps
+    /// of the input events. See [`PointerHandle::set_middle_button_emulation`] for details.
+    pub fn flush_middle_button_emulation(&self, data: &mut D, dh: &DisplayHandle, time: u32) {
+        let mut inner = self.inner.lock().unwrap();
+        if let Some(press) = inner.middle.flush_expired(time) {
+            inner.button(data, dh, &press);
+        }
+    }
+
+    /// Invert the scroll direction of the given axis (natural scrolling)
+    ///
+    /// The inversion is applied when axis events are sent to clients, so all input sources
+    /// passed to [`PointerHandle::axis`] share the same setting. Continuous and discrete values
+    /// of the axis are inverted alike. Grabs receive the values as they were passed in.
+    pub fn set_natural_scroll(&self, axis: Axis, inverted: bool) {
+        let mut inner = self.inner.lock().unwrap();
+        match axis {
+            Axis::HorizontalScroll => inner.natural_scroll.0 = inverted,
+            Axis::VerticalScroll => inner.natural_scroll.1 = inverted,
+            _ => unreachable!(),
+        }
+    }
+
+    /// Returns true, if the scroll direction of the given axis is inverted
+    pub fn natural_scroll(&self, axis: Axis) -> bool {
+        let inner = self.inner.lock().unwrap();
+        match axis {
+            Axis::HorizontalScroll => inner.natural_scroll.0,
+            Axis::VerticalScroll => inner.natural_scroll.1,
+            _ => unreachable!(),
+        }
+    }
+
+    /// Set the acceleration profile applied by [`PointerHandle::accelerate`]
+    ///
+    /// Input backends like libinput already apply acceleration to their events,
+    /// this is mostly useful for synthetic or remote input sources.
+    pub fn set_acceleration_profile(&self, profile: AccelProfile) {
+        self.inner.lock().unwrap().accel.profile = profile;
+    }
+
+    /// Current acceleration profile of this pointer
+    pub fn acceleration_profile(&self) -> AccelProfile {
+        self.inner.lock().unwrap().accel.profile
+    }
+
+    /// Apply the acceleration profile to a relative motion
+    ///
+    /// Returns the accelerated delta, that can be added to the [current location](PointerHandle::current_location)
+    /// to calculate the location of the next [`MotionEvent`]. `time` is the timestamp of the relative motion
+    /// with millisecond granularity and is used to calculate the velocity of the pointer.
+    pub fn accelerate(&self, delta: Point<f64, Logical>, time: u32) -> Point<f64, Logical> {
+        self.inner.lock().unwrap().accel.accelerate(delta, time)
+    }
+
+    /// Set the output the cursor is currently displayed on
+    ///
+    /// The surface set as the cursor image by a client is sent `wl_surface.enter` and
+    /// `wl_surface.leave` events accordingly, which allows clients to pick a cursor buffer
+    /// matching the scale of the output. Call this whenever the pointer moves onto another output.
+    pub fn set_cursor_output(&self, dh: &DisplayHandle, output: Option<&Output>) {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.cursor_output.as_ref() == output {
+            return;
+        }
+        if let Some(surface) = inner.cursor_surface.as_ref().filter(|surface| surface.alive()) {
+            if let Some(old_output) = inner.cursor_output.as_ref() {
+                old_output.leave(dh, surface);
+            }
+            if let Some(new_output) = output {
+                new_output.enter(dh, surface);
+            }
+        }
+        inner.cursor_output = output.cloned();
+    }
+
+    /// Output the cursor is currently displayed on, as set by [`PointerHandle::set_cursor_output`]
+    pub fn cursor_output(&self) -> Option<Output> {
+        self.inner.lock().unwrap().cursor_output.clone()
+    }
 }
 
 /// This inner handle is accessed from inside a pointer grab logic, and directly
@@ -364,6 +877,13 @@ impl<'a, D> PointerInnerHandle<'a, D> {
         &self.inner.pressed_buttons
     }
 
+    /// Click count of the last button press
+    ///
+    /// See [`PointerHandle::last_click_count`] for details.
+    pub fn click_count(&self) -> u32 {
+        self.inner.clicks.count()
+    }
+
     /// Notify that the pointer moved
     ///
     /// You provide the new location of the pointer, in the form of:
@@ -403,6 +923,7 @@ impl<'a, D> PointerInnerHandle<'a, D> {
     /// This will internally send the appropriate axis events to the client
     /// objects matching with the currently focused surface.
     pub fn axis(&mut self, details: AxisFrame) {
+        let details = details.invert(self.inner.natural_scroll);
         self.inner.with_focused_pointers(|pointer, _| {
             // axis
             if details.axis.0 != 0.0 {
@@ -470,7 +991,7 @@ where
         pointer: &WlPointer,
         request: wl_pointer::Request,
         data: &PointerUserData<D>,
-        _dh: &DisplayHandle,
+        dh: &DisplayHandle,
         _data_init: &mut wayland_server::DataInit<'_, D>,
     ) {
         match request {
@@ -483,9 +1004,11 @@ where
                 if let Some(ref handle) = data.handle {
                     let mut guard = handle.inner.lock().unwrap();
                     // only allow setting the cursor icon if the current pointer focus
-                    // is of the same client
-                    let PointerInternal { ref focus, .. } = *guard;
-                    if let Some((ref focus, _)) = *focus {
+                    // as announced to the clients is of the same client
+                    let PointerInternal {
+                        ref announced_focus, ..
+                    } = *guard;
+                    if let Some(ref focus) = *announced_focus {
                         if focus.id().same_client_as(&pointer.id()) {
                             match surface {
                                 Some(surface) => {
@@ -514,9 +1037,17 @@ where
                                             .hotspot = (hotspot_x, hotspot_y).into();
                                     });
 
+                                    if guard.cursor_surface.as_ref() != Some(&surface) {
+                                        if let Some(output) = guard.cursor_output.as_ref() {
+                                            output.enter(dh, &surface);
+                                        }
+                                        guard.cursor_surface = Some(surface.clone());
+                                    }
+
                                     (guard.image_callback)(CursorImageStatus::Image(surface));
                                 }
                                 None => {
+                                    guard.cursor_surface = None;
                                     (guard.image_callback)(CursorImageStatus::Hidden);
                                 }
                             }
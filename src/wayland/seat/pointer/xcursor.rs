@@ -0,0 +1,121 @@
+use std::io::Read;
+
+use xcursor::{
+    parser::{parse_xcursor, Image},
+    CursorTheme,
+};
+
+use crate::utils::{Buffer, Logical, Point, Size};
+
+/// Errors that can occur while loading an xcursor theme
+#[derive(Debug, thiserror::Error)]
+pub enum XCursorError {
+    /// The theme does not contain a default cursor
+    #[error("Theme has no default cursor")]
+    NoDefaultCursor,
+    /// The cursor file could not be read
+    #[error("Error opening xcursor file: {0}")]
+    File(#[from] std::io::Error),
+    /// The cursor file could not be parsed
+    #[error("Failed to parse XCursor file")]
+    Parse,
+}
+
+/// Default cursor of an xcursor theme, containing the images for all sizes provided by the theme
+#[derive(Debug, Clone)]
+pub struct XCursorTheme {
+    images: Vec<Image>,
+    size: u32,
+}
+
+/// Image of an [`XCursorTheme`] for a given scale
+#[derive(Debug, Clone, Copy)]
+pub struct XCursorImage<'a> {
+    /// Pixel data of the image in RGBA format
+    pub pixels_rgba: &'a [u8],
+    /// Size of the image in buffer coordinates
+    pub size: Size<i32, Buffer>,
+    /// Hotspot of the image in buffer coordinates
+    pub hotspot: Point<i32, Buffer>,
+    /// Scale the image should be displayed at
+    pub scale: i32,
+    /// Time in milliseconds this frame of an animated cursor should be displayed
+    pub delay: u32,
+}
+
+impl<'a> XCursorImage<'a> {
+    /// Hotspot of the image in logical coordinates, taking the scale into account
+    pub fn logical_hotspot(&self) -> Point<i32, Logical> {
+        (self.hotspot.x / self.scale, self.hotspot.y / self.scale).into()
+    }
+}
+
+/// Load the default cursor of the xcursor theme with the given name
+///
+/// `size` is the nominal size of the cursor in logical pixels, images for higher scales
+/// are picked from the larger sizes of the theme by [`XCursorTheme::image`].
+pub fn load_xcursor_theme(name: &str, size: u32) -> Result<XCursorTheme, XCursorError> {
+    let theme = CursorTheme::load(name);
+    let icon_path = theme.load_icon("default").ok_or(XCursorError::NoDefaultCursor)?;
+    let mut cursor_file = std::fs::File::open(&icon_path)?;
+    let mut cursor_data = Vec::new();
+    cursor_file.read_to_end(&mut cursor_data)?;
+    let images = parse_xcursor(&cursor_data).ok_or(XCursorError::Parse)?;
+    if images.is_empty() {
+        return Err(XCursorError::Parse);
+    }
+    Ok(XCursorTheme { images, size })
+}
+
+impl XCursorTheme {
+    /// Nominal size of the cursor in logical pixels
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    /// Nominal sizes of the images provided by the theme
+    pub fn available_sizes(&self) -> Vec<u32> {
+        let mut sizes = self.images.iter().map(|image| image.size).collect::<Vec<_>>();
+        sizes.sort_unstable();
+        sizes.dedup();
+        sizes
+    }
+
+    /// Returns the image to display on an output with the given scale
+    ///
+    /// The image with the nominal size closest to `size * scale` is chosen.
+    /// For animated cursors `millis` selects the current frame of the animation.
+    pub fn image(&self, scale: i32, millis: u32) -> XCursorImage<'_> {
+        let scale = scale.max(1);
+        let target = self.size * scale as u32;
+        let nearest = self
+            .images
+            .iter()
+            .min_by_key(|image| (target as i32 - image.size as i32).abs())
+            .unwrap();
+        let frames = self
+            .images
+            .iter()
+            .filter(|image| image.width == nearest.width && image.height == nearest.height)
+            .collect::<Vec<_>>();
+        let total = frames.iter().fold(0, |acc, image| acc + image.delay);
+
+        let mut millis = if total == 0 { 0 } else { millis % total };
+        let mut frame = frames[0];
+        for image in frames {
+            if millis < image.delay {
+                frame = image;
+                break;
+            }
+            millis -= image.delay;
+        }
+
+        XCursorImage {
+            pixels_rgba: &frame.pixels_rgba,
+            size: (frame.width as i32, frame.height as i32).into(),
+            hotspot: (frame.xhot as i32, frame.yhot as i32).into(),
+            scale,
+            delay: frame.delay,
+        }
+    }
+}
@@ -0,0 +1,387 @@
+// Tests of the seat state shared by keyboards, pointers and touch, see `wayland::test_client`
+
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+use wayland_server::{
+    protocol::{
+        wl_keyboard::WlKeyState,
+        wl_pointer::{ButtonState, WlPointer},
+        wl_surface::WlSurface,
+        wl_touch::WlTouch,
+    },
+    Display, DisplayHandle, Resource,
+};
+
+use crate::{
+    backend::input::TouchSlot,
+    wayland::{
+        compositor::create_test_surface,
+        test_client::{TestClient, TestState},
+        Serial, SERIAL_COUNTER,
+    },
+};
+
+use super::{
+    AxisFrame, ButtonEvent, Focus, KeyboardGrab, KeyboardGrabStartData, KeyboardInnerHandle, MotionEvent,
+    PointerGrab, PointerGrabStartData, PointerHandle, PointerInnerHandle, PointerUserData, Seat,
+    TouchUserData, XkbConfig,
+};
+
+// A compositor with a single seat and two clients owning a surface each
+struct Harness {
+    display: Display<TestState>,
+    state: TestState,
+    seat: Seat<TestState>,
+    owner: TestClient,
+    owner_surface: WlSurface,
+    other: TestClient,
+    other_surface: WlSurface,
+}
+
+impl Harness {
+    fn new() -> Self {
+        let display = Display::<TestState>::new().unwrap();
+        let dh = display.handle();
+        let mut state = TestState::new(&dh);
+        let seat = Seat::new(&dh, "seat-0", None);
+        // as if a client bound the seat
+        state.seat_state.add_seat(&seat);
+
+        let owner = TestClient::new(&dh);
+        let other = TestClient::new(&dh);
+        let owner_surface = create_test_surface::<TestState>(&dh, &owner.client);
+        let other_surface = create_test_surface::<TestState>(&dh, &other.client);
+        Harness {
+            display,
+            state,
+            seat,
+            owner,
+            owner_surface,
+            other,
+            other_surface,
+        }
+    }
+
+    fn dh(&self) -> DisplayHandle {
+        self.display.handle()
+    }
+
+    fn cleanup_owner(&mut self) {
+        let dh = self.dh();
+        self.state.seat_state.cleanup_client(&dh, &self.owner.client);
+    }
+
+    fn motion(&mut self, surface: Option<&WlSurface>) {
+        let dh = self.dh();
+        let pointer = self.seat.get_pointer().unwrap();
+        let event = MotionEvent {
+            location: (5.0, 5.0).into(),
+            focus: surface.map(|surface| (surface.clone(), (0, 0).into())),
+            serial: SERIAL_COUNTER.next_serial(),
+            time: 0,
+        };
+        pointer.motion(&mut self.state, &dh, &event);
+    }
+
+    fn button(&mut self) {
+        let dh = self.dh();
+        let pointer = self.seat.get_pointer().unwrap();
+        let event = ButtonEvent {
+            serial: SERIAL_COUNTER.next_serial(),
+            time: 0,
+            button: 0x110,
+            state: ButtonState::Pressed,
+        };
+        pointer.button(&mut self.state, &dh, &event);
+    }
+
+    // Bind a pointer of the seat for both clients
+    fn bind_pointers(&mut self, pointer: &PointerHandle<TestState>) {
+        let dh = self.dh();
+        for client in [&self.owner.client, &self.other.client] {
+            let wl_pointer = client
+                .create_resource::<WlPointer, _, TestState>(
+                    &dh,
+                    7,
+                    PointerUserData {
+                        handle: Some(pointer.clone()),
+                    },
+                )
+                .unwrap();
+            pointer.new_pointer(wl_pointer);
+        }
+    }
+
+    // Opcodes of the pointer events received by both clients since the last call
+    fn pointer_events(&mut self) -> (Vec<u16>, Vec<u16>) {
+        self.display.flush_clients().unwrap();
+        let opcodes = |client: &mut TestClient| client.events().iter().map(|event| event.opcode).collect();
+        (opcodes(&mut self.owner), opcodes(&mut self.other))
+    }
+}
+
+// opcodes of the pointer events used by the tests
+const POINTER_ENTER: u16 = 0;
+const POINTER_MOTION: u16 = 2;
+const POINTER_BUTTON: u16 = 3;
+const POINTER_FRAME: u16 = 5;
+
+// Grab ignoring all input, which records when it is dropped
+struct TestGrab<S> {
+    start_data: S,
+    dropped: Arc<AtomicBool>,
+}
+
+impl<S> TestGrab<S> {
+    fn new(start_data: S) -> (Self, Arc<AtomicBool>) {
+        let dropped = Arc::new(AtomicBool::new(false));
+        let grab = TestGrab {
+            start_data,
+            dropped: dropped.clone(),
+        };
+        (grab, dropped)
+    }
+}
+
+impl<S> Drop for TestGrab<S> {
+    fn drop(&mut self) {
+        self.dropped.store(true, Ordering::SeqCst);
+    }
+}
+
+impl KeyboardGrab for TestGrab<KeyboardGrabStartData> {
+    fn input(
+        &mut self,
+        _dh: &DisplayHandle,
+        _handle: &mut KeyboardInnerHandle<'_>,
+        _keycode: u32,
+        _key_state: WlKeyState,
+        _modifiers: Option<(u32, u32, u32, u32)>,
+        _serial: Serial,
+        _time: u32,
+    ) {
+    }
+
+    fn set_focus(
+        &mut self,
+        _dh: &DisplayHandle,
+        _handle: &mut KeyboardInnerHandle<'_>,
+        _focus: Option<&WlSurface>,
+        _serial: Serial,
+    ) {
+    }
+
+    fn start_data(&self) -> &KeyboardGrabStartData {
+        &self.start_data
+    }
+}
+
+impl PointerGrab<TestState> for TestGrab<PointerGrabStartData> {
+    fn motion(
+        &mut self,
+        _data: &mut TestState,
+        _dh: &DisplayHandle,
+        _handle: &mut PointerInnerHandle<'_, TestState>,
+        _event: &MotionEvent,
+    ) {
+    }
+
+    fn button(
+        &mut self,
+        _data: &mut TestState,
+        _dh: &DisplayHandle,
+        _handle: &mut PointerInnerHandle<'_, TestState>,
+        _event: &ButtonEvent,
+    ) {
+    }
+
+    fn axis(
+        &mut self,
+        _data: &mut TestState,
+        _dh: &DisplayHandle,
+        _handle: &mut PointerInnerHandle<'_, TestState>,
+        _details: AxisFrame,
+    ) {
+    }
+
+    fn start_data(&self) -> &PointerGrabStartData {
+        &self.start_data
+    }
+}
+
+#[test]
+fn cleanup_removes_keyboard_focus() {
+    let mut harness = Harness::new();
+    let dh = harness.dh();
+    let keyboard = harness
+        .seat
+        .add_keyboard(XkbConfig::default(), 200, 25, |_, _| {})
+        .unwrap();
+    let owner = harness.owner.client.id();
+
+    keyboard.set_focus(&dh, Some(&harness.owner_surface), SERIAL_COUNTER.next_serial());
+    assert!(keyboard.has_focus(&owner));
+
+    harness.cleanup_owner();
+    assert!(!keyboard.has_focus(&owner));
+}
+
+#[test]
+fn cleanup_ends_keyboard_grab() {
+    let mut harness = Harness::new();
+    let dh = harness.dh();
+    let keyboard = harness
+        .seat
+        .add_keyboard(XkbConfig::default(), 200, 25, |_, _| {})
+        .unwrap();
+    let other = harness.other_surface.client_id().unwrap();
+
+    keyboard.set_focus(&dh, Some(&harness.owner_surface), SERIAL_COUNTER.next_serial());
+    let (grab, dropped) = TestGrab::new(KeyboardGrabStartData {
+        focus: Some(harness.owner_surface.clone()),
+    });
+    keyboard.set_grab(grab, SERIAL_COUNTER.next_serial());
+    // held back by the grab
+    keyboard.set_focus(&dh, Some(&harness.other_surface), SERIAL_COUNTER.next_serial());
+    assert!(!keyboard.has_focus(&other));
+
+    harness.cleanup_owner();
+    assert!(!keyboard.is_grabbed());
+    assert!(dropped.load(Ordering::SeqCst));
+    // ending the grab restores the focus it held back
+    assert!(keyboard.has_focus(&other));
+}
+
+#[test]
+fn cleanup_removes_pointer_focus() {
+    let mut harness = Harness::new();
+    let pointer = harness.seat.add_pointer(|_| {});
+
+    let surface = harness.owner_surface.clone();
+    harness.motion(Some(&surface));
+    assert_eq!(pointer.current_focus().map(|(focus, _)| focus), Some(surface));
+
+    harness.cleanup_owner();
+    assert!(pointer.current_focus().is_none());
+}
+
+#[test]
+fn cleanup_ends_pointer_grab() {
+    let mut harness = Harness::new();
+    let pointer = harness.seat.add_pointer(|_| {});
+
+    let (grab, dropped) = TestGrab::new(PointerGrabStartData {
+        focus: Some((harness.owner_surface.clone(), (0, 0).into())),
+        button: 0x110,
+        location: (5.0, 5.0).into(),
+    });
+    pointer.set_grab(grab, SERIAL_COUNTER.next_serial(), Focus::Clear);
+    // held back by the grab
+    let surface = harness.other_surface.clone();
+    harness.motion(Some(&surface));
+    assert!(pointer.current_focus().is_none());
+
+    harness.cleanup_owner();
+    assert!(!pointer.is_grabbed());
+    assert!(dropped.load(Ordering::SeqCst));
+    // ending the grab restores the focus it held back
+    assert_eq!(pointer.current_focus().map(|(focus, _)| focus), Some(surface));
+}
+
+#[test]
+fn cleanup_removes_touch_points() {
+    let mut harness = Harness::new();
+    let dh = harness.dh();
+    let mut touch = harness.seat.add_touch();
+    let wl_touch = harness
+        .owner
+        .client
+        .create_resource::<WlTouch, _, TestState>(
+            &dh,
+            7,
+            TouchUserData {
+                handle: Some(touch.clone()),
+            },
+        )
+        .unwrap();
+    touch.new_touch(wl_touch);
+
+    let slot = TouchSlot::from(Some(0));
+    let surface = harness.owner_surface.clone();
+    touch.down(
+        SERIAL_COUNTER.next_serial(),
+        0,
+        &surface,
+        (0, 0).into(),
+        slot,
+        (1.0, 1.0).into(),
+    );
+    touch.motion(1, slot, (2.0, 2.0).into());
+    harness.display.flush_clients().unwrap();
+    assert!(!harness.owner.events().is_empty());
+
+    harness.cleanup_owner();
+    touch.motion(2, slot, (3.0, 3.0).into());
+    harness.display.flush_clients().unwrap();
+    assert!(harness.owner.events().is_empty());
+}
+
+#[test]
+fn coalesced_focus_skips_surfaces_passed_over() {
+    let mut harness = Harness::new();
+    let pointer = harness.seat.add_pointer(|_| {});
+    harness.bind_pointers(&pointer);
+    pointer.set_focus_coalescing(true);
+
+    let (owner_surface, other_surface) = (harness.owner_surface.clone(), harness.other_surface.clone());
+    harness.motion(Some(&owner_surface));
+    harness.motion(Some(&other_surface));
+    harness.motion(None);
+    pointer.flush_focus();
+    assert_eq!(harness.pointer_events(), (vec![], vec![]));
+}
+
+#[test]
+fn coalesced_focus_returning_to_the_entered_surface_is_a_motion() {
+    let mut harness = Harness::new();
+    let pointer = harness.seat.add_pointer(|_| {});
+    harness.bind_pointers(&pointer);
+    pointer.set_focus_coalescing(true);
+
+    let (owner_surface, other_surface) = (harness.owner_surface.clone(), harness.other_surface.clone());
+    harness.motion(Some(&owner_surface));
+    pointer.flush_focus();
+    assert_eq!(
+        harness.pointer_events(),
+        (vec![POINTER_ENTER, POINTER_FRAME], vec![])
+    );
+
+    harness.motion(Some(&other_surface));
+    harness.motion(Some(&owner_surface));
+    pointer.flush_focus();
+    assert_eq!(
+        harness.pointer_events(),
+        (vec![POINTER_MOTION, POINTER_FRAME], vec![])
+    );
+}
+
+#[test]
+fn button_flushes_the_coalesced_focus() {
+    let mut harness = Harness::new();
+    let pointer = harness.seat.add_pointer(|_| {});
+    harness.bind_pointers(&pointer);
+    pointer.set_focus_coalescing(true);
+
+    let surface = harness.owner_surface.clone();
+    harness.motion(Some(&surface));
+    assert_eq!(harness.pointer_events(), (vec![], vec![]));
+
+    harness.button();
+    let (owner, other) = harness.pointer_events();
+    assert_eq!(owner[..2], [POINTER_ENTER, POINTER_FRAME]);
+    assert!(owner[2..].contains(&POINTER_BUTTON));
+    assert!(other.is_empty());
+}
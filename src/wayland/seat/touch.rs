@@ -9,7 +9,7 @@ use wayland_server::{
     Dispatch, DisplayHandle, Resource,
 };
 
-use super::{SeatHandler, SeatState};
+use super::{InputActivity, SeatHandler, SeatState};
 use crate::backend::input::TouchSlot;
 use crate::utils::{Logical, Point};
 use crate::wayland::seat::wl_surface::WlSurface;
@@ -24,12 +24,14 @@ use crate::wayland::Serial;
 #[derive(Debug, Clone)]
 pub struct TouchHandle {
     inner: Arc<Mutex<TouchInternal>>,
+    activity: Arc<InputActivity>,
 }
 
 impl TouchHandle {
-    pub(crate) fn new() -> Self {
+    pub(crate) fn new(activity: Arc<InputActivity>) -> Self {
         Self {
             inner: Default::default(),
+            activity,
         }
     }
 
@@ -40,6 +42,16 @@ impl TouchHandle {
         self.inner.lock().unwrap().known_handles.push(touch);
     }
 
+    /// Forget the touch points on the surfaces of the given client
+    pub(crate) fn cleanup_client(&self, client: &ClientId) {
+        self.inner.lock().unwrap().focus.retain(|_, focus| {
+            !focus
+                .handles
+                .iter()
+                .any(|handle| handle.client_id().as_ref() == Some(client))
+        });
+    }
+
     // TODO: Any ideas how to group some of those args?
     #[allow(clippy::too_many_arguments)]
     /// Notify clients about new touch points.
@@ -52,6 +64,7 @@ impl TouchHandle {
         slot: TouchSlot,
         location: Point<f64, Logical>,
     ) {
+        self.activity.notify();
         self.inner
             .lock()
             .unwrap()
@@ -60,11 +73,13 @@ impl TouchHandle {
 
     /// Notify clients about touch point removal.
     pub fn up(&self, serial: Serial, time: u32, slot: TouchSlot) {
+        self.activity.notify();
         self.inner.lock().unwrap().up(serial, time, slot);
     }
 
     /// Notify clients about touch motion.
     pub fn motion(&self, time: u32, slot: TouchSlot, location: Point<f64, Logical>) {
+        self.activity.notify();
         self.inner.lock().unwrap().motion(time, slot, location);
     }
 
@@ -0,0 +1,163 @@
+// Clients for in-process tests of the protocol handlers
+//
+// Clients are inserted into the display using one end of a socket pair. Their objects are
+// created on the server side, so no client library is required. Events sent to a client are
+// parsed from the other end of the socket pair.
+//
+// Tests of modules without a handler of their own can use `TestState`, which supports
+// the compositor and the seats.
+
+use std::{
+    io::{ErrorKind, Read, Write},
+    os::unix::net::UnixStream,
+    sync::Arc,
+};
+
+use wayland_server::{
+    backend::{ClientData, ClientId, DisconnectReason},
+    protocol::wl_surface::WlSurface,
+    Client, DisplayHandle,
+};
+
+use crate::wayland::{
+    compositor::{CompositorHandler, CompositorState},
+    seat::{SeatHandler, SeatState},
+};
+
+pub(crate) struct TestState {
+    pub compositor_state: CompositorState,
+    pub seat_state: SeatState<TestState>,
+}
+
+impl TestState {
+    pub fn new(dh: &DisplayHandle) -> Self {
+        TestState {
+            compositor_state: CompositorState::new::<TestState, _>(dh, None),
+            seat_state: SeatState::new(),
+        }
+    }
+}
+
+impl CompositorHandler for TestState {
+    fn compositor_state(&mut self) -> &mut CompositorState {
+        &mut self.compositor_state
+    }
+
+    fn commit(&mut self, _dh: &DisplayHandle, _surface: &WlSurface) {}
+}
+
+impl SeatHandler for TestState {
+    fn seat_state(&mut self) -> &mut SeatState<Self> {
+        &mut self.seat_state
+    }
+}
+
+crate::delegate_compositor!(TestState);
+crate::delegate_seat!(TestState);
+
+pub(crate) struct TestClientData;
+
+impl ClientData for TestClientData {
+    fn initialized(&self, _client_id: ClientId) {}
+    fn disconnected(&self, _client_id: ClientId, _reason: DisconnectReason) {}
+}
+
+#[derive(Debug, PartialEq)]
+pub(crate) struct Event {
+    pub object: u32,
+    pub opcode: u16,
+    pub args: Vec<u8>,
+}
+
+impl Event {
+    pub fn uint_arg(&self) -> u32 {
+        u32::from_ne_bytes(self.word(0))
+    }
+
+    // only valid, if all arguments up to `index` are 32 bit wide
+    pub fn int_arg(&self, index: usize) -> i32 {
+        i32::from_ne_bytes(self.word(index))
+    }
+
+    pub fn string_arg(&self) -> String {
+        // the length includes the terminating NUL byte
+        let len = self.uint_arg() as usize;
+        String::from_utf8(self.args[4..4 + len - 1].to_vec()).unwrap()
+    }
+
+    fn word(&self, index: usize) -> [u8; 4] {
+        self.args[index * 4..index * 4 + 4].try_into().unwrap()
+    }
+}
+
+pub(crate) struct TestClient {
+    pub client: Client,
+    pub stream: UnixStream,
+}
+
+impl TestClient {
+    pub fn new(dh: &DisplayHandle) -> Self {
+        let (server_stream, stream) = UnixStream::pair().unwrap();
+        stream.set_nonblocking(true).unwrap();
+        let client = dh.insert_client(server_stream, Arc::new(TestClientData)).unwrap();
+        TestClient { client, stream }
+    }
+
+    // Read all events sent to this client so far
+    pub fn events(&mut self) -> Vec<Event> {
+        let mut data = Vec::new();
+        let mut buffer = [0u8; 4096];
+        loop {
+            match self.stream.read(&mut buffer) {
+                Ok(0) => break,
+                Ok(len) => data.extend_from_slice(&buffer[..len]),
+                Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+                Err(err) => panic!("failed to read events: {}", err),
+            }
+        }
+
+        let mut events = Vec::new();
+        let mut data = &data[..];
+        while data.len() >= 8 {
+            let object = u32::from_ne_bytes([data[0], data[1], data[2], data[3]]);
+            let word = u32::from_ne_bytes([data[4], data[5], data[6], data[7]]);
+            let size = (word >> 16) as usize;
+            events.push(Event {
+                object,
+                opcode: (word & 0xffff) as u16,
+                args: data[8..size].to_vec(),
+            });
+            data = &data[size..];
+        }
+        events
+    }
+
+    // Send an encoded request, the display still needs to dispatch it
+    pub fn send(&mut self, message: &[u8]) {
+        self.stream.write_all(message).unwrap();
+    }
+}
+
+// Encode a request without arguments
+pub(crate) fn request(object: u32, opcode: u16) -> Vec<u8> {
+    let mut message = Vec::new();
+    message.extend_from_slice(&object.to_ne_bytes());
+    message.extend_from_slice(&((8 << 16) | opcode as u32).to_ne_bytes());
+    message
+}
+
+// Encode a request with a single string argument
+pub(crate) fn string_request(object: u32, opcode: u16, string: &str) -> Vec<u8> {
+    let mut string = string.as_bytes().to_vec();
+    string.push(0);
+    let len = string.len() as u32;
+    string.resize((string.len() + 3) & !3, 0);
+
+    let size = 8 + 4 + string.len() as u32;
+    let mut message = Vec::new();
+    message.extend_from_slice(&object.to_ne_bytes());
+    message.extend_from_slice(&((size << 16) | opcode as u32).to_ne_bytes());
+    message.extend_from_slice(&len.to_ne_bytes());
+    message.extend_from_slice(&string);
+    message
+}
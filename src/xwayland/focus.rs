@@ -0,0 +1,106 @@
+use std::sync::{Arc, Mutex};
+
+use slog::warn;
+use wayland_server::protocol::wl_surface::WlSurface;
+use x11rb::{
+    connection::Connection as _,
+    protocol::xproto::{ConnectionExt as _, InputFocus, Window},
+    rust_connection::RustConnection,
+};
+
+use crate::utils::IsAlive;
+
+/// Keeps the X11 input focus in sync with the keyboard focus of a seat
+///
+/// Clones share the same state, so one handle can be kept by your X11 window manager, which
+/// connects it and pairs surfaces with their windows, while another one is updated from the
+/// focus hook of the keyboard, which has no access to your compositor state:
+///
+/// ```ignore
+/// let x11_focus = X11Focus::default();
+/// let hook_focus = x11_focus.clone();
+/// seat.add_keyboard(XkbConfig::default(), 200, 25, move |_seat, surface| {
+///     hook_focus.set_focus(surface);
+/// })?;
+/// ```
+///
+/// The keymap does not need to be forwarded, XWayland receives it through its own `wl_keyboard`.
+#[derive(Debug, Default, Clone)]
+pub struct X11Focus {
+    inner: Arc<Mutex<Option<X11FocusInner>>>,
+}
+
+#[derive(Debug)]
+struct X11FocusInner {
+    conn: Arc<RustConnection>,
+    windows: Vec<(WlSurface, Window)>,
+    log: slog::Logger,
+}
+
+impl X11Focus {
+    /// Start forwarding the focus using the connection of your X11 window manager
+    ///
+    /// Windows paired before are forgotten.
+    pub fn connect<L>(&self, conn: Arc<RustConnection>, logger: L)
+    where
+        L: Into<Option<::slog::Logger>>,
+    {
+        let log = crate::slog_or_fallback(logger).new(slog::o!("smithay_module" => "xwayland_focus"));
+        *self.inner.lock().unwrap() = Some(X11FocusInner {
+            conn,
+            windows: Vec::new(),
+            log,
+        });
+    }
+
+    /// Stop forwarding the focus, e.g. once XWayland exited
+    pub fn disconnect(&self) {
+        self.inner.lock().unwrap().take();
+    }
+
+    /// Returns true, if the focus is forwarded to an X11 connection
+    pub fn is_connected(&self) -> bool {
+        self.inner.lock().unwrap().is_some()
+    }
+
+    /// Pair the surface of an X11 window with the window
+    ///
+    /// Call this once XWayland announced the surface of a window, i.e. on its `WL_SURFACE_ID` message.
+    /// Pairs of destroyed surfaces are dropped automatically. Does nothing, if not connected.
+    pub fn insert_window(&self, surface: WlSurface, window: Window) {
+        if let Some(inner) = self.inner.lock().unwrap().as_mut() {
+            inner.windows.retain(|(surface, _)| surface.alive());
+            inner.windows.push((surface, window));
+        }
+    }
+
+    /// Update the X11 input focus to match the new keyboard focus
+    ///
+    /// If the focused surface does not belong to an X11 window, the X11 focus is removed,
+    /// so that X11 clients do not keep receiving input in the background.
+    pub fn set_focus(&self, surface: Option<&WlSurface>) {
+        let guard = self.inner.lock().unwrap();
+        let inner = match guard.as_ref() {
+            Some(inner) => inner,
+            None => return,
+        };
+
+        let window = surface
+            .and_then(|surface| {
+                inner
+                    .windows
+                    .iter()
+                    .find(|(s, _)| s == surface)
+                    .map(|(_, window)| *window)
+            })
+            .unwrap_or(x11rb::NONE);
+        let res = inner
+            .conn
+            .set_input_focus(InputFocus::NONE, window, x11rb::CURRENT_TIME)
+            .map(|_| ())
+            .and_then(|_| inner.conn.flush());
+        if let Err(err) = res {
+            warn!(inner.log, "Failed to update the X11 input focus: {}", err);
+        }
+    }
+}
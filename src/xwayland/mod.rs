@@ -10,9 +10,15 @@
 //! function properly. You'll need to treat XWayland (and all its X11 apps) as one
 //! special client, and play the role of an X11 Window Manager.
 //!
-//! Smithay does not provide any helper for doing that yet, but it is planned.
+//! Smithay does not provide a complete X11 Window Manager yet, but it is planned. With the
+//! `x11rb_event_source` feature, [`X11Focus`] keeps the X11 input focus in sync with the keyboard
+//! focus of a seat.
 
+#[cfg(feature = "x11rb_event_source")]
+mod focus;
 mod x11_sockets;
 mod xserver;
 
+#[cfg(feature = "x11rb_event_source")]
+pub use self::focus::X11Focus;
 pub use self::xserver::{XWayland, XWaylandEvent, XWaylandSource};
@@ -3,7 +3,10 @@ use std::cell::Cell;
 use smithay::{
     backend::{
         allocator::dmabuf::Dmabuf,
-        renderer::{Frame, ImportDma, ImportDmaWl, ImportMem, ImportMemWl, Renderer, Texture, TextureFilter},
+        renderer::{
+            AlphaMode, Frame, ImportDma, ImportDmaWl, ImportMem, ImportMemWl, Renderer, Texture,
+            TextureFilter,
+        },
         SwapBuffersError,
     },
     reexports::wayland_server::protocol::wl_buffer,
@@ -56,6 +59,7 @@ impl ImportMem for DummyRenderer {
         _data: &[u8],
         _size: Size<i32, Buffer>,
         _flipped: bool,
+        _alpha: AlphaMode,
     ) -> Result<<Self as Renderer>::TextureId, <Self as Renderer>::Error> {
         unimplemented!()
     }